@@ -0,0 +1,236 @@
+//! Reading markdown entries out of a zip archive for `stow --from-zip`
+//!
+//! Entries are read into memory, so nothing from the archive is extracted
+//! next to it. Entry names are untrusted: absolute names are taken relative
+//! to the archive root, and names that climb out of the archive with `..`
+//! are reported instead of being imported.
+
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{Local, NaiveDate};
+
+use crate::config::Config;
+use crate::file_discovery::FileDiscovery;
+use crate::run_summary::SkipReason;
+
+/// Error types for reading archives
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid zip archive: {0}")]
+    ZipError(#[from] zip::result::ZipError),
+}
+
+/// A file read from an archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    /// Relative path of the entry inside the archive, with `.` and `..` resolved
+    pub path: PathBuf,
+    pub content: Vec<u8>,
+    /// Modification time stored in the archive, or the time it was read
+    pub modified: SystemTime,
+}
+
+/// Entries of an archive with a configured page or attachment extension,
+/// and the ones left out
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ArchiveReport {
+    pub entries: Vec<ArchiveEntry>,
+    /// Entry names with a configured page or attachment extension that were rejected, and why
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+/// Public interface for reading archives
+pub struct Archive;
+
+impl Archive {
+    /// Read the pages and attachments from the zip file at `path`
+    pub fn read_file(path: &Path, config: &Config) -> Result<ArchiveReport, ArchiveError> {
+        Self::read(File::open(path)?, config)
+    }
+
+    /// Read the entries with one of `extensions` or `attachment_extensions`
+    /// from a zip archive
+    ///
+    /// Directories and symlinks are ignored. Entries whose name would
+    /// resolve outside the archive are skipped as [`SkipReason::UnsafePath`],
+    /// and entries that unpack to more than `max_file_size_bytes` as
+    /// [`SkipReason::TooLarge`], without reading them in full.
+    pub fn read<R: Read + Seek>(reader: R, config: &Config) -> Result<ArchiveReport, ArchiveError> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut report = ArchiveReport::default();
+        let extensions = [&config.extensions[..], &config.attachment_extensions[..]].concat();
+
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            if !file.is_file() || file.is_symlink() {
+                continue;
+            }
+            let name = PathBuf::from(file.name()?.as_ref());
+            if !FileDiscovery::has_extension(&name, &extensions) {
+                continue;
+            }
+            let Some(path) = file.enclosed_name() else {
+                report.skipped.push((name, SkipReason::UnsafePath));
+                continue;
+            };
+            let max_size = config.max_file_size_bytes.unwrap_or(u64::MAX);
+            if file.size() > max_size {
+                report.skipped.push((name, SkipReason::TooLarge));
+                continue;
+            }
+
+            let modified = file
+                .last_modified()
+                .and_then(|time| {
+                    NaiveDate::from_ymd_opt(
+                        time.year().into(),
+                        time.month().into(),
+                        time.day().into(),
+                    )?
+                    .and_hms_opt(
+                        time.hour().into(),
+                        time.minute().into(),
+                        time.second().into(),
+                    )?
+                    .and_local_timezone(Local)
+                    .earliest()
+                })
+                .map(SystemTime::from)
+                .unwrap_or_else(SystemTime::now);
+            // The size in the archive is not trusted: read one byte past the
+            // limit to catch an entry that unpacks to more
+            let mut content = Vec::with_capacity(file.size().try_into().unwrap_or(0));
+            file.by_ref()
+                .take(max_size.saturating_add(1))
+                .read_to_end(&mut content)?;
+            if content.len() as u64 > max_size {
+                report.skipped.push((name, SkipReason::TooLarge));
+                continue;
+            }
+
+            report.entries.push(ArchiveEntry {
+                path,
+                content,
+                modified,
+            });
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+
+    /// Build a zip archive in memory from `(name, content)` pairs
+    fn zip_bytes(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_read_markdown_entries() {
+        let bytes = zip_bytes(&[
+            ("export/first.md", "# First"),
+            ("export/nested/./second.md", "# Second"),
+            ("export/image.png", "binary"),
+        ]);
+
+        let report = Archive::read(Cursor::new(bytes), &Config::default()).unwrap();
+
+        let entries: Vec<_> = report
+            .entries
+            .iter()
+            .map(|entry| (entry.path.clone(), String::from_utf8_lossy(&entry.content)))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (PathBuf::from("export/first.md"), "# First".into()),
+                (PathBuf::from("export/nested/second.md"), "# Second".into()),
+            ]
+        );
+        assert!(report.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_read_attachment_entries() {
+        let bytes = zip_bytes(&[
+            ("export/page.md", "# Page"),
+            ("export/report.pdf", "%PDF"),
+            ("export/image.png", "binary"),
+        ]);
+        let config = Config {
+            attachment_extensions: vec!["pdf".to_string()],
+            ..Default::default()
+        };
+
+        let report = Archive::read(Cursor::new(bytes), &config).unwrap();
+
+        let paths: Vec<_> = report.entries.iter().map(|entry| &entry.path).collect();
+        assert_eq!(
+            paths,
+            vec![Path::new("export/page.md"), Path::new("export/report.pdf")]
+        );
+    }
+
+    #[test]
+    fn test_read_rejects_entries_escaping_the_archive() {
+        let bytes = zip_bytes(&[
+            ("../evil.md", "escaped"),
+            ("/etc/evil.md", "absolute"),
+            ("notes/../../evil.md", "escaped"),
+            ("notes/../kept.md", "kept"),
+        ]);
+
+        let report = Archive::read(Cursor::new(bytes), &Config::default()).unwrap();
+
+        let paths: Vec<_> = report.entries.iter().map(|entry| &entry.path).collect();
+        assert_eq!(paths, vec![Path::new("etc/evil.md"), Path::new("kept.md")]);
+        assert_eq!(
+            report.skipped,
+            vec![
+                (PathBuf::from("../evil.md"), SkipReason::UnsafePath),
+                (PathBuf::from("notes/../../evil.md"), SkipReason::UnsafePath),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_skips_entries_above_max_file_size() {
+        let bytes = zip_bytes(&[("small.md", "# Hi"), ("large.md", "# Much longer page")]);
+        let config = Config {
+            max_file_size_bytes: Some(8),
+            ..Default::default()
+        };
+
+        let report = Archive::read(Cursor::new(bytes), &config).unwrap();
+
+        let paths: Vec<_> = report.entries.iter().map(|entry| &entry.path).collect();
+        assert_eq!(paths, vec![Path::new("small.md")]);
+        assert_eq!(
+            report.skipped,
+            vec![(PathBuf::from("large.md"), SkipReason::TooLarge)]
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_archive() {
+        let result = Archive::read(Cursor::new(b"not a zip".to_vec()), &Config::default());
+        assert!(matches!(result, Err(ArchiveError::ZipError(_))));
+    }
+}
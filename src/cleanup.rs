@@ -0,0 +1,289 @@
+//! Removal of empty journals and directories left behind by earlier runs
+//!
+//! Only two kinds of leftovers are ever touched: zero-byte journal files
+//! named like the ones `stow` writes (`YYYY_MM_DD.md` by default), and
+//! directories that `stow` created, as recorded in the ledger, and that
+//! contain nothing at all. The Knowledge Base, pages, destination and
+//! journals directories themselves are always kept.
+//! Anything with content, including hidden files, is left alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::journal_management::JournalManager;
+
+/// Error types for cleanup operations
+#[derive(Debug, thiserror::Error)]
+pub enum CleanupError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error(
+        "Refusing to clean {0}: the pages directory is the Knowledge Base itself, so its folders belong to the vault"
+    )]
+    PagesDirIsKnowledgeBase(PathBuf),
+}
+
+/// Leftovers that a cleanup would remove
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CleanupPlan {
+    /// Zero-byte journal files
+    pub files: Vec<PathBuf>,
+    /// Empty directories, deepest first so each is empty once its children are gone
+    pub directories: Vec<PathBuf>,
+}
+
+impl CleanupPlan {
+    /// Whether there is nothing to remove
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.directories.is_empty()
+    }
+}
+
+/// Public interface for cleanup operations
+pub struct Cleanup;
+
+impl Cleanup {
+    /// Find the empty journals and directories in the Knowledge Base
+    ///
+    /// Only directories in `created`, the ones earlier runs created, are
+    /// considered. They are listed when they are empty or only hold other
+    /// listed entries. The Knowledge Base, pages, destination and journals
+    /// directories are never listed, and nothing is planned at all when the
+    /// pages directory is the Knowledge Base itself.
+    pub fn plan(config: &Config, created: &[PathBuf]) -> Result<CleanupPlan, CleanupError> {
+        let knowledge_base = config.expanded_knowledge_base_path().to_path_buf();
+        let pages_dir = config.knowledge_base_subdir(&config.pages_dir_name);
+        if pages_dir == knowledge_base {
+            return Err(CleanupError::PagesDirIsKnowledgeBase(knowledge_base));
+        }
+
+        let mut plan = CleanupPlan::default();
+        let journals_dir = config.knowledge_base_subdir(&config.journals_dir_name);
+
+        if journals_dir.is_dir() {
+            for entry in fs::read_dir(&journals_dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if entry.file_type()?.is_file()
+                    && JournalManager::is_journal_file(&path, &config.journal_file_format)
+                    && entry.metadata()?.len() == 0
+                {
+                    plan.files.push(path);
+                }
+            }
+            plan.files.sort();
+        }
+
+        let roots = [
+            knowledge_base.clone(),
+            pages_dir,
+            config.knowledge_base_subdir(config.destination_dir_name()),
+            journals_dir,
+        ];
+        let mut candidates: Vec<&PathBuf> = created
+            .iter()
+            .filter(|directory| directory.starts_with(&knowledge_base))
+            .filter(|directory| !roots.contains(directory))
+            .collect();
+        // Deepest first, so children are decided before their parents
+        candidates.sort_by(|a, b| {
+            b.components()
+                .count()
+                .cmp(&a.components().count())
+                .then_with(|| a.cmp(b))
+        });
+        candidates.dedup();
+
+        for directory in candidates {
+            if directory.is_dir() && Self::holds_only(directory, &plan)? {
+                plan.directories.push(directory.clone());
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Remove everything in `plan`, returning what was actually removed
+    ///
+    /// Each entry is checked again right before removal: files that gained
+    /// content and directories that are no longer empty are kept.
+    pub fn execute(plan: &CleanupPlan) -> Result<CleanupPlan, CleanupError> {
+        let mut removed = CleanupPlan::default();
+
+        for file in &plan.files {
+            if fs::metadata(file).is_ok_and(|metadata| metadata.is_file() && metadata.len() == 0) {
+                fs::remove_file(file)?;
+                removed.files.push(file.clone());
+            }
+        }
+
+        for directory in &plan.directories {
+            // remove_dir refuses directories that are not empty
+            if fs::remove_dir(directory).is_ok() {
+                removed.directories.push(directory.clone());
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Whether every entry of `directory` is already listed in `plan`
+    fn holds_only(directory: &Path, plan: &CleanupPlan) -> Result<bool, CleanupError> {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if !plan.files.contains(&path) && !plan.directories.contains(&path) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_test_config(kb_path: &Path) -> Config {
+        Config {
+            knowledge_base_path: kb_path.display().to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_lists_empty_journals_and_created_directories() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(temp_dir.path());
+        let journals = temp_dir.path().join("journals");
+        let pages = temp_dir.path().join("pages");
+        fs::create_dir_all(&journals).unwrap();
+        fs::create_dir_all(pages.join("2024/03")).unwrap();
+        fs::write(journals.join("2024_03_01.md"), "").unwrap();
+        fs::write(journals.join("2024_03_02.md"), "- [[article]]\n").unwrap();
+        fs::write(journals.join("notes.md"), "").unwrap();
+        let created = vec![pages.join("2024/03"), pages.join("2024"), pages.clone()];
+
+        let plan = Cleanup::plan(&config, &created).unwrap();
+
+        assert_eq!(plan.files, vec![journals.join("2024_03_01.md")]);
+        assert_eq!(
+            plan.directories,
+            vec![pages.join("2024/03"), pages.join("2024")]
+        );
+    }
+
+    #[test]
+    fn test_plan_keeps_empty_directories_the_tool_did_not_create() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(temp_dir.path());
+        let pages = temp_dir.path().join("pages");
+        fs::create_dir_all(pages.join("drafts")).unwrap();
+        fs::create_dir_all(pages.join("2024/03")).unwrap();
+
+        let plan = Cleanup::plan(&config, &[pages.join("2024/03")]).unwrap();
+
+        assert_eq!(plan.directories, vec![pages.join("2024/03")]);
+    }
+
+    #[test]
+    fn test_execute_removes_empty_journal_and_keeps_content() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(temp_dir.path());
+        let journals = temp_dir.path().join("journals");
+        fs::create_dir_all(&journals).unwrap();
+        fs::create_dir_all(temp_dir.path().join("pages")).unwrap();
+        fs::write(journals.join("2024_03_01.md"), "").unwrap();
+        fs::write(journals.join("2024_03_02.md"), "- [[article]]\n").unwrap();
+
+        let removed = Cleanup::execute(&Cleanup::plan(&config, &[]).unwrap()).unwrap();
+
+        assert_eq!(removed.files, vec![journals.join("2024_03_01.md")]);
+        assert!(removed.directories.is_empty());
+        assert!(!journals.join("2024_03_01.md").exists());
+        assert_eq!(
+            fs::read_to_string(journals.join("2024_03_02.md")).unwrap(),
+            "- [[article]]\n"
+        );
+        assert!(journals.is_dir());
+        assert!(temp_dir.path().join("pages").is_dir());
+    }
+
+    #[test]
+    fn test_roots_are_kept_even_when_created_and_empty() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(temp_dir.path());
+        let journals = temp_dir.path().join("journals");
+        let pages = temp_dir.path().join("pages");
+        fs::create_dir_all(&journals).unwrap();
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(journals.join("2024_03_01.md"), "").unwrap();
+        let created = vec![
+            temp_dir.path().to_path_buf(),
+            pages.clone(),
+            journals.clone(),
+        ];
+
+        let removed = Cleanup::execute(&Cleanup::plan(&config, &created).unwrap()).unwrap();
+
+        assert!(removed.directories.is_empty());
+        assert!(journals.is_dir());
+        assert!(pages.is_dir());
+        assert!(temp_dir.path().exists());
+    }
+
+    #[test]
+    fn test_plan_refuses_when_pages_is_the_knowledge_base() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            pages_dir_name: ".".to_string(),
+            ..create_test_config(temp_dir.path())
+        };
+        let folder = temp_dir.path().join("Projects");
+        fs::create_dir_all(&folder).unwrap();
+
+        let result = Cleanup::plan(&config, std::slice::from_ref(&folder));
+
+        assert!(matches!(
+            result,
+            Err(CleanupError::PagesDirIsKnowledgeBase(_))
+        ));
+        assert!(folder.is_dir());
+        assert!(temp_dir.path().exists());
+    }
+
+    #[test]
+    fn test_execute_keeps_entries_that_changed_since_planning() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(temp_dir.path());
+        let pages = temp_dir.path().join("pages");
+        let journals = temp_dir.path().join("journals");
+        fs::create_dir_all(pages.join("2024")).unwrap();
+        fs::create_dir_all(&journals).unwrap();
+        fs::write(journals.join("2024_03_01.md"), "").unwrap();
+
+        let plan = Cleanup::plan(&config, &[pages.join("2024")]).unwrap();
+        fs::write(pages.join("2024/new.md"), "# New").unwrap();
+        fs::write(journals.join("2024_03_01.md"), "- [[new]]\n").unwrap();
+        let removed = Cleanup::execute(&plan).unwrap();
+
+        assert!(removed.is_empty());
+        assert!(pages.join("2024/new.md").exists());
+        assert!(journals.join("2024_03_01.md").exists());
+    }
+
+    #[test]
+    fn test_hidden_entries_keep_directories() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(temp_dir.path());
+        let pages = temp_dir.path().join("pages");
+        fs::create_dir_all(pages.join("2024/.obsidian")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("journals")).unwrap();
+        fs::write(temp_dir.path().join("journals/.keep"), "").unwrap();
+
+        let plan = Cleanup::plan(&config, &[pages.join("2024")]).unwrap();
+
+        assert!(plan.is_empty());
+    }
+}
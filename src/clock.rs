@@ -0,0 +1,34 @@
+//! The current time behind a trait, so time-dependent behavior can be tested
+//!
+//! Journal timestamps, the journal day (including the `day_start_hour`
+//! rollover), the `max_age_days` filter and the ledger timestamps read the
+//! time from a [`Clock`]. Production code uses [`SystemClock`]; tests pin
+//! the time with a [`FixedClock`].
+
+use chrono::{DateTime, Local};
+
+/// Source of the current time
+pub trait Clock: Sync {
+    /// The current time
+    fn now(&self) -> DateTime<Local>;
+}
+
+/// The system clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// A clock that always reads the same time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub DateTime<Local>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+}
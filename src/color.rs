@@ -0,0 +1,62 @@
+//! Colored terminal output, chosen with `--color`
+//!
+//! Moved files and other successes are green, skipped files and warnings
+//! yellow, and failures red. With [`ColorChoice::Auto`] colors are only used
+//! when the stream is a terminal and `NO_COLOR` is not set.
+
+use std::fmt::Display;
+
+use owo_colors::{OwoColorize, Stream};
+
+/// When output is colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color output to terminals unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    /// Always color output, even when redirected
+    Always,
+    /// Never color output
+    Never,
+}
+
+impl ColorChoice {
+    /// Use this choice for all output of the process
+    pub fn apply(self) {
+        match self {
+            ColorChoice::Auto => owo_colors::unset_override(),
+            ColorChoice::Always => owo_colors::set_override(true),
+            ColorChoice::Never => owo_colors::set_override(false),
+        }
+    }
+}
+
+/// Text for stdout reporting something that worked, in green
+pub fn success(text: impl Display) -> String {
+    text.if_supports_color(Stream::Stdout, |text| text.green())
+        .to_string()
+}
+
+/// Text for stdout reporting something left undone, in yellow
+pub fn warning(text: impl Display) -> String {
+    text.if_supports_color(Stream::Stdout, |text| text.yellow())
+        .to_string()
+}
+
+/// Like [`warning`], for text printed to stderr
+pub fn warning_stderr(text: impl Display) -> String {
+    text.if_supports_color(Stream::Stderr, |text| text.yellow())
+        .to_string()
+}
+
+/// Text for stdout reporting a failure, in red
+pub fn failure(text: impl Display) -> String {
+    text.if_supports_color(Stream::Stdout, |text| text.red())
+        .to_string()
+}
+
+/// Like [`failure`], for text printed to stderr
+pub fn failure_stderr(text: impl Display) -> String {
+    text.if_supports_color(Stream::Stderr, |text| text.red())
+        .to_string()
+}
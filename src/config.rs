@@ -1,18 +1,563 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::file_discovery::FileDiscovery;
+use crate::paths;
+use crate::prompt::Prompt;
+
+/// Current configuration schema version
+pub const CURRENT_CONFIG_VERSION: u32 = 1;
+
+/// Configuration path (`--config -`) that reads the configuration from stdin
+pub const STDIN_CONFIG_PATH: &str = "-";
+
+/// Default `link_format`: a Logseq wikilink
+pub const DEFAULT_LINK_FORMAT: &str = "[[{name}]]";
+
+/// Default `attachment_link_format`: an embed of the attachment
+pub const DEFAULT_ATTACHMENT_LINK_FORMAT: &str = "![[{name}]]";
+
+/// Key of a YAML configuration file naming another file whose keys it
+/// overrides, relative to the including file
+pub const INCLUDE_KEY: &str = "include";
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
 pub struct Config {
+    /// Schema version of the configuration file (0 when absent)
+    #[serde(default)]
+    pub version: u32,
     pub knowledge_base_path: String,
+    /// Descend into subdirectories of the scanned directory
+    pub recursive: bool,
+    /// Maximum subdirectory depth for recursive discovery (unlimited when unset)
+    pub max_depth: Option<usize>,
+    /// Import symlinked files; when false they are skipped and reported
+    pub follow_symlinks: bool,
+    /// Glob patterns matched against file names to exclude from discovery
+    pub exclude_patterns: Vec<String>,
+    /// Match `exclude_patterns` without regard to case
+    pub exclude_case_insensitive: bool,
+    /// Skip files smaller than this many bytes (0 disables the filter)
+    pub min_file_size_bytes: u64,
+    /// Skip files larger than this many bytes instead of moving them (unlimited when unset)
+    pub max_file_size_bytes: Option<u64>,
+    /// Only import files modified within this many days
+    pub max_age_days: Option<u64>,
+    /// Only import files modified on or after this day (`YYYY-MM-DD`, local time)
+    pub modified_since: Option<String>,
+    /// Only import files modified on or before this day (`YYYY-MM-DD`, local time)
+    pub modified_until: Option<String>,
+    /// Treat a journal entry as a duplicate when today's journal already links
+    /// the same file, regardless of timestamp
+    pub journal_dedupe_ignore_timestamp: bool,
+    /// Tags appended to every journal entry (e.g. `import` renders as `#import`)
+    pub journal_tags: Vec<String>,
+    /// Text shown after the link of every journal entry; `{words}` is the
+    /// page's word count and `{size}` its size, e.g. `({words} words)`
+    pub journal_entry_details: Option<String>,
+    /// Add a nested journal entry linking to each `## ` heading of the
+    /// imported page, e.g. `  - [[name#Section]]`
+    pub journal_heading_entries: bool,
+    /// Put every file of a run back where it came from when the journal
+    /// entries cannot be written, so a run imports everything or nothing
+    pub transactional: bool,
+    /// Write page properties at the top of imported pages
+    pub add_page_properties: bool,
+    /// Page properties to write; values may use `{date}` and `{source}` placeholders
+    pub page_properties: BTreeMap<String, String>,
+    /// Order in which discovered files are moved and journaled
+    pub process_order: ProcessOrder,
+    /// Subdirectory of the Knowledge Base that imported files are moved into
+    /// (defaults to `pages_dir_name`)
+    pub destination_subdir: Option<String>,
+    /// Place imported files in dated subdirectories of the destination
+    pub organize_by_date: bool,
+    /// chrono format for the dated subdirectories, applied to the file's
+    /// modification time
+    pub date_subdir_pattern: String,
+    /// File extensions imported by discovery, compared without regard to case
+    pub extensions: Vec<String>,
+    /// Multi-part extensions such as `excalidraw.md` that make a file its own
+    /// kind rather than a regular page
+    pub compound_extensions: Vec<String>,
+    /// Names of sibling folders moved along with a page, such as
+    /// `{stem}.assets` or `{stem}_files`; `{stem}` is the page's file stem
+    pub asset_folder_patterns: Vec<String>,
+    /// Extensions of attachments such as `pdf` that discovery imports too;
+    /// they are moved into `assets_dir_name` as they are and journaled with
+    /// `attachment_link_format`
+    pub attachment_extensions: Vec<String>,
+    /// Subdirectory of the Knowledge Base that attachments are moved into
+    pub assets_dir_name: String,
+    /// Template for journal entry links to attachments: `{name}` is the file
+    /// name with its extension, `{path}` the path relative to the Knowledge Base
+    pub attachment_link_format: String,
+    /// Skip files whose size changes within this many milliseconds (0 disables the check)
+    pub stability_check_ms: u64,
+    /// Link journal entries by the page path relative to `pages` (e.g.
+    /// `[[projects/foo]]`) instead of the bare file name
+    pub namespaced_links: bool,
+    /// What journal entry links point at; `RelativePath` takes precedence
+    /// over `namespaced_links`
+    pub link_style: LinkStyle,
+    /// Template for journal entry links: `{name}` is the link name chosen by
+    /// `link_style`, `{path}` the page path relative to the Knowledge Base
+    pub link_format: String,
+    /// Rewrite links to a file's original name in recent journals when the
+    /// import gives it a different name
+    pub rewrite_links: bool,
+    /// How many days of journals, today included, `rewrite_links` searches
+    pub rewrite_links_days: u32,
+    /// Heading in the daily journal to insert entries under (e.g. `## Imported`);
+    /// entries are appended to the end of the file when unset. Ignored with
+    /// `journal_mode: SingleFile`
+    pub journal_section: Option<String>,
+    /// Layout of the Knowledge Base; `Auto` detects it from the `.obsidian`
+    /// or `logseq` directory, and explicitly set layout options win
+    pub vault_type: VaultType,
+    /// Name of the pages directory inside the Knowledge Base (`.` for the
+    /// Knowledge Base itself, as in Obsidian vaults)
+    pub pages_dir_name: String,
+    /// Name of the journals directory inside the Knowledge Base
+    pub journals_dir_name: String,
+    /// chrono format of daily journal file names, without the `.md` extension
+    pub journal_file_format: String,
+    /// Whether entries go to one journal file per day or under a
+    /// `## YYYY-MM-DD` heading of a single `journal_file`
+    pub journal_mode: JournalMode,
+    /// Name of the journal file inside the journals directory when
+    /// `journal_mode` is `SingleFile`
+    pub journal_file: String,
+    /// Allow `pages_dir_name` and `journals_dir_name` to contain path separators
+    pub allow_nested_dir_names: bool,
+    /// Show the time of each import in journal entries
+    pub include_timestamp: bool,
+    /// Render the journal entry timestamp in bold (`**14:30**`)
+    pub bold_timestamp: bool,
+    /// Number of files moved concurrently (1 moves files one at a time)
+    pub parallel_moves: usize,
+    /// Maximum number of files processed per run, taken in `process_order`
+    /// (unset processes every file)
+    pub limit: Option<usize>,
+    /// Fail the run when the source has no files to import, instead of
+    /// finishing successfully
+    pub fail_on_empty: bool,
+    /// Copy the journal to `<journal>.bak` before a run modifies it
+    pub journal_backup: bool,
+    /// Line ending of the lines written to journals
+    pub line_ending: LineEnding,
+    /// Rename imported files with any other allowed extension to `.md`
+    pub normalize_extension: bool,
+    /// Skip files whose content matches any existing page, whatever its name
+    pub dedupe_by_content: bool,
+    /// Delete the source of a file skipped by `dedupe_by_content`
+    pub dedupe_delete_source: bool,
+    /// Largest page, in bytes, hashed for `dedupe_by_content`
+    pub dedupe_max_file_size_bytes: u64,
+    /// How handled source files are marked so later runs skip them
+    pub processed_marker: ProcessedMarker,
+    /// Add journal entries for moved files
+    pub write_journal: bool,
+    /// Append the SHA-256 and path of every imported page to `manifest.csv`
+    /// in the Knowledge Base
+    pub write_manifest: bool,
+    /// Shell command run for every moved file, with `LOCAL_SHELF_SOURCE` and
+    /// `LOCAL_SHELF_DEST` set to its original and new path
+    pub per_file_hook: Option<String>,
+    /// IANA time zone for journal timestamps and daily-note dates, e.g.
+    /// `Europe/Berlin` (unset uses the system time zone)
+    pub timezone: Option<String>,
+    /// Hour (0-23) at which a new journal day starts; imports before it go
+    /// into the previous day's journal
+    pub day_start_hour: u32,
+    /// Normalize file names and journal links to Unicode NFC and strip
+    /// zero-width and control characters
+    pub normalize_unicode: bool,
+    /// Replace characters that are illegal in file names on some OS, trim
+    /// trailing dots and spaces and cap the name length
+    pub sanitize_filenames: bool,
+    /// Substitute for illegal characters when `sanitize_filenames` is enabled
+    pub filename_replacement: String,
+    /// Maximum destination file name length in bytes, before any collision suffix
+    pub max_filename_length: usize,
+    /// Maximum number of suffixed names tried when a destination name is taken
+    pub max_collision_attempts: usize,
+    /// Extra attempts for a move that fails with a transient IO error
+    /// (interrupted, busy, would block or timed out)
+    pub io_retries: u32,
+    /// Delay before the first retry in milliseconds, doubled on each further retry
+    pub io_retry_delay_ms: u64,
+    /// Where to record the absolute path each file was imported from
+    pub record_source_path: SourcePathAnnotation,
+    /// Case applied to the stem of every destination file name
+    pub filename_case: FilenameCase,
+    /// Text added before the stem of every destination file name
+    pub filename_prefix: String,
+    /// Text added after the stem of every destination file name, before the extension
+    pub filename_suffix: String,
+    /// Reject configuration files with unknown keys instead of warning about them
+    pub strict_config: bool,
+    /// Rules sending matching files to other subdirectories of the Knowledge
+    /// Base, tried in order; files matching none go to the destination directory
+    pub routing: Vec<RouteRule>,
+    /// How a file copied across filesystems is compared with its source
+    /// before the source is deleted
+    pub integrity_hash: IntegrityHash,
+    /// `knowledge_base_path` as expanded on first use, together with the
+    /// path it was expanded from
+    #[serde(skip)]
+    pub expanded_path_cache: ExpandedPath,
+}
+
+/// Memoized expansion of `knowledge_base_path`
+///
+/// Not part of the configuration's value: it is never serialized and every
+/// two caches compare equal.
+#[derive(Debug, Default, Clone)]
+pub struct ExpandedPath(OnceLock<(String, PathBuf)>);
+
+impl PartialEq for ExpandedPath {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// Order in which discovered files are processed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessOrder {
+    /// Alphabetical by file path
+    #[default]
+    NameAsc,
+    /// Oldest modification time first
+    ModifiedAsc,
+    /// Newest modification time first
+    ModifiedDesc,
+    /// Largest file first
+    SizeDesc,
+}
+
+/// Where the original location of an imported file is recorded
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourcePathAnnotation {
+    /// The source path is not recorded
+    #[default]
+    Off,
+    /// A `source-path` property at the top of the moved page
+    PageProperty,
+    /// A trailing `(from /path/to/file.md)` note on the journal entry
+    Journal,
+}
+
+/// Check that a copied file matches its source before the source is deleted
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegrityHash {
+    /// Compare SHA-256 digests of both files
+    #[default]
+    Sha256,
+    /// Compare XXH3 digests, much faster but not cryptographic
+    XxHash,
+    /// Only compare file sizes
+    SizeOnly,
+}
+
+/// How journal entries are laid out on disk
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JournalMode {
+    /// One journal file per day, named with `journal_file_format`
+    #[default]
+    PerDay,
+    /// One `journal_file` with a `## YYYY-MM-DD` heading per day
+    SingleFile,
+}
+
+/// Line ending used for the lines written to journals
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    Crlf,
+    /// The ending most lines of the existing journal use, or the platform's
+    /// own for a new journal
+    Native,
+}
+
+impl LineEnding {
+    /// The newline sequence to use for a journal currently holding `existing`
+    pub fn resolve(self, existing: &str) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Native => {
+                let crlf = existing.matches("\r\n").count();
+                let lf = existing.matches('\n').count() - crlf;
+                if crlf > lf || (lf == 0 && crlf == 0 && cfg!(windows)) {
+                    "\r\n"
+                } else {
+                    "\n"
+                }
+            }
+        }
+    }
+}
+
+/// What the `[[...]]` link of a journal entry points at
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkStyle {
+    /// The page's file name without extension (`[[article]]`), or its
+    /// namespace with `namespaced_links`
+    #[default]
+    BasenameStem,
+    /// The page's path relative to the Knowledge Base without extension
+    /// (`[[pages/projects/article]]`), as Obsidian needs for ambiguous names
+    RelativePath,
+}
+
+/// How source files are marked once handled, so later runs skip them
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessedMarker {
+    /// Nothing is marked
+    #[default]
+    Off,
+    /// Files left in the source directory are renamed to `<name>.done`
+    DoneSuffix,
+    /// Files left in the source directory are moved into a `processed`
+    /// subfolder next to them
+    ProcessedFolder,
+    /// The path and content hash of every handled file is recorded in a
+    /// seen-set file, and files matching an entry are skipped
+    SeenSet,
+}
+
+/// How the stem of an imported file name is cased
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilenameCase {
+    /// Keep the name as downloaded (`My Article Title.md`)
+    #[default]
+    Preserve,
+    /// Lowercase the name (`my article title.md`)
+    Lower,
+    /// Lowercase words joined by hyphens (`my-article-title.md`)
+    Kebab,
+    /// Lowercase words joined by underscores (`my_article_title.md`)
+    Snake,
+}
+
+/// Note-taking app whose directory layout the Knowledge Base follows
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VaultType {
+    /// Obsidian when the Knowledge Base has a `.obsidian` directory, Logseq
+    /// otherwise
+    #[default]
+    Auto,
+    /// `pages` and `journals` directories, journals named `YYYY_MM_DD.md`
+    Logseq,
+    /// Pages and daily notes in the vault root, daily notes named `YYYY-MM-DD.md`
+    Obsidian,
+}
+
+impl VaultType {
+    /// `pages_dir_name`, `journals_dir_name` and `journal_file_format` for
+    /// this vault type
+    fn layout(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            VaultType::Obsidian => (".", ".", "%Y-%m-%d"),
+            VaultType::Auto | VaultType::Logseq => ("pages", "journals", "%Y_%m_%d"),
+        }
+    }
+}
+
+/// Destination for files matching a pattern, overriding `destination_subdir`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RouteRule {
+    /// Extension such as `excalidraw.md` (compared without regard to case), or
+    /// a glob such as `*-draft.md` matched against the file name
+    pub pattern: String,
+    /// Subdirectory of the Knowledge Base that matching files are moved into
+    pub destination: String,
+    /// Add journal entries for files moved by this rule
+    #[serde(default = "RouteRule::default_journal")]
+    pub journal: bool,
+}
+
+impl RouteRule {
+    fn default_journal() -> bool {
+        true
+    }
+
+    /// Whether the pattern contains glob syntax rather than naming an extension
+    fn is_glob(&self) -> bool {
+        self.pattern.contains(['*', '?', '['])
+    }
+
+    /// Whether a file with this name is routed by the rule
+    pub fn matches(&self, file_name: &str) -> bool {
+        if self.is_glob() {
+            return glob::Pattern::new(&self.pattern)
+                .is_ok_and(|pattern| pattern.matches(file_name));
+        }
+
+        FileDiscovery::ends_with_extension(file_name, &self.pattern)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            version: CURRENT_CONFIG_VERSION,
             knowledge_base_path: "~/Knowledge Base".to_string(),
+            recursive: false,
+            max_depth: None,
+            follow_symlinks: true,
+            exclude_patterns: Vec::new(),
+            exclude_case_insensitive: false,
+            min_file_size_bytes: 0,
+            max_file_size_bytes: None,
+            max_age_days: None,
+            modified_since: None,
+            modified_until: None,
+            journal_dedupe_ignore_timestamp: false,
+            journal_tags: Vec::new(),
+            journal_entry_details: None,
+            journal_heading_entries: false,
+            transactional: false,
+            add_page_properties: false,
+            page_properties: BTreeMap::from([
+                ("import-date".to_string(), "{date}".to_string()),
+                ("source".to_string(), "{source}".to_string()),
+            ]),
+            process_order: ProcessOrder::default(),
+            destination_subdir: None,
+            organize_by_date: false,
+            date_subdir_pattern: "%Y/%m".to_string(),
+            extensions: vec!["md".to_string()],
+            compound_extensions: vec!["excalidraw.md".to_string()],
+            asset_folder_patterns: Vec::new(),
+            attachment_extensions: Vec::new(),
+            assets_dir_name: "assets".to_string(),
+            attachment_link_format: DEFAULT_ATTACHMENT_LINK_FORMAT.to_string(),
+            stability_check_ms: 0,
+            namespaced_links: false,
+            link_style: LinkStyle::BasenameStem,
+            link_format: DEFAULT_LINK_FORMAT.to_string(),
+            rewrite_links: false,
+            rewrite_links_days: 7,
+            journal_section: None,
+            vault_type: VaultType::Auto,
+            pages_dir_name: "pages".to_string(),
+            journals_dir_name: "journals".to_string(),
+            journal_file_format: "%Y_%m_%d".to_string(),
+            journal_mode: JournalMode::PerDay,
+            journal_file: "journal.md".to_string(),
+            allow_nested_dir_names: false,
+            include_timestamp: true,
+            bold_timestamp: true,
+            parallel_moves: 4,
+            limit: None,
+            fail_on_empty: false,
+            journal_backup: false,
+            line_ending: LineEnding::Lf,
+            normalize_extension: false,
+            dedupe_by_content: false,
+            dedupe_delete_source: false,
+            dedupe_max_file_size_bytes: 10 * 1024 * 1024,
+            processed_marker: ProcessedMarker::Off,
+            write_journal: true,
+            write_manifest: false,
+            per_file_hook: None,
+            timezone: None,
+            day_start_hour: 0,
+            normalize_unicode: false,
+            sanitize_filenames: true,
+            filename_replacement: "-".to_string(),
+            max_filename_length: 200,
+            max_collision_attempts: 1000,
+            io_retries: 3,
+            io_retry_delay_ms: 100,
+            record_source_path: SourcePathAnnotation::Off,
+            filename_prefix: String::new(),
+            filename_suffix: String::new(),
+            filename_case: FilenameCase::Preserve,
+            strict_config: false,
+            routing: Vec::new(),
+            integrity_hash: IntegrityHash::Sha256,
+            expanded_path_cache: ExpandedPath::default(),
+        }
+    }
+}
+
+/// Where a resolved configuration value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    File,
+    Environment,
+    /// Given on the command line for this run
+    CommandLine,
+    /// Picked for the vault type detected by `vault_type: Auto`
+    Detected,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File => write!(f, "file"),
+            ConfigSource::Environment => write!(f, "env"),
+            ConfigSource::CommandLine => write!(f, "cli"),
+            ConfigSource::Detected => write!(f, "detected"),
+        }
+    }
+}
+
+/// A loaded configuration along with the file it was read from and the
+/// source of each value
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    pub config: Config,
+    pub config_path: PathBuf,
+    pub sources: BTreeMap<String, ConfigSource>,
+}
+
+impl ResolvedConfig {
+    /// Get the source of a configuration key
+    pub fn source_of(&self, key: &str) -> ConfigSource {
+        self.sources
+            .get(key)
+            .copied()
+            .unwrap_or(ConfigSource::Default)
+    }
+
+    /// Render every resolved value as `key: value (source)`, one per line,
+    /// sorted by key. Paths also show their expanded form.
+    pub fn render(&self) -> String {
+        let values = serde_json::to_value(&self.config).unwrap_or_default();
+        let mut output = String::new();
+
+        if let serde_json::Value::Object(map) = values {
+            for (key, value) in map {
+                let mut rendered = value.to_string();
+                if key == "knowledge_base_path" {
+                    rendered = format!("{} -> {}", rendered, self.config.get_knowledge_base_path());
+                }
+                output.push_str(&format!(
+                    "{}: {} ({})\n",
+                    key,
+                    rendered,
+                    self.source_of(&key)
+                ));
+            }
         }
+
+        output
     }
 }
 
@@ -20,7 +565,18 @@ impl Default for Config {
 pub enum ConfigError {
     IoError(std::io::Error),
     YamlError(serde_yaml::Error),
+    TomlError(toml::de::Error),
     ValidationError(String),
+    UnsupportedVersion(u32),
+    UnknownKeys(Vec<String>),
+    /// A file was reached again while following `include` keys
+    IncludeCycle(PathBuf),
+    MissingInclude {
+        path: PathBuf,
+        included_from: PathBuf,
+    },
+    /// The Knowledge Base given on the command line is not a directory
+    KnowledgeBaseNotFound(String),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -28,7 +584,37 @@ impl std::fmt::Display for ConfigError {
         match self {
             ConfigError::IoError(e) => write!(f, "IO error: {}", e),
             ConfigError::YamlError(e) => write!(f, "YAML error: {}", e),
+            ConfigError::TomlError(e) => write!(f, "TOML error: {}", e),
             ConfigError::ValidationError(msg) => write!(f, "Validation error: {}", msg),
+            ConfigError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported config version {} (this version of local_shelf supports up to {})",
+                version, CURRENT_CONFIG_VERSION
+            ),
+            ConfigError::UnknownKeys(keys) => write!(
+                f,
+                "Unknown configuration key(s): {} (set strict_config: false to only warn)",
+                keys.join(", ")
+            ),
+            ConfigError::IncludeCycle(path) => write!(
+                f,
+                "Configuration include cycle: {} includes itself",
+                path.display()
+            ),
+            ConfigError::MissingInclude {
+                path,
+                included_from,
+            } => write!(
+                f,
+                "Included configuration file not found: {} (included from {})",
+                path.display(),
+                included_from.display()
+            ),
+            ConfigError::KnowledgeBaseNotFound(path) => write!(
+                f,
+                "Knowledge Base '{}' does not exist (a misspelled command is read as a Knowledge Base path)",
+                path
+            ),
         }
     }
 }
@@ -47,241 +633,2589 @@ impl From<serde_yaml::Error> for ConfigError {
     }
 }
 
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        ConfigError::TomlError(error)
+    }
+}
+
 impl Config {
     /// Get the configuration directory path
+    ///
+    /// `$XDG_CONFIG_HOME/local_shelf` when `XDG_CONFIG_HOME` is set to an
+    /// absolute path, otherwise `local_shelf` in the platform configuration
+    /// directory (`~/.config` on Linux).
     pub fn config_dir() -> Result<PathBuf, ConfigError> {
-        dirs::config_dir()
-            .map(|mut path| {
-                path.push("local_shelf");
-                path
-            })
-            .ok_or_else(|| {
-                ConfigError::ValidationError("Unable to determine config directory".to_string())
-            })
+        Ok(Self::config_base_dir()?.join("local_shelf"))
     }
 
-    /// Get the legacy configuration directory path  
+    /// Get the legacy configuration directory path, next to [`Self::config_dir`]
     pub fn legacy_config_dir() -> Result<PathBuf, ConfigError> {
-        dirs::config_dir()
-            .map(|mut path| {
-                path.push("local-shelf");
-                path
-            })
-            .ok_or_else(|| {
+        Ok(Self::config_base_dir()?.join("local-shelf"))
+    }
+
+    /// Directory holding per-application configuration directories
+    ///
+    /// A relative or empty `XDG_CONFIG_HOME` is ignored, as the XDG Base
+    /// Directory specification requires.
+    fn config_base_dir() -> Result<PathBuf, ConfigError> {
+        match env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+            Some(path) if path.is_absolute() => Ok(path),
+            _ => dirs::config_dir().ok_or_else(|| {
                 ConfigError::ValidationError("Unable to determine config directory".to_string())
-            })
+            }),
+        }
     }
 
     /// Migrate configuration from legacy directory if needed
     pub fn migrate_from_legacy() -> Result<bool, ConfigError> {
-        let legacy_dir = Self::legacy_config_dir()?;
-        let new_dir = Self::config_dir()?;
+        Self::migrate_directory(&Self::legacy_config_dir()?, &Self::config_dir()?)
+    }
+
+    /// Whether `LOCAL_SHELF_NO_MIGRATE` asks to skip the legacy migration
+    fn migration_disabled_by_env() -> bool {
+        env::var("LOCAL_SHELF_NO_MIGRATE")
+            .map(|value| !matches!(value.trim(), "" | "0" | "false"))
+            .unwrap_or(false)
+    }
+
+    /// Migrate the legacy directory when `enabled`, reporting what happened
+    ///
+    /// When migration is disabled neither directory is touched; a note is
+    /// printed if a legacy directory is present.
+    fn run_legacy_migration(
+        legacy_dir: &Path,
+        new_dir: &Path,
+        enabled: bool,
+    ) -> Result<(), ConfigError> {
+        if !enabled {
+            if legacy_dir.exists() {
+                eprintln!(
+                    "Note: legacy configuration in {} was left in place because migration is disabled",
+                    legacy_dir.display()
+                );
+            }
+            return Ok(());
+        }
+
+        if Self::migrate_directory(legacy_dir, new_dir)? {
+            eprintln!(
+                "Configuration migrated from {} to {}",
+                legacy_dir.display(),
+                new_dir.display()
+            );
+        }
+        Ok(())
+    }
 
-        // Check if migration is needed
-        if legacy_dir.exists() && !new_dir.exists() {
-            // Create new directory
-            fs::create_dir_all(&new_dir)?;
+    /// Move the configuration from `legacy_dir` to `new_dir` as one step
+    ///
+    /// The legacy directory is removed only after the copied config has been
+    /// verified. If any step fails, the partially created `new_dir` is removed,
+    /// the legacy directory is left untouched and the error is returned.
+    fn migrate_directory(legacy_dir: &Path, new_dir: &Path) -> Result<bool, ConfigError> {
+        if !legacy_dir.exists() || new_dir.exists() {
+            return Ok(false); // No migration needed
+        }
 
-            // Copy config file if it exists
-            let legacy_config = legacy_dir.join("config.yaml");
-            let new_config = new_dir.join("config.yaml");
+        let legacy_config = legacy_dir.join("config.yaml");
+        let had_config = legacy_config.exists();
+        let result = Self::copy_legacy_config(legacy_dir, new_dir)
+            .and_then(|_| fs::remove_dir_all(legacy_dir).map_err(ConfigError::from));
 
-            if legacy_config.exists() {
-                fs::copy(&legacy_config, &new_config)?;
+        if let Err(e) = result {
+            // Keep the new copy only if the legacy config is already gone
+            if !had_config || legacy_config.exists() {
+                let _ = fs::remove_dir_all(new_dir);
             }
+            return Err(e);
+        }
 
-            // Remove legacy directory after successful migration
-            fs::remove_dir_all(&legacy_dir)?;
+        Ok(true) // Migration performed
+    }
 
-            Ok(true) // Migration performed
-        } else {
-            Ok(false) // No migration needed
+    /// Copy the legacy config file into a newly created directory and verify it
+    fn copy_legacy_config(legacy_dir: &Path, new_dir: &Path) -> Result<(), ConfigError> {
+        fs::create_dir_all(new_dir)?;
+
+        let legacy_config = legacy_dir.join("config.yaml");
+        if !legacy_config.exists() {
+            return Ok(());
+        }
+
+        let new_config = new_dir.join("config.yaml");
+        fs::copy(&legacy_config, &new_config)?;
+        if fs::read(&legacy_config)? != fs::read(&new_config)? {
+            return Err(ConfigError::ValidationError(format!(
+                "Copied configuration {} does not match {}",
+                new_config.display(),
+                legacy_config.display()
+            )));
         }
+
+        Ok(())
     }
 
     /// Get the configuration file path
+    ///
+    /// Uses `config.yaml` unless only a `config.toml` is present.
     pub fn config_file_path() -> Result<PathBuf, ConfigError> {
-        let mut config_dir = Self::config_dir()?;
-        config_dir.push("config.yaml");
-        Ok(config_dir)
+        let config_dir = Self::config_dir()?;
+        let yaml_path = config_dir.join("config.yaml");
+        let toml_path = config_dir.join("config.toml");
+
+        if !yaml_path.exists() && toml_path.exists() {
+            Ok(toml_path)
+        } else {
+            Ok(yaml_path)
+        }
+    }
+
+    /// Resolve the configuration file to use
+    ///
+    /// This is the single place the location is decided. Precedence:
+    ///
+    /// 1. `LOCAL_SHELF_CONFIG` environment variable
+    /// 2. explicit override (`--config`)
+    /// 3. `$XDG_CONFIG_HOME/local_shelf`
+    /// 4. `local_shelf` in the platform configuration directory (`~/.config`
+    ///    on Linux)
+    ///
+    /// Steps 3 and 4 are [`Self::config_file_path`].
+    pub fn resolve_config_file_path(
+        config_override: Option<&Path>,
+    ) -> Result<PathBuf, ConfigError> {
+        if let Ok(path) = env::var("LOCAL_SHELF_CONFIG")
+            && !path.trim().is_empty()
+        {
+            return Ok(PathBuf::from(path));
+        }
+
+        match config_override {
+            Some(path) => Ok(path.to_path_buf()),
+            None => Self::config_file_path(),
+        }
     }
 
     /// Load configuration with hierarchy: defaults < config file < environment variables
     pub fn load() -> Result<Config, ConfigError> {
+        Self::load_from(&Self::resolve_config_file_path(None)?)
+    }
+
+    /// Load configuration from the file at `path`
+    ///
+    /// Runs the same hierarchy as [`Config::load`] (defaults < file <
+    /// environment variables, then validation) without looking up the
+    /// configuration directory. A missing file yields the defaults.
+    pub fn load_from(path: &Path) -> Result<Config, ConfigError> {
+        Self::resolve_file(path.to_path_buf(), None, false).map(|resolved| resolved.config)
+    }
+
+    /// Load configuration, reading the file from `config_override` when given
+    pub fn load_with_override(config_override: Option<&Path>) -> Result<Config, ConfigError> {
+        Self::resolve(config_override).map(|resolved| resolved.config)
+    }
+
+    /// Load configuration and record where each value came from
+    pub fn resolve(config_override: Option<&Path>) -> Result<ResolvedConfig, ConfigError> {
+        Self::resolve_with(config_override, false)
+    }
+
+    /// Load configuration like [`Config::resolve`], optionally forcing past
+    /// the checks [`Config::validate_with`] can skip
+    pub fn resolve_with(
+        config_override: Option<&Path>,
+        force: bool,
+    ) -> Result<ResolvedConfig, ConfigError> {
+        Self::resolve_for(config_override, None, force)
+    }
+
+    /// Load configuration like [`Config::resolve_with`], using the
+    /// Knowledge Base given on the command line when there is one
+    ///
+    /// `knowledge_base` takes precedence over the file and `KNOWLEDGE_BASE`,
+    /// and is expanded and validated like `knowledge_base_path`.
+    pub fn resolve_for(
+        config_override: Option<&Path>,
+        knowledge_base: Option<&str>,
+        force: bool,
+    ) -> Result<ResolvedConfig, ConfigError> {
+        Self::resolve_file(
+            Self::resolve_config_file_path(config_override)?,
+            knowledge_base,
+            force,
+        )
+    }
+
+    /// Load configuration from `config_path`, or from stdin for `-`
+    fn resolve_file(
+        config_path: PathBuf,
+        knowledge_base: Option<&str>,
+        force: bool,
+    ) -> Result<ResolvedConfig, ConfigError> {
+        if Self::is_stdin_path(&config_path) {
+            return Self::resolve_reader(std::io::stdin().lock(), knowledge_base, force);
+        }
+
         let mut config = Config::default();
+        let mut sources = BTreeMap::new();
 
         // Try to load from config file
-        let config_path = Self::config_file_path()?;
         if config_path.exists() {
-            let content = fs::read_to_string(&config_path)?;
-            config = serde_yaml::from_str(&content)?;
-        }
+            let (content, included) = Self::read_with_includes(&config_path)?;
+            config = Self::parse(&content, &config_path)?;
+            for key in Self::file_keys(&content, &config_path)? {
+                sources.insert(key, ConfigSource::File);
+            }
 
-        // Override with environment variables
-        if let Ok(kb_path) = env::var("KNOWLEDGE_BASE") {
-            config.knowledge_base_path = kb_path;
+            // Only the `version` line is written back, so the user's comments
+            // and keys left at their defaults stay as they are. Rewriting is
+            // best-effort: the upgrade is already in memory and is retried
+            // on the next load. The version of a file with includes may come
+            // from a shared file, so such files are only upgraded in memory.
+            if Self::migrate(&mut config)? && !included {
+                let _ = Self::write_version(&config_path);
+            }
         }
 
-        config.validate()?;
-        Ok(config)
+        Self::finish_resolve(config, config_path, sources, knowledge_base, force)
     }
 
-    /// Create default configuration file if it doesn't exist
-    pub fn initialize() -> Result<(), ConfigError> {
-        // Skip initialization during tests to avoid contaminating user config
-        if env::var("LOCAL_SHELF_SKIP_CONFIG_INIT").is_ok() {
-            return Ok(());
-        }
+    /// Load configuration from `reader` instead of a file
+    ///
+    /// The content may be YAML or TOML; it is read as YAML when it parses
+    /// as a YAML mapping and as TOML otherwise. Defaults and environment
+    /// variables apply as for a file, and an older schema version is
+    /// upgraded in memory only.
+    pub fn resolve_from_reader(
+        reader: impl Read,
+        force: bool,
+    ) -> Result<ResolvedConfig, ConfigError> {
+        Self::resolve_reader(reader, None, force)
+    }
 
-        // Try to migrate from legacy config first
-        let migrated = Self::migrate_from_legacy()?;
-        if migrated {
-            println!(
-                "Configuration migrated from ~/.config/local-shelf/ to ~/.config/local_shelf/"
-            );
-        }
+    /// Load configuration from `reader` with an optional command line Knowledge Base
+    fn resolve_reader(
+        mut reader: impl Read,
+        knowledge_base: Option<&str>,
+        force: bool,
+    ) -> Result<ResolvedConfig, ConfigError> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
 
-        let config_dir = Self::config_dir()?;
-        let config_path = Self::config_file_path()?;
+        let format_path = if serde_yaml::from_str::<serde_yaml::Mapping>(&content).is_ok() {
+            Path::new("stdin.yaml")
+        } else {
+            Path::new("stdin.toml")
+        };
+        let mut config = Self::parse(&content, format_path)?;
+        let sources = Self::file_keys(&content, format_path)?
+            .into_iter()
+            .map(|key| (key, ConfigSource::File))
+            .collect();
+        Self::migrate(&mut config)?;
 
-        // Create config directory if it doesn't exist
-        if !config_dir.exists() {
-            fs::create_dir_all(&config_dir)?;
-        }
+        Self::finish_resolve(
+            config,
+            PathBuf::from(STDIN_CONFIG_PATH),
+            sources,
+            knowledge_base,
+            force,
+        )
+    }
 
-        // Create default config file if it doesn't exist
-        if !config_path.exists() {
-            let default_config = Config::default();
-            let yaml_content = format!(
-                "# Local Shelf Configuration\n# \n# Knowledge Base path - where markdown files will be organized\n# Can be overridden with KNOWLEDGE_BASE environment variable\nknowledge_base_path: \"{}\"\n",
-                default_config.knowledge_base_path
+    /// Keys among `keys` that are not configuration options
+    ///
+    /// These are usually typos, or options added by a newer version.
+    pub fn unknown_keys<'a>(keys: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+        let known = serde_json::to_value(Config::default()).unwrap_or_default();
+        keys.into_iter()
+            .filter(|key| known.get(key.as_str()).is_none())
+            .cloned()
+            .collect()
+    }
+
+    /// Whether a configuration path asks for the configuration on stdin
+    pub fn is_stdin_path(path: &Path) -> bool {
+        path == Path::new(STDIN_CONFIG_PATH)
+    }
+
+    /// Apply environment and command line overrides to a loaded
+    /// configuration and validate it
+    fn finish_resolve(
+        mut config: Config,
+        config_path: PathBuf,
+        mut sources: BTreeMap<String, ConfigSource>,
+        knowledge_base: Option<&str>,
+        force: bool,
+    ) -> Result<ResolvedConfig, ConfigError> {
+        let unknown = Self::unknown_keys(sources.keys());
+        if !unknown.is_empty() {
+            if config.strict_config {
+                return Err(ConfigError::UnknownKeys(unknown));
+            }
+            eprintln!(
+                "⚠ Ignoring unknown configuration key(s) in {}: {}",
+                config_path.display(),
+                unknown.join(", ")
             );
-            fs::write(&config_path, yaml_content)?;
         }
 
-        Ok(())
+        // Override with environment variables
+        if let Ok(kb_path) = env::var("KNOWLEDGE_BASE") {
+            config.set_knowledge_base_path(kb_path);
+            sources.insert("knowledge_base_path".to_string(), ConfigSource::Environment);
+        }
+        if let Some(kb_path) = knowledge_base {
+            config.set_knowledge_base_path(Self::command_line_knowledge_base(kb_path)?);
+            sources.insert("knowledge_base_path".to_string(), ConfigSource::CommandLine);
+        }
+
+        config.apply_vault_layout(&mut sources);
+
+        config.validate_with(force)?;
+        // Expand once, so the rest of the run sees one Knowledge Base even
+        // if the environment changes
+        config.expanded_knowledge_base_path();
+        Ok(ResolvedConfig {
+            config,
+            config_path,
+            sources,
+        })
     }
 
-    /// Validate configuration values
-    pub fn validate(&self) -> Result<(), ConfigError> {
-        if self.knowledge_base_path.trim().is_empty() {
-            return Err(ConfigError::ValidationError(
-                "knowledge_base_path cannot be empty".to_string(),
-            ));
+    /// Absolute path of a Knowledge Base given on the command line
+    ///
+    /// The path is expanded and made absolute against the current
+    /// directory, and has to name an existing directory.
+    fn command_line_knowledge_base(path: &str) -> Result<String, ConfigError> {
+        let absolute = std::path::absolute(paths::expand(path))?;
+        if !absolute.is_dir() {
+            return Err(ConfigError::KnowledgeBaseNotFound(path.to_string()));
         }
+        Ok(absolute.to_string_lossy().into_owned())
+    }
 
-        // Expand tilde and validate path
-        let expanded_path = Self::expand_path(&self.knowledge_base_path);
-        let path = Path::new(&expanded_path);
+    /// Upgrade a configuration loaded from an older schema version
+    ///
+    /// Missing keys are already filled with defaults during parsing, so
+    /// upgrading only needs to bump the version. Returns whether anything
+    /// changed; versions newer than this build understands are rejected.
+    pub fn migrate(config: &mut Config) -> Result<bool, ConfigError> {
+        if config.version > CURRENT_CONFIG_VERSION {
+            return Err(ConfigError::UnsupportedVersion(config.version));
+        }
 
-        // Try to create the directory if it doesn't exist
-        if let Some(parent) = path.parent()
-            && !parent.exists()
-        {
-            return Err(ConfigError::ValidationError(format!(
-                "Parent directory does not exist: {}",
-                parent.display()
-            )));
+        if config.version == CURRENT_CONFIG_VERSION {
+            return Ok(false);
         }
 
+        config.version = CURRENT_CONFIG_VERSION;
+        Ok(true)
+    }
+
+    /// Set the top-level `version` of the file at `path` to the current version
+    ///
+    /// Only that line is touched; the rest of the file, including its
+    /// `include`, is written back unchanged.
+    fn write_version(path: &Path) -> Result<(), ConfigError> {
+        let content = fs::read_to_string(path)?;
+        let upgraded =
+            Self::with_top_level_assignment(&content, "version", &Self::version_assignment(path));
+        fs::write(path, upgraded)?;
         Ok(())
     }
 
-    /// Expand tilde (~) in path
-    pub fn expand_path(path: &str) -> String {
-        if let Some(stripped) = path.strip_prefix("~/")
-            && let Some(home_dir) = dirs::home_dir()
-        {
-            format!("{}/{}", home_dir.display(), stripped)
+    /// Serialize a configuration to a file, as TOML or YAML by extension
+    pub fn write_to(config: &Config, path: &Path) -> Result<(), ConfigError> {
+        let content = if Self::is_toml_path(path) {
+            toml::to_string(config).map_err(|e| {
+                ConfigError::ValidationError(format!("Failed to serialize config: {}", e))
+            })?
         } else {
-            path.to_string()
-        }
+            serde_yaml::to_string(config)?
+        };
+        fs::write(path, content)?;
+        Ok(())
     }
 
-    /// Get the expanded knowledge base path
-    pub fn get_knowledge_base_path(&self) -> String {
-        Self::expand_path(&self.knowledge_base_path)
+    /// List the top-level keys set in a configuration file
+    fn file_keys(content: &str, path: &Path) -> Result<Vec<String>, ConfigError> {
+        if Self::is_toml_path(path) {
+            let table: toml::Table = toml::from_str(content)?;
+            Ok(table.keys().cloned().collect())
+        } else {
+            let value: serde_yaml::Value = serde_yaml::from_str(content)?;
+            Ok(value
+                .as_mapping()
+                .map(|mapping| {
+                    mapping
+                        .keys()
+                        .filter_map(|key| key.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default())
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::env;
-    use tempfile::tempdir;
+    /// Content of a configuration file with the files it includes merged in
+    ///
+    /// Returns the content and whether any file was included. A YAML file
+    /// with an `include` key is read over the file it names, which may
+    /// include another in turn; TOML files are read as they are.
+    fn read_with_includes(path: &Path) -> Result<(String, bool), ConfigError> {
+        let content = fs::read_to_string(path)?;
+        let has_include = !Self::is_toml_path(path)
+            && serde_yaml::from_str::<serde_yaml::Value>(&content)?
+                .get(INCLUDE_KEY)
+                .is_some();
+        if !has_include {
+            return Ok((content, false));
+        }
 
-    #[test]
-    fn test_default_config() {
-        let config = Config::default();
-        assert_eq!(config.knowledge_base_path, "~/Knowledge Base");
+        let merged = Self::merge_includes(path, &mut Vec::new())?;
+        Ok((serde_yaml::to_string(&merged)?, true))
     }
 
-    #[test]
-    fn test_config_validation_empty_path() {
-        let config = Config {
-            knowledge_base_path: "".to_string(),
+    /// Top-level keys of a YAML configuration file over those of the file it includes
+    ///
+    /// `chain` holds the files that led to `path`, to detect cycles.
+    fn merge_includes(
+        path: &Path,
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<serde_yaml::Mapping, ConfigError> {
+        let canonical = fs::canonicalize(path)?;
+        if chain.contains(&canonical) {
+            return Err(ConfigError::IncludeCycle(canonical));
+        }
+
+        let mut mapping = match serde_yaml::from_str(&fs::read_to_string(path)?)? {
+            serde_yaml::Value::Mapping(mapping) => mapping,
+            serde_yaml::Value::Null => serde_yaml::Mapping::new(),
+            _ => {
+                return Err(ConfigError::ValidationError(format!(
+                    "{} is not a YAML mapping",
+                    path.display()
+                )));
+            }
         };
-        assert!(config.validate().is_err());
+        let Some(include) = mapping.remove(INCLUDE_KEY) else {
+            return Ok(mapping);
+        };
+        let include = include.as_str().ok_or_else(|| {
+            ConfigError::ValidationError(format!(
+                "{} in {} must be a path",
+                INCLUDE_KEY,
+                path.display()
+            ))
+        })?;
+
+        let target = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(paths::expand(include));
+        if !target.is_file() {
+            return Err(ConfigError::MissingInclude {
+                path: target,
+                included_from: path.to_path_buf(),
+            });
+        }
+
+        chain.push(canonical);
+        let mut merged = Self::merge_includes(&target, chain)?;
+        merged.extend(mapping);
+        Ok(merged)
     }
 
-    #[test]
-    fn test_config_validation_whitespace_path() {
-        let config = Config {
-            knowledge_base_path: "   ".to_string(),
-        };
-        assert!(config.validate().is_err());
+    /// Check whether a config path should be parsed as TOML
+    fn is_toml_path(path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
     }
 
-    #[test]
-    fn test_expand_path_with_tilde() {
-        let home = dirs::home_dir().unwrap();
-        let expanded = Config::expand_path("~/Documents");
-        assert_eq!(expanded, format!("{}/Documents", home.display()));
+    /// Parse configuration content, choosing TOML or YAML by the file extension
+    pub fn parse(content: &str, path: &Path) -> Result<Config, ConfigError> {
+        if Self::is_toml_path(path) {
+            Ok(toml::from_str(content)?)
+        } else {
+            Ok(serde_yaml::from_str(content)?)
+        }
     }
 
-    #[test]
-    fn test_expand_path_without_tilde() {
-        let path = "/absolute/path";
-        let expanded = Config::expand_path(path);
-        assert_eq!(expanded, path);
+    /// Create default configuration file if it doesn't exist
+    pub fn initialize() -> Result<(), ConfigError> {
+        Self::initialize_with_override(None)
     }
 
-    #[test]
-    fn test_environment_variable_override() {
-        // Set environment variable
-        unsafe {
-            env::set_var("KNOWLEDGE_BASE", "/tmp/test_kb");
+    /// Create default configuration file at the resolved location if it doesn't exist
+    ///
+    /// Legacy directory migration only applies to the default location.
+    pub fn initialize_with_override(config_override: Option<&Path>) -> Result<(), ConfigError> {
+        Self::initialize_with_prompt(config_override, None, false, true)
+    }
+
+    /// Create the configuration file on first run
+    ///
+    /// With a prompt, the Knowledge Base path is asked for and validated
+    /// instead of silently writing the default. `force` runs the prompt even
+    /// when the configuration file already exists.
+    pub fn initialize_with_prompt(
+        config_override: Option<&Path>,
+        prompt: Option<&mut Prompt>,
+        force: bool,
+        migrate_legacy: bool,
+    ) -> Result<(), ConfigError> {
+        // Skip initialization during tests to avoid contaminating user config
+        if env::var("LOCAL_SHELF_SKIP_CONFIG_INIT").is_ok() {
+            return Ok(());
         }
 
-        // Create a temporary config file with different value
-        let temp_dir = tempdir().unwrap();
-        let config_content = "knowledge_base_path: \"/different/path\"";
-        let config_path = temp_dir.path().join("config.yaml");
-        fs::write(&config_path, config_content).unwrap();
+        let config_path = Self::resolve_config_file_path(config_override)?;
+        if Self::is_stdin_path(&config_path) {
+            return Ok(());
+        }
 
-        // Mock the config file path (this is simplified - in real test we'd need to mock the entire path)
-        // For now, let's test the environment override logic directly
-        let mut config = Config {
-            knowledge_base_path: "/different/path".to_string(),
-        };
+        if config_path == Self::config_file_path()? {
+            // Try to migrate from legacy config first
+            Self::run_legacy_migration(
+                &Self::legacy_config_dir()?,
+                &Self::config_dir()?,
+                migrate_legacy && !Self::migration_disabled_by_env(),
+            )?;
+        }
 
-        // Simulate environment override
-        if let Ok(kb_path) = env::var("KNOWLEDGE_BASE") {
-            config.knowledge_base_path = kb_path;
+        // Create config directory if it doesn't exist
+        if let Some(config_dir) = config_path.parent()
+            && !config_dir.as_os_str().is_empty()
+            && !config_dir.exists()
+        {
+            fs::create_dir_all(config_dir)?;
         }
 
-        assert_eq!(config.knowledge_base_path, "/tmp/test_kb");
+        let run_prompt = prompt.is_some() && (force || !config_path.exists());
 
-        // Clean up
-        unsafe {
-            env::remove_var("KNOWLEDGE_BASE");
+        // Create default config file if it doesn't exist
+        if !config_path.exists() || run_prompt {
+            let knowledge_base_path = match prompt {
+                Some(prompt) if run_prompt => Self::ask_knowledge_base_path(prompt)?,
+                _ => Config::default().knowledge_base_path,
+            };
+            // A forced setup only replaces the Knowledge Base path of an
+            // existing file, keeping every other setting and comment
+            let content = match fs::read_to_string(&config_path) {
+                Ok(existing) => {
+                    Self::with_knowledge_base_path(&existing, &config_path, &knowledge_base_path)
+                }
+                Err(_) => Self::initial_file_content(&config_path, &knowledge_base_path),
+            };
+            fs::write(&config_path, content)?;
         }
+
+        Ok(())
     }
 
-    #[test]
-    fn test_yaml_serialization() {
-        let config = Config {
-            knowledge_base_path: "/test/path".to_string(),
+    /// `content` of a configuration file with its top-level
+    /// `knowledge_base_path` set to `knowledge_base_path`
+    ///
+    /// The existing assignment line is replaced; without one the assignment
+    /// is added as the first line, where it is top-level in YAML and TOML alike.
+    fn with_knowledge_base_path(
+        content: &str,
+        config_path: &Path,
+        knowledge_base_path: &str,
+    ) -> String {
+        let assignment = Self::knowledge_base_assignment(config_path, knowledge_base_path);
+        Self::with_top_level_assignment(content, "knowledge_base_path", &assignment)
+    }
+
+    /// `content` with the top-level assignment of `key` replaced by `assignment`,
+    /// or with `assignment` added as the first line when there is none
+    fn with_top_level_assignment(content: &str, key: &str, assignment: &str) -> String {
+        let is_assignment = |line: &str| {
+            line.strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with([':', '=']))
+        };
+
+        let mut lines: Vec<&str> = content.lines().collect();
+        match lines.iter().position(|line| is_assignment(line)) {
+            Some(index) => lines[index] = assignment,
+            None => lines.insert(0, assignment),
+        }
+        let mut merged = lines.join("\n");
+        merged.push('\n');
+        merged
+    }
+
+    /// Ask for the Knowledge Base path until a valid one is given
+    fn ask_knowledge_base_path(prompt: &mut Prompt) -> Result<String, ConfigError> {
+        let default_path = Config::default().knowledge_base_path;
+        prompt.say("Welcome to Local Shelf! Let's set up your configuration.")?;
+
+        loop {
+            let answer =
+                prompt.ask("Where is your Knowledge Base located?", Some(&default_path))?;
+            let candidate = Config {
+                knowledge_base_path: answer.clone(),
+                ..Default::default()
+            };
+
+            match candidate.validate() {
+                Ok(()) => return Ok(answer),
+                Err(e) => prompt.say(&format!("{}. Please try again.", e))?,
+            }
+        }
+    }
+
+    /// Build the commented content of a freshly created configuration file
+    fn initial_file_content(config_path: &Path, knowledge_base_path: &str) -> String {
+        let assignment = Self::knowledge_base_assignment(config_path, knowledge_base_path);
+        let version = Self::version_assignment(config_path);
+
+        format!(
+            "# Local Shelf Configuration\n{}\n# \n# Knowledge Base path - where markdown files will be organized\n# Can be overridden with KNOWLEDGE_BASE environment variable\n{}\n",
+            version, assignment
+        )
+    }
+
+    /// The line setting the current `version` in the format of `config_path`
+    fn version_assignment(config_path: &Path) -> String {
+        if Self::is_toml_path(config_path) {
+            format!("version = {}", CURRENT_CONFIG_VERSION)
+        } else {
+            format!("version: {}", CURRENT_CONFIG_VERSION)
+        }
+    }
+
+    /// The line assigning `knowledge_base_path` in the format of `config_path`
+    fn knowledge_base_assignment(config_path: &Path, knowledge_base_path: &str) -> String {
+        let quoted = format!(
+            "\"{}\"",
+            knowledge_base_path
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        );
+        if Self::is_toml_path(config_path) {
+            format!("knowledge_base_path = {}", quoted)
+        } else {
+            format!("knowledge_base_path: {}", quoted)
+        }
+    }
+
+    /// Validate configuration values
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.validate_with(false).map(|_| ())
+    }
+
+    /// Validate configuration values, optionally downgrading the path checks
+    ///
+    /// With `force`, a missing parent directory or an unwritable Knowledge
+    /// Base is returned as a warning instead of an error. Every other rule,
+    /// including the non-empty `knowledge_base_path`, is still enforced.
+    pub fn validate_with(&self, force: bool) -> Result<Vec<String>, ConfigError> {
+        if self.knowledge_base_path.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "knowledge_base_path cannot be empty".to_string(),
+            ));
+        }
+
+        for pattern in &self.exclude_patterns {
+            glob::Pattern::new(pattern).map_err(|e| {
+                ConfigError::ValidationError(format!(
+                    "Invalid exclude pattern '{}': {}",
+                    pattern, e
+                ))
+            })?;
+        }
+
+        for (key, value) in [
+            ("modified_since", &self.modified_since),
+            ("modified_until", &self.modified_until),
+        ] {
+            if let Some(value) = value {
+                Self::parse_date(value).map_err(|_| {
+                    ConfigError::ValidationError(format!(
+                        "Invalid {} '{}': expected a date like 2026-02-07",
+                        key, value
+                    ))
+                })?;
+            }
+        }
+        if let (Some(since), Some(until)) = self.modified_range()
+            && since > until
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "modified_since ({}) must not be after modified_until ({})",
+                since, until
+            )));
+        }
+
+        if let Some(max_size) = self.max_file_size_bytes
+            && max_size < self.min_file_size_bytes
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "max_file_size_bytes ({}) must not be below min_file_size_bytes ({})",
+                max_size, self.min_file_size_bytes
+            )));
+        }
+
+        if self.parallel_moves == 0 {
+            return Err(ConfigError::ValidationError(
+                "parallel_moves must be at least 1".to_string(),
+            ));
+        }
+
+        if self
+            .per_file_hook
+            .as_ref()
+            .is_some_and(|hook| hook.trim().is_empty())
+        {
+            return Err(ConfigError::ValidationError(
+                "per_file_hook must not be empty".to_string(),
+            ));
+        }
+
+        if self.limit == Some(0) {
+            return Err(ConfigError::ValidationError(
+                "limit must be at least 1".to_string(),
+            ));
+        }
+
+        if self.extensions.is_empty()
+            || self
+                .extensions
+                .iter()
+                .any(|ext| ext.trim_start_matches('.').trim().is_empty())
+        {
+            return Err(ConfigError::ValidationError(
+                "extensions must list at least one non-empty extension".to_string(),
+            ));
+        }
+
+        if let Some(extension) = self.attachment_extensions.iter().find(|ext| {
+            ext.trim_start_matches('.').trim().is_empty()
+                || FileDiscovery::has_extension(
+                    Path::new(&format!("file.{}", ext)),
+                    &self.extensions,
+                )
+        }) {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid attachment extension '{}': must be non-empty and not listed in extensions",
+                extension
+            )));
+        }
+
+        if !Self::is_knowledge_base_subdir(&self.assets_dir_name) {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid assets_dir_name '{}': must be a relative path inside the Knowledge Base",
+                self.assets_dir_name
+            )));
+        }
+
+        for extension in &self.compound_extensions {
+            let parts: Vec<&str> = extension.trim_start_matches('.').split('.').collect();
+            if parts.len() < 2 || parts.iter().any(|part| part.trim().is_empty()) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Invalid compound extension '{}': must have at least two parts, such as excalidraw.md",
+                    extension
+                )));
+            }
+        }
+
+        for pattern in &self.asset_folder_patterns {
+            if !pattern.contains("{stem}") || pattern.contains(std::path::is_separator) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Invalid asset folder pattern '{}': must be a folder name containing {{stem}}, such as {{stem}}.assets",
+                    pattern
+                )));
+            }
+        }
+
+        for tag in &self.journal_tags {
+            let name = tag.strip_prefix('#').unwrap_or(tag);
+            if name.is_empty() || name.chars().any(char::is_whitespace) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Invalid journal tag '{}': tags must be non-empty and contain no whitespace",
+                    tag
+                )));
+            }
+        }
+
+        if !self.link_format.contains("{name}") {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid link_format '{}': must contain {{name}}",
+                self.link_format
+            )));
+        }
+
+        if self.rewrite_links_days == 0 {
+            return Err(ConfigError::ValidationError(
+                "rewrite_links_days must be at least 1".to_string(),
+            ));
+        }
+
+        if let Some(destination_subdir) = &self.destination_subdir
+            && !Self::is_knowledge_base_subdir(destination_subdir)
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid destination_subdir '{}': must be a relative path inside the Knowledge Base",
+                destination_subdir
+            )));
+        }
+
+        for route in &self.routing {
+            if route.pattern.trim_start_matches('.').trim().is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "routing patterns must not be empty".to_string(),
+                ));
+            }
+            if route.is_glob() {
+                glob::Pattern::new(&route.pattern).map_err(|e| {
+                    ConfigError::ValidationError(format!(
+                        "Invalid routing pattern '{}': {}",
+                        route.pattern, e
+                    ))
+                })?;
+            }
+            if !Self::is_knowledge_base_subdir(&route.destination) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Invalid routing destination '{}': must be a relative path inside the Knowledge Base",
+                    route.destination
+                )));
+            }
+        }
+
+        for (key, name) in [
+            ("pages_dir_name", &self.pages_dir_name),
+            ("journals_dir_name", &self.journals_dir_name),
+        ] {
+            let path = Path::new(name);
+            let nested = name.contains(std::path::is_separator);
+            if name.trim().is_empty()
+                || path.is_absolute()
+                || path
+                    .components()
+                    .any(|c| matches!(c, std::path::Component::ParentDir))
+                || (nested && !self.allow_nested_dir_names)
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "Invalid {} '{}': must be a non-empty directory name without path separators \
+                     (set allow_nested_dir_names to use nested paths)",
+                    key, name
+                )));
+            }
+        }
+
+        if chrono::format::StrftimeItems::new(&self.date_subdir_pattern)
+            .any(|item| matches!(item, chrono::format::Item::Error))
+            || self.date_subdir_pattern.trim().is_empty()
+            || Path::new(&self.date_subdir_pattern).is_absolute()
+            || self.date_subdir_pattern.split('/').any(|part| part == "..")
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid date_subdir_pattern '{}': must be a relative chrono format such as %Y/%m",
+                self.date_subdir_pattern
+            )));
+        }
+
+        if chrono::format::StrftimeItems::new(&self.journal_file_format)
+            .any(|item| matches!(item, chrono::format::Item::Error))
+            || self.journal_file_format.trim().is_empty()
+            || self.journal_file_format.contains(std::path::is_separator)
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid journal_file_format '{}': must be a chrono format without path separators, such as %Y_%m_%d",
+                self.journal_file_format
+            )));
+        }
+
+        if self.journal_file.trim().is_empty()
+            || self.journal_file.contains(std::path::is_separator)
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "Invalid journal_file '{}': must be a file name without path separators, such as journal.md",
+                self.journal_file
+            )));
+        }
+
+        if self
+            .filename_replacement
+            .chars()
+            .any(|c| c.is_control() || crate::file_naming::ILLEGAL_FILENAME_CHARS.contains(&c))
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "filename_replacement {:?} must not contain characters that are illegal in file names",
+                self.filename_replacement
+            )));
+        }
+
+        for (key, affix) in [
+            ("filename_prefix", &self.filename_prefix),
+            ("filename_suffix", &self.filename_suffix),
+        ] {
+            if affix
+                .chars()
+                .any(|c| c.is_control() || std::path::is_separator(c))
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "{} {:?} must not contain path separators or control characters",
+                    key, affix
+                )));
+            }
+        }
+
+        if !(16..=255).contains(&self.max_filename_length) {
+            return Err(ConfigError::ValidationError(format!(
+                "max_filename_length must be between 16 and 255, got {}",
+                self.max_filename_length
+            )));
+        }
+
+        if self.max_collision_attempts == 0 {
+            return Err(ConfigError::ValidationError(
+                "max_collision_attempts must be at least 1".to_string(),
+            ));
+        }
+
+        if self.io_retries > 10 {
+            return Err(ConfigError::ValidationError(format!(
+                "io_retries must be at most 10, got {}",
+                self.io_retries
+            )));
+        }
+
+        if self.day_start_hour > 23 {
+            return Err(ConfigError::ValidationError(format!(
+                "day_start_hour must be between 0 and 23, got {}",
+                self.day_start_hour
+            )));
+        }
+
+        if let Some(timezone) = &self.timezone {
+            timezone.parse::<chrono_tz::Tz>().map_err(|_| {
+                ConfigError::ValidationError(format!(
+                    "Invalid timezone '{}': expected an IANA name such as Europe/Berlin",
+                    timezone
+                ))
+            })?;
+        }
+
+        if let Some(problem) = self.knowledge_base_path_problem() {
+            if !force {
+                return Err(ConfigError::ValidationError(problem));
+            }
+            return Ok(vec![problem]);
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// Check that the Knowledge Base location can be used right now
+    ///
+    /// These are the checks `--force` skips: the parent directory must exist
+    /// and an existing Knowledge Base directory must be writable.
+    fn knowledge_base_path_problem(&self) -> Option<String> {
+        let path = &*self.expanded_knowledge_base_path();
+
+        if let Some(parent) = path.parent()
+            && !parent.exists()
+        {
+            return Some(format!(
+                "Parent directory does not exist: {}",
+                parent.display()
+            ));
+        }
+
+        if fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly()) {
+            return Some(format!(
+                "Knowledge Base directory is not writable: {}",
+                path.display()
+            ));
+        }
+
+        None
+    }
+
+    /// Expand tilde (~) and environment variables in path
+    ///
+    /// `$VAR` and `${VAR}` are replaced with the variable's value. References
+    /// to variables that are not set are left in the path literally. A bare
+    /// `~` or a `~/` prefix expands to the home directory; `~user` paths are
+    /// left unchanged.
+    pub fn expand_path(path: &str) -> String {
+        paths::expand(path).to_string_lossy().into_owned()
+    }
+
+    /// Get the expanded knowledge base path
+    pub fn get_knowledge_base_path(&self) -> String {
+        self.expanded_knowledge_base_path()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// The expanded knowledge base path, computed on the first call and
+    /// reused afterwards
+    ///
+    /// A path assigned after the first call is expanded again on every call;
+    /// [`Config::set_knowledge_base_path`] refreshes the cached expansion.
+    pub fn expanded_knowledge_base_path(&self) -> Cow<'_, Path> {
+        let (source, expanded) = self.expanded_path_cache.0.get_or_init(|| {
+            (
+                self.knowledge_base_path.clone(),
+                paths::expand(&self.knowledge_base_path),
+            )
+        });
+        if source == &self.knowledge_base_path {
+            Cow::Borrowed(expanded)
+        } else {
+            Cow::Owned(paths::expand(&self.knowledge_base_path))
+        }
+    }
+
+    /// Change the knowledge base path, dropping its cached expansion
+    pub fn set_knowledge_base_path(&mut self, path: impl Into<String>) {
+        self.knowledge_base_path = path.into();
+        self.expanded_path_cache = ExpandedPath::default();
+    }
+
+    /// Configured journal time zone, or `None` for the system time zone
+    ///
+    /// Invalid names are rejected by [`Config::validate`] and treated as unset here.
+    pub fn journal_timezone(&self) -> Option<chrono_tz::Tz> {
+        self.timezone.as_deref()?.parse().ok()
+    }
+
+    /// Parse a `YYYY-MM-DD` date as used by `modified_since` and `modified_until`
+    pub fn parse_date(value: &str) -> Result<chrono::NaiveDate, ConfigError> {
+        chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|e| {
+            ConfigError::ValidationError(format!(
+                "Invalid date '{}' ({}): expected YYYY-MM-DD, e.g. 2026-02-07",
+                value, e
+            ))
+        })
+    }
+
+    /// The `modified_since` and `modified_until` days; invalid dates are
+    /// rejected by [`Config::validate`] and treated as unset here
+    pub fn modified_range(&self) -> (Option<chrono::NaiveDate>, Option<chrono::NaiveDate>) {
+        let parse = |value: &Option<String>| {
+            value
+                .as_deref()
+                .and_then(|value| Self::parse_date(value).ok())
+        };
+        (parse(&self.modified_since), parse(&self.modified_until))
+    }
+
+    /// Whether `path` has one of `attachment_extensions`
+    pub fn is_attachment(&self, path: &Path) -> bool {
+        FileDiscovery::has_extension(path, &self.attachment_extensions)
+    }
+
+    /// First routing rule matching the file name of `path`, if any
+    pub fn route_for(&self, path: &Path) -> Option<&RouteRule> {
+        let file_name = path.file_name()?.to_str()?;
+        self.routing.iter().find(|route| route.matches(file_name))
+    }
+
+    /// Whether `name` is a non-empty relative path that stays inside the Knowledge Base
+    fn is_knowledge_base_subdir(name: &str) -> bool {
+        let path = Path::new(name);
+        !name.trim().is_empty()
+            && !path.is_absolute()
+            && path
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)))
+    }
+
+    /// Name of the directory imported files are moved into
+    pub fn destination_dir_name(&self) -> &str {
+        self.destination_subdir
+            .as_deref()
+            .unwrap_or(&self.pages_dir_name)
+    }
+
+    /// The vault type in effect, detecting it from the Knowledge Base for `Auto`
+    ///
+    /// A `.obsidian` directory means Obsidian; a `logseq` directory, or
+    /// neither, means Logseq.
+    pub fn detect_vault_type(&self) -> VaultType {
+        match self.vault_type {
+            VaultType::Auto if self.knowledge_base_subdir(".obsidian").is_dir() => {
+                VaultType::Obsidian
+            }
+            VaultType::Auto => VaultType::Logseq,
+            vault_type => vault_type,
+        }
+    }
+
+    /// Set the layout options of the detected vault type, except the ones
+    /// present in `sources`, recording the changed ones as detected
+    pub fn apply_vault_layout(&mut self, sources: &mut BTreeMap<String, ConfigSource>) {
+        let (pages, journals, file_format) = self.detect_vault_type().layout();
+        for (key, option, value) in [
+            ("pages_dir_name", &mut self.pages_dir_name, pages),
+            ("journals_dir_name", &mut self.journals_dir_name, journals),
+            (
+                "journal_file_format",
+                &mut self.journal_file_format,
+                file_format,
+            ),
+        ] {
+            if !sources.contains_key(key) && option != value {
+                *option = value.to_string();
+                sources.insert(key.to_string(), ConfigSource::Detected);
+            }
+        }
+    }
+
+    /// Resolve a directory name relative to the Knowledge Base
+    ///
+    /// `.` components are dropped, so `.` resolves to the Knowledge Base itself.
+    pub fn knowledge_base_subdir(&self, name: &str) -> PathBuf {
+        name.split(std::path::is_separator)
+            .filter(|component| !component.is_empty() && *component != ".")
+            .fold(
+                self.expanded_knowledge_base_path().to_path_buf(),
+                |path, component| path.join(component),
+            )
+    }
+}
+
+/// Serializes tests that read or mutate process environment variables
+#[cfg(test)]
+pub(crate) static TEST_ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_migrate_directory_moves_config() {
+        let temp_dir = tempdir().unwrap();
+        let legacy_dir = temp_dir.path().join("local-shelf");
+        let new_dir = temp_dir.path().join("local_shelf");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join("config.yaml"), "knowledge_base_path: /kb\n").unwrap();
+
+        assert!(Config::migrate_directory(&legacy_dir, &new_dir).unwrap());
+        assert!(!legacy_dir.exists());
+        assert_eq!(
+            fs::read_to_string(new_dir.join("config.yaml")).unwrap(),
+            "knowledge_base_path: /kb\n"
+        );
+
+        // Nothing left to migrate
+        assert!(!Config::migrate_directory(&legacy_dir, &new_dir).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_directory_survives_when_migration_disabled() {
+        let temp_dir = tempdir().unwrap();
+        let legacy_dir = temp_dir.path().join("local-shelf");
+        let new_dir = temp_dir.path().join("local_shelf");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join("config.yaml"), "knowledge_base_path: /kb\n").unwrap();
+
+        Config::run_legacy_migration(&legacy_dir, &new_dir, false).unwrap();
+        assert!(legacy_dir.join("config.yaml").exists());
+        assert!(!new_dir.exists());
+
+        // Both directories present: still untouched
+        fs::create_dir_all(&new_dir).unwrap();
+        Config::run_legacy_migration(&legacy_dir, &new_dir, false).unwrap();
+        assert!(legacy_dir.join("config.yaml").exists());
+        assert_eq!(fs::read_dir(&new_dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(&new_dir).unwrap();
+        Config::run_legacy_migration(&legacy_dir, &new_dir, true).unwrap();
+        assert!(!legacy_dir.exists());
+        assert!(new_dir.join("config.yaml").exists());
+    }
+
+    #[test]
+    fn test_migration_disabled_by_env() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        unsafe {
+            env::remove_var("LOCAL_SHELF_NO_MIGRATE");
+        }
+        assert!(!Config::migration_disabled_by_env());
+
+        for (value, disabled) in [("1", true), ("yes", true), ("0", false), ("false", false)] {
+            unsafe {
+                env::set_var("LOCAL_SHELF_NO_MIGRATE", value);
+            }
+            assert_eq!(Config::migration_disabled_by_env(), disabled, "{:?}", value);
+        }
+
+        unsafe {
+            env::remove_var("LOCAL_SHELF_NO_MIGRATE");
+        }
+    }
+
+    #[test]
+    fn test_migrate_directory_copy_failure_rolls_back() {
+        let temp_dir = tempdir().unwrap();
+        let legacy_dir = temp_dir.path().join("local-shelf");
+        let new_dir = temp_dir.path().join("local_shelf");
+        // A directory named config.yaml makes the copy fail
+        fs::create_dir_all(legacy_dir.join("config.yaml")).unwrap();
+        fs::write(legacy_dir.join("config.yaml/keep"), "data").unwrap();
+
+        let result = Config::migrate_directory(&legacy_dir, &new_dir);
+
+        assert!(matches!(result, Err(ConfigError::IoError(_))));
+        assert!(!new_dir.exists());
+        assert!(legacy_dir.join("config.yaml/keep").exists());
+    }
+
+    #[test]
+    fn test_migrate_directory_unwritable_destination_keeps_legacy() {
+        let temp_dir = tempdir().unwrap();
+        let legacy_dir = temp_dir.path().join("local-shelf");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join("config.yaml"), "knowledge_base_path: /kb\n").unwrap();
+        // The new directory cannot be created below a regular file
+        fs::write(temp_dir.path().join("blocker"), "").unwrap();
+        let new_dir = temp_dir.path().join("blocker/local_shelf");
+
+        assert!(Config::migrate_directory(&legacy_dir, &new_dir).is_err());
+        assert!(legacy_dir.join("config.yaml").exists());
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+        assert_eq!(config.knowledge_base_path, "~/Knowledge Base");
+        assert_eq!(config.version, CURRENT_CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_config_validation_empty_path() {
+        let config = Config {
+            knowledge_base_path: "".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_whitespace_path() {
+        let config = Config {
+            knowledge_base_path: "   ".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_invalid_exclude_pattern() {
+        let config = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            exclude_patterns: vec!["[unclosed".to_string()],
+            ..Default::default()
+        };
+        let result = config.validate();
+        assert!(
+            matches!(result, Err(ConfigError::ValidationError(ref msg)) if msg.contains("[unclosed"))
+        );
+    }
+
+    #[test]
+    fn test_line_ending_resolve() {
+        assert_eq!(LineEnding::Lf.resolve("a\r\nb\r\n"), "\n");
+        assert_eq!(LineEnding::Crlf.resolve("a\nb\n"), "\r\n");
+        assert_eq!(LineEnding::Native.resolve("a\r\nb\r\nc\n"), "\r\n");
+        assert_eq!(LineEnding::Native.resolve("a\nb\nc\r\n"), "\n");
+        let platform = if cfg!(windows) { "\r\n" } else { "\n" };
+        assert_eq!(LineEnding::Native.resolve(""), platform);
+    }
+
+    #[test]
+    fn test_config_validation_modified_range() {
+        let range = |since: Option<&str>, until: Option<&str>| Config {
+            knowledge_base_path: "/tmp".to_string(),
+            modified_since: since.map(str::to_string),
+            modified_until: until.map(str::to_string),
+            ..Default::default()
+        };
+
+        assert!(range(Some("2026-02-02"), None).validate().is_ok());
+        assert!(
+            range(Some("2026-02-02"), Some("2026-02-02"))
+                .validate()
+                .is_ok()
+        );
+        assert_eq!(
+            range(Some("2026-02-02"), Some("2026-02-08")).modified_range(),
+            (
+                chrono::NaiveDate::from_ymd_opt(2026, 2, 2),
+                chrono::NaiveDate::from_ymd_opt(2026, 2, 8)
+            )
+        );
+        for (since, until) in [
+            (Some("last monday"), None),
+            (None, Some("2026-02-30")),
+            (Some("2026-02-08"), Some("2026-02-02")),
+        ] {
+            assert!(
+                range(since, until).validate().is_err(),
+                "{:?}..{:?} should be rejected",
+                since,
+                until
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_validation_file_size_range() {
+        let sizes = |min: u64, max: Option<u64>| Config {
+            knowledge_base_path: "/tmp".to_string(),
+            min_file_size_bytes: min,
+            max_file_size_bytes: max,
+            ..Default::default()
+        };
+
+        assert!(sizes(10, None).validate().is_ok());
+        assert!(sizes(10, Some(10)).validate().is_ok());
+        assert!(matches!(
+            sizes(10, Some(9)).validate(),
+            Err(ConfigError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_validation_journal_tags() {
+        let valid = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            journal_tags: vec!["import".to_string(), "#web".to_string()],
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        for tag in ["two words", "", "#", "tab\there"] {
+            let invalid = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                journal_tags: vec![tag.to_string()],
+                ..Default::default()
+            };
+            assert!(
+                invalid.validate().is_err(),
+                "tag {:?} should be rejected",
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_validation_compound_extensions() {
+        let valid = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            compound_extensions: vec![".excalidraw.md".to_string(), "canvas.md".to_string()],
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        for extension in ["md", "", "excalidraw.", "excalidraw..md"] {
+            let invalid = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                compound_extensions: vec![extension.to_string()],
+                ..Default::default()
+            };
+            assert!(
+                invalid.validate().is_err(),
+                "extension {:?} should be rejected",
+                extension
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_validation_link_format() {
+        let valid = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            link_format: "[{name}]({path})".to_string(),
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        let invalid = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            link_format: "[link]({path})".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            invalid.validate(),
+            Err(ConfigError::ValidationError(ref msg)) if msg.contains("{name}")
+        ));
+    }
+
+    #[test]
+    fn test_config_validation_rewrite_links_days() {
+        let invalid = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            rewrite_links_days: 0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            invalid.validate(),
+            Err(ConfigError::ValidationError(ref msg)) if msg.contains("rewrite_links_days")
+        ));
+    }
+
+    #[test]
+    fn test_config_validation_date_subdir_pattern() {
+        let valid = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            date_subdir_pattern: "%Y/%m/%d".to_string(),
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        for pattern in ["", "%Q", "/%Y", "../%Y"] {
+            let invalid = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                date_subdir_pattern: pattern.to_string(),
+                ..Default::default()
+            };
+            assert!(
+                invalid.validate().is_err(),
+                "{:?} should be rejected",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_validation_timezone() {
+        let valid = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            timezone: Some("Europe/Berlin".to_string()),
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+        assert_eq!(valid.journal_timezone(), Some(chrono_tz::Europe::Berlin));
+
+        let invalid = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            timezone: Some("Mars/Olympus".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            invalid.validate(),
+            Err(ConfigError::ValidationError(message)) if message.contains("Mars/Olympus")
+        ));
+    }
+
+    #[test]
+    fn test_config_validation_day_start_hour() {
+        for (hour, valid) in [(0, true), (23, true), (24, false)] {
+            let config = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                day_start_hour: hour,
+                ..Default::default()
+            };
+            assert_eq!(config.validate().is_ok(), valid, "hour {}", hour);
+        }
+    }
+
+    #[test]
+    fn test_config_validation_filename_sanitizing() {
+        let base = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            ..Default::default()
+        };
+        assert!(base.validate().is_ok());
+
+        for replacement in [":", "/", "a?b"] {
+            let config = Config {
+                filename_replacement: replacement.to_string(),
+                ..base.clone()
+            };
+            assert!(config.validate().is_err(), "{:?}", replacement);
+        }
+
+        for length in [0, 15, 256] {
+            let config = Config {
+                max_filename_length: length,
+                ..base.clone()
+            };
+            assert!(config.validate().is_err(), "{}", length);
+        }
+    }
+
+    #[test]
+    fn test_validate_with_force_downgrades_path_checks() {
+        let config = Config {
+            knowledge_base_path: "/nonexistent/automount/vault".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let warnings = config.validate_with(true).unwrap();
+        assert_eq!(
+            warnings,
+            vec!["Parent directory does not exist: /nonexistent/automount".to_string()]
+        );
+
+        let empty = Config {
+            knowledge_base_path: " ".to_string(),
+            ..Default::default()
+        };
+        assert!(empty.validate_with(true).is_err());
+
+        let invalid = Config {
+            parallel_moves: 0,
+            ..config
+        };
+        assert!(invalid.validate_with(true).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_read_only_knowledge_base() {
+        let temp_dir = tempdir().unwrap();
+        let mut permissions = fs::metadata(temp_dir.path()).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(temp_dir.path(), permissions.clone()).unwrap();
+
+        let config = Config {
+            knowledge_base_path: temp_dir.path().display().to_string(),
+            ..Default::default()
+        };
+        let result = config.validate();
+        let forced = config.validate_with(true);
+
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(temp_dir.path(), permissions).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(forced.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_config_validation_filename_affixes() {
+        let base = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            filename_prefix: "web-".to_string(),
+            filename_suffix: " (imported)".to_string(),
+            ..Default::default()
+        };
+        assert!(base.validate().is_ok());
+
+        for affix in ["inbox/", "a\tb"] {
+            let prefixed = Config {
+                filename_prefix: affix.to_string(),
+                ..base.clone()
+            };
+            assert!(prefixed.validate().is_err(), "{:?}", affix);
+            let suffixed = Config {
+                filename_suffix: affix.to_string(),
+                ..base.clone()
+            };
+            assert!(suffixed.validate().is_err(), "{:?}", affix);
+        }
+    }
+
+    #[test]
+    fn test_resolve_from_reader_parses_yaml() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original = env::var("KNOWLEDGE_BASE").ok();
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+        }
+
+        let input = std::io::Cursor::new("knowledge_base_path: /tmp/piped\nrecursive: true\n");
+        let resolved = Config::resolve_from_reader(input, false);
+
+        if let Some(value) = original {
+            unsafe {
+                env::set_var("KNOWLEDGE_BASE", value);
+            }
+        }
+        let resolved = resolved.unwrap();
+        assert_eq!(resolved.config.knowledge_base_path, "/tmp/piped");
+        assert!(resolved.config.recursive);
+        assert_eq!(resolved.config.version, CURRENT_CONFIG_VERSION);
+        assert_eq!(resolved.config_path, PathBuf::from(STDIN_CONFIG_PATH));
+        assert_eq!(resolved.source_of("recursive"), ConfigSource::File);
+        assert_eq!(resolved.source_of("max_depth"), ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_resolve_from_reader_parses_toml() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original = env::var("KNOWLEDGE_BASE").ok();
+        unsafe {
+            env::set_var("KNOWLEDGE_BASE", "/tmp/from-env");
+        }
+
+        let input = std::io::Cursor::new("knowledge_base_path = \"/tmp/piped\"\nmax_depth = 3\n");
+        let resolved = Config::resolve_from_reader(input, false);
+
+        unsafe {
+            match original {
+                Some(value) => env::set_var("KNOWLEDGE_BASE", value),
+                None => env::remove_var("KNOWLEDGE_BASE"),
+            }
+        }
+        let config = resolved.unwrap().config;
+        assert_eq!(config.knowledge_base_path, "/tmp/from-env");
+        assert_eq!(config.max_depth, Some(3));
+    }
+
+    #[test]
+    fn test_config_validation_io_retries() {
+        for (retries, valid) in [(0, true), (10, true), (11, false)] {
+            let config = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                io_retries: retries,
+                ..Default::default()
+            };
+            assert_eq!(config.validate().is_ok(), valid, "retries {}", retries);
+        }
+    }
+
+    #[test]
+    fn test_config_validation_extensions() {
+        for extensions in [
+            vec![],
+            vec!["md".to_string(), "".to_string()],
+            vec![".".to_string()],
+        ] {
+            let invalid = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                extensions: extensions.clone(),
+                ..Default::default()
+            };
+            assert!(
+                invalid.validate().is_err(),
+                "{:?} should be rejected",
+                extensions
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_validation_attachments() {
+        let config = |attachments: &[&str], assets: &str| Config {
+            knowledge_base_path: "/tmp".to_string(),
+            attachment_extensions: attachments.iter().map(|ext| ext.to_string()).collect(),
+            assets_dir_name: assets.to_string(),
+            ..Default::default()
+        };
+        assert!(config(&["pdf", ".png"], "assets").validate().is_ok());
+        assert!(config(&["MD"], "assets").validate().is_err());
+        assert!(config(&[""], "assets").validate().is_err());
+        assert!(config(&["pdf"], "../assets").validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_dir_names() {
+        let obsidian = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            pages_dir_name: ".".to_string(),
+            journals_dir_name: "Daily Notes".to_string(),
+            ..Default::default()
+        };
+        assert!(obsidian.validate().is_ok());
+
+        for name in ["", " ", "notes/daily", "..", "/abs"] {
+            let invalid = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                journals_dir_name: name.to_string(),
+                ..Default::default()
+            };
+            assert!(invalid.validate().is_err(), "{:?} should be rejected", name);
+        }
+
+        let nested = Config {
+            knowledge_base_path: "/tmp".to_string(),
+            journals_dir_name: "notes/daily".to_string(),
+            allow_nested_dir_names: true,
+            ..Default::default()
+        };
+        assert!(nested.validate().is_ok());
+
+        let escaping = Config {
+            journals_dir_name: "notes/../../outside".to_string(),
+            ..nested
+        };
+        assert!(escaping.validate().is_err());
+    }
+
+    #[test]
+    fn test_knowledge_base_subdir() {
+        let config = Config {
+            knowledge_base_path: "/kb".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.knowledge_base_subdir("."), PathBuf::from("/kb"));
+        assert_eq!(
+            config.knowledge_base_subdir("Daily Notes"),
+            PathBuf::from("/kb/Daily Notes")
+        );
+        assert_eq!(
+            config.knowledge_base_subdir("notes/daily"),
+            PathBuf::from("/kb/notes/daily")
+        );
+        assert_eq!(config.destination_dir_name(), "pages");
+    }
+
+    #[test]
+    fn test_config_validation_destination_subdir() {
+        for subdir in ["inbox", "pages/imported"] {
+            let valid = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                destination_subdir: Some(subdir.to_string()),
+                ..Default::default()
+            };
+            assert!(valid.validate().is_ok(), "{:?} should be accepted", subdir);
+        }
+
+        for subdir in ["", "  ", "/abs/inbox", "../outside", "inbox/../.."] {
+            let invalid = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                destination_subdir: Some(subdir.to_string()),
+                ..Default::default()
+            };
+            assert!(
+                invalid.validate().is_err(),
+                "{:?} should be rejected",
+                subdir
+            );
+        }
+    }
+
+    #[test]
+    fn test_route_for_uses_first_matching_rule() {
+        let config = Config {
+            routing: vec![
+                RouteRule {
+                    pattern: "excalidraw.md".to_string(),
+                    destination: "assets".to_string(),
+                    journal: false,
+                },
+                RouteRule {
+                    pattern: "*.md".to_string(),
+                    destination: "inbox".to_string(),
+                    journal: true,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let route = |name: &str| {
+            config
+                .route_for(Path::new(name))
+                .map(|route| route.destination.as_str())
+        };
+        assert_eq!(route("/downloads/Sketch.Excalidraw.md"), Some("assets"));
+        assert_eq!(route("/downloads/note.md"), Some("inbox"));
+        assert_eq!(route("/downloads/note.txt"), None);
+        assert_eq!(route("/downloads/excalidraw.md"), Some("inbox"));
+    }
+
+    #[test]
+    fn test_routing_journal_defaults_to_true() {
+        let config: Config =
+            serde_yaml::from_str("routing:\n  - pattern: excalidraw.md\n    destination: assets\n")
+                .unwrap();
+
+        assert!(config.routing[0].journal);
+    }
+
+    #[test]
+    fn test_config_validation_routing() {
+        let rule = |pattern: &str, destination: &str| Config {
+            knowledge_base_path: "/tmp".to_string(),
+            routing: vec![RouteRule {
+                pattern: pattern.to_string(),
+                destination: destination.to_string(),
+                journal: true,
+            }],
+            ..Default::default()
+        };
+
+        assert!(rule(".excalidraw.md", "assets").validate().is_ok());
+        assert!(rule("*-draft.md", "drafts/imported").validate().is_ok());
+        for (pattern, destination) in [
+            ("", "assets"),
+            (".", "assets"),
+            ("[unclosed", "assets"),
+            ("md", "../outside"),
+            ("md", "/abs/assets"),
+            ("md", ""),
+        ] {
+            assert!(
+                rule(pattern, destination).validate().is_err(),
+                "{:?} -> {:?} should be rejected",
+                pattern,
+                destination
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_path_with_tilde() {
+        let home = paths::home_dir().unwrap();
+        let expanded = Config::expand_path("~/Documents");
+        assert_eq!(expanded, home.join("Documents").display().to_string());
+    }
+
+    #[test]
+    fn test_expand_path_bare_tilde() {
+        let home = paths::home_dir().unwrap();
+        assert_eq!(Config::expand_path("~"), home.display().to_string());
+        assert_eq!(Config::expand_path("~/"), home.display().to_string());
+        assert_eq!(
+            Config::expand_path("~/sub"),
+            home.join("sub").display().to_string()
+        );
+    }
+
+    #[test]
+    fn test_expand_path_without_tilde() {
+        let path = "/absolute/path";
+        let expanded = Config::expand_path(path);
+        assert_eq!(expanded, path);
+    }
+
+    #[test]
+    fn test_expand_path_uses_platform_separator() {
+        let home = paths::home_dir().unwrap();
+        let expanded = Config::expand_path("~/Knowledge Base/inbox");
+        assert_eq!(
+            PathBuf::from(expanded),
+            home.join("Knowledge Base").join("inbox")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_expand_path_windows_user_profile() {
+        let profile = env::var("USERPROFILE").unwrap();
+        assert_eq!(
+            Config::expand_path("~/Knowledge Base"),
+            format!("{}\\Knowledge Base", profile)
+        );
+        assert_eq!(
+            Config::expand_path("~\\Knowledge Base"),
+            format!("{}\\Knowledge Base", profile)
+        );
+    }
+
+    #[test]
+    fn test_expand_path_with_dollar_variable() {
+        let home = env::var("HOME").unwrap();
+        assert_eq!(Config::expand_path("$HOME/x"), format!("{}/x", home));
+    }
+
+    #[test]
+    fn test_expand_path_with_braced_variable() {
+        let home = env::var("HOME").unwrap();
+        assert_eq!(Config::expand_path("${HOME}/x"), format!("{}/x", home));
+    }
+
+    #[test]
+    fn test_expand_path_with_undefined_variable_left_literal() {
+        let path = "$LOCAL_SHELF_UNDEFINED_VAR/x/${LOCAL_SHELF_UNDEFINED_VAR}";
+        assert_eq!(Config::expand_path(path), path);
+    }
+
+    #[test]
+    fn test_expand_path_with_lone_dollar_signs() {
+        assert_eq!(Config::expand_path("/price$/a${b"), "/price$/a${b");
+    }
+
+    #[test]
+    fn test_environment_variable_override() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        let file_kb = temp_dir.path().join("vault").display().to_string();
+        fs::write(
+            &config_path,
+            format!("knowledge_base_path: \"{}\"\nrecursive: true\n", file_kb),
+        )
+        .unwrap();
+
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+        }
+        let from_file = Config::load_from(&config_path);
+
+        unsafe {
+            env::set_var("KNOWLEDGE_BASE", "/tmp/test_kb");
+        }
+        let from_env = Config::load_from(&config_path);
+        let defaults = Config::load_from(&temp_dir.path().join("missing.yaml"));
+
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+        }
+
+        // The file overrides defaults, the environment overrides the file
+        let from_file = from_file.unwrap();
+        assert_eq!(from_file.knowledge_base_path, file_kb);
+        assert!(from_file.recursive);
+
+        let from_env = from_env.unwrap();
+        assert_eq!(from_env.knowledge_base_path, "/tmp/test_kb");
+        assert!(from_env.recursive);
+
+        let defaults = defaults.unwrap();
+        assert_eq!(defaults.knowledge_base_path, "/tmp/test_kb");
+        assert!(!defaults.recursive);
+    }
+
+    #[test]
+    fn test_load_from_reports_unknown_keys() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        let content = |strict: bool| {
+            format!(
+                "knowledge_base_path: \"{}\"\nknowlege_base_path: /typo\nstrict_config: {}\n",
+                temp_dir.path().display(),
+                strict
+            )
+        };
+
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+        }
+        fs::write(&config_path, content(false)).unwrap();
+        let lenient = Config::load_from(&config_path);
+        fs::write(&config_path, content(true)).unwrap();
+        let strict = Config::load_from(&config_path);
+
+        assert_eq!(
+            lenient.unwrap().knowledge_base_path,
+            temp_dir.path().display().to_string()
+        );
+        match strict {
+            Err(ConfigError::UnknownKeys(keys)) => {
+                assert_eq!(keys, vec!["knowlege_base_path".to_string()])
+            }
+            other => panic!("expected unknown keys error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let keys = [
+            "knowledge_base_path".to_string(),
+            "recursive".to_string(),
+            "future_option".to_string(),
+        ];
+        assert_eq!(
+            Config::unknown_keys(&keys),
+            vec!["future_option".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_load_from_validates_file_values() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            "knowledge_base_path = \"/tmp/vault\"\nparallel_moves = 0\n",
+        )
+        .unwrap();
+
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+        }
+        let result = Config::load_from(&config_path);
+
+        assert!(matches!(result, Err(ConfigError::ValidationError(_))));
+    }
+
+    #[test]
+    fn test_config_env_var_overrides_default_location() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("portable.yaml");
+        fs::write(
+            &config_path,
+            format!("knowledge_base_path: \"{}\"", temp_dir.path().display()),
+        )
+        .unwrap();
+
+        unsafe {
+            env::set_var("LOCAL_SHELF_CONFIG", &config_path);
+        }
+
+        let resolved = Config::resolve_config_file_path(None).unwrap();
+        let config = Config::load();
+
+        unsafe {
+            env::remove_var("LOCAL_SHELF_CONFIG");
+        }
+
+        assert_eq!(resolved, config_path);
+        assert_eq!(
+            config.unwrap().knowledge_base_path,
+            temp_dir.path().display().to_string()
+        );
+    }
+
+    #[test]
+    fn test_env_var_takes_precedence_over_config_flag() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let env_path = temp_dir.path().join("env.toml");
+        fs::write(
+            &env_path,
+            format!("knowledge_base_path = \"{}\"", temp_dir.path().display()),
+        )
+        .unwrap();
+        let flag_path = temp_dir.path().join("flag.yaml");
+
+        unsafe {
+            env::set_var("LOCAL_SHELF_CONFIG", &env_path);
+        }
+
+        let resolved = Config::resolve_config_file_path(Some(&flag_path)).unwrap();
+        let config = Config::load_with_override(Some(&flag_path));
+
+        unsafe {
+            env::remove_var("LOCAL_SHELF_CONFIG");
+        }
+
+        assert_eq!(resolved, env_path);
+        assert_eq!(
+            config.unwrap().knowledge_base_path,
+            temp_dir.path().display().to_string()
+        );
+    }
+
+    /// Run `f` with `XDG_CONFIG_HOME` set to `value` and no `LOCAL_SHELF_CONFIG`
+    fn with_xdg_config_home<T>(value: &std::ffi::OsStr, f: impl FnOnce() -> T) -> T {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = env::var_os("XDG_CONFIG_HOME");
+        let previous_config = env::var_os("LOCAL_SHELF_CONFIG");
+        unsafe {
+            env::set_var("XDG_CONFIG_HOME", value);
+            env::remove_var("LOCAL_SHELF_CONFIG");
+        }
+
+        let result = f();
+
+        unsafe {
+            match previous {
+                Some(value) => env::set_var("XDG_CONFIG_HOME", value),
+                None => env::remove_var("XDG_CONFIG_HOME"),
+            }
+            if let Some(value) = previous_config {
+                env::set_var("LOCAL_SHELF_CONFIG", value);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_config_read_from_xdg_config_home() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().join("local_shelf");
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(
+            config_dir.join("config.yaml"),
+            "knowledge_base_path: /tmp/xdg-vault\n",
+        )
+        .unwrap();
+
+        let (dir, resolved, config) = with_xdg_config_home(temp_dir.path().as_os_str(), || {
+            (
+                Config::config_dir().unwrap(),
+                Config::resolve_config_file_path(None).unwrap(),
+                Config::resolve(None).map(|resolved| resolved.config),
+            )
+        });
+
+        assert_eq!(dir, config_dir);
+        assert_eq!(resolved, config_dir.join("config.yaml"));
+        assert_eq!(config.unwrap().knowledge_base_path, "/tmp/xdg-vault");
+
+        // An explicit --config still wins over XDG_CONFIG_HOME
+        let flag_path = temp_dir.path().join("flag.yaml");
+        let resolved = with_xdg_config_home(temp_dir.path().as_os_str(), || {
+            Config::resolve_config_file_path(Some(&flag_path)).unwrap()
+        });
+        assert_eq!(resolved, flag_path);
+    }
+
+    #[test]
+    fn test_relative_xdg_config_home_is_ignored() {
+        let dir =
+            with_xdg_config_home("relative/config".as_ref(), || Config::config_dir().unwrap());
+        assert_eq!(dir, dirs::config_dir().unwrap().join("local_shelf"));
+    }
+
+    #[test]
+    fn test_initialize_with_override_creates_file() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if env::var("LOCAL_SHELF_SKIP_CONFIG_INIT").is_ok() {
+            return;
+        }
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("usb/config.yaml");
+
+        Config::initialize_with_override(Some(&config_path)).unwrap();
+
+        let content = fs::read_to_string(&config_path).unwrap();
+        assert!(content.contains("knowledge_base_path"));
+    }
+
+    #[test]
+    fn test_resolve_tracks_value_sources() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            format!(
+                "knowledge_base_path: \"{}\"\nrecursive: true\n",
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let resolved = Config::resolve(Some(&config_path)).unwrap();
+        assert_eq!(resolved.config_path, config_path);
+        assert_eq!(
+            resolved.source_of("knowledge_base_path"),
+            ConfigSource::File
+        );
+        assert_eq!(resolved.source_of("recursive"), ConfigSource::File);
+        assert_eq!(resolved.source_of("max_depth"), ConfigSource::Default);
+
+        unsafe {
+            env::set_var("KNOWLEDGE_BASE", temp_dir.path());
+        }
+        let resolved = Config::resolve(Some(&config_path));
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+        }
+        assert_eq!(
+            resolved.unwrap().source_of("knowledge_base_path"),
+            ConfigSource::Environment
+        );
+    }
+
+    #[test]
+    fn test_resolve_merges_include_chain() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let shared = temp_dir.path().join("shared");
+        fs::create_dir(&shared).unwrap();
+        fs::write(
+            shared.join("base.yaml"),
+            format!(
+                "knowledge_base_path: \"{}\"\nrecursive: true\nmax_depth: 2\njournal_tags: [base]\n",
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            shared.join("team.yaml"),
+            "include: base.yaml\nmax_depth: 3\njournal_tags: [team]\n",
+        )
+        .unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            "include: shared/team.yaml\njournal_tags: [laptop]\n",
+        )
+        .unwrap();
+
+        let resolved = Config::resolve(Some(&config_path)).unwrap();
+
+        assert!(resolved.config.recursive);
+        assert_eq!(resolved.config.max_depth, Some(3));
+        assert_eq!(resolved.config.journal_tags, vec!["laptop"]);
+        assert_eq!(resolved.source_of("recursive"), ConfigSource::File);
+        assert_eq!(resolved.source_of(INCLUDE_KEY), ConfigSource::Default);
+        // The including file is left as written
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            "include: shared/team.yaml\njournal_tags: [laptop]\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_rejects_include_cycles_and_missing_includes() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(&config_path, "include: base.yaml\nrecursive: true\n").unwrap();
+        fs::write(
+            temp_dir.path().join("base.yaml"),
+            "include: ./config.yaml\n",
+        )
+        .unwrap();
+
+        let result = Config::resolve(Some(&config_path));
+        assert!(
+            matches!(result, Err(ConfigError::IncludeCycle(ref path)) if path.ends_with("config.yaml")),
+            "{:?}",
+            result
+        );
+
+        fs::write(temp_dir.path().join("base.yaml"), "include: missing.yaml\n").unwrap();
+        let result = Config::resolve(Some(&config_path));
+        assert!(matches!(
+            result,
+            Err(ConfigError::MissingInclude { ref path, ref included_from })
+                if *path == temp_dir.path().join("missing.yaml")
+                    && *included_from == temp_dir.path().join("base.yaml")
+        ));
+    }
+
+    #[test]
+    fn test_command_line_knowledge_base_overrides_environment() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let vault = temp_dir.path().join("vault");
+        fs::create_dir(&vault).unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(&config_path, "knowledge_base_path: \"/from/file\"\n").unwrap();
+
+        unsafe {
+            env::set_var("KNOWLEDGE_BASE", "/from/env");
+        }
+        let resolved =
+            Config::resolve_for(Some(&config_path), Some(vault.to_str().unwrap()), false);
+        let missing =
+            Config::resolve_for(Some(&config_path), Some("/nonexistent/parent/vault"), false);
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+        }
+
+        let resolved = resolved.unwrap();
+        assert_eq!(resolved.config.expanded_knowledge_base_path(), vault);
+        assert_eq!(
+            resolved.source_of("knowledge_base_path"),
+            ConfigSource::CommandLine
+        );
+        assert!(matches!(
+            missing,
+            Err(ConfigError::KnowledgeBaseNotFound(ref path)) if path == "/nonexistent/parent/vault"
+        ));
+    }
+
+    /// Resolve a configuration file for a Knowledge Base in `temp_dir`
+    /// containing `markers`, with `extra` appended to the file
+    fn resolve_vault(temp_dir: &Path, markers: &[&str], extra: &str) -> ResolvedConfig {
+        for marker in markers {
+            fs::create_dir_all(temp_dir.join(marker)).unwrap();
+        }
+        let config_path = temp_dir.join("config.yaml");
+        fs::write(
+            &config_path,
+            format!("knowledge_base_path: \"{}\"\n{}", temp_dir.display(), extra),
+        )
+        .unwrap();
+        Config::resolve(Some(&config_path)).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_detects_obsidian_vault() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = tempdir().unwrap();
+
+        let resolved = resolve_vault(temp_dir.path(), &[".obsidian"], "");
+
+        assert_eq!(resolved.config.detect_vault_type(), VaultType::Obsidian);
+        assert_eq!(resolved.config.pages_dir_name, ".");
+        assert_eq!(resolved.config.journals_dir_name, ".");
+        assert_eq!(resolved.config.journal_file_format, "%Y-%m-%d");
+        assert_eq!(
+            resolved.source_of("journal_file_format"),
+            ConfigSource::Detected
+        );
+    }
+
+    #[test]
+    fn test_resolve_detects_logseq_vault() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = tempdir().unwrap();
+
+        let resolved = resolve_vault(temp_dir.path(), &["logseq", "pages"], "");
+
+        assert_eq!(resolved.config.detect_vault_type(), VaultType::Logseq);
+        assert_eq!(resolved.config.pages_dir_name, "pages");
+        assert_eq!(resolved.config.journals_dir_name, "journals");
+        assert_eq!(resolved.config.journal_file_format, "%Y_%m_%d");
+        assert_eq!(resolved.source_of("pages_dir_name"), ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_resolve_explicit_layout_overrides_detection() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_dir = tempdir().unwrap();
+
+        let resolved = resolve_vault(
+            temp_dir.path(),
+            &[".obsidian"],
+            "journals_dir_name: \"Daily Notes\"\n",
+        );
+        assert_eq!(resolved.config.pages_dir_name, ".");
+        assert_eq!(resolved.config.journals_dir_name, "Daily Notes");
+        assert_eq!(resolved.source_of("journals_dir_name"), ConfigSource::File);
+
+        let resolved = resolve_vault(temp_dir.path(), &[".obsidian"], "vault_type: Logseq\n");
+        assert_eq!(resolved.config.detect_vault_type(), VaultType::Logseq);
+        assert_eq!(resolved.config.pages_dir_name, "pages");
+        assert_eq!(resolved.config.journal_file_format, "%Y_%m_%d");
+    }
+
+    #[test]
+    fn test_config_validation_journal_file_format() {
+        for (format, valid) in [("%Y-%m-%d", true), ("%Y/%m/%d", false), ("%Q", false)] {
+            let config = Config {
+                knowledge_base_path: "/tmp".to_string(),
+                journal_file_format: format.to_string(),
+                ..Default::default()
+            };
+            assert_eq!(config.validate().is_ok(), valid, "format {:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_resolved_config_render() {
+        let mut sources = BTreeMap::new();
+        sources.insert("knowledge_base_path".to_string(), ConfigSource::File);
+        let resolved = ResolvedConfig {
+            config: Config {
+                knowledge_base_path: "/vault".to_string(),
+                max_depth: Some(2),
+                ..Default::default()
+            },
+            config_path: PathBuf::from("/config.yaml"),
+            sources,
+        };
+
+        let rendered = resolved.render();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert!(lines.contains(&"knowledge_base_path: \"/vault\" -> /vault (file)"));
+        assert!(lines.contains(&"max_depth: 2 (default)"));
+        assert!(lines.contains(&"recursive: false (default)"));
+
+        let mut sorted = lines.clone();
+        sorted.sort();
+        assert_eq!(lines, sorted);
+        assert_eq!(rendered, resolved.render());
+    }
+
+    #[test]
+    fn test_initialize_with_prompt_writes_confirmed_path() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if env::var("LOCAL_SHELF_SKIP_CONFIG_INIT").is_ok() {
+            return;
+        }
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        let vault = temp_dir.path().join("vault");
+
+        // First answer is rejected because its parent does not exist
+        let input = format!("/nonexistent/deep/vault\n{}\n", vault.display());
+        let mut output = Vec::new();
+        let mut prompt = Prompt::new(std::io::Cursor::new(input), &mut output);
+
+        Config::initialize_with_prompt(Some(&config_path), Some(&mut prompt), false, true).unwrap();
+        drop(prompt);
+
+        let config = Config::parse(&fs::read_to_string(&config_path).unwrap(), &config_path);
+        assert_eq!(
+            config.unwrap().knowledge_base_path,
+            vault.display().to_string()
+        );
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains("Please try again")
+        );
+    }
+
+    #[test]
+    fn test_initialize_with_prompt_skips_existing_file_unless_forced() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if env::var("LOCAL_SHELF_SKIP_CONFIG_INIT").is_ok() {
+            return;
+        }
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(&config_path, "knowledge_base_path = \"/existing\"\n").unwrap();
+
+        let mut prompt = Prompt::new(std::io::Cursor::new(""), std::io::sink());
+        Config::initialize_with_prompt(Some(&config_path), Some(&mut prompt), false, true).unwrap();
+        let content = fs::read_to_string(&config_path).unwrap();
+        assert!(content.contains("/existing"));
+
+        let input = format!("{}\n", temp_dir.path().display());
+        let mut prompt = Prompt::new(std::io::Cursor::new(input), std::io::sink());
+        Config::initialize_with_prompt(Some(&config_path), Some(&mut prompt), true, true).unwrap();
+        let config = Config::parse(&fs::read_to_string(&config_path).unwrap(), &config_path);
+        assert_eq!(
+            config.unwrap().knowledge_base_path,
+            temp_dir.path().display().to_string()
+        );
+    }
+
+    #[test]
+    fn test_forced_initialize_keeps_other_settings() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if env::var("LOCAL_SHELF_SKIP_CONFIG_INIT").is_ok() {
+            return;
+        }
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            "# My settings\nknowledge_base_path: \"/existing\"\ninclude_timestamp: false\n",
+        )
+        .unwrap();
+
+        let input = format!("{}\n", temp_dir.path().display());
+        let mut prompt = Prompt::new(std::io::Cursor::new(input), std::io::sink());
+        Config::initialize_with_prompt(Some(&config_path), Some(&mut prompt), true, true).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            format!(
+                "# My settings\nknowledge_base_path: \"{}\"\ninclude_timestamp: false\n",
+                temp_dir.path().display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_with_knowledge_base_path_adds_missing_assignment() {
+        let merged = Config::with_knowledge_base_path(
+            "include_timestamp = false\n\n[[routing]]\npattern = \"*.pdf\"\n",
+            Path::new("config.toml"),
+            "/vault",
+        );
+        assert_eq!(
+            merged,
+            "knowledge_base_path = \"/vault\"\ninclude_timestamp = false\n\n[[routing]]\npattern = \"*.pdf\"\n"
+        );
+    }
+
+    #[test]
+    fn test_initial_file_content_escapes_path() {
+        let yaml = Config::initial_file_content(Path::new("config.yaml"), "C:\\Vault \"A\"");
+        let toml_content =
+            Config::initial_file_content(Path::new("config.toml"), "C:\\Vault \"A\"");
+
+        let from_yaml = Config::parse(&yaml, Path::new("config.yaml")).unwrap();
+        let from_toml = Config::parse(&toml_content, Path::new("config.toml")).unwrap();
+        assert_eq!(from_yaml.knowledge_base_path, "C:\\Vault \"A\"");
+        assert_eq!(from_toml.knowledge_base_path, "C:\\Vault \"A\"");
+    }
+
+    #[test]
+    fn test_load_migrates_versionless_config() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        let content = format!(
+            "# My vault\nknowledge_base_path: \"{}\"\n",
+            temp_dir.path().display()
+        );
+        fs::write(&config_path, &content).unwrap();
+
+        let config = Config::load_with_override(Some(&config_path)).unwrap();
+        assert_eq!(config.version, CURRENT_CONFIG_VERSION);
+        assert_eq!(config.min_file_size_bytes, 0);
+
+        // Only the version is written back; the comment and keys stay
+        let rewritten = fs::read_to_string(&config_path).unwrap();
+        assert_eq!(
+            rewritten,
+            format!("version: {}\n{}", CURRENT_CONFIG_VERSION, content)
+        );
+        let reloaded = Config::parse(&rewritten, &config_path).unwrap();
+        assert_eq!(reloaded.version, CURRENT_CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_load_replaces_older_toml_version() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                "version = 0 # old\nknowledge_base_path = \"{}\"\n",
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        Config::load_with_override(Some(&config_path)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            format!(
+                "version = {}\nknowledge_base_path = \"{}\"\n",
+                CURRENT_CONFIG_VERSION,
+                temp_dir.path().display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_future_version() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        let content = format!(
+            "version = {}\nknowledge_base_path = \"{}\"\n",
+            CURRENT_CONFIG_VERSION + 1,
+            temp_dir.path().display()
+        );
+        fs::write(&config_path, &content).unwrap();
+
+        let result = Config::load_with_override(Some(&config_path));
+        assert!(matches!(
+            result,
+            Err(ConfigError::UnsupportedVersion(v)) if v == CURRENT_CONFIG_VERSION + 1
+        ));
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_migrate_current_version_is_noop() {
+        let mut config = Config::default();
+        assert!(!Config::migrate(&mut config).unwrap());
+    }
+
+    #[test]
+    fn test_yaml_serialization() {
+        let config = Config {
+            knowledge_base_path: "/test/path".to_string(),
+            ..Default::default()
         };
 
         let yaml = serde_yaml::to_string(&config).unwrap();
@@ -290,14 +3224,90 @@ mod tests {
         assert_eq!(config, deserialized);
     }
 
+    #[test]
+    fn test_toml_serialization() {
+        let config = Config {
+            knowledge_base_path: "/test/path".to_string(),
+            exclude_patterns: vec!["_*.md".to_string()],
+            max_age_days: Some(3),
+            ..Default::default()
+        };
+
+        let toml_content = toml::to_string(&config).unwrap();
+        let deserialized = Config::parse(&toml_content, Path::new("config.toml")).unwrap();
+
+        assert_eq!(config, deserialized);
+    }
+
+    #[test]
+    fn test_parse_dispatches_on_extension() {
+        let yaml = Config::parse("knowledge_base_path: \"/yaml\"", Path::new("config.yaml"));
+        assert_eq!(yaml.unwrap().knowledge_base_path, "/yaml");
+
+        let toml_config = Config::parse("knowledge_base_path = \"/toml\"", Path::new("a.TOML"));
+        assert_eq!(toml_config.unwrap().knowledge_base_path, "/toml");
+    }
+
+    #[test]
+    fn test_parse_invalid_toml() {
+        let result = Config::parse("knowledge_base_path = ", Path::new("config.toml"));
+        assert!(matches!(result, Err(ConfigError::TomlError(_))));
+    }
+
+    #[test]
+    fn test_expanded_knowledge_base_path_is_stable() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut config = Config {
+            knowledge_base_path: "$LOCAL_SHELF_TEST_VAULT/notes".to_string(),
+            ..Default::default()
+        };
+
+        unsafe {
+            env::set_var("LOCAL_SHELF_TEST_VAULT", "/first");
+        }
+        let first = config.expanded_knowledge_base_path().to_path_buf();
+        unsafe {
+            env::set_var("LOCAL_SHELF_TEST_VAULT", "/second");
+        }
+        let second = config.expanded_knowledge_base_path();
+        assert_eq!(first, PathBuf::from("/first/notes"));
+        assert_eq!(second, first);
+        assert!(std::ptr::eq(
+            &*second,
+            &*config.expanded_knowledge_base_path()
+        ));
+        assert_eq!(config.get_knowledge_base_path(), "/first/notes");
+
+        config.set_knowledge_base_path("$LOCAL_SHELF_TEST_VAULT/other");
+        let changed = config.expanded_knowledge_base_path().to_path_buf();
+        unsafe {
+            env::remove_var("LOCAL_SHELF_TEST_VAULT");
+        }
+        assert_eq!(changed, PathBuf::from("/second/other"));
+    }
+
+    #[test]
+    fn test_expanded_knowledge_base_path_follows_assigned_path() {
+        let mut config = Config {
+            knowledge_base_path: "/first".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.expanded_knowledge_base_path(), Path::new("/first"));
+
+        config.knowledge_base_path = "/second".to_string();
+        assert_eq!(config.expanded_knowledge_base_path(), Path::new("/second"));
+        assert_eq!(config.get_knowledge_base_path(), "/second");
+    }
+
     #[test]
     fn test_get_knowledge_base_path() {
         let config = Config {
             knowledge_base_path: "~/Test".to_string(),
+            ..Default::default()
         };
 
         let expanded = config.get_knowledge_base_path();
-        let home = dirs::home_dir().unwrap();
-        assert_eq!(expanded, format!("{}/Test", home.display()));
+        let home = paths::home_dir().unwrap();
+        assert_eq!(expanded, home.join("Test").display().to_string());
     }
 }
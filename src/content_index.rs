@@ -0,0 +1,264 @@
+//! Content hashes of the pages already in the Knowledge Base
+//!
+//! Used by `dedupe_by_content` to spot a re-download of an article that was
+//! imported earlier under a different name. The index is built once per run
+//! by streaming every file through SHA-256, ignoring the page properties an
+//! import adds, and grows with every page the run imports.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use crate::config::Config;
+use crate::file_operations::{FileOperationError, FileOperations};
+
+/// Map from content hash to an existing page with that content
+///
+/// A hash mapped to `None` is being imported by a move that has not
+/// finished yet.
+#[derive(Debug, Default)]
+pub struct ContentIndex {
+    pages: Mutex<HashMap<String, Option<PathBuf>>>,
+    finished: Condvar,
+    max_file_size: u64,
+}
+
+/// Result of looking up a source file with [`ContentIndex::reserve`]
+#[derive(Debug, PartialEq)]
+pub enum Lookup {
+    /// A page with the same content already exists
+    Existing(PathBuf),
+    /// No page has the content yet, and it is reserved for the source
+    /// under this hash until [`ContentIndex::finish`]
+    Reserved(String),
+    /// The source is over the size cap and was not looked up
+    Unindexed,
+}
+
+impl ContentIndex {
+    /// Hash every file under `directory`, skipping files larger than
+    /// `dedupe_max_file_size_bytes`
+    ///
+    /// Hidden files and directories (including the tool's own temporary
+    /// files) are ignored and symlinked directories are not followed. A
+    /// missing directory yields an empty index.
+    pub fn build(directory: &Path, config: &Config) -> Result<Self, FileOperationError> {
+        let mut index = ContentIndex {
+            max_file_size: config.dedupe_max_file_size_bytes,
+            ..Default::default()
+        };
+        if directory.is_dir() {
+            let pages = index.pages.get_mut().unwrap_or_else(|e| e.into_inner());
+            Self::add_directory(pages, directory, index.max_file_size, config)?;
+        }
+        Ok(index)
+    }
+
+    /// Number of pages in the index
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no page was indexed
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Find an existing page with the same content as `source`, or reserve
+    /// the content for it
+    ///
+    /// While another source with the same content is being imported this
+    /// waits for that move to [`Self::finish`]. Sources over the size cap
+    /// are never looked up, since no indexed page can match them.
+    pub fn reserve(&self, source: &Path, config: &Config) -> Result<Lookup, FileOperationError> {
+        if fs::metadata(source)?.len() > self.max_file_size {
+            return Ok(Lookup::Unindexed);
+        }
+        let hash = FileOperations::page_hash(source, config)?;
+
+        let mut pages = self.lock();
+        loop {
+            match pages.get(&hash) {
+                Some(Some(existing)) => return Ok(Lookup::Existing(existing.clone())),
+                Some(None) => {
+                    pages = self.finished.wait(pages).unwrap_or_else(|e| e.into_inner());
+                }
+                None => {
+                    pages.insert(hash.clone(), None);
+                    return Ok(Lookup::Reserved(hash));
+                }
+            }
+        }
+    }
+
+    /// Settle a reservation made by [`Self::reserve`]: record the `page`
+    /// now holding the content, or release the hash when nothing was imported
+    pub fn finish(&self, hash: &str, page: Option<&Path>) {
+        let mut pages = self.lock();
+        match page {
+            Some(page) => {
+                pages.insert(hash.to_string(), Some(page.to_path_buf()));
+            }
+            None => {
+                pages.remove(hash);
+            }
+        }
+        self.finished.notify_all();
+    }
+
+    fn add_directory(
+        pages: &mut HashMap<String, Option<PathBuf>>,
+        directory: &Path,
+        max_file_size: u64,
+        config: &Config,
+    ) -> Result<(), FileOperationError> {
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                Self::add_directory(pages, &path, max_file_size, config)?;
+            } else if file_type.is_file() && entry.metadata()?.len() <= max_file_size {
+                let hash = FileOperations::page_hash(&path, config)?;
+                pages.entry(hash).or_insert(Some(path));
+            }
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Option<PathBuf>>> {
+        self.pages.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn config(max_file_size: u64) -> Config {
+        Config {
+            dedupe_max_file_size_bytes: max_file_size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reserve_finds_match_across_names_and_subdirectories() {
+        let temp_dir = tempdir().unwrap();
+        let pages = temp_dir.path().join("pages");
+        fs::create_dir_all(pages.join("2024/03")).unwrap();
+        fs::write(pages.join("2024/03/original.md"), "# Article").unwrap();
+        fs::write(pages.join("other.md"), "# Other").unwrap();
+
+        let config = config(u64::MAX);
+        let index = ContentIndex::build(&pages, &config).unwrap();
+        assert_eq!(index.len(), 2);
+
+        let source = temp_dir.path().join("article (1).md");
+        fs::write(&source, "# Article").unwrap();
+        assert_eq!(
+            index.reserve(&source, &config).unwrap(),
+            Lookup::Existing(pages.join("2024/03/original.md"))
+        );
+
+        fs::write(&source, "# Something new").unwrap();
+        assert!(matches!(
+            index.reserve(&source, &config).unwrap(),
+            Lookup::Reserved(_)
+        ));
+    }
+
+    #[test]
+    fn test_finished_import_is_found_by_later_sources() {
+        let temp_dir = tempdir().unwrap();
+        let config = config(u64::MAX);
+        let index = ContentIndex::build(&temp_dir.path().join("pages"), &config).unwrap();
+        let first = temp_dir.path().join("article.md");
+        let second = temp_dir.path().join("article (1).md");
+        fs::write(&first, "# Article").unwrap();
+        fs::write(&second, "# Article").unwrap();
+
+        let Lookup::Reserved(hash) = index.reserve(&first, &config).unwrap() else {
+            panic!("content should be new");
+        };
+        let page = temp_dir.path().join("pages/article.md");
+        index.finish(&hash, Some(&page));
+
+        assert_eq!(
+            index.reserve(&second, &config).unwrap(),
+            Lookup::Existing(page)
+        );
+    }
+
+    #[test]
+    fn test_released_reservation_is_free_again() {
+        let temp_dir = tempdir().unwrap();
+        let config = config(u64::MAX);
+        let index = ContentIndex::build(&temp_dir.path().join("pages"), &config).unwrap();
+        let source = temp_dir.path().join("article.md");
+        fs::write(&source, "# Article").unwrap();
+
+        let Lookup::Reserved(hash) = index.reserve(&source, &config).unwrap() else {
+            panic!("content should be new");
+        };
+        index.finish(&hash, None);
+
+        assert!(matches!(
+            index.reserve(&source, &config).unwrap(),
+            Lookup::Reserved(_)
+        ));
+    }
+
+    #[test]
+    fn test_build_ignores_added_page_properties() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            add_page_properties: true,
+            ..config(u64::MAX)
+        };
+        let keys: Vec<_> = config.page_properties.keys().collect();
+        let properties: String = keys
+            .iter()
+            .map(|key| format!("{}:: value\n", key))
+            .collect();
+        fs::write(
+            temp_dir.path().join("article.md"),
+            format!("{}\n# Article\n", properties),
+        )
+        .unwrap();
+
+        let index = ContentIndex::build(temp_dir.path(), &config).unwrap();
+        let source = temp_dir.path().join("download.md");
+        fs::write(&source, "# Article\n").unwrap();
+
+        assert_eq!(
+            index.reserve(&source, &config).unwrap(),
+            Lookup::Existing(temp_dir.path().join("article.md"))
+        );
+    }
+
+    #[test]
+    fn test_build_skips_files_over_size_cap() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("small.md"), "tiny").unwrap();
+        fs::write(temp_dir.path().join("large.md"), "much larger content").unwrap();
+        fs::write(temp_dir.path().join(".hidden.md"), "tiny").unwrap();
+
+        let index = ContentIndex::build(temp_dir.path(), &config(4)).unwrap();
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn test_build_missing_directory_is_empty() {
+        let temp_dir = tempdir().unwrap();
+        let index =
+            ContentIndex::build(&temp_dir.path().join("missing"), &config(u64::MAX)).unwrap();
+        assert!(index.is_empty());
+    }
+}
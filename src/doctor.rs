@@ -0,0 +1,471 @@
+//! Setup diagnostics for `local_shelf doctor`
+//!
+//! Unlike loading the configuration for `stow`, which stops at the first
+//! problem, the doctor runs every check it can and reports each one as
+//! passed, worth a warning or failed, so a new setup can be fixed in one go.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::color;
+use crate::config::Config;
+use crate::file_discovery::FileDiscovery;
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    /// Symbol printed in front of the check
+    fn symbol(self) -> String {
+        match self {
+            CheckStatus::Pass => color::success("✓"),
+            CheckStatus::Warn => color::warning("⚠"),
+            CheckStatus::Fail => color::failure("✗"),
+        }
+    }
+}
+
+/// A check and what it found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// Every check run by the doctor, in order
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DoctorReport {
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    fn push(&mut self, name: &'static str, status: CheckStatus, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    /// Status of the check called `name`, if it ran
+    pub fn status_of(&self, name: &str) -> Option<CheckStatus> {
+        self.checks
+            .iter()
+            .find(|check| check.name == name)
+            .map(|check| check.status)
+    }
+
+    /// Whether any check failed
+    pub fn has_failures(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.status == CheckStatus::Fail)
+    }
+
+    /// Render the checks one per line, e.g. `✓ Source directory: ./downloads`
+    pub fn render(&self) -> String {
+        self.checks
+            .iter()
+            .map(|check| {
+                format!(
+                    "{} {}: {}\n",
+                    check.status.symbol(),
+                    check.name,
+                    check.detail
+                )
+            })
+            .collect()
+    }
+}
+
+/// Public interface for diagnosing a setup
+pub struct Doctor;
+
+impl Doctor {
+    /// Check the configuration read from `config_override` (or the usual
+    /// location) and the `source` directory `stow` would scan
+    ///
+    /// `knowledge_base` is the Knowledge Base given on the command line, if any.
+    pub fn diagnose(
+        config_override: Option<&Path>,
+        knowledge_base: Option<&str>,
+        source: &Path,
+    ) -> DoctorReport {
+        let mut report = DoctorReport::default();
+
+        let config = Self::check_config(config_override, knowledge_base, &mut report);
+        if let Some(config) = &config {
+            Self::check_knowledge_base(config, &mut report);
+            Self::check_subdirectory(
+                "Pages directory",
+                &config.knowledge_base_subdir(&config.pages_dir_name),
+                &mut report,
+            );
+            Self::check_subdirectory(
+                "Journals directory",
+                &config.knowledge_base_subdir(&config.journals_dir_name),
+                &mut report,
+            );
+        }
+
+        let source_ok = Self::check_source(source, &mut report);
+        if let Some(config) = &config
+            && source_ok
+        {
+            Self::check_candidates(config, source, &mut report);
+        }
+
+        report
+    }
+
+    /// Find and load the configuration file, returning the configuration
+    /// when it is usable
+    ///
+    /// The path checks `--force` can skip are left to the Knowledge Base
+    /// check, so they are reported there instead of failing the load.
+    fn check_config(
+        config_override: Option<&Path>,
+        knowledge_base: Option<&str>,
+        report: &mut DoctorReport,
+    ) -> Option<Config> {
+        match Config::resolve_config_file_path(config_override) {
+            Ok(path) if Config::is_stdin_path(&path) => {
+                report.push("Config file", CheckStatus::Pass, "read from stdin");
+            }
+            Ok(path) if path.is_file() => {
+                report.push(
+                    "Config file",
+                    CheckStatus::Pass,
+                    format!("found at {}", path.display()),
+                );
+            }
+            Ok(path) => {
+                report.push(
+                    "Config file",
+                    CheckStatus::Warn,
+                    format!("not found at {}; using the defaults", path.display()),
+                );
+            }
+            Err(e) => {
+                report.push("Config file", CheckStatus::Fail, e.to_string());
+                return None;
+            }
+        }
+
+        match Config::resolve_for(config_override, knowledge_base, true) {
+            Ok(resolved) => {
+                report.push("Configuration", CheckStatus::Pass, "parsed and valid");
+                Some(resolved.config)
+            }
+            Err(e) => {
+                report.push("Configuration", CheckStatus::Fail, e.to_string());
+                None
+            }
+        }
+    }
+
+    /// Check that the Knowledge Base exists and is writable, or can be created
+    fn check_knowledge_base(config: &Config, report: &mut DoctorReport) {
+        const NAME: &str = "Knowledge Base";
+        let path = &*config.expanded_knowledge_base_path();
+
+        if path.is_dir() {
+            match Self::probe_writable(path) {
+                Ok(()) => report.push(
+                    NAME,
+                    CheckStatus::Pass,
+                    format!("{} exists and is writable", path.display()),
+                ),
+                Err(e) => report.push(
+                    NAME,
+                    CheckStatus::Fail,
+                    format!("{} is not writable: {}", path.display(), e),
+                ),
+            }
+        } else if path.exists() {
+            report.push(
+                NAME,
+                CheckStatus::Fail,
+                format!("{} is not a directory", path.display()),
+            );
+        } else if path.parent().is_some_and(Path::is_dir) {
+            report.push(
+                NAME,
+                CheckStatus::Warn,
+                format!(
+                    "{} does not exist yet; it is created on the first import",
+                    path.display()
+                ),
+            );
+        } else {
+            report.push(
+                NAME,
+                CheckStatus::Fail,
+                format!(
+                    "{} does not exist and neither does its parent directory",
+                    path.display()
+                ),
+            );
+        }
+    }
+
+    /// Check that a Knowledge Base subdirectory is writable, or can be created
+    fn check_subdirectory(name: &'static str, path: &Path, report: &mut DoctorReport) {
+        if path.is_dir() {
+            match Self::probe_writable(path) {
+                Ok(()) => report.push(name, CheckStatus::Pass, path.display().to_string()),
+                Err(e) => report.push(
+                    name,
+                    CheckStatus::Fail,
+                    format!("{} is not writable: {}", path.display(), e),
+                ),
+            }
+            return;
+        }
+        if path.exists() {
+            report.push(
+                name,
+                CheckStatus::Fail,
+                format!("{} is not a directory", path.display()),
+            );
+            return;
+        }
+
+        let Some(ancestor) = path.ancestors().skip(1).find(|ancestor| ancestor.exists()) else {
+            report.push(
+                name,
+                CheckStatus::Fail,
+                format!("{} cannot be created", path.display()),
+            );
+            return;
+        };
+        let result = if ancestor.is_dir() {
+            Self::probe_writable(ancestor)
+        } else {
+            Err(io::Error::other(format!(
+                "{} is not a directory",
+                ancestor.display()
+            )))
+        };
+        match result {
+            Ok(()) => report.push(
+                name,
+                CheckStatus::Pass,
+                format!("{} can be created", path.display()),
+            ),
+            Err(e) => report.push(
+                name,
+                CheckStatus::Fail,
+                format!("{} cannot be created: {}", path.display(), e),
+            ),
+        }
+    }
+
+    /// Check that the source directory exists, returning whether it does
+    fn check_source(source: &Path, report: &mut DoctorReport) -> bool {
+        const NAME: &str = "Source directory";
+        if source.is_dir() {
+            report.push(NAME, CheckStatus::Pass, source.display().to_string());
+            true
+        } else if source.exists() {
+            report.push(
+                NAME,
+                CheckStatus::Fail,
+                format!("{} is not a directory", source.display()),
+            );
+            false
+        } else {
+            report.push(
+                NAME,
+                CheckStatus::Fail,
+                format!("{} does not exist", source.display()),
+            );
+            false
+        }
+    }
+
+    /// Count the files in `source` that `stow` would import
+    fn check_candidates(config: &Config, source: &Path, report: &mut DoctorReport) {
+        const NAME: &str = "Candidate files";
+        let mut discovery = match FileDiscovery::discover_in_directory_report(source, config) {
+            Ok(discovery) => discovery,
+            Err(e) => {
+                report.push(NAME, CheckStatus::Fail, e.to_string());
+                return;
+            }
+        };
+        FileDiscovery::skip_knowledge_base_files(&mut discovery, source, config);
+
+        let mut detail = format!(
+            "{} file(s) with extension(s) {} would be imported",
+            discovery.files.len(),
+            [&config.extensions[..], &config.attachment_extensions[..]]
+                .concat()
+                .join(", ")
+        );
+        if !discovery.skipped.is_empty() {
+            detail.push_str(&format!(
+                "; {} skipped (see `local_shelf scan`)",
+                discovery.skipped.len()
+            ));
+        }
+        let status = if discovery.files.is_empty() {
+            CheckStatus::Warn
+        } else {
+            CheckStatus::Pass
+        };
+        report.push(NAME, status, detail);
+    }
+
+    /// Create and remove a file in `directory` to see whether it is writable
+    fn probe_writable(directory: &Path) -> io::Result<()> {
+        let probe = directory.join(".local_shelf_doctor.tmp");
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&probe)?;
+        fs::remove_file(&probe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TEST_ENV_LOCK;
+    use std::env;
+    use tempfile::tempdir;
+
+    /// Diagnose with `KNOWLEDGE_BASE` unset so only the config file counts
+    fn diagnose_isolated(config_path: &Path, source: &Path) -> DoctorReport {
+        let _guard = TEST_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original = env::var("KNOWLEDGE_BASE").ok();
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+        }
+
+        let report = Doctor::diagnose(Some(config_path), None, source);
+
+        if let Some(value) = original {
+            unsafe {
+                env::set_var("KNOWLEDGE_BASE", value);
+            }
+        }
+        report
+    }
+
+    #[test]
+    fn test_diagnose_healthy_setup() {
+        let temp_dir = tempdir().unwrap();
+        let kb = temp_dir.path().join("kb");
+        let source = temp_dir.path().join("downloads");
+        fs::create_dir_all(kb.join("pages")).unwrap();
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("article.md"), "# Article").unwrap();
+        fs::write(source.join("notes.md"), "# Notes").unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            format!("knowledge_base_path: {}\n", kb.display()),
+        )
+        .unwrap();
+
+        let report = diagnose_isolated(&config_path, &source);
+
+        assert!(!report.has_failures(), "{}", report.render());
+        let statuses: Vec<_> = report
+            .checks
+            .iter()
+            .map(|check| (check.name, check.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("Config file", CheckStatus::Pass),
+                ("Configuration", CheckStatus::Pass),
+                ("Knowledge Base", CheckStatus::Pass),
+                ("Pages directory", CheckStatus::Pass),
+                ("Journals directory", CheckStatus::Pass),
+                ("Source directory", CheckStatus::Pass),
+                ("Candidate files", CheckStatus::Pass),
+            ]
+        );
+        assert!(
+            report
+                .render()
+                .contains("✓ Candidate files: 2 file(s) with extension(s) md would be imported\n")
+        );
+    }
+
+    #[test]
+    fn test_diagnose_reports_every_problem_of_a_broken_setup() {
+        let temp_dir = tempdir().unwrap();
+        let kb = temp_dir.path().join("kb");
+        fs::create_dir_all(&kb).unwrap();
+        // A file where the pages directory should be
+        fs::write(kb.join("pages"), "").unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            format!("knowledge_base_path: {}\n", kb.display()),
+        )
+        .unwrap();
+        let source = temp_dir.path().join("missing");
+
+        let report = diagnose_isolated(&config_path, &source);
+
+        assert!(report.has_failures());
+        assert_eq!(report.status_of("Knowledge Base"), Some(CheckStatus::Pass));
+        assert_eq!(report.status_of("Pages directory"), Some(CheckStatus::Fail));
+        assert_eq!(
+            report.status_of("Journals directory"),
+            Some(CheckStatus::Pass)
+        );
+        assert_eq!(
+            report.status_of("Source directory"),
+            Some(CheckStatus::Fail)
+        );
+        assert_eq!(report.status_of("Candidate files"), None);
+    }
+
+    #[test]
+    fn test_diagnose_invalid_config_and_missing_knowledge_base() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("downloads");
+        fs::create_dir_all(&source).unwrap();
+
+        // Unparsable configuration
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(&config_path, "knowledge_base_path: [unclosed\n").unwrap();
+        let report = diagnose_isolated(&config_path, &source);
+        assert_eq!(report.status_of("Config file"), Some(CheckStatus::Pass));
+        assert_eq!(report.status_of("Configuration"), Some(CheckStatus::Fail));
+        assert_eq!(report.status_of("Knowledge Base"), None);
+        assert_eq!(
+            report.status_of("Source directory"),
+            Some(CheckStatus::Pass)
+        );
+
+        // A Knowledge Base whose parent directory is missing
+        let kb = temp_dir.path().join("unmounted/kb");
+        fs::write(
+            &config_path,
+            format!("knowledge_base_path: {}\n", kb.display()),
+        )
+        .unwrap();
+        let report = diagnose_isolated(&config_path, &source);
+        assert_eq!(report.status_of("Configuration"), Some(CheckStatus::Pass));
+        assert_eq!(report.status_of("Knowledge Base"), Some(CheckStatus::Fail));
+        assert_eq!(report.status_of("Candidate files"), Some(CheckStatus::Warn));
+
+        // No config file at all falls back to the defaults
+        let report = diagnose_isolated(&temp_dir.path().join("absent.yaml"), &source);
+        assert_eq!(report.status_of("Config file"), Some(CheckStatus::Warn));
+    }
+}
@@ -0,0 +1,518 @@
+//! Progress events emitted by the `stow` pipeline
+//!
+//! [`crate::pipeline::run`] reports what it is doing through an
+//! [`EventSink`] instead of printing. The CLI uses [`StdoutSink`], wrapped
+//! in a [`ProgressSink`] on interactive terminals; embedders implement the
+//! trait to route progress into their own UI or logs, and [`SilentSink`]
+//! ignores everything.
+
+use std::path::Path;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::color;
+use crate::file_discovery::DiscoveredFile;
+use crate::file_operations::FileOperationError;
+use crate::hooks::HookError;
+use crate::journal_management::{JournalError, JournalPreview};
+use crate::ledger::LedgerError;
+use crate::manifest::ManifestError;
+use crate::run_summary::SkipReason;
+
+/// Receiver for pipeline progress events
+///
+/// Every method has an empty default so a sink only implements the events
+/// it cares about. [`EventSink::about_to_move`] and
+/// [`EventSink::file_processed`] are called from move worker threads, which
+/// is why sinks must be `Sync`.
+pub trait EventSink: Sync {
+    /// The run started scanning `source` for a Knowledge Base at `knowledge_base`
+    fn run_started(&self, _knowledge_base: &str, _source: &Path) {}
+
+    /// Discovery finished; `files` are about to be processed in this order
+    fn files_discovered(&self, _source: &Path, _files: &[DiscoveredFile]) {}
+
+    /// Only the first `limit` of `total` discovered files will be processed
+    fn limit_applied(&self, _limit: usize, _total: usize) {}
+
+    /// A file was left in place
+    fn skipped(&self, _path: &Path, _reason: SkipReason) {}
+
+    /// The run is a dry run and stops before moving anything
+    fn dry_run(&self) {}
+
+    /// A dry run would change today's journal as shown by `preview`
+    fn journal_preview(&self, _preview: &JournalPreview) {}
+
+    /// `total` files are about to be moved into `destination` inside the Knowledge Base
+    fn moves_started(&self, _destination: &str, _total: usize) {}
+
+    /// Existing pages were hashed for `dedupe_by_content`
+    fn content_indexed(&self, _pages: usize) {}
+
+    /// A worker picked up `path` and is about to move it
+    fn about_to_move(&self, _path: &Path) {}
+
+    /// A worker is done with `path`, whatever the outcome
+    fn file_processed(&self, _path: &Path) {}
+
+    /// A file was moved from `from` to `to`
+    fn moved(&self, _from: &Path, _to: &Path) {}
+
+    /// A file was skipped because `existing` already holds its content
+    fn duplicate_skipped(&self, _path: &Path, _existing: &Path, _source_removed: bool) {}
+
+    /// A file could not be moved
+    fn move_failed(&self, _path: &Path, _error: &FileOperationError) {}
+
+    /// All moves finished, `moved` of them successfully
+    fn moves_finished(&self, _moved: usize, _destination: &str) {}
+
+    /// Journaling was turned off, so no entries are written
+    fn journal_disabled(&self) {}
+
+    /// Journal entries are about to be written
+    fn journal_started(&self) {}
+
+    /// `entries` journal entries were written to `journal`
+    fn journal_written(&self, _journal: &Path, _entries: usize) {}
+
+    /// Journal entries could not be written
+    fn journal_failed(&self, _error: &JournalError) {}
+
+    /// A file moved to `to` was put back at `from` because the journal
+    /// could not be written
+    fn rolled_back(&self, _from: &Path, _to: &Path) {}
+
+    /// A moved file could not be put back
+    fn rollback_failed(&self, _path: &Path, _error: &str) {}
+
+    /// Links to moved pages were updated in `journals` journal files
+    fn journal_links_updated(&self, _journals: usize) {}
+
+    /// Links to moved pages could not be updated
+    fn journal_links_failed(&self, _error: &JournalError) {}
+
+    /// The moves could not be recorded for `undo`
+    fn ledger_failed(&self, _error: &LedgerError) {}
+
+    /// A handled file left in the source directory could not be marked as processed
+    fn processed_mark_failed(&self, _path: &Path, _error: &std::io::Error) {}
+
+    /// The seen set could not be read or updated
+    fn seen_set_failed(&self, _error: &std::io::Error) {}
+
+    /// The checksum manifest could not be updated
+    fn manifest_failed(&self, _error: &ManifestError) {}
+
+    /// The `per_file_hook` failed for a moved page
+    fn hook_failed(&self, _destination: &Path, _error: &HookError) {}
+}
+
+/// Sink that ignores every event
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SilentSink;
+
+impl EventSink for SilentSink {}
+
+/// Sink that prints progress the way the `stow` command always has
+///
+/// Progress goes to stdout and failures to stderr.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdoutSink {
+    /// Only report failures, keeping stdout free for other output such as JSON
+    pub quiet: bool,
+}
+
+impl EventSink for StdoutSink {
+    fn run_started(&self, knowledge_base: &str, source: &Path) {
+        if self.quiet {
+            return;
+        }
+        println!("Local Shelf starting...");
+        println!("Knowledge Base path: {}", knowledge_base);
+        println!("Scanning {} for markdown files...", source.display());
+    }
+
+    fn files_discovered(&self, source: &Path, files: &[DiscoveredFile]) {
+        if self.quiet {
+            return;
+        }
+        if files.is_empty() {
+            println!("No markdown files found in {}", source.display());
+            return;
+        }
+
+        println!(
+            "Found {} markdown file(s) in {}:",
+            files.len(),
+            source.display()
+        );
+        for file in files {
+            println!("  - {}", file.path.display());
+        }
+    }
+
+    fn limit_applied(&self, limit: usize, total: usize) {
+        if !self.quiet {
+            println!(
+                "Limiting this run to the first {} of {} file(s); the rest stay for the next run.",
+                limit, total
+            );
+        }
+    }
+
+    fn dry_run(&self) {
+        if !self.quiet {
+            println!("\nDry run: no files were moved.");
+        }
+    }
+
+    fn journal_preview(&self, preview: &JournalPreview) {
+        if !self.quiet {
+            println!(
+                "\nJournal changes that would be made to {}:",
+                preview.path.display()
+            );
+            print!("{}", preview.unified_diff());
+        }
+    }
+
+    fn moves_started(&self, destination: &str, _total: usize) {
+        if !self.quiet {
+            println!("\nMoving files to {{Knowledge Base}}/{}...", destination);
+        }
+    }
+
+    fn content_indexed(&self, pages: usize) {
+        if !self.quiet {
+            println!(
+                "Indexed {} existing page(s) for duplicate detection.",
+                pages
+            );
+        }
+    }
+
+    fn moved(&self, from: &Path, to: &Path) {
+        if !self.quiet {
+            println!(
+                "{} Moved {} → {}",
+                color::success("✓"),
+                from.file_name().unwrap_or_default().to_string_lossy(),
+                to.display()
+            );
+        }
+    }
+
+    fn duplicate_skipped(&self, path: &Path, existing: &Path, source_removed: bool) {
+        if !self.quiet {
+            println!(
+                "{} Skipped {}: identical to {}{}",
+                color::warning("="),
+                path.display(),
+                existing.display(),
+                if source_removed {
+                    " (source removed)"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+
+    fn move_failed(&self, path: &Path, error: &FileOperationError) {
+        eprintln!(
+            "{} Failed to move {}: {}",
+            color::failure_stderr("✗"),
+            path.display(),
+            error
+        );
+    }
+
+    fn moves_finished(&self, moved: usize, destination: &str) {
+        if self.quiet {
+            return;
+        }
+        if moved == 0 {
+            println!("No files were successfully moved.");
+        } else {
+            println!(
+                "\nSuccessfully moved {} file(s) to {} directory.",
+                moved, destination
+            );
+        }
+    }
+
+    fn journal_disabled(&self) {
+        if !self.quiet {
+            println!("Journaling disabled; no journal entries were written.");
+        }
+    }
+
+    fn journal_started(&self) {
+        if !self.quiet {
+            println!("Creating journal entries...");
+        }
+    }
+
+    fn journal_written(&self, journal: &Path, entries: usize) {
+        if !self.quiet {
+            println!(
+                "{} Added {} journal entr{} to {}",
+                color::success("✓"),
+                entries,
+                if entries == 1 { "y" } else { "ies" },
+                journal.display()
+            );
+        }
+    }
+
+    fn journal_failed(&self, error: &JournalError) {
+        eprintln!(
+            "{} Failed to create journal entries: {}",
+            color::failure_stderr("✗"),
+            error
+        );
+    }
+
+    fn rolled_back(&self, from: &Path, to: &Path) {
+        if !self.quiet {
+            println!(
+                "{} Rolled back {} → {}",
+                color::warning("↩"),
+                to.display(),
+                from.display()
+            );
+        }
+    }
+
+    fn rollback_failed(&self, path: &Path, error: &str) {
+        eprintln!(
+            "{} Failed to roll back {}: {}",
+            color::failure_stderr("✗"),
+            path.display(),
+            error
+        );
+    }
+
+    fn journal_links_updated(&self, journals: usize) {
+        if !self.quiet {
+            println!(
+                "{} Updated links in {} journal{}",
+                color::success("✓"),
+                journals,
+                if journals == 1 { "" } else { "s" }
+            );
+        }
+    }
+
+    fn journal_links_failed(&self, error: &JournalError) {
+        eprintln!(
+            "{} Failed to update journal links: {}",
+            color::failure_stderr("✗"),
+            error
+        );
+    }
+
+    fn ledger_failed(&self, error: &LedgerError) {
+        eprintln!(
+            "{} Failed to record moves for undo: {}",
+            color::failure_stderr("✗"),
+            error
+        );
+    }
+
+    fn processed_mark_failed(&self, path: &Path, error: &std::io::Error) {
+        eprintln!(
+            "{} Failed to mark {} as processed: {}",
+            color::failure_stderr("✗"),
+            path.display(),
+            error
+        );
+    }
+
+    fn seen_set_failed(&self, error: &std::io::Error) {
+        eprintln!(
+            "{} Failed to update the seen set: {}",
+            color::failure_stderr("✗"),
+            error
+        );
+    }
+
+    fn manifest_failed(&self, error: &ManifestError) {
+        eprintln!(
+            "{} Failed to update the checksum manifest: {}",
+            color::failure_stderr("✗"),
+            error
+        );
+    }
+
+    fn hook_failed(&self, destination: &Path, error: &HookError) {
+        eprintln!(
+            "{} Per-file hook failed for {}: {}",
+            color::failure_stderr("✗"),
+            destination.display(),
+            error
+        );
+    }
+}
+
+/// Sink that adds a progress bar for the move phase to a [`StdoutSink`]
+///
+/// The bar is drawn on stderr while files are being moved, shows how many
+/// files are done out of the total and the file a worker picked up last,
+/// and is cleared before the per-file results are printed.
+#[derive(Debug)]
+pub struct ProgressSink {
+    output: StdoutSink,
+    bar: ProgressBar,
+}
+
+impl ProgressSink {
+    /// Report everything through `output` and add a progress bar
+    pub fn new(output: StdoutSink) -> Self {
+        let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::hidden());
+        if let Ok(style) = ProgressStyle::with_template("{bar:30} {pos}/{len} {wide_msg}") {
+            bar.set_style(style);
+        }
+        ProgressSink { output, bar }
+    }
+}
+
+impl EventSink for ProgressSink {
+    fn run_started(&self, knowledge_base: &str, source: &Path) {
+        self.output.run_started(knowledge_base, source);
+    }
+
+    fn files_discovered(&self, source: &Path, files: &[DiscoveredFile]) {
+        self.output.files_discovered(source, files);
+    }
+
+    fn limit_applied(&self, limit: usize, total: usize) {
+        self.output.limit_applied(limit, total);
+    }
+
+    fn skipped(&self, path: &Path, reason: SkipReason) {
+        self.output.skipped(path, reason);
+    }
+
+    fn dry_run(&self) {
+        self.output.dry_run();
+    }
+
+    fn journal_preview(&self, preview: &JournalPreview) {
+        self.output.journal_preview(preview);
+    }
+
+    fn moves_started(&self, destination: &str, total: usize) {
+        self.output.moves_started(destination, total);
+        self.bar.set_length(total as u64);
+        self.bar.set_draw_target(ProgressDrawTarget::stderr());
+    }
+
+    fn content_indexed(&self, pages: usize) {
+        self.bar.suspend(|| self.output.content_indexed(pages));
+    }
+
+    fn about_to_move(&self, path: &Path) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.bar.set_message(name.into_owned());
+    }
+
+    fn file_processed(&self, _path: &Path) {
+        self.bar.inc(1);
+        if Some(self.bar.position()) >= self.bar.length() {
+            self.bar.finish_and_clear();
+        }
+    }
+
+    fn moved(&self, from: &Path, to: &Path) {
+        self.output.moved(from, to);
+    }
+
+    fn duplicate_skipped(&self, path: &Path, existing: &Path, source_removed: bool) {
+        self.output
+            .duplicate_skipped(path, existing, source_removed);
+    }
+
+    fn move_failed(&self, path: &Path, error: &FileOperationError) {
+        self.output.move_failed(path, error);
+    }
+
+    fn moves_finished(&self, moved: usize, destination: &str) {
+        self.bar.finish_and_clear();
+        self.output.moves_finished(moved, destination);
+    }
+
+    fn journal_disabled(&self) {
+        self.output.journal_disabled();
+    }
+
+    fn journal_started(&self) {
+        self.output.journal_started();
+    }
+
+    fn journal_written(&self, journal: &Path, entries: usize) {
+        self.output.journal_written(journal, entries);
+    }
+
+    fn journal_failed(&self, error: &JournalError) {
+        self.output.journal_failed(error);
+    }
+
+    fn journal_links_updated(&self, journals: usize) {
+        self.output.journal_links_updated(journals);
+    }
+
+    fn journal_links_failed(&self, error: &JournalError) {
+        self.output.journal_links_failed(error);
+    }
+
+    fn rolled_back(&self, from: &Path, to: &Path) {
+        self.output.rolled_back(from, to);
+    }
+
+    fn rollback_failed(&self, path: &Path, error: &str) {
+        self.output.rollback_failed(path, error);
+    }
+
+    fn ledger_failed(&self, error: &LedgerError) {
+        self.output.ledger_failed(error);
+    }
+
+    fn processed_mark_failed(&self, path: &Path, error: &std::io::Error) {
+        self.output.processed_mark_failed(path, error);
+    }
+
+    fn seen_set_failed(&self, error: &std::io::Error) {
+        self.output.seen_set_failed(error);
+    }
+
+    fn manifest_failed(&self, error: &ManifestError) {
+        self.output.manifest_failed(error);
+    }
+
+    fn hook_failed(&self, destination: &Path, error: &HookError) {
+        self.output.hook_failed(destination, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_sink_counts_processed_files() {
+        let sink = ProgressSink::new(StdoutSink { quiet: true });
+        sink.moves_started("pages", 2);
+
+        sink.about_to_move(Path::new("/downloads/a.md"));
+        sink.file_processed(Path::new("/downloads/a.md"));
+        assert_eq!(sink.bar.position(), 1);
+        assert_eq!(sink.bar.message(), "a.md");
+        assert!(!sink.bar.is_finished());
+
+        sink.file_processed(Path::new("/downloads/b.md"));
+        assert!(sink.bar.is_finished());
+    }
+}
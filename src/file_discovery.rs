@@ -1,5 +1,19 @@
-use std::fs;
-use std::path::PathBuf;
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, ProcessOrder, ProcessedMarker};
+use crate::filesystem::{FileSystem, RealFs};
+use crate::paths;
+use crate::processed::Processed;
+use crate::run_summary::SkipReason;
+
+/// Suffixes browsers and download tools use for files still being written
+const IN_PROGRESS_SUFFIXES: [&str; 3] = [".crdownload", ".part", ".tmp"];
+
+/// File in the scanned directory listing extra exclude patterns, one per line
+pub const IGNORE_FILE_NAME: &str = ".localshelfignore";
 
 /// Error types for file discovery operations
 #[derive(Debug, thiserror::Error)]
@@ -8,6 +22,58 @@ pub enum FileDiscoveryError {
     IoError(#[from] std::io::Error),
     #[error("Path expansion error: {0}")]
     PathExpansionError(String),
+    #[error("Invalid exclude pattern: {0}")]
+    InvalidPattern(String),
+}
+
+/// A discovered file together with the metadata read during discovery
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+impl DiscoveredFile {
+    /// Read the metadata for a file
+    pub fn from_path(path: PathBuf) -> Result<Self, std::io::Error> {
+        Self::from_path_with(path, &RealFs)
+    }
+
+    /// Read the metadata for a file through `fs`
+    fn from_path_with(path: PathBuf, fs: &dyn FileSystem) -> Result<Self, std::io::Error> {
+        let metadata = fs.metadata(&path)?;
+        Ok(DiscoveredFile {
+            size: metadata.len,
+            modified: metadata.modified,
+            path,
+        })
+    }
+}
+
+impl AsRef<Path> for DiscoveredFile {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// What a file is, judged by the full suffix of its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileKind {
+    /// A regular page such as `note.md`
+    Page,
+    /// A file with one of `compound_extensions`, such as `diagram.excalidraw.md`;
+    /// holds the matched extension, lowercase and without a leading dot
+    Compound(String),
+}
+
+/// Result of a discovery scan, including the candidates that were filtered out
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DiscoveryReport {
+    /// Files that passed every filter
+    pub files: Vec<DiscoveredFile>,
+    /// Files with a matching extension that a filter rejected, and why
+    pub skipped: Vec<(PathBuf, SkipReason)>,
 }
 
 /// Public interface for file discovery operations
@@ -15,7 +81,16 @@ pub struct FileDiscovery;
 
 impl FileDiscovery {
     /// Discover markdown files in the ~/Downloads directory
-    pub fn discover_markdown_files() -> Result<Vec<PathBuf>, FileDiscoveryError> {
+    pub fn discover_markdown_files(config: &Config) -> Result<Vec<PathBuf>, FileDiscoveryError> {
+        Ok(Self::into_paths(Self::discover_markdown_files_detailed(
+            config,
+        )?))
+    }
+
+    /// Discover markdown files in the ~/Downloads directory, including their metadata
+    pub fn discover_markdown_files_detailed(
+        config: &Config,
+    ) -> Result<Vec<DiscoveredFile>, FileDiscoveryError> {
         let downloads_path = Self::expand_path("~/Downloads")?;
 
         if !downloads_path.exists() {
@@ -29,62 +104,596 @@ impl FileDiscovery {
             )));
         }
 
-        let entries = fs::read_dir(&downloads_path)?;
+        Self::discover_in_directory_detailed(&downloads_path, config)
+    }
+
+    /// Discover markdown files in the given directory
+    pub fn discover_in_directory(
+        directory: &Path,
+        config: &Config,
+    ) -> Result<Vec<PathBuf>, FileDiscoveryError> {
+        Ok(Self::into_paths(Self::discover_in_directory_detailed(
+            directory, config,
+        )?))
+    }
+
+    /// Discover markdown files in the given directory, including their metadata
+    ///
+    /// Only the top level is scanned unless `recursive` is enabled in the
+    /// config, in which case subdirectories are walked up to `max_depth`
+    /// levels deep. Hidden directories and symlinked directories are never
+    /// descended into. Metadata is read once per file; files whose metadata
+    /// cannot be read and subdirectories that cannot be listed are dropped
+    /// without failing the rest of the scan.
+    pub fn discover_in_directory_detailed(
+        directory: &Path,
+        config: &Config,
+    ) -> Result<Vec<DiscoveredFile>, FileDiscoveryError> {
+        Ok(Self::discover_in_directory_report(directory, config)?.files)
+    }
+
+    /// Discover markdown files in the given directory and report why the
+    /// remaining candidates were rejected
+    ///
+    /// Files without a configured extension are not candidates and are not
+    /// reported; every other file ends up either in `files` or in `skipped`.
+    /// Patterns from an [`IGNORE_FILE_NAME`] file in `directory` are applied
+    /// together with `exclude_patterns`.
+    pub fn discover_in_directory_report(
+        directory: &Path,
+        config: &Config,
+    ) -> Result<DiscoveryReport, FileDiscoveryError> {
+        Self::discover_in_directory_report_with(directory, config, &RealFs, &SystemClock)
+    }
+
+    /// Discover markdown files like [`Self::discover_in_directory_report`],
+    /// reading the directory through `fs` and measuring `max_age_days` from
+    /// the time `clock` reads
+    pub fn discover_in_directory_report_with(
+        directory: &Path,
+        config: &Config,
+        fs: &dyn FileSystem,
+        clock: &dyn Clock,
+    ) -> Result<DiscoveryReport, FileDiscoveryError> {
+        if !fs.metadata(directory).is_ok_and(|metadata| metadata.is_dir) {
+            return Err(FileDiscoveryError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Directory '{}' does not exist", directory.display()),
+            )));
+        }
+
+        let max_depth = if config.recursive {
+            config.max_depth.unwrap_or(usize::MAX)
+        } else {
+            0
+        };
+
         let mut files = Vec::new();
+        let mut unreadable = Vec::new();
+        Self::collect_files(directory, 0, max_depth, &mut files, &mut unreadable, fs)?;
 
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
+        let mut skipped: Vec<_> = unreadable
+            .into_iter()
+            .map(|path| (path, SkipReason::Unreadable))
+            .collect();
+        let files = Self::filter_markdown_files(
+            files,
+            &[&config.extensions[..], &config.attachment_extensions[..]].concat(),
+        );
+        let files = Self::reject(files, SkipReason::InProgress, &mut skipped, |file| {
+            !Self::is_in_progress(file)
+        });
+        let mut exclude_patterns = config.exclude_patterns.clone();
+        exclude_patterns.extend(Self::read_ignore_file_with(directory, fs)?);
+        let is_excluded =
+            Self::exclusion_matcher(&exclude_patterns, config.exclude_case_insensitive)?;
+        let files = Self::reject(files, SkipReason::Excluded, &mut skipped, |file| {
+            !is_excluded(file)
+        });
+        let files = Self::reject(files, SkipReason::Excluded, &mut skipped, |file| {
+            config.processed_marker != ProcessedMarker::ProcessedFolder
+                || !Processed::is_in_processed_folder(file)
+        });
+        let files = Self::reject(files, SkipReason::Symlink, &mut skipped, |file| {
+            config.follow_symlinks
+                || !fs
+                    .symlink_metadata(file)
+                    .is_ok_and(|metadata| metadata.is_symlink)
+        });
 
-            if path.is_file() {
+        let mut discovered = Vec::with_capacity(files.len());
+        for path in files {
+            match DiscoveredFile::from_path_with(path.clone(), fs) {
+                Ok(file) => discovered.push(file),
+                Err(_) => skipped.push((path, SkipReason::Unreadable)),
+            }
+        }
+
+        if config.stability_check_ms > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(config.stability_check_ms));
+            discovered = Self::reject(discovered, SkipReason::Unstable, &mut skipped, |file| {
+                Self::is_unchanged(file, fs)
+            });
+        }
+        let discovered = Self::reject(discovered, SkipReason::TooSmall, &mut skipped, |file| {
+            file.size >= config.min_file_size_bytes
+        });
+        let discovered = Self::reject(discovered, SkipReason::TooLarge, &mut skipped, |file| {
+            config
+                .max_file_size_bytes
+                .is_none_or(|max_size| file.size <= max_size)
+        });
+        let now = clock.now();
+        let discovered = Self::reject(discovered, SkipReason::TooOld, &mut skipped, |file| {
+            Self::is_within_max_age(file, config.max_age_days, now)
+        });
+        let (since, until) = config.modified_range();
+        let discovered = Self::reject(
+            discovered,
+            SkipReason::OutsideDateRange,
+            &mut skipped,
+            |file| Self::is_within_date_range(file, since, until),
+        );
+
+        Ok(DiscoveryReport {
+            files: discovered,
+            skipped,
+        })
+    }
+
+    /// Build the discovery filters for files found outside a directory scan,
+    /// such as the entries of an archive
+    ///
+    /// The returned predicate gives the reason
+    /// [`Self::discover_in_directory_report`] would skip a file for: an
+    /// in-progress name, a name matching `exclude_patterns` or the
+    /// [`IGNORE_FILE_NAME`] file in `directory`, or a size, age or
+    /// modification date outside the configured limits, with `max_age_days`
+    /// measured from the time `clock` reads.
+    pub fn entry_filter<'a>(
+        directory: &Path,
+        config: &'a Config,
+        clock: &dyn Clock,
+    ) -> Result<impl Fn(&DiscoveredFile) -> Option<SkipReason> + 'a, FileDiscoveryError> {
+        let mut exclude_patterns = config.exclude_patterns.clone();
+        exclude_patterns.extend(Self::read_ignore_file_with(directory, &RealFs)?);
+        let is_excluded =
+            Self::exclusion_matcher(&exclude_patterns, config.exclude_case_insensitive)?;
+        let now = clock.now();
+        let (since, until) = config.modified_range();
+
+        Ok(move |file: &DiscoveredFile| {
+            if Self::is_in_progress(&file.path) {
+                Some(SkipReason::InProgress)
+            } else if is_excluded(&file.path) {
+                Some(SkipReason::Excluded)
+            } else if file.size < config.min_file_size_bytes {
+                Some(SkipReason::TooSmall)
+            } else if config
+                .max_file_size_bytes
+                .is_some_and(|max_size| file.size > max_size)
+            {
+                Some(SkipReason::TooLarge)
+            } else if !Self::is_within_max_age(file, config.max_age_days, now) {
+                Some(SkipReason::TooOld)
+            } else if !Self::is_within_date_range(file, since, until) {
+                Some(SkipReason::OutsideDateRange)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Skip discovered files that live in the Knowledge Base's own directories
+    ///
+    /// Guards against a source `directory` that overlaps the Knowledge Base,
+    /// such as the Knowledge Base itself: files under the pages, journals,
+    /// destination or routing directories, or the configuration directory,
+    /// are moved from `files` to `skipped` as [`SkipReason::InKnowledgeBase`].
+    /// Paths are compared canonicalized, so symlinks and `..` cannot hide the
+    /// overlap.
+    pub fn skip_knowledge_base_files(
+        report: &mut DiscoveryReport,
+        directory: &Path,
+        config: &Config,
+    ) {
+        let Ok(source) = directory.canonicalize() else {
+            return;
+        };
+        let mut protected = vec![
+            config.knowledge_base_subdir(&config.pages_dir_name),
+            config.knowledge_base_subdir(&config.journals_dir_name),
+            config.knowledge_base_subdir(config.destination_dir_name()),
+        ];
+        protected.extend(
+            config
+                .routing
+                .iter()
+                .map(|route| config.knowledge_base_subdir(&route.destination)),
+        );
+        protected.extend(Config::config_dir().ok());
+        let overlapping: Vec<PathBuf> = protected
+            .iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .filter(|dir| dir.starts_with(&source) || source.starts_with(dir))
+            .collect();
+        if overlapping.is_empty() {
+            return;
+        }
+
+        report.files = Self::reject(
+            std::mem::take(&mut report.files),
+            SkipReason::InKnowledgeBase,
+            &mut report.skipped,
+            |file| {
+                file.path
+                    .canonicalize()
+                    .is_ok_and(|path| !overlapping.iter().any(|dir| path.starts_with(dir)))
+            },
+        );
+    }
+
+    /// Read the glob patterns listed in `directory`'s [`IGNORE_FILE_NAME`]
+    ///
+    /// Blank lines and lines starting with `#` are ignored. A missing file
+    /// yields no patterns; an invalid pattern is reported as an error.
+    pub fn read_ignore_file(directory: &Path) -> Result<Vec<String>, FileDiscoveryError> {
+        Self::read_ignore_file_with(directory, &RealFs)
+    }
+
+    /// Read the ignore file like [`Self::read_ignore_file`], through `fs`
+    fn read_ignore_file_with(
+        directory: &Path,
+        fs: &dyn FileSystem,
+    ) -> Result<Vec<String>, FileDiscoveryError> {
+        let path = directory.join(IGNORE_FILE_NAME);
+        let content = match fs.read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .map(|_| pattern.to_string())
+                    .map_err(|e| {
+                        FileDiscoveryError::InvalidPattern(format!(
+                            "'{}' in {}: {}",
+                            pattern,
+                            path.display(),
+                            e
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Keep the files accepted by `keep`, recording the rest as skipped for `reason`
+    fn reject<T: AsRef<Path>>(
+        files: Vec<T>,
+        reason: SkipReason,
+        skipped: &mut Vec<(PathBuf, SkipReason)>,
+        keep: impl Fn(&T) -> bool,
+    ) -> Vec<T> {
+        let (kept, rejected): (Vec<T>, Vec<T>) = files.into_iter().partition(|file| keep(file));
+        skipped.extend(
+            rejected
+                .into_iter()
+                .map(|file| (file.as_ref().to_path_buf(), reason)),
+        );
+        kept
+    }
+
+    /// Read metadata for each path, dropping files whose metadata is unreadable
+    pub fn with_metadata(files: Vec<PathBuf>) -> Vec<DiscoveredFile> {
+        files
+            .into_iter()
+            .filter_map(|path| DiscoveredFile::from_path(path).ok())
+            .collect()
+    }
+
+    /// Drop files that look like in-progress downloads
+    ///
+    /// A file is in progress when its name carries one of the
+    /// [`IN_PROGRESS_SUFFIXES`], either at the end (`article.md.part`) or just
+    /// before the extension (`article.crdownload.md`).
+    pub fn filter_in_progress(files: Vec<PathBuf>) -> Vec<PathBuf> {
+        files
+            .into_iter()
+            .filter(|file| !Self::is_in_progress(file))
+            .collect()
+    }
+
+    /// Check whether a file name carries one of the [`IN_PROGRESS_SUFFIXES`]
+    fn is_in_progress(file: &Path) -> bool {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let stem = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        IN_PROGRESS_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix) || stem.ends_with(suffix))
+    }
+
+    /// Keep only files whose size is unchanged after `wait` returns
+    ///
+    /// Sizes recorded during discovery are compared with a second read taken
+    /// once `wait` has run, so files that are still growing are skipped.
+    pub fn filter_stable(files: Vec<DiscoveredFile>, wait: impl FnOnce()) -> Vec<DiscoveredFile> {
+        wait();
+        files
+            .into_iter()
+            .filter(|file| Self::is_unchanged(file, &RealFs))
+            .collect()
+    }
+
+    /// Check whether a file still has the size recorded during discovery
+    fn is_unchanged(file: &DiscoveredFile, fs: &dyn FileSystem) -> bool {
+        fs.metadata(&file.path)
+            .map(|metadata| metadata.len == file.size)
+            .unwrap_or(false)
+    }
+
+    /// Sort discovered files into the given processing order
+    ///
+    /// Ties are broken by path so the order is deterministic.
+    pub fn sort_files(files: &mut [DiscoveredFile], order: ProcessOrder) {
+        files.sort_by(|a, b| Self::compare_files(a, b, order));
+    }
+
+    /// Compare two files by the given processing order, then by path
+    pub fn compare_files(
+        a: &DiscoveredFile,
+        b: &DiscoveredFile,
+        order: ProcessOrder,
+    ) -> std::cmp::Ordering {
+        let primary = match order {
+            ProcessOrder::NameAsc => std::cmp::Ordering::Equal,
+            ProcessOrder::ModifiedAsc => a.modified.cmp(&b.modified),
+            ProcessOrder::ModifiedDesc => b.modified.cmp(&a.modified),
+            ProcessOrder::SizeDesc => b.size.cmp(&a.size),
+        };
+        primary.then_with(|| a.path.cmp(&b.path))
+    }
+
+    /// Strip metadata, keeping only the paths
+    pub fn into_paths(files: Vec<DiscoveredFile>) -> Vec<PathBuf> {
+        files.into_iter().map(|file| file.path).collect()
+    }
+
+    /// Collect files from a directory, recursing into subdirectories while
+    /// `depth` is below `max_depth`
+    ///
+    /// Only failing to list `directory` itself is an error. Entries that
+    /// cannot be inspected and subdirectories that cannot be listed are
+    /// added to `unreadable` and the scan continues with the rest.
+    fn collect_files(
+        directory: &Path,
+        depth: usize,
+        max_depth: usize,
+        files: &mut Vec<PathBuf>,
+        unreadable: &mut Vec<PathBuf>,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileDiscoveryError> {
+        for entry in fs.read_dir(directory)? {
+            let Ok(path) = entry else {
+                if !unreadable.iter().any(|path| path == directory) {
+                    unreadable.push(directory.to_path_buf());
+                }
+                continue;
+            };
+
+            // symlink_metadata does not follow symlinks, so linked
+            // directories are never walked and cannot create cycles
+            let Ok(metadata) = fs.symlink_metadata(&path) else {
+                unreadable.push(path);
+                continue;
+            };
+            if metadata.is_dir {
+                if depth < max_depth
+                    && !Self::is_hidden(&path)
+                    && Self::collect_files(&path, depth + 1, max_depth, files, unreadable, fs)
+                        .is_err()
+                {
+                    unreadable.push(path);
+                }
+            } else if fs.metadata(&path).is_ok_and(|metadata| metadata.is_file) {
                 files.push(path);
             }
         }
 
-        Ok(Self::filter_markdown_files(files))
+        Ok(())
+    }
+
+    /// Check whether a path's final component starts with a dot
+    fn is_hidden(path: &Path) -> bool {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with('.'))
+            .unwrap_or(false)
     }
 
     /// Expand tilde (~) notation to home directory
+    ///
+    /// Uses the same rules as `Config::expand_path` but reports an error
+    /// when a home reference cannot be resolved.
     pub fn expand_path(path: &str) -> Result<PathBuf, FileDiscoveryError> {
-        if let Some(path_without_tilde) = path.strip_prefix('~') {
-            if let Some(home_dir) = dirs::home_dir() {
-                let path_without_tilde =
-                    if let Some(stripped) = path_without_tilde.strip_prefix('/') {
-                        stripped
-                    } else {
-                        path_without_tilde
-                    };
-                Ok(home_dir.join(path_without_tilde))
-            } else {
-                Err(FileDiscoveryError::PathExpansionError(
-                    "Could not determine home directory".to_string(),
-                ))
-            }
-        } else {
-            Ok(PathBuf::from(path))
+        if paths::is_home_reference(path) && paths::home_dir().is_none() {
+            return Err(FileDiscoveryError::PathExpansionError(
+                "Could not determine home directory".to_string(),
+            ));
         }
+
+        Ok(paths::expand(path))
+    }
+
+    /// Remove files whose name matches any of the given glob patterns
+    pub fn filter_excluded(
+        files: Vec<PathBuf>,
+        patterns: &[String],
+        case_insensitive: bool,
+    ) -> Result<Vec<PathBuf>, FileDiscoveryError> {
+        let is_excluded = Self::exclusion_matcher(patterns, case_insensitive)?;
+        Ok(files
+            .into_iter()
+            .filter(|file| !is_excluded(file))
+            .collect())
+    }
+
+    /// Compile exclude patterns into a predicate matching excluded file names
+    fn exclusion_matcher(
+        patterns: &[String],
+        case_insensitive: bool,
+    ) -> Result<impl Fn(&Path) -> bool + use<>, FileDiscoveryError> {
+        let compiled = patterns
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern).map_err(|e| {
+                    FileDiscoveryError::InvalidPattern(format!("'{}': {}", pattern, e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let options = glob::MatchOptions {
+            case_sensitive: !case_insensitive,
+            ..Default::default()
+        };
+
+        Ok(move |file: &Path| {
+            let name = file
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default();
+            compiled
+                .iter()
+                .any(|pattern| pattern.matches_with(&name, options))
+        })
+    }
+
+    /// Keep only files whose size is at least `min_size` bytes
+    pub fn filter_by_min_size(files: Vec<DiscoveredFile>, min_size: u64) -> Vec<DiscoveredFile> {
+        files
+            .into_iter()
+            .filter(|file| file.size >= min_size)
+            .collect()
+    }
+
+    /// Keep only files modified within `max_age_days` of `now`
+    ///
+    /// Files with a modification time in the future are kept.
+    pub fn filter_by_max_age(
+        files: Vec<DiscoveredFile>,
+        max_age_days: Option<u64>,
+        now: DateTime<Local>,
+    ) -> Vec<DiscoveredFile> {
+        files
+            .into_iter()
+            .filter(|file| Self::is_within_max_age(file, max_age_days, now))
+            .collect()
+    }
+
+    /// Check whether a file was modified within `max_age_days` of `now`
+    fn is_within_max_age(
+        file: &DiscoveredFile,
+        max_age_days: Option<u64>,
+        now: DateTime<Local>,
+    ) -> bool {
+        let Some(days) = max_age_days else {
+            return true;
+        };
+        let max_age = i64::try_from(days)
+            .ok()
+            .and_then(Duration::try_days)
+            .unwrap_or(Duration::MAX);
+
+        let modified: DateTime<Local> = file.modified.into();
+        now.signed_duration_since(modified) <= max_age
+    }
+
+    /// Check whether a file was modified on a day between `since` and
+    /// `until`, both inclusive, in local time
+    fn is_within_date_range(
+        file: &DiscoveredFile,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+    ) -> bool {
+        let day = DateTime::<Local>::from(file.modified).date_naive();
+        since.is_none_or(|since| day >= since) && until.is_none_or(|until| day <= until)
     }
 
     /// Filter markdown files from a list of files
-    pub fn filter_markdown_files(files: Vec<PathBuf>) -> Vec<PathBuf> {
+    ///
+    /// Keeps files whose extension matches one of `extensions`, ignoring case
+    /// and any leading dot in the configured values.
+    pub fn filter_markdown_files(files: Vec<PathBuf>, extensions: &[String]) -> Vec<PathBuf> {
         files
             .into_iter()
-            .filter(|file| {
-                file.extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(|ext| ext.to_lowercase() == "md")
-                    .unwrap_or(false)
-            })
+            .filter(|file| Self::has_extension(file, extensions))
             .collect()
     }
+
+    /// Classify a file by the longest of `compound_extensions` its name ends
+    /// with, ignoring case and any leading dot in the configured values
+    ///
+    /// A name that is nothing but the extension (`.excalidraw.md`) is a page.
+    pub fn file_kind(path: &Path, compound_extensions: &[String]) -> FileKind {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            return FileKind::Page;
+        };
+
+        compound_extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .filter(|extension| Self::ends_with_extension(file_name, extension))
+            .max_by_key(String::len)
+            .map_or(FileKind::Page, FileKind::Compound)
+    }
+
+    /// Whether `file_name` ends with `.extension` after a non-empty stem,
+    /// ignoring case; `extension` may have several parts (`excalidraw.md`)
+    pub fn ends_with_extension(file_name: &str, extension: &str) -> bool {
+        let extension = format!(".{}", extension.trim_start_matches('.')).to_lowercase();
+        let file_name = file_name.to_lowercase();
+        file_name.len() > extension.len() && file_name.ends_with(&extension)
+    }
+
+    /// Whether a path has one of `extensions`, ignoring case and any leading
+    /// dot in the configured values
+    pub fn has_extension(path: &Path, extensions: &[String]) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| {
+                let ext = ext.to_lowercase();
+                extensions
+                    .iter()
+                    .any(|allowed| allowed.trim_start_matches('.').to_lowercase() == ext)
+            })
+            .unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
+    use crate::clock::FixedClock;
+    use crate::filesystem::MockFs;
+    use chrono::TimeZone;
+    use std::fs::{self, File};
     use tempfile::tempdir;
 
+    /// Noon local time on 2024-03-15, the "now" of the age tests
+    fn fixed_now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap()
+    }
+
     #[test]
     fn test_expand_path_with_tilde() {
         let result = FileDiscovery::expand_path("~/Downloads");
@@ -102,6 +711,14 @@ mod tests {
         assert_eq!(result.unwrap().to_string_lossy(), absolute_path);
     }
 
+    #[test]
+    fn test_expand_path_matches_config_expansion() {
+        for path in ["~", "~/", "~/sub", "~user/sub", "/absolute"] {
+            let discovered = FileDiscovery::expand_path(path).unwrap();
+            assert_eq!(discovered.to_string_lossy(), Config::expand_path(path));
+        }
+    }
+
     #[test]
     fn test_filter_markdown_files_mixed_extensions() {
         let files = vec![
@@ -112,7 +729,7 @@ mod tests {
             PathBuf::from("script.py"),
         ];
 
-        let filtered = FileDiscovery::filter_markdown_files(files);
+        let filtered = FileDiscovery::filter_markdown_files(files, &Config::default().extensions);
         assert_eq!(filtered.len(), 3);
         assert!(filtered.iter().all(|f| {
             let ext = f.extension().and_then(|s| s.to_str()).unwrap_or("");
@@ -128,22 +745,124 @@ mod tests {
             PathBuf::from("script.py"),
         ];
 
-        let filtered = FileDiscovery::filter_markdown_files(files);
+        let filtered = FileDiscovery::filter_markdown_files(files, &Config::default().extensions);
         assert_eq!(filtered.len(), 0);
     }
 
     #[test]
     fn test_filter_markdown_files_empty_list() {
         let files = vec![];
-        let filtered = FileDiscovery::filter_markdown_files(files);
+        let filtered = FileDiscovery::filter_markdown_files(files, &Config::default().extensions);
         assert_eq!(filtered.len(), 0);
     }
 
+    #[test]
+    fn test_filter_markdown_files_custom_extensions() {
+        let files = vec![
+            PathBuf::from("notes.org"),
+            PathBuf::from("todo.TXT"),
+            PathBuf::from("document.md"),
+        ];
+
+        let extensions = vec!["org".to_string(), ".txt".to_string()];
+        let filtered = FileDiscovery::filter_markdown_files(files, &extensions);
+        assert_eq!(
+            filtered,
+            vec![PathBuf::from("notes.org"), PathBuf::from("todo.TXT")]
+        );
+    }
+
+    #[test]
+    fn test_file_kind_distinguishes_compound_extensions() {
+        let compound = vec!["excalidraw.md".to_string(), ".canvas.md".to_string()];
+
+        assert_eq!(
+            FileDiscovery::file_kind(Path::new("note.md"), &compound),
+            FileKind::Page
+        );
+        assert_eq!(
+            FileDiscovery::file_kind(Path::new("/downloads/diagram.excalidraw.md"), &compound),
+            FileKind::Compound("excalidraw.md".to_string())
+        );
+        assert_eq!(
+            FileDiscovery::file_kind(Path::new("Board.Canvas.MD"), &compound),
+            FileKind::Compound("canvas.md".to_string())
+        );
+        assert_eq!(
+            FileDiscovery::file_kind(Path::new(".excalidraw.md"), &compound),
+            FileKind::Page
+        );
+        assert_eq!(
+            FileDiscovery::file_kind(Path::new("diagram.excalidraw.md"), &[]),
+            FileKind::Page
+        );
+    }
+
+    #[test]
+    fn test_file_kind_prefers_longest_extension() {
+        let compound = vec!["draft.md".to_string(), "sketch.draft.md".to_string()];
+
+        assert_eq!(
+            FileDiscovery::file_kind(Path::new("plan.sketch.draft.md"), &compound),
+            FileKind::Compound("sketch.draft.md".to_string())
+        );
+        assert_eq!(
+            FileDiscovery::file_kind(Path::new("plan.draft.md"), &compound),
+            FileKind::Compound("draft.md".to_string())
+        );
+    }
+
+    #[test]
+    fn test_filter_in_progress_suffixes() {
+        let files = vec![
+            PathBuf::from("article.md"),
+            PathBuf::from("article.md.crdownload"),
+            PathBuf::from("draft.crdownload.md"),
+            PathBuf::from("notes.PART.md"),
+            PathBuf::from("cache.tmp"),
+        ];
+
+        let filtered = FileDiscovery::filter_in_progress(files);
+        assert_eq!(filtered, vec![PathBuf::from("article.md")]);
+    }
+
+    #[test]
+    fn test_filter_stable_skips_growing_file() {
+        let temp_dir = tempdir().unwrap();
+        let growing = temp_dir.path().join("growing.md");
+        let settled = temp_dir.path().join("settled.md");
+        fs::write(&growing, "partial").unwrap();
+        fs::write(&settled, "complete").unwrap();
+
+        // First size read happens here, the second one after the download grows
+        let files = discovered(&[&growing, &settled]);
+        let filtered = FileDiscovery::filter_stable(files, || {
+            let mut file = fs::OpenOptions::new().append(true).open(&growing).unwrap();
+            std::io::Write::write_all(&mut file, b" more bytes").unwrap();
+        });
+
+        assert_eq!(FileDiscovery::into_paths(filtered), vec![settled]);
+    }
+
+    #[test]
+    fn test_discover_in_directory_with_stability_check() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("done.md"), "complete").unwrap();
+        fs::write(temp_dir.path().join("loading.crdownload.md"), "partial").unwrap();
+
+        let config = Config {
+            stability_check_ms: 1,
+            ..Default::default()
+        };
+        let files = FileDiscovery::discover_in_directory(temp_dir.path(), &config).unwrap();
+        assert_eq!(file_names(&files), vec!["done.md"]);
+    }
+
     #[test]
     fn test_discover_markdown_files_interface() {
         // This test will ensure the interface exists and returns appropriate type
         // Implementation will be tested once we have the actual implementation
-        let result = FileDiscovery::discover_markdown_files();
+        let result = FileDiscovery::discover_markdown_files(&Config::default());
         match result {
             Ok(_) | Err(_) => {} // Either outcome is acceptable for interface test
         }
@@ -173,10 +892,615 @@ mod tests {
             }
         }
 
-        let markdown_files = FileDiscovery::filter_markdown_files(files);
+        let markdown_files =
+            FileDiscovery::filter_markdown_files(files, &Config::default().extensions);
         assert_eq!(markdown_files.len(), 2);
     }
 
+    fn create_nested_tree(root: &Path) {
+        fs::create_dir_all(root.join("level1/level2/level3")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        File::create(root.join("top.md")).unwrap();
+        File::create(root.join("level1/one.md")).unwrap();
+        File::create(root.join("level1/level2/two.md")).unwrap();
+        File::create(root.join("level1/level2/level3/three.md")).unwrap();
+        File::create(root.join(".hidden/secret.md")).unwrap();
+    }
+
+    fn file_names(files: &[PathBuf]) -> Vec<String> {
+        let mut names: Vec<String> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_discover_in_directory_non_recursive_by_default() {
+        let temp_dir = tempdir().unwrap();
+        create_nested_tree(temp_dir.path());
+
+        let files =
+            FileDiscovery::discover_in_directory(temp_dir.path(), &Config::default()).unwrap();
+        assert_eq!(file_names(&files), vec!["top.md"]);
+    }
+
+    #[test]
+    fn test_discover_in_directory_recursive_unlimited() {
+        let temp_dir = tempdir().unwrap();
+        create_nested_tree(temp_dir.path());
+
+        let config = Config {
+            recursive: true,
+            ..Default::default()
+        };
+        let files = FileDiscovery::discover_in_directory(temp_dir.path(), &config).unwrap();
+        assert_eq!(
+            file_names(&files),
+            vec!["one.md", "three.md", "top.md", "two.md"]
+        );
+    }
+
+    #[test]
+    fn test_discover_in_directory_respects_max_depth() {
+        let temp_dir = tempdir().unwrap();
+        create_nested_tree(temp_dir.path());
+
+        let config = Config {
+            recursive: true,
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let files = FileDiscovery::discover_in_directory(temp_dir.path(), &config).unwrap();
+        assert_eq!(file_names(&files), vec!["one.md", "top.md", "two.md"]);
+    }
+
+    #[test]
+    fn test_discover_in_directory_skips_hidden_directories() {
+        let temp_dir = tempdir().unwrap();
+        create_nested_tree(temp_dir.path());
+
+        let config = Config {
+            recursive: true,
+            ..Default::default()
+        };
+        let files = FileDiscovery::discover_in_directory(temp_dir.path(), &config).unwrap();
+        assert!(!file_names(&files).contains(&"secret.md".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_in_directory_does_not_follow_symlinked_directories() {
+        let temp_dir = tempdir().unwrap();
+        create_nested_tree(temp_dir.path());
+
+        // A link back to the root would loop forever if followed
+        std::os::unix::fs::symlink(temp_dir.path(), temp_dir.path().join("level1/loop")).unwrap();
+
+        let config = Config {
+            recursive: true,
+            ..Default::default()
+        };
+        let files = FileDiscovery::discover_in_directory(temp_dir.path(), &config).unwrap();
+        assert_eq!(files.len(), 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_in_directory_follow_symlinks() {
+        let temp_dir = tempdir().unwrap();
+        let elsewhere = tempdir().unwrap();
+        File::create(temp_dir.path().join("local.md")).unwrap();
+        let target = elsewhere.path().join("shared.md");
+        fs::write(&target, "# Shared").unwrap();
+        let link = temp_dir.path().join("shared.md");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let report =
+            FileDiscovery::discover_in_directory_report(temp_dir.path(), &Config::default())
+                .unwrap();
+        assert_eq!(
+            file_names(&FileDiscovery::into_paths(report.files)),
+            vec!["local.md", "shared.md"]
+        );
+        assert!(report.skipped.is_empty());
+
+        let config = Config {
+            follow_symlinks: false,
+            ..Default::default()
+        };
+        let report = FileDiscovery::discover_in_directory_report(temp_dir.path(), &config).unwrap();
+        assert_eq!(
+            file_names(&FileDiscovery::into_paths(report.files)),
+            vec!["local.md"]
+        );
+        assert_eq!(report.skipped, vec![(link, SkipReason::Symlink)]);
+        assert!(target.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_in_directory_continues_past_unreadable_directory() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let temp_dir = tempdir().unwrap();
+        // Permission checks do not apply to root, which owns what it creates
+        if fs::metadata(temp_dir.path()).unwrap().uid() == 0 {
+            eprintln!("skipped: permission checks do not apply when running as root");
+            return;
+        }
+        create_nested_tree(temp_dir.path());
+        let locked = temp_dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        File::create(locked.join("hidden-away.md")).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+        let config = Config {
+            recursive: true,
+            ..Default::default()
+        };
+        let report = FileDiscovery::discover_in_directory_report(temp_dir.path(), &config);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let report = report.unwrap();
+        let names = file_names(&FileDiscovery::into_paths(report.files));
+        for name in ["top.md", "one.md", "two.md", "three.md"] {
+            assert!(names.contains(&name.to_string()), "{}", name);
+        }
+        assert!(!names.contains(&"hidden-away.md".to_string()));
+        assert_eq!(report.skipped, vec![(locked, SkipReason::Unreadable)]);
+    }
+
+    #[test]
+    fn test_discover_in_directory_nonexistent() {
+        let result = FileDiscovery::discover_in_directory(
+            Path::new("/nonexistent/directory"),
+            &Config::default(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_filter_excluded_drops_matching_names() {
+        let files = vec![
+            PathBuf::from("/downloads/_template.md"),
+            PathBuf::from("/downloads/notes.md"),
+        ];
+
+        let filtered =
+            FileDiscovery::filter_excluded(files, &["_*.md".to_string()], false).unwrap();
+        assert_eq!(filtered, vec![PathBuf::from("/downloads/notes.md")]);
+    }
+
+    #[test]
+    fn test_filter_excluded_case_sensitivity() {
+        let files = vec![PathBuf::from("Draft-idea.md"), PathBuf::from("notes.md")];
+        let patterns = vec!["draft-*".to_string()];
+
+        let sensitive = FileDiscovery::filter_excluded(files.clone(), &patterns, false).unwrap();
+        assert_eq!(sensitive.len(), 2);
+
+        let insensitive = FileDiscovery::filter_excluded(files, &patterns, true).unwrap();
+        assert_eq!(insensitive, vec![PathBuf::from("notes.md")]);
+    }
+
+    #[test]
+    fn test_filter_excluded_invalid_pattern() {
+        let result =
+            FileDiscovery::filter_excluded(vec![PathBuf::from("a.md")], &["[".to_string()], false);
+        assert!(matches!(result, Err(FileDiscoveryError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn test_discover_in_directory_applies_exclude_patterns() {
+        let temp_dir = tempdir().unwrap();
+        File::create(temp_dir.path().join("_template.md")).unwrap();
+        File::create(temp_dir.path().join("notes.md")).unwrap();
+
+        let config = Config {
+            exclude_patterns: vec!["_*.md".to_string()],
+            ..Default::default()
+        };
+        let files = FileDiscovery::discover_in_directory(temp_dir.path(), &config).unwrap();
+        assert_eq!(file_names(&files), vec!["notes.md"]);
+    }
+
+    #[test]
+    fn test_discover_in_directory_applies_ignore_file() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(IGNORE_FILE_NAME),
+            "# never import drafts\n\ndrafts-*.md\n",
+        )
+        .unwrap();
+        File::create(temp_dir.path().join("drafts-idea.md")).unwrap();
+        File::create(temp_dir.path().join("notes.md")).unwrap();
+        File::create(temp_dir.path().join("_template.md")).unwrap();
+
+        let config = Config {
+            exclude_patterns: vec!["_*.md".to_string()],
+            ..Default::default()
+        };
+
+        let files = FileDiscovery::discover_in_directory(temp_dir.path(), &config).unwrap();
+        assert_eq!(file_names(&files), vec!["notes.md"]);
+    }
+
+    #[test]
+    fn test_discover_reports_unlistable_subdirectory_as_unreadable() {
+        let temp_dir = tempdir().unwrap();
+        let locked = temp_dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::write(locked.join("hidden.md"), "# Hidden").unwrap();
+        fs::write(temp_dir.path().join("visible.md"), "# Visible").unwrap();
+        let config = Config {
+            recursive: true,
+            ..Default::default()
+        };
+        let fs =
+            MockFs::default().fail_path("read_dir", &locked, std::io::ErrorKind::PermissionDenied);
+
+        let report = FileDiscovery::discover_in_directory_report_with(
+            temp_dir.path(),
+            &config,
+            &fs,
+            &SystemClock,
+        )
+        .unwrap();
+
+        assert_eq!(
+            FileDiscovery::into_paths(report.files),
+            vec![temp_dir.path().join("visible.md")]
+        );
+        assert_eq!(report.skipped, vec![(locked, SkipReason::Unreadable)]);
+    }
+
+    #[test]
+    fn test_discover_fails_when_top_directory_cannot_be_listed() {
+        let temp_dir = tempdir().unwrap();
+        let fs = MockFs::default().fail_path(
+            "read_dir",
+            temp_dir.path(),
+            std::io::ErrorKind::PermissionDenied,
+        );
+
+        let result = FileDiscovery::discover_in_directory_report_with(
+            temp_dir.path(),
+            &Config::default(),
+            &fs,
+            &SystemClock,
+        );
+
+        assert!(matches!(result, Err(FileDiscoveryError::IoError(_))));
+    }
+
+    #[test]
+    fn test_read_ignore_file_missing_is_noop() {
+        let temp_dir = tempdir().unwrap();
+        assert!(
+            FileDiscovery::read_ignore_file(temp_dir.path())
+                .unwrap()
+                .is_empty()
+        );
+
+        File::create(temp_dir.path().join("drafts-idea.md")).unwrap();
+        let files =
+            FileDiscovery::discover_in_directory(temp_dir.path(), &Config::default()).unwrap();
+        assert_eq!(file_names(&files), vec!["drafts-idea.md"]);
+    }
+
+    #[test]
+    fn test_read_ignore_file_invalid_pattern() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join(IGNORE_FILE_NAME), "[\n").unwrap();
+
+        let result = FileDiscovery::read_ignore_file(temp_dir.path());
+        assert!(matches!(result, Err(FileDiscoveryError::InvalidPattern(_))));
+    }
+
+    fn discovered(paths: &[&PathBuf]) -> Vec<DiscoveredFile> {
+        FileDiscovery::with_metadata(paths.iter().map(|p| p.to_path_buf()).collect())
+    }
+
+    #[test]
+    fn test_filter_by_min_size_threshold() {
+        let temp_dir = tempdir().unwrap();
+        let empty = temp_dir.path().join("empty.md");
+        let exact = temp_dir.path().join("exact.md");
+        let larger = temp_dir.path().join("larger.md");
+        File::create(&empty).unwrap();
+        fs::write(&exact, "12345").unwrap();
+        fs::write(&larger, "123456789").unwrap();
+
+        let filtered = FileDiscovery::filter_by_min_size(discovered(&[&empty, &exact, &larger]), 5);
+        assert_eq!(FileDiscovery::into_paths(filtered), vec![exact, larger]);
+    }
+
+    #[test]
+    fn test_filter_by_min_size_disabled() {
+        let temp_dir = tempdir().unwrap();
+        let empty = temp_dir.path().join("empty.md");
+        File::create(&empty).unwrap();
+
+        let filtered = FileDiscovery::filter_by_min_size(discovered(&[&empty]), 0);
+        assert_eq!(FileDiscovery::into_paths(filtered), vec![empty]);
+    }
+
+    #[test]
+    fn test_with_metadata_unreadable_file_does_not_abort() {
+        let temp_dir = tempdir().unwrap();
+        let missing = temp_dir.path().join("missing.md");
+        let present = temp_dir.path().join("present.md");
+        fs::write(&present, "content").unwrap();
+
+        let files = FileDiscovery::with_metadata(vec![missing, present.clone()]);
+        assert_eq!(FileDiscovery::into_paths(files), vec![present]);
+    }
+
+    #[test]
+    fn test_discovered_file_metadata_populated() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("article.md");
+        fs::write(&path, "# Article").unwrap();
+        let mtime = filetime::FileTime::from_unix_time(1_700_000_000, 0);
+        filetime::set_file_mtime(&path, mtime).unwrap();
+
+        let file = DiscoveredFile::from_path(path.clone()).unwrap();
+        assert_eq!(file.path, path);
+        assert_eq!(file.size, 9);
+        assert_eq!(
+            file.modified,
+            SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)
+        );
+    }
+
+    #[test]
+    fn test_sort_files_modified_asc() {
+        let temp_dir = tempdir().unwrap();
+        let now = fixed_now();
+        let newest = temp_dir.path().join("a.md");
+        let oldest = temp_dir.path().join("b.md");
+        let middle = temp_dir.path().join("c.md");
+        for path in [&newest, &oldest, &middle] {
+            File::create(path).unwrap();
+        }
+        set_age(&newest, now, Duration::hours(1));
+        set_age(&oldest, now, Duration::days(3));
+        set_age(&middle, now, Duration::days(1));
+
+        let mut files = discovered(&[&newest, &oldest, &middle]);
+        FileDiscovery::sort_files(&mut files, ProcessOrder::ModifiedAsc);
+        assert_eq!(
+            FileDiscovery::into_paths(files.clone()),
+            vec![oldest.clone(), middle.clone(), newest.clone()]
+        );
+
+        FileDiscovery::sort_files(&mut files, ProcessOrder::ModifiedDesc);
+        assert_eq!(
+            FileDiscovery::into_paths(files),
+            vec![newest, middle, oldest]
+        );
+    }
+
+    #[test]
+    fn test_sort_files_name_and_size() {
+        let temp_dir = tempdir().unwrap();
+        let small = temp_dir.path().join("a.md");
+        let large = temp_dir.path().join("b.md");
+        fs::write(&small, "1").unwrap();
+        fs::write(&large, "123456").unwrap();
+
+        let mut files = discovered(&[&large, &small]);
+        FileDiscovery::sort_files(&mut files, ProcessOrder::NameAsc);
+        assert_eq!(
+            FileDiscovery::into_paths(files.clone()),
+            vec![small.clone(), large.clone()]
+        );
+
+        FileDiscovery::sort_files(&mut files, ProcessOrder::SizeDesc);
+        assert_eq!(FileDiscovery::into_paths(files), vec![large, small]);
+    }
+
+    #[test]
+    fn test_discover_in_directory_detailed_returns_metadata() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("article.md"), "12345").unwrap();
+
+        let files =
+            FileDiscovery::discover_in_directory_detailed(temp_dir.path(), &Config::default())
+                .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].size, 5);
+        assert!(files[0].modified <= SystemTime::now());
+    }
+
+    #[test]
+    fn test_discover_in_directory_applies_min_size() {
+        let temp_dir = tempdir().unwrap();
+        File::create(temp_dir.path().join("stub.md")).unwrap();
+        fs::write(temp_dir.path().join("article.md"), "# Article").unwrap();
+
+        let config = Config {
+            min_file_size_bytes: 1,
+            ..Default::default()
+        };
+        let files = FileDiscovery::discover_in_directory(temp_dir.path(), &config).unwrap();
+        assert_eq!(file_names(&files), vec!["article.md"]);
+    }
+
+    #[test]
+    fn test_discover_in_directory_applies_max_size() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("under.md"), vec![b'a'; 1024]).unwrap();
+        fs::write(temp_dir.path().join("over.md"), vec![b'a'; 1025]).unwrap();
+
+        let config = Config {
+            max_file_size_bytes: Some(1024),
+            ..Default::default()
+        };
+        let report = FileDiscovery::discover_in_directory_report(temp_dir.path(), &config).unwrap();
+
+        assert_eq!(
+            FileDiscovery::into_paths(report.files),
+            vec![temp_dir.path().join("under.md")]
+        );
+        assert_eq!(
+            report.skipped,
+            vec![(temp_dir.path().join("over.md"), SkipReason::TooLarge)]
+        );
+    }
+
+    #[test]
+    fn test_discover_in_directory_report_lists_rejections() {
+        let temp_dir = tempdir().unwrap();
+        File::create(temp_dir.path().join("stub.md")).unwrap();
+        fs::write(temp_dir.path().join("_template.md"), "# Template").unwrap();
+        fs::write(temp_dir.path().join("article.md"), "# Article").unwrap();
+        fs::write(temp_dir.path().join("image.jpg"), "binary").unwrap();
+        fs::write(temp_dir.path().join("loading.crdownload.md"), "partial").unwrap();
+
+        let config = Config {
+            min_file_size_bytes: 1,
+            exclude_patterns: vec!["_*".to_string()],
+            ..Default::default()
+        };
+        let report = FileDiscovery::discover_in_directory_report(temp_dir.path(), &config).unwrap();
+
+        assert_eq!(
+            FileDiscovery::into_paths(report.files),
+            vec![temp_dir.path().join("article.md")]
+        );
+        let mut skipped = report.skipped;
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            vec![
+                (temp_dir.path().join("_template.md"), SkipReason::Excluded),
+                (
+                    temp_dir.path().join("loading.crdownload.md"),
+                    SkipReason::InProgress
+                ),
+                (temp_dir.path().join("stub.md"), SkipReason::TooSmall),
+            ]
+        );
+    }
+
+    fn set_age(path: &Path, now: DateTime<Local>, age: Duration) {
+        let modified = now - age;
+        let mtime = filetime::FileTime::from_unix_time(modified.timestamp(), 0);
+        filetime::set_file_mtime(path, mtime).unwrap();
+    }
+
+    #[test]
+    fn test_filter_by_max_age_drops_old_files() {
+        let temp_dir = tempdir().unwrap();
+        let now = fixed_now();
+
+        let fresh = temp_dir.path().join("fresh.md");
+        let old = temp_dir.path().join("old.md");
+        File::create(&fresh).unwrap();
+        File::create(&old).unwrap();
+        set_age(&fresh, now, Duration::hours(3));
+        set_age(&old, now, Duration::days(10));
+
+        let filtered = FileDiscovery::filter_by_max_age(discovered(&[&fresh, &old]), Some(1), now);
+        assert_eq!(FileDiscovery::into_paths(filtered), vec![fresh]);
+    }
+
+    #[test]
+    fn test_filter_by_max_age_keeps_future_files() {
+        let temp_dir = tempdir().unwrap();
+        let now = fixed_now();
+
+        let future = temp_dir.path().join("future.md");
+        File::create(&future).unwrap();
+        set_age(&future, now, Duration::days(-5));
+
+        let filtered = FileDiscovery::filter_by_max_age(discovered(&[&future]), Some(1), now);
+        assert_eq!(FileDiscovery::into_paths(filtered), vec![future]);
+    }
+
+    #[test]
+    fn test_filter_by_max_age_disabled() {
+        let files = vec![DiscoveredFile {
+            path: PathBuf::from("/does/not/exist.md"),
+            size: 0,
+            modified: SystemTime::UNIX_EPOCH,
+        }];
+        let filtered = FileDiscovery::filter_by_max_age(files.clone(), None, fixed_now());
+        assert_eq!(filtered, files);
+    }
+
+    #[test]
+    fn test_discover_in_directory_applies_max_age() {
+        let temp_dir = tempdir().unwrap();
+        let now = fixed_now();
+        let fresh = temp_dir.path().join("fresh.md");
+        let old = temp_dir.path().join("old.md");
+        File::create(&fresh).unwrap();
+        File::create(&old).unwrap();
+        set_age(&fresh, now, Duration::days(7));
+        set_age(&old, now, Duration::days(7) + Duration::seconds(1));
+
+        let config = Config {
+            max_age_days: Some(7),
+            ..Default::default()
+        };
+        let report = FileDiscovery::discover_in_directory_report_with(
+            temp_dir.path(),
+            &config,
+            &RealFs,
+            &FixedClock(now),
+        )
+        .unwrap();
+        assert_eq!(FileDiscovery::into_paths(report.files), vec![fresh]);
+        assert_eq!(report.skipped, vec![(old, SkipReason::TooOld)]);
+    }
+
+    #[test]
+    fn test_discover_in_directory_applies_modified_range() {
+        let temp_dir = tempdir().unwrap();
+        let set_modified = |name: &str, date: &str| {
+            let path = temp_dir.path().join(name);
+            File::create(&path).unwrap();
+            let modified = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_local_timezone(Local)
+                .unwrap();
+            let mtime = filetime::FileTime::from_unix_time(modified.timestamp(), 0);
+            filetime::set_file_mtime(&path, mtime).unwrap();
+            path
+        };
+        let before = set_modified("before.md", "2026-01-31");
+        set_modified("first-day.md", "2026-02-02");
+        set_modified("last-day.md", "2026-02-08");
+        let after = set_modified("after.md", "2026-02-09");
+
+        let config = Config {
+            modified_since: Some("2026-02-02".to_string()),
+            modified_until: Some("2026-02-08".to_string()),
+            ..Default::default()
+        };
+        let report = FileDiscovery::discover_in_directory_report(temp_dir.path(), &config).unwrap();
+
+        assert_eq!(
+            file_names(&FileDiscovery::into_paths(report.files)),
+            vec!["first-day.md", "last-day.md"]
+        );
+        let mut skipped = report.skipped;
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            vec![
+                (after, SkipReason::OutsideDateRange),
+                (before, SkipReason::OutsideDateRange)
+            ]
+        );
+    }
+
     #[test]
     fn test_error_handling_invalid_path() {
         // Test path expansion with invalid tilde path
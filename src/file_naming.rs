@@ -0,0 +1,224 @@
+//! Clean-up applied to file names before they become pages and links
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::FilenameCase;
+
+/// Zero-width characters that are invisible but break link matching
+const ZERO_WIDTH_CHARS: [char; 5] = ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+/// Characters that are not allowed in file names on at least one major OS
+pub const ILLEGAL_FILENAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Name used when sanitizing leaves nothing of the original name
+const FALLBACK_STEM: &str = "untitled";
+
+/// Public interface for file name normalization
+pub struct FileNaming;
+
+impl FileNaming {
+    /// Normalize a name to Unicode NFC and drop zero-width and control characters
+    ///
+    /// Logseq stores page names in NFC, so a decomposed `cafe\u{301}` from a
+    /// download would otherwise never match the `café` page.
+    pub fn normalize_unicode(name: &str) -> String {
+        name.nfc()
+            .filter(|c| !c.is_control() && !ZERO_WIDTH_CHARS.contains(c))
+            .collect()
+    }
+
+    /// Make a file name safe to create on Windows, macOS and Linux
+    ///
+    /// Each of the [`ILLEGAL_FILENAME_CHARS`] and every control character is
+    /// replaced with `replacement`, trailing dots and spaces are trimmed from
+    /// the name and its stem, and the stem is shortened so the whole name
+    /// fits in `max_length` bytes. The extension is kept.
+    pub fn sanitize(name: &str, replacement: &str, max_length: usize) -> String {
+        let cleaned: String = name
+            .chars()
+            .map(|c| {
+                if c.is_control() || ILLEGAL_FILENAME_CHARS.contains(&c) {
+                    replacement.to_string()
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect();
+        let cleaned = cleaned.trim_end_matches(['.', ' ']);
+
+        let (stem, ext) = match cleaned.rfind('.') {
+            Some(dot_pos) if dot_pos > 0 => (&cleaned[..dot_pos], &cleaned[dot_pos..]),
+            _ => (cleaned, ""),
+        };
+        let mut stem = stem.trim_end_matches(['.', ' ']);
+        if stem.is_empty() {
+            stem = FALLBACK_STEM;
+        }
+
+        let mut stem_budget = max_length.saturating_sub(ext.len());
+        while stem_budget > 0 && !stem.is_char_boundary(stem_budget.min(stem.len())) {
+            stem_budget -= 1;
+        }
+        let stem = &stem[..stem_budget.min(stem.len())];
+        let stem = stem.trim_end_matches(['.', ' ']);
+
+        format!("{}{}", stem, ext)
+    }
+
+    /// Change the case of a file stem
+    ///
+    /// `Kebab` and `Snake` lowercase the letters and digits and join each run
+    /// of them with `-` or `_`: whitespace and punctuation between words
+    /// collapse into a single separator and are trimmed from both ends, and
+    /// apostrophes are dropped so `Don't Panic` becomes `dont-panic`. A stem
+    /// with no letters or digits becomes `untitled`.
+    pub fn apply_case(stem: &str, case: FilenameCase) -> String {
+        let separator = match case {
+            FilenameCase::Preserve => return stem.to_string(),
+            FilenameCase::Lower => return stem.to_lowercase(),
+            FilenameCase::Kebab => "-",
+            FilenameCase::Snake => "_",
+        };
+
+        let mut words = vec![String::new()];
+        for c in stem.nfc().filter(|c| !matches!(c, '\'' | '\u{2019}')) {
+            if c.is_alphanumeric() {
+                words.last_mut().unwrap().extend(c.to_lowercase());
+            } else if !words.last().unwrap().is_empty() {
+                words.push(String::new());
+            }
+        }
+        words.retain(|word| !word.is_empty());
+
+        if words.is_empty() {
+            FALLBACK_STEM.to_string()
+        } else {
+            words.join(separator)
+        }
+    }
+
+    /// Add `prefix` before and `suffix` after the stem of a file name
+    ///
+    /// The extension (everything from the last dot, unless the name starts
+    /// with it) stays at the end: `article.md` becomes `web-article-imported.md`.
+    pub fn affix(name: &str, prefix: &str, suffix: &str) -> String {
+        let (stem, ext) = match name.rfind('.') {
+            Some(dot_pos) if dot_pos > 0 => (&name[..dot_pos], &name[dot_pos..]),
+            _ => (name, ""),
+        };
+        format!("{}{}{}{}", prefix, stem, suffix, ext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_case_modes() {
+        let stem = "My Article Title";
+        assert_eq!(
+            FileNaming::apply_case(stem, FilenameCase::Preserve),
+            "My Article Title"
+        );
+        assert_eq!(
+            FileNaming::apply_case(stem, FilenameCase::Lower),
+            "my article title"
+        );
+        assert_eq!(
+            FileNaming::apply_case(stem, FilenameCase::Kebab),
+            "my-article-title"
+        );
+        assert_eq!(
+            FileNaming::apply_case(stem, FilenameCase::Snake),
+            "my_article_title"
+        );
+    }
+
+    #[test]
+    fn test_apply_case_collapses_whitespace_and_punctuation() {
+        assert_eq!(
+            FileNaming::apply_case("  Rust:  A  Guide (2nd ed.) ", FilenameCase::Kebab),
+            "rust-a-guide-2nd-ed"
+        );
+        assert_eq!(
+            FileNaming::apply_case("Don't Panic — Café", FilenameCase::Snake),
+            "dont_panic_café"
+        );
+        assert_eq!(
+            FileNaming::apply_case("cafe\u{301} Notes", FilenameCase::Kebab),
+            "café-notes"
+        );
+        assert_eq!(
+            FileNaming::apply_case("???", FilenameCase::Kebab),
+            "untitled"
+        );
+    }
+
+    #[test]
+    fn test_normalize_unicode_composes_nfd() {
+        let decomposed = "cafe\u{301}.md";
+        assert_eq!(FileNaming::normalize_unicode(decomposed), "caf\u{e9}.md");
+    }
+
+    #[test]
+    fn test_sanitize_replaces_illegal_characters() {
+        assert_eq!(
+            FileNaming::sanitize("Q&A: what/why\\how? *really*.md", "-", 200),
+            "Q&A- what-why-how- -really-.md"
+        );
+        assert_eq!(
+            FileNaming::sanitize("a<b>c|d\"e.md", "_", 200),
+            "a_b_c_d_e.md"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_trims_trailing_dots_and_spaces() {
+        assert_eq!(FileNaming::sanitize("notes. . .md", "-", 200), "notes.md");
+        assert_eq!(FileNaming::sanitize("draft.md. ", "-", 200), "draft.md");
+        assert_eq!(FileNaming::sanitize("...md", "-", 200), "untitled.md");
+    }
+
+    #[test]
+    fn test_sanitize_caps_length_keeping_extension() {
+        let long = format!("{}.md", "a".repeat(300));
+        let sanitized = FileNaming::sanitize(&long, "-", 200);
+        assert_eq!(sanitized.len(), 200);
+        assert!(sanitized.ends_with("a.md"));
+
+        // Multi-byte characters are never split
+        let accented = format!("{}.md", "é".repeat(10));
+        assert_eq!(FileNaming::sanitize(&accented, "-", 8), "éé.md");
+    }
+
+    #[test]
+    fn test_affix_keeps_extension_last() {
+        assert_eq!(
+            FileNaming::affix("article.md", "web-", ""),
+            "web-article.md"
+        );
+        assert_eq!(
+            FileNaming::affix("article.md", "", "-imported"),
+            "article-imported.md"
+        );
+        assert_eq!(
+            FileNaming::affix("article.md", "web-", "-imported"),
+            "web-article-imported.md"
+        );
+        assert_eq!(FileNaming::affix(".notes", "web-", "-x"), "web-.notes-x");
+        assert_eq!(FileNaming::affix("README", "web-", ""), "web-README");
+    }
+
+    #[test]
+    fn test_normalize_unicode_strips_invisible_characters() {
+        assert_eq!(
+            FileNaming::normalize_unicode("\u{FEFF}re\u{200B}port\u{7}\t.md"),
+            "report.md"
+        );
+        assert_eq!(
+            FileNaming::normalize_unicode("plain name.md"),
+            "plain name.md"
+        );
+    }
+}
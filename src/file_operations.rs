@@ -1,10 +1,20 @@
-use std::collections::hash_map::DefaultHasher;
+use chrono::{DateTime, Local};
+use filetime::FileTime;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
-use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
-
-use crate::config::{Config, ConfigError};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::archive::ArchiveEntry;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, ConfigError, FilenameCase, IntegrityHash, SourcePathAnnotation};
+use crate::file_discovery::{FileDiscovery, FileKind};
+use crate::file_naming::FileNaming;
+use crate::filesystem::{FileSystem, RealFs};
+use crate::page_properties::PageProperties;
 
 /// Error types for file operations
 #[derive(Debug, thiserror::Error)]
@@ -21,8 +31,126 @@ pub enum FileOperationError {
     IntegrityCheckFailed(String),
     #[error("Move operation failed: {0}")]
     MoveOperationFailed(String),
+    #[error(
+        "No free name for {} after {attempts} attempts (raise max_collision_attempts)",
+        .path.display()
+    )]
+    CollisionLimitReached { path: PathBuf, attempts: usize },
+    #[error("Identical file already exists: {}", .0.display())]
+    DuplicateContent(PathBuf),
+}
+
+/// Destinations claimed by moves that are still in progress
+///
+/// Name resolution treats these as taken so concurrent moves never pick the
+/// same destination before either file has landed on disk.
+static RESERVED_DESTINATIONS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Number of hex characters of the content hash used as a collision suffix
+const CONTENT_HASH_SUFFIX_LEN: usize = 8;
+
+/// Destinations written by a single run
+///
+/// Names claimed during the run stay taken for its whole duration, even if
+/// the file system (e.g. a network mount) does not show the new file to
+/// later existence checks yet. Directories the run had to create are
+/// remembered as well, so `clean` knows which ones it may remove later.
+#[derive(Debug, Default)]
+pub struct RunDestinations {
+    claimed: Mutex<HashSet<PathBuf>>,
+    created: Mutex<Vec<PathBuf>>,
+}
+
+impl RunDestinations {
+    /// Number of destinations claimed so far
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether nothing was claimed yet
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Whether `path` was claimed earlier in the run
+    pub fn contains(&self, path: &Path) -> bool {
+        self.lock().contains(path)
+    }
+
+    /// Directories created by the run that contain `path`, deepest first
+    pub fn created_ancestors(&self, path: &Path) -> Vec<PathBuf> {
+        let created = self.created.lock().unwrap_or_else(|e| e.into_inner());
+        path.ancestors()
+            .skip(1)
+            .filter(|ancestor| created.iter().any(|dir| dir == ancestor))
+            .map(Path::to_path_buf)
+            .collect()
+    }
+
+    fn insert(&self, path: PathBuf) {
+        self.lock().insert(path);
+    }
+
+    fn record_created(&self, directories: Vec<PathBuf>) {
+        let mut created = self.created.lock().unwrap_or_else(|e| e.into_inner());
+        for directory in directories {
+            if !created.contains(&directory) {
+                created.push(directory);
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashSet<PathBuf>> {
+        self.claimed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// How a move picks a free name when its destination is taken
+#[derive(Clone, Copy)]
+struct Collisions<'a> {
+    /// SHA-256 of the content; its prefix suffixes a taken name
+    content_hash: &'a str,
+    /// With `dedupe_by_content`, the [`FileOperations::page_hash`] of the
+    /// content and the configuration to hash other pages with, so an
+    /// identical page under the suffixed name makes the move a duplicate
+    duplicate_of: Option<(&'a str, &'a Config)>,
+    /// Suffixed names tried before giving up
+    max_attempts: usize,
+}
+
+impl<'a> Collisions<'a> {
+    /// Collisions that never count a taken name as a duplicate
+    #[cfg(test)]
+    fn new(content_hash: &'a str, max_attempts: usize) -> Self {
+        Collisions {
+            content_hash,
+            duplicate_of: None,
+            max_attempts,
+        }
+    }
+
+    /// Collisions capped by `max_collision_attempts`, counting a page with
+    /// the same `page_hash` as a duplicate when one is given
+    fn for_config(content_hash: &'a str, page_hash: Option<&'a str>, config: &'a Config) -> Self {
+        Collisions {
+            content_hash,
+            duplicate_of: page_hash.map(|hash| (hash, config)),
+            max_attempts: config.max_collision_attempts,
+        }
+    }
+
+    /// Whether the page at `path` already holds the content
+    fn is_duplicate(&self, path: &Path) -> bool {
+        self.duplicate_of.is_some_and(|(page_hash, config)| {
+            path.is_file()
+                && FileOperations::page_hash(path, config).is_ok_and(|hash| hash == page_hash)
+        })
+    }
 }
 
+/// Page property holding the absolute path a file was imported from
+const SOURCE_PATH_PROPERTY: &str = "source-path";
+
 /// Public interface for file operations
 pub struct FileOperations;
 
@@ -42,48 +170,828 @@ impl FileOperations {
     pub fn move_to_pages(
         source_path: &Path,
         config: &Config,
+    ) -> Result<PathBuf, FileOperationError> {
+        Self::move_to_pages_in_run(
+            source_path,
+            config,
+            &RunDestinations::default(),
+            &SystemClock,
+        )
+    }
+
+    /// Move a file like [`Self::move_to_pages`], avoiding every destination
+    /// already claimed in `run`
+    ///
+    /// The chosen destination is added to `run`, so moves sharing it never
+    /// land on the same name. Page properties take their date from `clock`.
+    pub fn move_to_pages_in_run(
+        source_path: &Path,
+        config: &Config,
+        run: &RunDestinations,
+        clock: &dyn Clock,
+    ) -> Result<PathBuf, FileOperationError> {
+        Self::move_to_pages_with(source_path, config, run, clock, &RealFs)
+    }
+
+    /// Move a file like [`Self::move_to_pages_in_run`], doing the move
+    /// through `fs`
+    pub fn move_to_pages_with(
+        source_path: &Path,
+        config: &Config,
+        run: &RunDestinations,
+        clock: &dyn Clock,
+        fs: &dyn FileSystem,
     ) -> Result<PathBuf, FileOperationError> {
         // Validate source file exists
-        if !source_path.exists() {
+        if fs.metadata(source_path).is_err() {
             return Err(FileOperationError::FileNotFound(
                 source_path.display().to_string(),
             ));
         }
 
         // Construct destination directory
-        let pages_dir = Self::get_pages_directory(config)?;
+        let pages_dir = Self::destination_directory(source_path, config, fs)?;
 
         // Ensure destination directory exists
-        Self::ensure_directory_exists(&pages_dir)?;
+        Self::ensure_directory_in_run(&pages_dir, run, fs)?;
+
+        // Get destination filename
+        let filename = Self::destination_filename(source_path, config)?;
+
+        // Resolve and claim the destination path with collision handling,
+        // keeping the names of the page's asset folders free too
+        let content_hash = Self::content_hash_with(source_path, fs)?;
+        let page_hash = config
+            .dedupe_by_content
+            .then(|| Self::page_hash_with(source_path, config, fs))
+            .transpose()?;
+        let assets = Self::asset_folders(source_path, config, fs);
+        let patterns: Vec<&str> = assets.iter().map(|(_, pattern)| *pattern).collect();
+        let dest_path = Self::claim_destination_path_with(
+            &pages_dir,
+            &filename,
+            Collisions::for_config(&content_hash, page_hash.as_deref(), config),
+            run,
+            &patterns,
+            fs,
+            |_| {},
+        )?;
+
+        // Perform atomic move operation, replacing the empty claimed file
+        let properties = ((config.add_page_properties
+            || config.record_source_path == SourcePathAnnotation::PageProperty)
+            && !config.is_attachment(source_path))
+        .then(|| Self::render_page_properties(source_path, config, clock));
+        let retry_delay = Duration::from_millis(config.io_retry_delay_ms);
+        let result = Self::with_retries(config.io_retries, retry_delay, || match &properties {
+            Some(properties) => Self::move_with_properties(source_path, &dest_path, properties, fs),
+            None => Self::atomic_move(source_path, &dest_path, config.integrity_hash, fs),
+        });
+        if result.is_err() && fs.metadata(&dest_path).is_ok_and(|m| m.len == 0) {
+            let _ = fs.remove_file(&dest_path);
+        }
+        result?;
+
+        // Move the asset folders next to the page; the page goes back to
+        // its source if they cannot follow
+        if let Err(e) = Self::move_asset_folders(&assets, &dest_path, config, fs) {
+            let _ = Self::atomic_move(&dest_path, source_path, config.integrity_hash, fs);
+            return Err(e);
+        }
+
+        Ok(dest_path)
+    }
+
+    /// Sibling folders of `source_path` matching `asset_folder_patterns`,
+    /// with the pattern each one matched
+    fn asset_folders<'a>(
+        source_path: &Path,
+        config: &'a Config,
+        fs: &dyn FileSystem,
+    ) -> Vec<(PathBuf, &'a str)> {
+        config
+            .asset_folder_patterns
+            .iter()
+            .filter_map(|pattern| {
+                let folder = Self::asset_folder_path(source_path, pattern)?;
+                fs.metadata(&folder)
+                    .is_ok_and(|metadata| metadata.is_dir)
+                    .then_some((folder, pattern.as_str()))
+            })
+            .collect()
+    }
+
+    /// The folder `pattern` names for a page, next to it
+    fn asset_folder_path(page: &Path, pattern: &str) -> Option<PathBuf> {
+        let stem = page.file_stem()?.to_str()?;
+        Some(page.with_file_name(pattern.replace("{stem}", stem)))
+    }
+
+    /// Move the `assets` of a page that was moved to `dest_path`
+    ///
+    /// Each folder is renamed after the page's new stem. When that changes
+    /// its name, references to the folder in the page are rewritten. If a
+    /// folder cannot be moved, the ones already moved are put back.
+    fn move_asset_folders(
+        assets: &[(PathBuf, &str)],
+        dest_path: &Path,
+        config: &Config,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileOperationError> {
+        let mut moved: Vec<(&Path, PathBuf)> = Vec::new();
+        for (folder, pattern) in assets {
+            let result = Self::asset_folder_path(dest_path, pattern)
+                .ok_or_else(|| {
+                    FileOperationError::MoveOperationFailed(format!(
+                        "Invalid page name for asset folder: {}",
+                        dest_path.display()
+                    ))
+                })
+                .and_then(|destination| {
+                    Self::move_directory(folder, &destination, config.integrity_hash, fs)?;
+                    Ok(destination)
+                });
+            match result {
+                Ok(destination) => moved.push((folder, destination)),
+                Err(e) => {
+                    for (folder, destination) in moved.into_iter().rev() {
+                        let _ =
+                            Self::move_directory(&destination, folder, config.integrity_hash, fs);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let renamed: Vec<(String, String)> = moved
+            .iter()
+            .filter_map(|(folder, destination)| {
+                let old = folder.file_name()?.to_str()?;
+                let new = destination.file_name()?.to_str()?;
+                (old != new).then(|| (old.to_string(), new.to_string()))
+            })
+            .collect();
+        if !renamed.is_empty()
+            && let Ok(content) = fs.read_to_string(dest_path)
+        {
+            let rewritten = Self::rewrite_folder_links(&content, &renamed);
+            if rewritten != content {
+                let modified = fs.metadata(dest_path)?.modified;
+                fs.write(dest_path, rewritten.as_bytes())?;
+                let _ = filetime::set_file_mtime(dest_path, FileTime::from_system_time(modified));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Point links into each renamed folder of `renamed` at its new name
+    ///
+    /// Only link targets are rewritten: the part in parentheses of a
+    /// markdown link or image and the inside of an `![[...]]` embed, when it
+    /// starts with the folder (optionally after `<` or `./`). The folder name
+    /// is matched as written and with spaces encoded as `%20`; the same text
+    /// elsewhere in the page is left alone.
+    fn rewrite_folder_links(content: &str, renamed: &[(String, String)]) -> String {
+        let mut rewritten = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some((at, open, close)) = [("](", ")"), ("![[", "]]")]
+            .into_iter()
+            .filter_map(|(open, close)| rest.find(open).map(|at| (at, open, close)))
+            .min_by_key(|(at, _, _)| *at)
+        {
+            let start = at + open.len();
+            let end = rest[start..]
+                .find(close)
+                .map_or(rest.len(), |end| start + end);
+            rewritten.push_str(&rest[..start]);
+            rewritten.push_str(&Self::rewrite_folder_target(&rest[start..end], renamed));
+            rest = &rest[end..];
+        }
+        rewritten.push_str(rest);
+        rewritten
+    }
+
+    /// A link `target` moved into the new name of the renamed folder it starts with
+    fn rewrite_folder_target(target: &str, renamed: &[(String, String)]) -> String {
+        let path = target.strip_prefix('<').unwrap_or(target);
+        let path = path.strip_prefix("./").unwrap_or(path);
+        let lead = &target[..target.len() - path.len()];
+        for (old, new) in renamed {
+            for (old, new) in [
+                (old.clone(), new.clone()),
+                (old.replace(' ', "%20"), new.replace(' ', "%20")),
+            ] {
+                if let Some(tail) = path.strip_prefix(&format!("{}/", old)) {
+                    return format!("{}{}/{}", lead, new, tail);
+                }
+            }
+        }
+        target.to_string()
+    }
+
+    /// Move a directory and everything in it to `destination`, which must not exist
+    ///
+    /// Falls back to moving file by file when a rename is not possible,
+    /// e.g. across filesystems.
+    fn move_directory(
+        source: &Path,
+        destination: &Path,
+        integrity: IntegrityHash,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileOperationError> {
+        if fs.symlink_metadata(destination).is_ok() {
+            return Err(FileOperationError::MoveOperationFailed(format!(
+                "Asset folder already exists: {}",
+                destination.display()
+            )));
+        }
+        if fs.rename(source, destination).is_ok() {
+            return Ok(());
+        }
+
+        fs.create_dir_all(destination)?;
+        for entry in fs.read_dir(source)? {
+            let path = entry?;
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            if fs.symlink_metadata(&path)?.is_dir {
+                Self::move_directory(&path, &destination.join(name), integrity, fs)?;
+            } else {
+                Self::atomic_move(&path, &destination.join(name), integrity, fs)?;
+            }
+        }
+        fs.remove_dir(source)?;
+        Ok(())
+    }
+
+    /// Destination a move of `source_path` would try first
+    ///
+    /// Collisions are not resolved, so the actual move may add a hash
+    /// suffix when a different page already has this name.
+    pub fn planned_destination(
+        source_path: &Path,
+        config: &Config,
+    ) -> Result<PathBuf, FileOperationError> {
+        Ok(Self::destination_directory(source_path, config, &RealFs)?
+            .join(Self::destination_filename(source_path, config)?))
+    }
+
+    /// Find a page that already holds the same content as `source_path`
+    ///
+    /// Looks at the names a move would try first: the source's own name and
+    /// its content-hash suffixed variant in the destination directory.
+    /// Contents are compared by [`Self::page_hash`], so page properties added
+    /// at import don't hide a match.
+    pub fn find_identical(
+        source_path: &Path,
+        config: &Config,
+    ) -> Result<Option<PathBuf>, FileOperationError> {
+        let dest_dir = Self::destination_directory(source_path, config, &RealFs)?;
+        let Ok(filename) = Self::destination_filename(source_path, config) else {
+            return Ok(None);
+        };
+        let content_hash = Self::content_hash(source_path)?;
+        let page_hash = Self::page_hash(source_path, config)?;
+
+        Ok(Self::find_identical_in(
+            &dest_dir,
+            &filename,
+            &content_hash,
+            &page_hash,
+            config,
+        ))
+    }
+
+    /// Find a page that already holds an archive entry's content, like
+    /// [`Self::find_identical`]
+    pub fn find_identical_entry(
+        archive: &Path,
+        entry: &ArchiveEntry,
+        config: &Config,
+    ) -> Result<Option<PathBuf>, FileOperationError> {
+        let (dest_dir, filename) = Self::entry_destination(archive, entry, config)?;
+        Ok(Self::find_identical_in(
+            &dest_dir,
+            &filename,
+            &Self::bytes_hash(&entry.content),
+            &Self::page_bytes_hash(&entry.content, &entry.path, config),
+            config,
+        ))
+    }
+
+    /// Write an archive entry as a new page, like a move of a file with its content
+    ///
+    /// The page is named after the entry's file name, so nested entries are
+    /// flattened; with `namespaced_links` the entry's directories inside the
+    /// archive are kept below the destination directory instead, so journal
+    /// links name them too. Page properties record `<archive>/<entry path>`
+    /// as the source.
+    pub fn import_archive_entry(
+        archive: &Path,
+        entry: &ArchiveEntry,
+        config: &Config,
+        run: &RunDestinations,
+        clock: &dyn Clock,
+    ) -> Result<PathBuf, FileOperationError> {
+        let (dest_dir, filename) = Self::entry_destination(archive, entry, config)?;
+        let source = archive.join(&entry.path);
+        let source = std::path::absolute(&source).unwrap_or(source);
+        Self::write_new_page(
+            &dest_dir,
+            &filename,
+            &entry.content,
+            &Self::render_page_properties_for(&source.display().to_string(), config, clock),
+            entry.modified,
+            config,
+            run,
+        )
+    }
+
+    /// The destination a fetched page named `name` would take first, or its
+    /// content-hash suffixed variant, if it already holds `content`
+    pub fn find_identical_page(
+        name: &str,
+        content: &[u8],
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Result<Option<PathBuf>, FileOperationError> {
+        let (dest_dir, filename) = Self::named_destination(name, config, clock)?;
+        Ok(Self::find_identical_in(
+            &dest_dir,
+            &filename,
+            &Self::bytes_hash(content),
+            &Self::page_bytes_hash(content, Path::new(name), config),
+            config,
+        ))
+    }
+
+    /// Write content fetched from `source`, such as a URL, as a new page
+    ///
+    /// The page is named and placed as if a file called `name` had been
+    /// moved at `clock`'s time, and page properties record `source` as is.
+    pub fn import_page(
+        source: &str,
+        name: &str,
+        content: &[u8],
+        config: &Config,
+        run: &RunDestinations,
+        clock: &dyn Clock,
+    ) -> Result<PathBuf, FileOperationError> {
+        let (dest_dir, filename) = Self::named_destination(name, config, clock)?;
+        Self::write_new_page(
+            &dest_dir,
+            &filename,
+            content,
+            &Self::render_page_properties_for(source, config, clock),
+            clock.now().into(),
+            config,
+            run,
+        )
+    }
+
+    /// Destination directory and file name of a page called `name` that has no source file
+    fn named_destination(
+        name: &str,
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Result<(PathBuf, std::ffi::OsString), FileOperationError> {
+        let path = Path::new(name);
+        let dest_dir = Self::destination_directory_at(path, config, || Ok(clock.now().into()))?;
+        Ok((dest_dir, Self::destination_filename(path, config)?))
+    }
+
+    /// Write `content` to a free name in `dest_dir`, merging `properties`
+    fn write_new_page(
+        dest_dir: &Path,
+        filename: &std::ffi::OsStr,
+        content: &[u8],
+        properties: &[(String, String)],
+        modified: SystemTime,
+        config: &Config,
+        run: &RunDestinations,
+    ) -> Result<PathBuf, FileOperationError> {
+        let fs = &RealFs;
+        Self::ensure_directory_in_run(dest_dir, run, fs)?;
+        let content_hash = Self::bytes_hash(content);
+        let page_hash = config
+            .dedupe_by_content
+            .then(|| Self::page_bytes_hash(content, Path::new(filename), config));
+        let dest_path = Self::claim_destination_path(
+            dest_dir,
+            filename,
+            Collisions::for_config(&content_hash, page_hash.as_deref(), config),
+            run,
+            |_| {},
+        )?;
+
+        let content = if !properties.is_empty() && !config.is_attachment(Path::new(filename)) {
+            PageProperties::merge(&String::from_utf8_lossy(content), properties).into_bytes()
+        } else {
+            content.to_vec()
+        };
+
+        // Write next to the claimed name and rename over it, so the page is
+        // never seen half-written
+        let temp_path =
+            dest_path.with_file_name(format!(".{}.local_shelf.tmp", filename.to_string_lossy()));
+        let result = fs.write(&temp_path, &content).and_then(|()| {
+            let _ = filetime::set_file_mtime(&temp_path, FileTime::from_system_time(modified));
+            fs.rename(&temp_path, &dest_path)
+        });
+        if let Err(e) = result {
+            let _ = fs.remove_file(&temp_path);
+            let _ = fs.remove_file(&dest_path);
+            return Err(e.into());
+        }
+
+        Ok(dest_path)
+    }
+
+    /// Destination directory and file name of an archive entry
+    fn entry_destination(
+        archive: &Path,
+        entry: &ArchiveEntry,
+        config: &Config,
+    ) -> Result<(PathBuf, std::ffi::OsString), FileOperationError> {
+        let source = archive.join(&entry.path);
+        let mut dest_dir = Self::destination_directory_at(&source, config, || Ok(entry.modified))?;
+        if config.namespaced_links
+            && let Some(parent) = entry.path.parent()
+        {
+            dest_dir.push(parent);
+        }
+        Ok((dest_dir, Self::destination_filename(&source, config)?))
+    }
+
+    /// The name a move would try first in `dest_dir`, or its content-hash
+    /// suffixed variant, if it holds content with `content_hash`
+    fn find_identical_in(
+        dest_dir: &Path,
+        filename: &std::ffi::OsStr,
+        content_hash: &str,
+        page_hash: &str,
+        config: &Config,
+    ) -> Option<PathBuf> {
+        let mut candidates = vec![dest_dir.join(filename)];
+        if let Some(filename) = filename.to_str() {
+            let (name, ext) = match filename.rfind('.') {
+                Some(dot_pos) => (&filename[..dot_pos], &filename[dot_pos..]),
+                None => (filename, ""),
+            };
+            candidates.push(dest_dir.join(format!(
+                "{}_{}{}",
+                name,
+                &content_hash[..CONTENT_HASH_SUFFIX_LEN],
+                ext
+            )));
+        }
+
+        candidates.into_iter().find(|candidate| {
+            candidate.is_file()
+                && Self::page_hash(candidate, config).is_ok_and(|hash| hash == page_hash)
+        })
+    }
 
-        // Get source filename
+    /// File name a source file gets in the destination directory
+    ///
+    /// With `normalize_extension` the extension of a page is replaced by `.md`,
+    /// `filename_case` recases the stem (keeping any compound extension), with
+    /// `normalize_unicode` the name is converted to NFC and with
+    /// `sanitize_filenames` illegal characters are replaced, so collision
+    /// checks run against the name the page will actually have.
+    fn destination_filename(
+        source_path: &Path,
+        config: &Config,
+    ) -> Result<std::ffi::OsString, FileOperationError> {
         let filename = source_path.file_name().ok_or_else(|| {
             FileOperationError::MoveOperationFailed("Invalid source file path".to_string())
         })?;
 
-        // Resolve destination path with collision handling
-        let dest_path = Self::resolve_destination_path(&pages_dir, filename)?;
+        let mut destination = filename.to_os_string();
+        if config.normalize_extension
+            && source_path.extension().is_some()
+            && !config.is_attachment(source_path)
+        {
+            destination = source_path.file_stem().unwrap_or(filename).to_os_string();
+            destination.push(".md");
+        }
+
+        if config.filename_case != FilenameCase::Preserve
+            && let Some(name) = destination.to_str()
+        {
+            let stem_len =
+                match FileDiscovery::file_kind(Path::new(name), &config.compound_extensions) {
+                    FileKind::Compound(extension) => name.len() - extension.len() - 1,
+                    FileKind::Page => name.rfind('.').filter(|&dot| dot > 0).unwrap_or(name.len()),
+                };
+            let (stem, ext) = name.split_at(stem_len);
+            destination = format!(
+                "{}{}",
+                FileNaming::apply_case(stem, config.filename_case),
+                ext
+            )
+            .into();
+        }
+
+        if (!config.filename_prefix.is_empty() || !config.filename_suffix.is_empty())
+            && let Some(name) = destination.to_str()
+        {
+            destination =
+                FileNaming::affix(name, &config.filename_prefix, &config.filename_suffix).into();
+        }
+
+        if config.normalize_unicode
+            && let Some(name) = destination.to_str()
+        {
+            destination = FileNaming::normalize_unicode(name).into();
+        }
+
+        if config.sanitize_filenames
+            && let Some(name) = destination.to_str()
+        {
+            destination = FileNaming::sanitize(
+                name,
+                &config.filename_replacement,
+                config.max_filename_length,
+            )
+            .into();
+        }
+
+        Ok(destination)
+    }
+
+    /// Directory a source file is moved into, including any dated subdirectory
+    ///
+    /// The first `routing` rule matching the file name picks the directory;
+    /// attachments matching none go to `assets_dir_name` and other files to
+    /// the pages directory. With `organize_by_date` the file is dated by
+    /// its modification time as read through `fs`.
+    fn destination_directory(
+        source_path: &Path,
+        config: &Config,
+        fs: &dyn FileSystem,
+    ) -> Result<PathBuf, FileOperationError> {
+        Self::destination_directory_at(source_path, config, || {
+            Ok(fs.metadata(source_path)?.modified)
+        })
+    }
+
+    /// Destination directory like [`Self::destination_directory`], dating
+    /// the file by `modified`, which is only called with `organize_by_date`
+    fn destination_directory_at(
+        source_path: &Path,
+        config: &Config,
+        modified: impl FnOnce() -> Result<SystemTime, FileOperationError>,
+    ) -> Result<PathBuf, FileOperationError> {
+        let mut pages_dir = match config.route_for(source_path) {
+            Some(route) => config.knowledge_base_subdir(&route.destination),
+            None if config.is_attachment(source_path) => {
+                config.knowledge_base_subdir(&config.assets_dir_name)
+            }
+            None => Self::get_pages_directory(config)?,
+        };
+        if config.organize_by_date {
+            pages_dir.push(Self::date_subdirectory(
+                modified()?,
+                &config.date_subdir_pattern,
+            ));
+        }
+        Ok(pages_dir)
+    }
+
+    /// Resolve a free destination path and claim it on disk
+    ///
+    /// The name is claimed by creating an empty file with `create_new`, so a
+    /// file that appears between resolving and claiming (from another run or
+    /// process) is never overwritten; resolution is retried instead. The
+    /// claimed name is recorded in `run`. `after_resolve` runs between the
+    /// two steps.
+    fn claim_destination_path(
+        dest_dir: &Path,
+        filename: &std::ffi::OsStr,
+        collisions: Collisions,
+        run: &RunDestinations,
+        after_resolve: impl FnMut(&Path),
+    ) -> Result<PathBuf, FileOperationError> {
+        Self::claim_destination_path_with(
+            dest_dir,
+            filename,
+            collisions,
+            run,
+            &[],
+            &RealFs,
+            after_resolve,
+        )
+    }
+
+    /// Claim a destination like [`Self::claim_destination_path`], also
+    /// treating a name as taken when any folder the `asset_patterns` name
+    /// for it exists
+    fn claim_destination_path_with(
+        dest_dir: &Path,
+        filename: &std::ffi::OsStr,
+        collisions: Collisions,
+        run: &RunDestinations,
+        asset_patterns: &[&str],
+        fs: &dyn FileSystem,
+        mut after_resolve: impl FnMut(&Path),
+    ) -> Result<PathBuf, FileOperationError> {
+        // A lost claim leaves its name taken, so the next resolution moves
+        // on to the following name: like resolution, claiming gives up after
+        // the plain name and `max_attempts` suffixed names
+        for _ in 0..=collisions.max_attempts {
+            let dest_path = Self::reserve_destination_path(
+                dest_dir,
+                filename,
+                collisions,
+                run,
+                asset_patterns,
+                fs,
+            )?;
+            after_resolve(&dest_path);
+
+            let claimed = fs.create_new(&dest_path);
+            Self::release_destination_path(&dest_path);
+
+            match claimed {
+                Ok(_) => {
+                    run.insert(dest_path.clone());
+                    return Ok(dest_path);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
 
-        // Perform atomic move operation
-        Self::atomic_move(source_path, &dest_path)?;
+        Err(FileOperationError::CollisionLimitReached {
+            path: dest_dir.join(filename),
+            attempts: collisions.max_attempts,
+        })
+    }
 
+    /// Resolve a free destination path and reserve it for the calling move
+    fn reserve_destination_path(
+        dest_dir: &Path,
+        filename: &std::ffi::OsStr,
+        collisions: Collisions,
+        run: &RunDestinations,
+        asset_patterns: &[&str],
+        fs: &dyn FileSystem,
+    ) -> Result<PathBuf, FileOperationError> {
+        let mut reserved = RESERVED_DESTINATIONS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let dest_path = Self::resolve_destination_path(dest_dir, filename, collisions, |path| {
+            reserved.contains(path)
+                || run.contains(path)
+                || asset_patterns.iter().any(|pattern| {
+                    Self::asset_folder_path(path, pattern)
+                        .is_none_or(|folder| fs.symlink_metadata(&folder).is_ok())
+                })
+        })?;
+        reserved.insert(dest_path.clone());
         Ok(dest_path)
     }
 
+    /// Release a destination reserved by [`Self::reserve_destination_path`]
+    fn release_destination_path(dest_path: &Path) {
+        RESERVED_DESTINATIONS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(dest_path);
+    }
+
+    /// Render the page properties to write for a source file
+    ///
+    /// These are the configured `page_properties` when `add_page_properties`
+    /// is set, followed by `source-path` when `record_source_path` asks for it.
+    fn render_page_properties(
+        source_path: &Path,
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Vec<(String, String)> {
+        let source = std::path::absolute(source_path)
+            .unwrap_or_else(|_| source_path.to_path_buf())
+            .display()
+            .to_string();
+        Self::render_page_properties_for(&source, config, clock)
+    }
+
+    /// Page properties for a page imported from `source`, which is recorded as given
+    fn render_page_properties_for(
+        source: &str,
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Vec<(String, String)> {
+        let date = clock.now().format("%Y-%m-%d").to_string();
+
+        let mut properties: Vec<(String, String)> = config
+            .page_properties
+            .iter()
+            .filter(|_| config.add_page_properties)
+            .map(|(key, value)| {
+                (
+                    key.clone(),
+                    PageProperties::render_value(value, &date, source),
+                )
+            })
+            .collect();
+        if config.record_source_path == SourcePathAnnotation::PageProperty {
+            properties.push((SOURCE_PATH_PROPERTY.to_string(), source.to_string()));
+        }
+        properties
+    }
+
+    /// Move a file while merging page properties into its content
+    ///
+    /// The new content is written to a temporary file next to the destination
+    /// and renamed into place, so the destination never holds a partial
+    /// page. The source is only removed once the destination is complete.
+    fn move_with_properties(
+        source: &Path,
+        destination: &Path,
+        properties: &[(String, String)],
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileOperationError> {
+        let content = fs.read_to_string(source)?;
+        let merged = PageProperties::merge(&content, properties);
+
+        let file_name = destination
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temp_path = destination.with_file_name(format!(".{}.local_shelf.tmp", file_name));
+
+        let result = fs.write(&temp_path, merged.as_bytes()).and_then(|()| {
+            if let Ok(metadata) = fs.metadata(source) {
+                let _ = filetime::set_file_mtime(
+                    &temp_path,
+                    FileTime::from_system_time(metadata.modified),
+                );
+            }
+            fs.rename(&temp_path, destination)
+        });
+
+        if let Err(e) = result {
+            let _ = fs.remove_file(&temp_path);
+            return Err(e.into());
+        }
+
+        fs.remove_file(source).map_err(|e| {
+            FileOperationError::MoveOperationFailed(format!(
+                "Failed to remove source file after copy: {}",
+                e
+            ))
+        })?;
+
+        Ok(())
+    }
+
     /// Get the pages directory path from config
     ///
-    /// Constructs the full path to {{Knowledge Base}}/pages
+    /// Constructs the full path to {{Knowledge Base}}/{{destination_subdir}},
+    /// which falls back to the pages directory (`pages_dir_name`)
     fn get_pages_directory(config: &Config) -> Result<PathBuf, FileOperationError> {
-        let kb_path = config.get_knowledge_base_path();
-        let mut pages_path = PathBuf::from(kb_path);
-        pages_path.push("pages");
-        Ok(pages_path)
+        Ok(config.knowledge_base_subdir(config.destination_dir_name()))
+    }
+
+    /// Build the dated subdirectory for a file from its modification time
+    ///
+    /// `pattern` is a chrono format string such as `%Y/%m`.
+    fn date_subdirectory(modified: SystemTime, pattern: &str) -> PathBuf {
+        let modified: DateTime<Local> = modified.into();
+        PathBuf::from(modified.format(pattern).to_string())
+    }
+
+    /// Ensure directory exists like [`Self::ensure_directory_exists`],
+    /// recording in `run` every directory that had to be created
+    fn ensure_directory_in_run(
+        dir_path: &Path,
+        run: &RunDestinations,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileOperationError> {
+        let missing: Vec<PathBuf> = dir_path
+            .ancestors()
+            .take_while(|ancestor| {
+                !ancestor.as_os_str().is_empty() && fs.metadata(ancestor).is_err()
+            })
+            .map(Path::to_path_buf)
+            .collect();
+        Self::ensure_directory_exists(dir_path, fs)?;
+        run.record_created(missing);
+        Ok(())
     }
 
     /// Ensure directory exists, creating it if necessary
-    fn ensure_directory_exists(dir_path: &Path) -> Result<(), FileOperationError> {
-        if !dir_path.exists() {
-            fs::create_dir_all(dir_path).map_err(|e| {
+    fn ensure_directory_exists(
+        dir_path: &Path,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileOperationError> {
+        if fs.metadata(dir_path).is_err() {
+            fs.create_dir_all(dir_path).map_err(|e| {
                 FileOperationError::DirectoryCreationFailed(format!(
                     "{}: {}",
                     dir_path.display(),
@@ -94,18 +1002,93 @@ impl FileOperations {
         Ok(())
     }
 
+    /// Property keys that imports add to pages under `config`
+    fn added_property_keys(config: &Config) -> Vec<&str> {
+        let mut keys: Vec<&str> = config
+            .page_properties
+            .iter()
+            .filter(|_| config.add_page_properties)
+            .map(|(key, _)| key.as_str())
+            .collect();
+        if config.record_source_path == SourcePathAnnotation::PageProperty {
+            keys.push(SOURCE_PATH_PROPERTY);
+        }
+        keys
+    }
+
+    /// Hex-encoded SHA-256 of a page's content without the page properties
+    /// an import adds, so a stored page and its source hash the same
+    ///
+    /// Attachments and pages that are not UTF-8 are hashed as they are.
+    pub fn page_hash(path: &Path, config: &Config) -> Result<String, FileOperationError> {
+        if Self::added_property_keys(config).is_empty() || config.is_attachment(path) {
+            return Self::content_hash(path);
+        }
+        Ok(Self::page_bytes_hash(&fs::read(path)?, path, config))
+    }
+
+    /// Page hash like [`Self::page_hash`], reading the page through `fs`
+    fn page_hash_with(
+        path: &Path,
+        config: &Config,
+        fs: &dyn FileSystem,
+    ) -> Result<String, FileOperationError> {
+        Ok(Self::page_bytes_hash(&fs.read(path)?, path, config))
+    }
+
+    /// Page hash like [`Self::page_hash`] of in-memory content for a page named `path`
+    fn page_bytes_hash(content: &[u8], path: &Path, config: &Config) -> String {
+        let keys = Self::added_property_keys(config);
+        match std::str::from_utf8(content) {
+            Ok(text) if !keys.is_empty() && !config.is_attachment(path) => {
+                Self::bytes_hash(PageProperties::strip(text, &keys).as_bytes())
+            }
+            _ => Self::bytes_hash(content),
+        }
+    }
+
+    /// Hex-encoded SHA-256 of in-memory content, matching [`Self::content_hash`]
+    fn bytes_hash(content: &[u8]) -> String {
+        Self::hex(&Sha256::digest(content))
+    }
+
+    /// Hex-encoded SHA-256 of a file's content
+    pub fn content_hash(path: &Path) -> Result<String, FileOperationError> {
+        let mut file = fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        Ok(Self::hex(&hasher.finalize()))
+    }
+
+    /// Content hash like [`Self::content_hash`], reading the file through `fs`
+    fn content_hash_with(path: &Path, fs: &dyn FileSystem) -> Result<String, FileOperationError> {
+        Ok(Self::bytes_hash(&fs.read(path)?))
+    }
+
     /// Resolve destination path with collision handling
     ///
     /// If a file already exists at the destination, generates a unique filename
-    /// by appending a hash postfix derived from the current timestamp.
+    /// by appending a short prefix of the file's SHA-256 `content_hash`, so
+    /// identical files get identical suffixes and different files different
+    /// ones. A numeric counter is added when the suffixed name is taken too,
+    /// trying at most `max_attempts` suffixed names in all. When
+    /// `collisions` checks for duplicates and the suffixed name already
+    /// holds the same content, the file is a duplicate and
+    /// [`FileOperationError::DuplicateContent`] is returned instead.
+    ///
+    /// Paths for which `is_reserved` returns true count as collisions even if
+    /// nothing exists there yet.
     fn resolve_destination_path(
         dest_dir: &Path,
         filename: &std::ffi::OsStr,
+        collisions: Collisions,
+        is_reserved: impl Fn(&Path) -> bool,
     ) -> Result<PathBuf, FileOperationError> {
+        let is_free = |path: &Path| !path.exists() && !is_reserved(path);
         let mut dest_path = dest_dir.join(filename);
 
         // If no collision, return original path
-        if !dest_path.exists() {
+        if is_free(&dest_path) {
             return Ok(dest_path);
         }
 
@@ -121,20 +1104,11 @@ impl FileOperations {
             (filename_str, "")
         };
 
-        // Generate unique hash postfix based on current timestamp
-        let mut hasher = DefaultHasher::new();
-        SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos()
-            .hash(&mut hasher);
-
-        let hash = hasher.finish();
-        let hash_postfix = format!("_{:x}", hash);
+        // Hash postfix derived from the file content
+        let hash_postfix = format!("_{}", &collisions.content_hash[..CONTENT_HASH_SUFFIX_LEN]);
 
         // Try with hash postfix, keep generating until we find a unique name
-        let mut attempt = 0;
-        loop {
+        for attempt in 0..collisions.max_attempts {
             let new_filename = if attempt == 0 {
                 format!("{}{}{}", name, hash_postfix, ext)
             } else {
@@ -143,40 +1117,109 @@ impl FileOperations {
 
             dest_path = dest_dir.join(&new_filename);
 
-            if !dest_path.exists() {
-                break;
+            if is_free(&dest_path) {
+                return Ok(dest_path);
             }
 
-            attempt += 1;
-
-            // Safety check to prevent infinite loop
-            if attempt > 1000 {
-                return Err(FileOperationError::MoveOperationFailed(
-                    "Unable to generate unique filename after 1000 attempts".to_string(),
-                ));
+            // Identical content under the suffixed name is the same file;
+            // counter suffixes would only add copies of it
+            if attempt == 0 && collisions.is_duplicate(&dest_path) {
+                return Err(FileOperationError::DuplicateContent(dest_path));
             }
         }
 
-        Ok(dest_path)
+        Err(FileOperationError::CollisionLimitReached {
+            path: dest_dir.join(filename),
+            attempts: collisions.max_attempts,
+        })
     }
 
     /// Perform atomic move operation with basic rollback capability
     ///
-    /// Uses copy + delete approach for cross-filesystem moves
-    fn atomic_move(source: &Path, destination: &Path) -> Result<(), FileOperationError> {
+    /// Uses copy + delete approach for cross-filesystem moves, checking the
+    /// copy with `integrity` before the source is deleted
+    pub(crate) fn atomic_move(
+        source: &Path,
+        destination: &Path,
+        integrity: IntegrityHash,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileOperationError> {
         // First, try a simple rename (works for same filesystem)
-        if let Ok(()) = fs::rename(source, destination) {
+        if let Ok(()) = fs.rename(source, destination) {
             return Ok(());
         }
 
         // If rename fails (likely cross-filesystem), use copy + delete
-        fs::copy(source, destination)?;
+        Self::copy_and_remove(source, destination, integrity, fs)
+    }
 
-        // Verify the copy was successful by checking file exists and size matches
-        Self::verify_file_integrity(source, destination)?;
+    /// Run `operation`, retrying up to `retries` more times on transient IO errors
+    ///
+    /// The delay starts at `base_delay` and doubles after every attempt.
+    /// Permanent errors such as a denied permission are returned straight
+    /// away; once retries are exhausted the last error is returned.
+    pub(crate) fn with_retries<T>(
+        retries: u32,
+        base_delay: Duration,
+        mut operation: impl FnMut() -> Result<T, FileOperationError>,
+    ) -> Result<T, FileOperationError> {
+        let mut delay = base_delay;
+        let mut attempt = 0;
+        loop {
+            match operation() {
+                Err(FileOperationError::IoError(e))
+                    if attempt < retries && Self::is_transient(&e) =>
+                {
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Whether an IO error is likely to go away when the operation is retried
+    fn is_transient(error: &std::io::Error) -> bool {
+        use std::io::ErrorKind;
+        matches!(
+            error.kind(),
+            ErrorKind::Interrupted
+                | ErrorKind::WouldBlock
+                | ErrorKind::TimedOut
+                | ErrorKind::ResourceBusy
+        )
+    }
+
+    /// Move a file by copying it and deleting the source
+    ///
+    /// The destination keeps the source's modification time, which a plain
+    /// copy would otherwise reset to the current time.
+    fn copy_and_remove(
+        source: &Path,
+        destination: &Path,
+        integrity: IntegrityHash,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileOperationError> {
+        if let Err(e) = fs.copy(source, destination) {
+            // Don't leave a partial copy behind
+            let _ = fs.remove_file(destination);
+            return Err(e.into());
+        }
+
+        // Preserving the timestamp is best-effort; the content is already safe
+        if let Ok(metadata) = fs.metadata(source) {
+            let _ = filetime::set_file_mtime(
+                destination,
+                FileTime::from_system_time(metadata.modified),
+            );
+        }
+
+        // Verify the copy was successful by checking file exists and its content matches
+        Self::verify_file_integrity(source, destination, integrity, fs)?;
 
         // Only delete source after successful copy and verification
-        fs::remove_file(source).map_err(|e| {
+        fs.remove_file(source).map_err(|e| {
             FileOperationError::MoveOperationFailed(format!(
                 "Failed to remove source file after copy: {}",
                 e
@@ -187,34 +1230,70 @@ impl FileOperations {
     }
 
     /// Verify file integrity after copy operation
-    fn verify_file_integrity(source: &Path, destination: &Path) -> Result<(), FileOperationError> {
-        let source_metadata = fs::metadata(source)?;
-        let dest_metadata = fs::metadata(destination)?;
-
-        if source_metadata.len() != dest_metadata.len() {
+    ///
+    /// Sizes are always compared; unless `integrity` is
+    /// [`IntegrityHash::SizeOnly`] the contents are compared by digest too.
+    fn verify_file_integrity(
+        source: &Path,
+        destination: &Path,
+        integrity: IntegrityHash,
+        fs: &dyn FileSystem,
+    ) -> Result<(), FileOperationError> {
+        let source_metadata = fs.metadata(source)?;
+        let dest_metadata = fs.metadata(destination)?;
+
+        if source_metadata.len != dest_metadata.len {
             // Clean up partial copy
-            let _ = fs::remove_file(destination);
+            let _ = fs.remove_file(destination);
             return Err(FileOperationError::IntegrityCheckFailed(format!(
                 "File size mismatch: source {} bytes, destination {} bytes",
-                source_metadata.len(),
-                dest_metadata.len()
+                source_metadata.len, dest_metadata.len
+            )));
+        }
+
+        let digest = |path: &Path| -> Result<Option<String>, FileOperationError> {
+            Ok(match integrity {
+                IntegrityHash::Sha256 => Some(Self::hex(&Sha256::digest(fs.read(path)?))),
+                IntegrityHash::XxHash => Some(format!("{:016x}", xxh3_64(&fs.read(path)?))),
+                IntegrityHash::SizeOnly => None,
+            })
+        };
+        if digest(source)? != digest(destination)? {
+            let _ = fs.remove_file(destination);
+            return Err(FileOperationError::IntegrityCheckFailed(format!(
+                "Content mismatch: {:?} digests of source and destination differ",
+                integrity
             )));
         }
 
         Ok(())
     }
+
+    /// Lowercase hex encoding of a digest
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::FixedClock;
+    use crate::config::RouteRule;
+    use crate::filesystem::MockFs;
+    use crate::journal_management::JournalManager;
+    use chrono::TimeZone;
     use std::fs::File;
     use std::io::Write;
+    use std::time::SystemTime;
     use tempfile::tempdir;
 
+    const TEST_HASH: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
     fn create_test_config(kb_path: &str) -> Config {
         Config {
             knowledge_base_path: kb_path.to_string(),
+            ..Default::default()
         }
     }
 
@@ -225,6 +1304,33 @@ mod tests {
         assert_eq!(pages_dir, PathBuf::from("/test/kb/pages"));
     }
 
+    #[test]
+    fn test_get_pages_directory_custom_subdir() {
+        let config = Config {
+            destination_subdir: Some("inbox".to_string()),
+            ..create_test_config("/test/kb")
+        };
+        let pages_dir = FileOperations::get_pages_directory(&config).unwrap();
+        assert_eq!(pages_dir, PathBuf::from("/test/kb/inbox"));
+    }
+
+    #[test]
+    fn test_get_pages_directory_custom_pages_dir_name() {
+        let config = Config {
+            pages_dir_name: ".".to_string(),
+            ..create_test_config("/test/kb")
+        };
+        let pages_dir = FileOperations::get_pages_directory(&config).unwrap();
+        assert_eq!(pages_dir, PathBuf::from("/test/kb"));
+
+        let config = Config {
+            pages_dir_name: "Notes".to_string(),
+            ..create_test_config("/test/kb")
+        };
+        let pages_dir = FileOperations::get_pages_directory(&config).unwrap();
+        assert_eq!(pages_dir, PathBuf::from("/test/kb/Notes"));
+    }
+
     #[test]
     fn test_ensure_directory_exists_creates_directory() {
         let temp_dir = tempdir().unwrap();
@@ -232,7 +1338,7 @@ mod tests {
 
         assert!(!new_dir_path.exists());
 
-        FileOperations::ensure_directory_exists(&new_dir_path).unwrap();
+        FileOperations::ensure_directory_exists(&new_dir_path, &RealFs).unwrap();
 
         assert!(new_dir_path.exists());
         assert!(new_dir_path.is_dir());
@@ -243,7 +1349,7 @@ mod tests {
         let temp_dir = tempdir().unwrap();
 
         // Should not fail for existing directory
-        FileOperations::ensure_directory_exists(temp_dir.path()).unwrap();
+        FileOperations::ensure_directory_exists(temp_dir.path(), &RealFs).unwrap();
     }
 
     #[test]
@@ -251,8 +1357,13 @@ mod tests {
         let temp_dir = tempdir().unwrap();
         let filename = std::ffi::OsStr::new("test.md");
 
-        let dest_path =
-            FileOperations::resolve_destination_path(temp_dir.path(), filename).unwrap();
+        let dest_path = FileOperations::resolve_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::new(TEST_HASH, 1000),
+            |_| false,
+        )
+        .unwrap();
 
         assert_eq!(dest_path, temp_dir.path().join("test.md"));
     }
@@ -266,8 +1377,13 @@ mod tests {
         let existing_file_path = temp_dir.path().join("test.md");
         File::create(&existing_file_path).unwrap();
 
-        let dest_path =
-            FileOperations::resolve_destination_path(temp_dir.path(), filename).unwrap();
+        let dest_path = FileOperations::resolve_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::new(TEST_HASH, 1000),
+            |_| false,
+        )
+        .unwrap();
 
         // Should generate a different filename with hash postfix
         assert_ne!(dest_path, existing_file_path);
@@ -275,6 +1391,282 @@ mod tests {
         assert!(dest_path.to_string_lossy().ends_with(".md"));
     }
 
+    #[test]
+    fn test_resolve_destination_path_stops_at_max_attempts() {
+        let temp_dir = tempdir().unwrap();
+        let filename = std::ffi::OsStr::new("test.md");
+        fs::write(temp_dir.path().join("test.md"), "first").unwrap();
+        fs::write(temp_dir.path().join("test_01234567.md"), "second").unwrap();
+
+        let error = FileOperations::resolve_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::new(TEST_HASH, 1),
+            |_| false,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            &error,
+            FileOperationError::CollisionLimitReached { path, attempts: 1 }
+                if *path == temp_dir.path().join("test.md")
+        ));
+        assert!(error.to_string().contains("test.md"));
+
+        let dest_path = FileOperations::resolve_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::new(TEST_HASH, 2),
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(dest_path, temp_dir.path().join("test_01234567_1.md"));
+    }
+
+    #[test]
+    fn test_resolve_destination_path_identical_suffixed_name_is_duplicate() {
+        let temp_dir = tempdir().unwrap();
+        let filename = std::ffi::OsStr::new("test.md");
+        let content_hash = FileOperations::bytes_hash(b"same");
+        let suffixed = temp_dir.path().join(format!(
+            "test_{}.md",
+            &content_hash[..CONTENT_HASH_SUFFIX_LEN]
+        ));
+        fs::write(temp_dir.path().join("test.md"), "other").unwrap();
+        fs::write(&suffixed, "same").unwrap();
+
+        let config = Config::default();
+        let error = FileOperations::resolve_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::for_config(&content_hash, Some(&content_hash), &config),
+            |_| false,
+        )
+        .unwrap_err();
+        assert!(matches!(error, FileOperationError::DuplicateContent(path) if path == suffixed));
+
+        // Without dedupe_by_content the identical page is just another taken name
+        let dest_path = FileOperations::resolve_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::for_config(&content_hash, None, &config),
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(
+            dest_path,
+            temp_dir.path().join(format!(
+                "test_{}_1.md",
+                &content_hash[..CONTENT_HASH_SUFFIX_LEN]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_claim_destination_path_stops_at_max_attempts() {
+        let temp_dir = tempdir().unwrap();
+        let filename = std::ffi::OsStr::new("test.md");
+        fs::write(temp_dir.path().join("test.md"), "first").unwrap();
+
+        // Every claim is lost to another process
+        let error = FileOperations::claim_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::new(TEST_HASH, 2),
+            &RunDestinations::default(),
+            |path| fs::write(path, "created by another run").unwrap(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            error,
+            FileOperationError::CollisionLimitReached { attempts: 2, .. }
+        ));
+        assert!(temp_dir.path().join("test_01234567_1.md").exists());
+        assert!(!temp_dir.path().join("test_01234567_2.md").exists());
+    }
+
+    #[test]
+    fn test_claim_destination_path_retries_when_name_taken() {
+        let temp_dir = tempdir().unwrap();
+        let filename = std::ffi::OsStr::new("article.md");
+        let raced = temp_dir.path().join("article.md");
+
+        // Another process creates the resolved name before it can be claimed
+        let mut calls = 0;
+        let run = RunDestinations::default();
+        let claimed = FileOperations::claim_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::new(TEST_HASH, 1000),
+            &run,
+            |path| {
+                calls += 1;
+                if calls == 1 {
+                    assert_eq!(path, raced);
+                    fs::write(path, "created by another run").unwrap();
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(calls, 2);
+        assert_ne!(claimed, raced);
+        assert_eq!(
+            fs::read_to_string(&raced).unwrap(),
+            "created by another run"
+        );
+        assert_eq!(fs::read(&claimed).unwrap().len(), 0);
+        assert!(run.contains(&claimed));
+    }
+
+    #[test]
+    fn test_move_to_pages_in_run_keeps_claimed_names_taken() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().join("kb").display().to_string());
+        let run = RunDestinations::default();
+
+        let first_source = temp_dir.path().join("one/untitled.md");
+        let second_source = temp_dir.path().join("two/untitled.md");
+        for (source, content) in [(&first_source, "# First"), (&second_source, "# Second")] {
+            fs::create_dir_all(source.parent().unwrap()).unwrap();
+            fs::write(source, content).unwrap();
+        }
+
+        let first =
+            FileOperations::move_to_pages_in_run(&first_source, &config, &run, &SystemClock)
+                .unwrap();
+        // The first page is not visible on disk yet, as on a lagging network mount
+        fs::remove_file(&first).unwrap();
+        let second =
+            FileOperations::move_to_pages_in_run(&second_source, &config, &run, &SystemClock)
+                .unwrap();
+
+        assert_eq!(first.file_name().unwrap(), "untitled.md");
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&second).unwrap(), "# Second");
+        assert_eq!(run.len(), 2);
+    }
+
+    #[test]
+    fn test_move_to_pages_replaces_claimed_placeholder() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().display().to_string());
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "new content").unwrap();
+
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "new content");
+        assert!(
+            !temp_dir
+                .path()
+                .join("pages")
+                .read_dir()
+                .unwrap()
+                .any(|entry| fs::metadata(entry.unwrap().path()).unwrap().len() == 0)
+        );
+    }
+
+    #[test]
+    fn test_content_hash_is_sha256() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hello.md");
+        fs::write(&path, "hello").unwrap();
+
+        assert_eq!(
+            FileOperations::content_hash(&path).unwrap(),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+    }
+
+    #[test]
+    fn test_collision_suffix_follows_content() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().display().to_string());
+        let pages_dir = temp_dir.path().join("pages");
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::write(pages_dir.join("article.md"), "existing").unwrap();
+
+        let move_copy = |dir: &str, content: &str| {
+            let source_dir = temp_dir.path().join(dir);
+            fs::create_dir_all(&source_dir).unwrap();
+            let source = source_dir.join("article.md");
+            fs::write(&source, content).unwrap();
+            let destination = FileOperations::move_to_pages(&source, &config).unwrap();
+            let name = destination
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            // Remove it again so the next move sees the same collision
+            fs::remove_file(&destination).unwrap();
+            name
+        };
+
+        let first = move_copy("a", "same content");
+        let second = move_copy("b", "same content");
+        let different = move_copy("c", "other content");
+
+        assert_eq!(first, second);
+        assert_ne!(first, different);
+
+        let hash = {
+            let probe = temp_dir.path().join("probe.md");
+            fs::write(&probe, "same content").unwrap();
+            FileOperations::content_hash(&probe).unwrap()
+        };
+        assert_eq!(first, format!("article_{}.md", &hash[..8]));
+    }
+
+    #[test]
+    fn test_find_identical_matches_name_and_suffixed_name() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().display().to_string());
+        let pages_dir = temp_dir.path().join("pages");
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::write(pages_dir.join("article.md"), "other content").unwrap();
+
+        let source = temp_dir.path().join("article.md");
+        fs::write(&source, "same content").unwrap();
+        assert_eq!(
+            FileOperations::find_identical(&source, &config).unwrap(),
+            None
+        );
+
+        // An earlier import of the same content landed under the suffixed name
+        let imported = FileOperations::move_to_pages(&source, &config).unwrap();
+        fs::write(&source, "same content").unwrap();
+        assert_eq!(
+            FileOperations::find_identical(&source, &config).unwrap(),
+            Some(imported)
+        );
+    }
+
+    #[test]
+    fn test_find_identical_ignores_added_page_properties() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            add_page_properties: true,
+            record_source_path: SourcePathAnnotation::PageProperty,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let source = temp_dir.path().join("article.md");
+        fs::write(&source, "# Article\n").unwrap();
+        let imported = FileOperations::move_to_pages(&source, &config).unwrap();
+        assert_ne!(fs::read_to_string(&imported).unwrap(), "# Article\n");
+
+        fs::write(&source, "# Article\n").unwrap();
+        assert_eq!(
+            FileOperations::find_identical(&source, &config).unwrap(),
+            Some(imported)
+        );
+
+        fs::write(&source, "# Article, revised\n").unwrap();
+        assert_eq!(
+            FileOperations::find_identical(&source, &config).unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn test_resolve_destination_path_multiple_collisions() {
         let temp_dir = tempdir().unwrap();
@@ -284,13 +1676,23 @@ mod tests {
         File::create(temp_dir.path().join("test.md")).unwrap();
 
         // Generate first collision-resolved name
-        let first_dest =
-            FileOperations::resolve_destination_path(temp_dir.path(), filename).unwrap();
+        let first_dest = FileOperations::resolve_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::new(TEST_HASH, 1000),
+            |_| false,
+        )
+        .unwrap();
         File::create(&first_dest).unwrap();
 
         // Generate second collision-resolved name
-        let second_dest =
-            FileOperations::resolve_destination_path(temp_dir.path(), filename).unwrap();
+        let second_dest = FileOperations::resolve_destination_path(
+            temp_dir.path(),
+            filename,
+            Collisions::new(TEST_HASH, 1000),
+            |_| false,
+        )
+        .unwrap();
 
         // All three should be different
         let original = temp_dir.path().join("test.md");
@@ -313,7 +1715,8 @@ mod tests {
         let dest_path = temp_dir.path().join("destination.md");
 
         // Perform move
-        FileOperations::atomic_move(&source_path, &dest_path).unwrap();
+        FileOperations::atomic_move(&source_path, &dest_path, IntegrityHash::Sha256, &RealFs)
+            .unwrap();
 
         // Verify move
         assert!(!source_path.exists());
@@ -324,6 +1727,191 @@ mod tests {
         assert_eq!(content, "Test content\n");
     }
 
+    #[test]
+    fn test_copy_and_remove_preserves_modification_time() {
+        let temp_dir = tempdir().unwrap();
+
+        let source_path = temp_dir.path().join("source.md");
+        fs::write(&source_path, "Test content").unwrap();
+        let known_mtime = FileTime::from_unix_time(1_600_000_000, 0);
+        filetime::set_file_mtime(&source_path, known_mtime).unwrap();
+
+        let dest_path = temp_dir.path().join("destination.md");
+        FileOperations::copy_and_remove(&source_path, &dest_path, IntegrityHash::Sha256, &RealFs)
+            .unwrap();
+
+        assert!(!source_path.exists());
+        let dest_mtime = FileTime::from_last_modification_time(&fs::metadata(&dest_path).unwrap());
+        assert!((dest_mtime.unix_seconds() - known_mtime.unix_seconds()).abs() <= 1);
+    }
+
+    #[test]
+    fn test_copy_and_remove_cleans_up_after_failed_copy() {
+        let temp_dir = tempdir().unwrap();
+        let source_path = temp_dir.path().join("source.md");
+        let dest_path = temp_dir.path().join("destination.md");
+        fs::write(&source_path, "Content that is only half copied").unwrap();
+        let fs = MockFs::default().fail_copy_midway();
+
+        let result =
+            FileOperations::copy_and_remove(&source_path, &dest_path, IntegrityHash::Sha256, &fs);
+
+        assert!(matches!(result, Err(FileOperationError::IoError(_))));
+        assert_eq!(
+            fs::read_to_string(&source_path).unwrap(),
+            "Content that is only half copied"
+        );
+        assert!(!dest_path.exists());
+    }
+
+    #[test]
+    fn test_copy_and_remove_keeps_source_when_copy_is_corrupted() {
+        let temp_dir = tempdir().unwrap();
+        let source_path = temp_dir.path().join("source.md");
+        let dest_path = temp_dir.path().join("destination.md");
+        fs::write(&source_path, "Original content").unwrap();
+        let fs = MockFs::default().corrupt_copy();
+
+        let result =
+            FileOperations::copy_and_remove(&source_path, &dest_path, IntegrityHash::Sha256, &fs);
+
+        assert!(matches!(
+            result,
+            Err(FileOperationError::IntegrityCheckFailed(_))
+        ));
+        assert!(source_path.exists());
+        assert!(!dest_path.exists());
+    }
+
+    #[test]
+    fn test_move_to_pages_retries_transient_copy_failure() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            io_retry_delay_ms: 0,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "# Article").unwrap();
+        // Renames always fail as across filesystems, the first copy is interrupted
+        let fs = MockFs::default()
+            .fail("rename", std::io::ErrorKind::CrossesDevices, usize::MAX)
+            .fail("copy", std::io::ErrorKind::Interrupted, 1);
+
+        let destination = FileOperations::move_to_pages_with(
+            &source_path,
+            &config,
+            &RunDestinations::default(),
+            &SystemClock,
+            &fs,
+        )
+        .unwrap();
+
+        assert_eq!(destination, temp_dir.path().join("pages/article.md"));
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "# Article");
+        assert!(!source_path.exists());
+    }
+
+    #[test]
+    fn test_move_to_pages_removes_claimed_name_after_permanent_failure() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().display().to_string());
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "# Article").unwrap();
+        let fs = MockFs::default()
+            .fail("rename", std::io::ErrorKind::CrossesDevices, usize::MAX)
+            .fail("copy", std::io::ErrorKind::PermissionDenied, usize::MAX);
+
+        let result = FileOperations::move_to_pages_with(
+            &source_path,
+            &config,
+            &RunDestinations::default(),
+            &SystemClock,
+            &fs,
+        );
+
+        assert!(result.is_err());
+        assert!(source_path.exists());
+        assert!(!temp_dir.path().join("pages/article.md").exists());
+    }
+
+    #[test]
+    fn test_move_to_pages_reports_injected_hash_and_claim_failures() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().display().to_string());
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "# Article").unwrap();
+        let destination = temp_dir.path().join("pages/article.md");
+
+        for fs in [
+            MockFs::default().fail_path("read", &source_path, std::io::ErrorKind::PermissionDenied),
+            MockFs::default().fail_path(
+                "create_new",
+                &destination,
+                std::io::ErrorKind::PermissionDenied,
+            ),
+        ] {
+            let result = FileOperations::move_to_pages_with(
+                &source_path,
+                &config,
+                &RunDestinations::default(),
+                &SystemClock,
+                &fs,
+            );
+
+            assert!(
+                matches!(&result, Err(FileOperationError::IoError(e)) if e.kind() == std::io::ErrorKind::PermissionDenied),
+                "{:?}",
+                result
+            );
+            assert!(source_path.exists());
+            assert!(!destination.exists());
+        }
+    }
+
+    #[test]
+    fn test_with_retries_recovers_from_transient_errors() {
+        let mut calls = 0;
+        let result = FileOperations::with_retries(3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 {
+                Err(std::io::Error::from(std::io::ErrorKind::Interrupted).into())
+            } else {
+                Ok(calls)
+            }
+        });
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_with_retries_gives_up_after_exhausting_retries() {
+        let mut calls = 0;
+        let result: Result<(), _> = FileOperations::with_retries(2, Duration::ZERO, || {
+            calls += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into())
+        });
+
+        assert_eq!(calls, 3);
+        match result {
+            Err(FileOperationError::IoError(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::TimedOut)
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_with_retries_does_not_retry_permanent_errors() {
+        let mut calls = 0;
+        let result: Result<(), _> = FileOperations::with_retries(3, Duration::ZERO, || {
+            calls += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::PermissionDenied).into())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
     #[test]
     fn test_verify_file_integrity_success() {
         let temp_dir = tempdir().unwrap();
@@ -337,7 +1925,39 @@ mod tests {
         fs::write(&file2_path, content).unwrap();
 
         // Verification should pass
-        FileOperations::verify_file_integrity(&file1_path, &file2_path).unwrap();
+        FileOperations::verify_file_integrity(
+            &file1_path,
+            &file2_path,
+            IntegrityHash::Sha256,
+            &RealFs,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_verify_file_integrity_detects_same_size_content_change() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source.md");
+        let copy = temp_dir.path().join("copy.md");
+
+        for (integrity, detected) in [
+            (IntegrityHash::Sha256, true),
+            (IntegrityHash::XxHash, true),
+            (IntegrityHash::SizeOnly, false),
+        ] {
+            fs::write(&source, "Original content").unwrap();
+            fs::write(&copy, "Corrupted conten").unwrap();
+
+            let result = FileOperations::verify_file_integrity(&source, &copy, integrity, &RealFs);
+
+            assert_eq!(
+                matches!(result, Err(FileOperationError::IntegrityCheckFailed(_))),
+                detected,
+                "{:?}",
+                integrity
+            );
+            assert_eq!(copy.exists(), !detected, "{:?}", integrity);
+        }
     }
 
     #[test]
@@ -356,7 +1976,12 @@ mod tests {
         .unwrap();
 
         // Verification should fail
-        let result = FileOperations::verify_file_integrity(&file1_path, &file2_path);
+        let result = FileOperations::verify_file_integrity(
+            &file1_path,
+            &file2_path,
+            IntegrityHash::Sha256,
+            &RealFs,
+        );
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -414,6 +2039,384 @@ mod tests {
         assert!(pages_dir.is_dir());
     }
 
+    #[test]
+    fn test_move_to_pages_custom_subdir() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            destination_subdir: Some("inbox".to_string()),
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+
+        let source_path = temp_dir.path().join("triage.md");
+        fs::write(&source_path, "needs triage").unwrap();
+
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        assert_eq!(destination, temp_dir.path().join("inbox").join("triage.md"));
+        assert!(destination.exists());
+        assert!(!temp_dir.path().join("pages").exists());
+    }
+
+    #[test]
+    fn test_move_to_pages_follows_routing_rules() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            routing: vec![
+                RouteRule {
+                    pattern: "excalidraw.md".to_string(),
+                    destination: "assets".to_string(),
+                    journal: false,
+                },
+                RouteRule {
+                    pattern: "*-draft.md".to_string(),
+                    destination: "drafts".to_string(),
+                    journal: true,
+                },
+            ],
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+
+        let drawing = temp_dir.path().join("diagram.excalidraw.md");
+        let draft = temp_dir.path().join("essay-draft.md");
+        let page = temp_dir.path().join("article.md");
+        for path in [&drawing, &draft, &page] {
+            fs::write(path, "content").unwrap();
+        }
+
+        let move_file = |path: &Path| FileOperations::move_to_pages(path, &config).unwrap();
+        assert_eq!(
+            move_file(&drawing),
+            temp_dir.path().join("assets/diagram.excalidraw.md")
+        );
+        assert_eq!(
+            move_file(&draft),
+            temp_dir.path().join("drafts/essay-draft.md")
+        );
+        assert_eq!(move_file(&page), temp_dir.path().join("pages/article.md"));
+    }
+
+    #[test]
+    fn test_move_to_pages_organize_by_date() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            organize_by_date: true,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "content").unwrap();
+        let modified: DateTime<Local> = Local.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap();
+        filetime::set_file_mtime(
+            &source_path,
+            FileTime::from_system_time(SystemTime::from(modified)),
+        )
+        .unwrap();
+
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        assert_eq!(
+            destination,
+            temp_dir.path().join("pages/2024/03/article.md")
+        );
+        assert!(destination.exists());
+    }
+
+    #[test]
+    fn test_move_to_pages_organize_by_date_custom_pattern_with_collision() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            organize_by_date: true,
+            date_subdir_pattern: "%Y-%m-%d".to_string(),
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let modified: DateTime<Local> = Local.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap();
+        let dated_dir = temp_dir.path().join("pages/2024-03-15");
+        fs::create_dir_all(&dated_dir).unwrap();
+        fs::write(dated_dir.join("article.md"), "existing").unwrap();
+
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "new").unwrap();
+        filetime::set_file_mtime(
+            &source_path,
+            FileTime::from_system_time(SystemTime::from(modified)),
+        )
+        .unwrap();
+
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        assert_eq!(destination.parent().unwrap(), dated_dir);
+        assert_ne!(destination, dated_dir.join("article.md"));
+        assert_eq!(
+            fs::read_to_string(dated_dir.join("article.md")).unwrap(),
+            "existing"
+        );
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_move_to_pages_normalize_extension() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            normalize_extension: true,
+            extensions: vec!["md".to_string(), "markdown".to_string()],
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let pages_dir = temp_dir.path().join("pages");
+
+        let source_path = temp_dir.path().join("note.markdown");
+        fs::write(&source_path, "first").unwrap();
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+        assert_eq!(destination, pages_dir.join("note.md"));
+
+        // A second export collides with the normalized name, not the original one
+        fs::write(&source_path, "second").unwrap();
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+        assert_ne!(destination, pages_dir.join("note.md"));
+        assert!(destination.to_string_lossy().ends_with(".md"));
+        assert!(!pages_dir.join("note.markdown").exists());
+        assert_eq!(
+            fs::read_to_string(pages_dir.join("note.md")).unwrap(),
+            "first"
+        );
+    }
+
+    #[test]
+    fn test_move_to_pages_normalize_unicode() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            normalize_unicode: true,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+
+        let source_path = temp_dir.path().join("cafe\u{301}.md");
+        fs::write(&source_path, "# Café").unwrap();
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        assert_eq!(destination, temp_dir.path().join("pages/caf\u{e9}.md"));
+        assert!(destination.exists());
+    }
+
+    #[test]
+    fn test_move_to_pages_sanitizes_filename() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().display().to_string());
+
+        let source_path = temp_dir.path().join("Q&A: why\\how? *really*.md");
+        fs::write(&source_path, "# Q&A").unwrap();
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        assert_eq!(
+            destination,
+            temp_dir.path().join("pages/Q&A- why-how- -really-.md")
+        );
+
+        let config = Config {
+            sanitize_filenames: false,
+            ..config
+        };
+        fs::write(&source_path, "# Q&A again").unwrap();
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+        assert_eq!(
+            destination,
+            temp_dir.path().join("pages/Q&A: why\\how? *really*.md")
+        );
+    }
+
+    #[test]
+    fn test_move_to_pages_applies_filename_affixes() {
+        let temp_dir = tempdir().unwrap();
+        let base = create_test_config(&temp_dir.path().display().to_string());
+        let source_path = temp_dir.path().join("article.md");
+
+        for (prefix, suffix, expected) in [
+            ("web-", "", "web-article.md"),
+            ("", "-imported", "article-imported.md"),
+            ("web-", "-imported", "web-article-imported.md"),
+        ] {
+            let config = Config {
+                filename_prefix: prefix.to_string(),
+                filename_suffix: suffix.to_string(),
+                ..base.clone()
+            };
+            fs::write(&source_path, expected).unwrap();
+            let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+            assert_eq!(destination, temp_dir.path().join("pages").join(expected));
+        }
+    }
+
+    #[test]
+    fn test_move_to_pages_resolves_collisions_on_affixed_name() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            filename_prefix: "web-".to_string(),
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let pages = temp_dir.path().join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("web-article.md"), "# Existing").unwrap();
+
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "# New").unwrap();
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        let hash = FileOperations::content_hash(&destination).unwrap();
+        assert_eq!(
+            destination,
+            pages.join(format!(
+                "web-article_{}.md",
+                &hash[..CONTENT_HASH_SUFFIX_LEN]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_move_to_pages_applies_filename_case() {
+        let temp_dir = tempdir().unwrap();
+        let base = create_test_config(&temp_dir.path().display().to_string());
+        let source_path = temp_dir.path().join("My Article Title.md");
+
+        for (case, expected, link) in [
+            (
+                FilenameCase::Preserve,
+                "My Article Title.md",
+                "[[My Article Title]]",
+            ),
+            (
+                FilenameCase::Lower,
+                "my article title.md",
+                "[[my article title]]",
+            ),
+            (
+                FilenameCase::Kebab,
+                "my-article-title.md",
+                "[[my-article-title]]",
+            ),
+            (
+                FilenameCase::Snake,
+                "my_article_title.md",
+                "[[my_article_title]]",
+            ),
+        ] {
+            let config = Config {
+                filename_case: case,
+                ..base.clone()
+            };
+            fs::write(&source_path, format!("{:?}", case)).unwrap();
+            let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+            assert_eq!(destination, temp_dir.path().join("pages").join(expected));
+            let entries = JournalManager::create_entries(&[destination], &config).unwrap();
+            assert_eq!(entries[0].link(), link);
+        }
+    }
+
+    #[test]
+    fn test_move_to_pages_filename_case_collisions_and_compound_extensions() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            filename_case: FilenameCase::Kebab,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let pages = temp_dir.path().join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("my-article-title.md"), "# Existing").unwrap();
+
+        let source_path = temp_dir.path().join("My Article Title.md");
+        fs::write(&source_path, "# New").unwrap();
+        let destination = FileOperations::move_to_pages(&source_path, &config).unwrap();
+        let hash = FileOperations::content_hash(&destination).unwrap();
+        assert_eq!(
+            destination,
+            pages.join(format!(
+                "my-article-title_{}.md",
+                &hash[..CONTENT_HASH_SUFFIX_LEN]
+            ))
+        );
+
+        let drawing = temp_dir.path().join("Team Diagram.excalidraw.md");
+        fs::write(&drawing, "{}").unwrap();
+        let destination = FileOperations::move_to_pages(&drawing, &config).unwrap();
+        assert_eq!(destination, pages.join("team-diagram.excalidraw.md"));
+    }
+
+    #[test]
+    fn test_move_to_pages_with_page_properties() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            knowledge_base_path: temp_dir.path().join("kb").display().to_string(),
+            add_page_properties: true,
+            ..Default::default()
+        };
+
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "# Article\n").unwrap();
+
+        let clock = FixedClock(Local.with_ymd_and_hms(2024, 3, 9, 23, 30, 0).unwrap());
+        let dest_path = FileOperations::move_to_pages_in_run(
+            &source_path,
+            &config,
+            &RunDestinations::default(),
+            &clock,
+        )
+        .unwrap();
+
+        assert!(!source_path.exists());
+        let content = fs::read_to_string(&dest_path).unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "import-date:: 2024-03-09\nsource:: {}\n\n# Article\n",
+                source_path.display()
+            )
+        );
+
+        // No temporary file is left behind
+        let entries: Vec<_> = fs::read_dir(dest_path.parent().unwrap()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn test_move_to_pages_records_source_path_property() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            knowledge_base_path: temp_dir.path().join("kb").display().to_string(),
+            record_source_path: SourcePathAnnotation::PageProperty,
+            ..Default::default()
+        };
+
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "---\ntitle: Article\n---\n# Article\n").unwrap();
+
+        let dest_path = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&dest_path).unwrap(),
+            format!(
+                "---\ntitle: Article\nsource-path: {}\n---\n# Article\n",
+                source_path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_move_to_pages_merges_existing_front_matter() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            knowledge_base_path: temp_dir.path().join("kb").display().to_string(),
+            add_page_properties: true,
+            ..Default::default()
+        };
+
+        let source_path = temp_dir.path().join("article.md");
+        fs::write(&source_path, "---\nsource: web\n---\n# Article\n").unwrap();
+
+        let dest_path = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        let content = fs::read_to_string(&dest_path).unwrap();
+        assert_eq!(content.matches("source:").count(), 1);
+        assert!(content.starts_with("---\nsource: web\nimport-date: "));
+        assert!(content.ends_with("---\n# Article\n"));
+    }
+
     #[test]
     fn test_move_to_pages_file_not_found() {
         let temp_dir = tempdir().unwrap();
@@ -430,6 +2433,134 @@ mod tests {
         ));
     }
 
+    /// A page linking an image in its `{stem}.assets` folder, in `dir`
+    fn create_page_with_assets(dir: &Path) -> PathBuf {
+        let source_path = dir.join("note.md");
+        fs::write(&source_path, "![chart](note.assets/chart.png)").unwrap();
+        fs::create_dir_all(dir.join("note.assets/nested")).unwrap();
+        fs::write(dir.join("note.assets/chart.png"), "png").unwrap();
+        fs::write(dir.join("note.assets/nested/data.csv"), "a,b").unwrap();
+        source_path
+    }
+
+    #[test]
+    fn test_move_to_pages_moves_asset_folder() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            asset_folder_patterns: vec!["{stem}.assets".to_string(), "{stem}_files".to_string()],
+            ..create_test_config(&temp_dir.path().join("kb").display().to_string())
+        };
+        let source_dir = temp_dir.path().join("downloads");
+        fs::create_dir_all(&source_dir).unwrap();
+        let source_path = create_page_with_assets(&source_dir);
+
+        let dest_path = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        let pages_dir = temp_dir.path().join("kb/pages");
+        assert_eq!(dest_path, pages_dir.join("note.md"));
+        assert_eq!(
+            fs::read_to_string(&dest_path).unwrap(),
+            "![chart](note.assets/chart.png)"
+        );
+        assert_eq!(
+            fs::read_to_string(pages_dir.join("note.assets/chart.png")).unwrap(),
+            "png"
+        );
+        assert!(pages_dir.join("note.assets/nested/data.csv").exists());
+        assert!(!source_dir.join("note.assets").exists());
+    }
+
+    #[test]
+    fn test_move_to_pages_keeps_asset_folder_paired_on_collision() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            asset_folder_patterns: vec!["{stem}.assets".to_string()],
+            ..create_test_config(&temp_dir.path().join("kb").display().to_string())
+        };
+        let pages_dir = temp_dir.path().join("kb/pages");
+        fs::create_dir_all(pages_dir.join("note.assets")).unwrap();
+        let source_dir = temp_dir.path().join("downloads");
+        fs::create_dir_all(&source_dir).unwrap();
+        let source_path = create_page_with_assets(&source_dir);
+
+        let dest_path = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        // The free page name has a taken asset folder, so both get the suffix
+        let stem = dest_path.file_stem().unwrap().to_str().unwrap().to_string();
+        assert!(stem.starts_with("note_"));
+        let assets = pages_dir.join(format!("{}.assets", stem));
+        assert!(assets.join("chart.png").exists());
+        assert_eq!(
+            fs::read_to_string(&dest_path).unwrap(),
+            format!("![chart]({}.assets/chart.png)", stem)
+        );
+        assert_eq!(
+            fs::read_dir(pages_dir.join("note.assets")).unwrap().count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_move_to_pages_rewrites_only_asset_folder_link_targets() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            asset_folder_patterns: vec!["{stem}.assets".to_string()],
+            ..create_test_config(&temp_dir.path().join("kb").display().to_string())
+        };
+        let pages_dir = temp_dir.path().join("kb/pages");
+        fs::create_dir_all(&pages_dir).unwrap();
+        fs::write(pages_dir.join("note.md"), "taken").unwrap();
+        let source_dir = temp_dir.path().join("downloads");
+        fs::create_dir_all(&source_dir).unwrap();
+        let source_path = create_page_with_assets(&source_dir);
+        fs::write(
+            &source_path,
+            "Files live in note.assets/ here.\n![chart](note.assets/chart.png) \
+             [data](<./note.assets/nested/data.csv>) ![[note.assets/chart.png]] \
+             [other](other_note.assets/x.png)",
+        )
+        .unwrap();
+
+        let dest_path = FileOperations::move_to_pages(&source_path, &config).unwrap();
+
+        let stem = dest_path.file_stem().unwrap().to_str().unwrap().to_string();
+        assert_ne!(stem, "note");
+        assert_eq!(
+            fs::read_to_string(&dest_path).unwrap(),
+            format!(
+                "Files live in note.assets/ here.\n![chart]({stem}.assets/chart.png) \
+                 [data](<./{stem}.assets/nested/data.csv>) ![[{stem}.assets/chart.png]] \
+                 [other](other_note.assets/x.png)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_move_directory_falls_back_to_moving_files() {
+        let temp_dir = tempdir().unwrap();
+        create_page_with_assets(temp_dir.path());
+        let destination = temp_dir.path().join("moved.assets");
+
+        let fs_mock = MockFs::default().fail("rename", std::io::ErrorKind::CrossesDevices, 1);
+        FileOperations::move_directory(
+            &temp_dir.path().join("note.assets"),
+            &destination,
+            IntegrityHash::Sha256,
+            &fs_mock,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(destination.join("chart.png")).unwrap(),
+            "png"
+        );
+        assert_eq!(
+            fs::read_to_string(destination.join("nested/data.csv")).unwrap(),
+            "a,b"
+        );
+        assert!(!temp_dir.path().join("note.assets").exists());
+    }
+
     #[test]
     fn test_move_to_pages_with_collision() {
         let temp_dir = tempdir().unwrap();
@@ -0,0 +1,377 @@
+//! Filesystem access behind a trait, so error paths can be tested
+//!
+//! [`FileOperations`](crate::file_operations::FileOperations),
+//! [`JournalManager`](crate::journal_management::JournalManager) and
+//! [`FileDiscovery`](crate::file_discovery::FileDiscovery) go through a
+//! [`FileSystem`] for the operations whose failures they have to handle.
+//! Production code uses [`RealFs`]; tests can inject failures with a mock.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Metadata of a file or directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub len: u64,
+    pub is_file: bool,
+    pub is_dir: bool,
+    /// The path itself is a symbolic link (only from [`FileSystem::symlink_metadata`])
+    pub is_symlink: bool,
+    pub modified: SystemTime,
+}
+
+impl FileMetadata {
+    fn from_std(metadata: fs::Metadata) -> io::Result<Self> {
+        Ok(FileMetadata {
+            len: metadata.len(),
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+            is_symlink: metadata.is_symlink(),
+            modified: metadata.modified()?,
+        })
+    }
+}
+
+/// Filesystem operations used by the move, journal and discovery code
+pub trait FileSystem: Sync {
+    /// Rename `from` to `to`, replacing `to` if it exists
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Copy the content of `from` to `to`, returning the number of bytes copied
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64>;
+
+    /// Metadata of `path`, following symlinks
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    /// Metadata of `path` itself, without following symlinks
+    fn symlink_metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    /// Paths of the entries in a directory; entries that cannot be read are errors
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>>;
+
+    /// Whole content of a file
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Whole content of a UTF-8 file
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Create or truncate a file with `contents`, synced to disk
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Create an empty file, failing with `AlreadyExists` if `path` exists
+    fn create_new(&self, path: &Path) -> io::Result<()>;
+
+    /// Append `contents` to a file, creating it if necessary
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Remove a file
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Create a directory and any missing parents
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Remove an empty directory
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+}
+
+/// The actual filesystem, through `std::fs`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        fs::copy(from, to)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        FileMetadata::from_std(fs::metadata(path)?)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        FileMetadata::from_std(fs::symlink_metadata(path)?)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+        Ok(fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        file.write_all(contents)?;
+        file.sync_all()
+    }
+
+    fn create_new(&self, path: &Path) -> io::Result<()> {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map(|_| ())
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(contents)?;
+        file.flush()
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(path)
+    }
+}
+
+#[cfg(test)]
+pub(crate) use mock::MockFs;
+
+#[cfg(test)]
+mod mock {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// How an injected failure shows up
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum FailureMode {
+        /// The operation returns the error without doing anything
+        Error(io::ErrorKind),
+        /// A copy writes the first half of the file, then returns the error
+        PartialCopy(io::ErrorKind),
+        /// A copy succeeds but writes different bytes of the same length
+        CorruptCopy,
+    }
+
+    #[derive(Debug)]
+    struct Failure {
+        operation: &'static str,
+        path: Option<PathBuf>,
+        mode: FailureMode,
+        remaining: usize,
+    }
+
+    /// [`RealFs`] with failures injected into chosen operations
+    ///
+    /// Operations run against the real disk (usually a temp dir) unless a
+    /// registered failure matches; each failure fires a fixed number of times.
+    #[derive(Debug, Default)]
+    pub(crate) struct MockFs {
+        failures: Mutex<Vec<Failure>>,
+    }
+
+    impl MockFs {
+        /// Fail the next `times` calls of `operation` (e.g. `"rename"`) with `kind`
+        pub(crate) fn fail(
+            self,
+            operation: &'static str,
+            kind: io::ErrorKind,
+            times: usize,
+        ) -> Self {
+            self.with_failure(operation, None, FailureMode::Error(kind), times)
+        }
+
+        /// Fail every call of `operation` on `path` with `kind`
+        pub(crate) fn fail_path(
+            self,
+            operation: &'static str,
+            path: &Path,
+            kind: io::ErrorKind,
+        ) -> Self {
+            self.with_failure(
+                operation,
+                Some(path.to_path_buf()),
+                FailureMode::Error(kind),
+                usize::MAX,
+            )
+        }
+
+        /// Make the next copy stop halfway through with an IO error
+        pub(crate) fn fail_copy_midway(self) -> Self {
+            self.with_failure(
+                "copy",
+                None,
+                FailureMode::PartialCopy(io::ErrorKind::StorageFull),
+                1,
+            )
+        }
+
+        /// Make the next copy write corrupted content of the right length
+        pub(crate) fn corrupt_copy(self) -> Self {
+            self.with_failure("copy", None, FailureMode::CorruptCopy, 1)
+        }
+
+        fn with_failure(
+            self,
+            operation: &'static str,
+            path: Option<PathBuf>,
+            mode: FailureMode,
+            times: usize,
+        ) -> Self {
+            self.failures.lock().unwrap().push(Failure {
+                operation,
+                path,
+                mode,
+                remaining: times,
+            });
+            self
+        }
+
+        /// The failure mode registered for this call, using it up
+        fn injected(&self, operation: &str, path: &Path) -> Option<FailureMode> {
+            let mut failures = self.failures.lock().unwrap();
+            let failure = failures.iter_mut().find(|failure| {
+                failure.operation == operation
+                    && failure.remaining > 0
+                    && failure.path.as_deref().is_none_or(|p| p == path)
+            })?;
+            failure.remaining -= 1;
+            Some(failure.mode)
+        }
+
+        fn check(&self, operation: &str, path: &Path) -> io::Result<()> {
+            match self.injected(operation, path) {
+                Some(FailureMode::Error(kind)) => Err(io::Error::new(
+                    kind,
+                    format!("injected {} failure", operation),
+                )),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl FileSystem for MockFs {
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            self.check("rename", from)?;
+            RealFs.rename(from, to)
+        }
+
+        fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+            let content = fs::read(from)?;
+            match self.injected("copy", from) {
+                Some(FailureMode::Error(kind)) => {
+                    Err(io::Error::new(kind, "injected copy failure"))
+                }
+                Some(FailureMode::PartialCopy(kind)) => {
+                    fs::write(to, &content[..content.len() / 2])?;
+                    Err(io::Error::new(
+                        kind,
+                        "injected failure in the middle of a copy",
+                    ))
+                }
+                Some(FailureMode::CorruptCopy) => {
+                    let corrupted: Vec<u8> = content.iter().map(|byte| byte ^ 0xff).collect();
+                    fs::write(to, corrupted)?;
+                    Ok(content.len() as u64)
+                }
+                None => RealFs.copy(from, to),
+            }
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+            self.check("metadata", path)?;
+            RealFs.metadata(path)
+        }
+
+        fn symlink_metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+            self.check("symlink_metadata", path)?;
+            RealFs.symlink_metadata(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+            self.check("read_dir", path)?;
+            RealFs.read_dir(path)
+        }
+
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.check("read", path)?;
+            RealFs.read(path)
+        }
+
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.check("write", path)?;
+            RealFs.write(path, contents)
+        }
+
+        fn create_new(&self, path: &Path) -> io::Result<()> {
+            self.check("create_new", path)?;
+            RealFs.create_new(path)
+        }
+
+        fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.check("append", path)?;
+            RealFs.append(path, contents)
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.check("remove_file", path)?;
+            RealFs.remove_file(path)
+        }
+
+        fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+            self.check("create_dir_all", path)?;
+            RealFs.create_dir_all(path)
+        }
+
+        fn remove_dir(&self, path: &Path) -> io::Result<()> {
+            self.check("remove_dir", path)?;
+            RealFs.remove_dir(path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_real_fs_symlink_metadata_does_not_follow_links() {
+        let temp_dir = tempdir().unwrap();
+        let target = temp_dir.path().join("target");
+        fs::create_dir(&target).unwrap();
+
+        assert!(RealFs.metadata(&target).unwrap().is_dir);
+        #[cfg(unix)]
+        {
+            let link = temp_dir.path().join("link");
+            std::os::unix::fs::symlink(&target, &link).unwrap();
+            assert!(RealFs.metadata(&link).unwrap().is_dir);
+            assert!(!RealFs.symlink_metadata(&link).unwrap().is_dir);
+        }
+    }
+
+    #[test]
+    fn test_mock_fs_fails_the_requested_number_of_times() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("note.md");
+        let fs = MockFs::default().fail("write", io::ErrorKind::Interrupted, 2);
+
+        for _ in 0..2 {
+            let error = fs.write(&path, b"content").unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        }
+        fs.write(&path, b"content").unwrap();
+
+        assert_eq!(fs.read_to_string(&path).unwrap(), "content");
+    }
+}
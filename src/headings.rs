@@ -0,0 +1,78 @@
+//! Second-level headings of imported pages, for `journal_heading_entries`
+//!
+//! Only what a journal link needs is parsed: `## ` headings at the start of
+//! a line, or as a Logseq block (`- ## Section`), outside fenced code blocks.
+//! The text is taken as written, without trailing closing `#`s.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Public interface for reading page headings
+pub struct Headings;
+
+impl Headings {
+    /// Second-level headings of a page file, in document order
+    pub fn read(path: &Path) -> io::Result<Vec<String>> {
+        let mut headings = Vec::new();
+        let mut fence = None;
+        for line in BufReader::new(File::open(path)?).lines() {
+            if let Some(heading) = Self::scan_line(&line?, &mut fence) {
+                headings.push(heading);
+            }
+        }
+        Ok(headings)
+    }
+
+    /// Second-level headings of page content, in document order
+    pub fn parse(content: &str) -> Vec<String> {
+        let mut fence = None;
+        content
+            .lines()
+            .filter_map(|line| Self::scan_line(line, &mut fence))
+            .collect()
+    }
+
+    /// Heading text of `line`, tracking the fenced code block it may open or close
+    fn scan_line(line: &str, fence: &mut Option<&'static str>) -> Option<String> {
+        let trimmed = line.trim_start();
+        if let Some(open) = *fence {
+            if trimmed.starts_with(open) {
+                *fence = None;
+            }
+            return None;
+        }
+        for marker in ["```", "~~~"] {
+            if trimmed.starts_with(marker) {
+                *fence = Some(marker);
+                return None;
+            }
+        }
+
+        let text = line
+            .strip_prefix("## ")
+            .or_else(|| line.strip_prefix("- ## "))?
+            .trim()
+            .trim_end_matches('#')
+            .trim_end();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_second_level_headings() {
+        let content = "# Title\n\n## Setup\ntext\n### Details\n- ## Usage ##\n##NoSpace\n##   \n";
+        assert_eq!(Headings::parse(content), vec!["Setup", "Usage"]);
+    }
+
+    #[test]
+    fn test_parse_ignores_headings_in_code_blocks() {
+        let content =
+            "## Before\n```sh\n## not a heading\n~~~\n```\n~~~\n## also not\n~~~\n## After\n";
+        assert_eq!(Headings::parse(content), vec!["Before", "After"]);
+    }
+}
@@ -0,0 +1,159 @@
+//! Commands run for every imported file (`per_file_hook`)
+//!
+//! The hook is a shell command (`sh -c`, or `cmd /C` on Windows) run once
+//! per moved file with two environment variables:
+//!
+//! * `LOCAL_SHELF_SOURCE` - where the file was imported from
+//! * `LOCAL_SHELF_DEST` - where the page now lives in the Knowledge Base
+//!
+//! Hooks run after all moves, on up to `parallel_moves` threads. Their
+//! output is captured so it cannot interleave with the run's own output; a
+//! hook that fails is reported with its stderr and never undoes the move.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+/// Error types for hook commands
+#[derive(Debug, thiserror::Error)]
+pub enum HookError {
+    #[error("Failed to start hook: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Hook failed ({status}){}", stderr_suffix(.stderr))]
+    Failed { status: ExitStatus, stderr: String },
+}
+
+/// The captured stderr of a failed hook, as shown after its exit status
+fn stderr_suffix(stderr: &str) -> String {
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", stderr)
+    }
+}
+
+/// Public interface for running hooks
+pub struct Hooks;
+
+impl Hooks {
+    /// Run `command` for a file moved from `source` to `destination`
+    pub fn run_per_file(command: &str, source: &Path, destination: &Path) -> Result<(), HookError> {
+        let output = Self::shell(command)
+            .env("LOCAL_SHELF_SOURCE", source)
+            .env("LOCAL_SHELF_DEST", destination)
+            .stdin(Stdio::null())
+            .output()?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(HookError::Failed {
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        }
+    }
+
+    /// Run `command` for every `(source, destination)` pair on up to `workers` threads
+    ///
+    /// Results are returned in the same order as `files`.
+    pub fn run_per_file_all(
+        command: &str,
+        files: &[(PathBuf, PathBuf)],
+        workers: usize,
+    ) -> Vec<Result<(), HookError>> {
+        let workers = workers.clamp(1, files.len().max(1));
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                let sender = sender.clone();
+                let next = &next;
+                scope.spawn(move || {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((source, destination)) = files.get(index) else {
+                            break;
+                        };
+                        let result = Self::run_per_file(command, source, destination);
+                        let _ = sender.send((index, result));
+                    }
+                });
+            }
+        });
+        drop(sender);
+
+        let mut results: Vec<_> = receiver.into_iter().collect();
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// The platform shell, set up to run `command`
+    fn shell(command: &str) -> Command {
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        shell.arg(command);
+        shell
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_run_per_file_all_runs_once_per_file_in_order() {
+        let temp_dir = tempdir().unwrap();
+        let log = temp_dir.path().join("hook.log");
+        let command = format!(
+            "printf '%s -> %s\\n' \"$LOCAL_SHELF_SOURCE\" \"$LOCAL_SHELF_DEST\" >> '{}'",
+            log.display()
+        );
+        let files: Vec<_> = (1..=3)
+            .map(|n| {
+                (
+                    PathBuf::from(format!("/downloads/note{}.md", n)),
+                    PathBuf::from(format!("/kb/pages/note{}.md", n)),
+                )
+            })
+            .collect();
+
+        let results = Hooks::run_per_file_all(&command, &files, 1);
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(
+            fs::read_to_string(&log).unwrap(),
+            "/downloads/note1.md -> /kb/pages/note1.md\n\
+             /downloads/note2.md -> /kb/pages/note2.md\n\
+             /downloads/note3.md -> /kb/pages/note3.md\n"
+        );
+    }
+
+    #[test]
+    fn test_run_per_file_reports_failure_with_stderr() {
+        let result = Hooks::run_per_file(
+            "echo 'index unavailable' >&2; exit 3",
+            Path::new("/downloads/note.md"),
+            Path::new("/kb/pages/note.md"),
+        );
+
+        match result {
+            Err(HookError::Failed { status, stderr }) => {
+                assert_eq!(status.code(), Some(3));
+                assert_eq!(stderr, "index unavailable");
+            }
+            other => panic!("expected a failed hook, got {:?}", other),
+        }
+    }
+}
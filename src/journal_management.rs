@@ -1,9 +1,15 @@
-use chrono::{Local, NaiveDate};
-use std::fs::{self, OpenOptions};
-use std::io::Write;
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, Timelike, Utc};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
-use crate::config::{Config, ConfigError};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, ConfigError, DEFAULT_LINK_FORMAT, JournalMode, LineEnding, LinkStyle};
+use crate::file_naming::FileNaming;
+use crate::filesystem::{FileSystem, RealFs};
+use crate::headings::Headings;
+use crate::run_summary::format_bytes;
 
 /// Error types for journal operations
 #[derive(Debug, thiserror::Error)]
@@ -20,23 +26,96 @@ pub enum JournalError {
     WriteOperationFailed(String),
 }
 
-/// Represents a journal entry with timestamp and file link
+/// Today's journal as it is and as it would be after writing new entries
 #[derive(Debug, Clone, PartialEq)]
+pub struct JournalPreview {
+    /// Journal file the entries would be written to
+    pub path: PathBuf,
+    /// Whether the journal file already exists
+    pub exists: bool,
+    /// Current content, empty for a journal that doesn't exist yet
+    pub current: String,
+    /// Content after the entries are added
+    pub proposed: String,
+}
+
+impl JournalPreview {
+    /// Whether writing the entries would change the journal
+    pub fn has_changes(&self) -> bool {
+        self.current != self.proposed
+    }
+
+    /// Unified diff from the current to the proposed content
+    ///
+    /// A journal that doesn't exist yet is diffed against `/dev/null`, so
+    /// every proposed line shows up as added.
+    pub fn unified_diff(&self) -> String {
+        let path = self.path.display().to_string();
+        let old_header = if self.exists {
+            path.as_str()
+        } else {
+            "/dev/null"
+        };
+        similar::TextDiff::from_lines(&self.current, &self.proposed)
+            .unified_diff()
+            .header(old_header, &path)
+            .to_string()
+    }
+}
+
+/// How the timestamp of a journal entry is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampStyle {
+    /// `- **14:30** [[name]]`
+    #[default]
+    Bold,
+    /// `- 14:30 [[name]]`
+    Plain,
+    /// `- [[name]]`
+    Hidden,
+}
+
+impl TimestampStyle {
+    /// Pick the style from the `include_timestamp` and `bold_timestamp` config flags
+    pub fn from_config(config: &Config) -> Self {
+        match (config.include_timestamp, config.bold_timestamp) {
+            (false, _) => TimestampStyle::Hidden,
+            (true, true) => TimestampStyle::Bold,
+            (true, false) => TimestampStyle::Plain,
+        }
+    }
+}
+
+/// Represents a journal entry with timestamp and file link
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct JournalEntry {
     pub timestamp: String, // HH:mm format
-    pub filename: String,  // filename without extension
+    pub filename: String,  // filename without extension, or namespaced page name
+    pub tags: Vec<String>, // tag names without the leading '#'
+    pub timestamp_style: TimestampStyle,
+    pub source: Option<String>, // original location, shown as a trailing `(from ...)` note
+    pub path: String, // page path relative to the Knowledge Base, for `{path}` in the link format
+    pub link_format: Option<String>, // link template; a wikilink when unset
+    pub details: Option<String>, // rendered `journal_entry_details`, shown after the link
+    pub sections: Vec<String>, // `## ` headings of the page, linked in nested entries
 }
 
 impl JournalEntry {
-    /// Create a new journal entry with current timestamp
+    /// Create a new journal entry stamped with the current time of `clock`
     ///
     /// # Arguments
     /// * `file_path` - Path to the file to link in the journal entry
+    /// * `clock` - Clock the timestamp is read from
     ///
     /// # Returns
     /// * `Ok(JournalEntry)` - New entry with current timestamp
     /// * `Err(JournalError)` - Error if filename extraction fails
-    pub fn new(file_path: &Path) -> Result<Self, JournalError> {
+    pub fn new(file_path: &Path, clock: &dyn Clock) -> Result<Self, JournalError> {
+        Self::new_at(file_path, clock.now().naive_local())
+    }
+
+    /// Create a new journal entry stamped with the given wall-clock time
+    pub fn new_at(file_path: &Path, now: NaiveDateTime) -> Result<Self, JournalError> {
         // Extract filename without extension
         let filename = file_path
             .file_stem()
@@ -49,23 +128,170 @@ impl JournalEntry {
             })?;
 
         // Generate timestamp in HH:mm format
-        let now = Local::now();
         let timestamp = now.format("%H:%M").to_string();
 
         Ok(JournalEntry {
             timestamp,
             filename: filename.to_string(),
+            tags: Vec::new(),
+            timestamp_style: TimestampStyle::default(),
+            source: None,
+            path: file_path.to_string_lossy().into_owned(),
+            link_format: None,
+            details: None,
+            sections: Vec::new(),
         })
     }
 
+    /// Attach tags to the entry, accepting names with or without a leading '#'
+    pub fn with_tags(mut self, tags: &[String]) -> Self {
+        self.tags = tags
+            .iter()
+            .map(|tag| tag.strip_prefix('#').unwrap_or(tag).to_string())
+            .collect();
+        self
+    }
+
+    /// Set how the timestamp is rendered
+    pub fn with_timestamp_style(mut self, style: TimestampStyle) -> Self {
+        self.timestamp_style = style;
+        self
+    }
+
+    /// Note where the file was imported from
+    pub fn with_source(mut self, source: &Path) -> Self {
+        self.source = Some(source.display().to_string());
+        self
+    }
+
+    /// Render the link with a template such as `[{name}]({path})`
+    pub fn with_link_format(mut self, format: &str, path: String) -> Self {
+        self.link_format = Some(format.to_string());
+        self.path = path;
+        self
+    }
+
+    /// Show `template` after the link, with `{words}` and `{size}` taken from `page`
+    ///
+    /// The page is streamed to count its words, so large files are never
+    /// held in memory. Details are left out when the page cannot be read,
+    /// as in a dry run before it has been moved.
+    pub fn with_details(mut self, template: &str, page: &Path) -> Self {
+        let words = if template.contains("{words}") {
+            match Self::count_words(page) {
+                Ok(words) => Some(words),
+                Err(_) => return self,
+            }
+        } else {
+            None
+        };
+        let Ok(metadata) = page.metadata() else {
+            return self;
+        };
+
+        let mut details = template.replace("{size}", &format_bytes(metadata.len()));
+        if let Some(words) = words {
+            details = details.replace("{words}", &Self::format_count(words));
+        }
+        self.details = Some(details);
+        self
+    }
+
+    /// Link each second-level heading of `page` in a nested entry
+    ///
+    /// Like details, sections are left out when the page cannot be read.
+    pub fn with_sections(mut self, page: &Path) -> Self {
+        self.sections = Headings::read(page).unwrap_or_default();
+        self
+    }
+
+    /// Number of whitespace-separated words in a file, read in chunks
+    fn count_words(path: &Path) -> std::io::Result<u64> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut words = 0;
+        let mut in_word = false;
+        loop {
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
+                return Ok(words);
+            }
+            for byte in chunk {
+                let is_space = byte.is_ascii_whitespace();
+                if !is_space && !in_word {
+                    words += 1;
+                }
+                in_word = !is_space;
+            }
+            let consumed = chunk.len();
+            reader.consume(consumed);
+        }
+    }
+
+    /// Format a count with `,` between groups of thousands, e.g. `1,234`
+    fn format_count(count: u64) -> String {
+        let digits = count.to_string();
+        let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                formatted.push(',');
+            }
+            formatted.push(digit);
+        }
+        formatted
+    }
+
     /// Format the journal entry as markdown
     ///
-    /// Returns the entry in the format: `- **HH:mm** [[Name of the file]]`
+    /// Returns the entry in the format: `- **HH:mm** [[Name of the file]]`,
+    /// followed by the details when set, ` #tag` for each tag and
+    /// ` (from /original/path.md)` when a source is set. The timestamp is
+    /// rendered without bold or left out entirely depending on the timestamp
+    /// style. Each section adds a nested
+    /// `  - [[Name of the file#Section]]` line.
     pub fn format(&self) -> String {
-        format!("- **{}** [[{}]]", self.timestamp, self.filename)
+        let mut line = match self.timestamp_style {
+            TimestampStyle::Bold => format!("- **{}** {}", self.timestamp, self.link()),
+            TimestampStyle::Plain => format!("- {} {}", self.timestamp, self.link()),
+            TimestampStyle::Hidden => format!("- {}", self.link()),
+        };
+        if let Some(details) = &self.details {
+            line.push(' ');
+            line.push_str(details);
+        }
+        for tag in &self.tags {
+            line.push_str(" #");
+            line.push_str(tag);
+        }
+        if let Some(source) = &self.source {
+            line.push_str(&format!(" (from {})", source));
+        }
+        for section in &self.sections {
+            line.push_str("\n  - ");
+            line.push_str(&self.render_link(
+                &format!("{}#{}", self.filename, section),
+                &format!("{}#{}", self.path, section),
+            ));
+        }
+        line
+    }
+
+    /// Format the link part of the entry: `[[Name of the file]]` unless a
+    /// link format is set
+    pub fn link(&self) -> String {
+        self.render_link(&self.filename, &self.path)
+    }
+
+    fn render_link(&self, name: &str, path: &str) -> String {
+        match &self.link_format {
+            Some(format) => format.replace("{name}", name).replace("{path}", path),
+            None => format!("[[{}]]", name),
+        }
     }
 }
 
+/// chrono format of the day headings in a single-file journal
+const DAY_HEADING_FORMAT: &str = "%Y-%m-%d";
+
 /// Public interface for journal management operations
 pub struct JournalManager;
 
@@ -83,61 +309,440 @@ impl JournalManager {
     /// * `Ok(PathBuf)` - Path to the journal file that was updated
     /// * `Err(JournalError)` - Error if operation failed
     pub fn add_entries(moved_files: &[PathBuf], config: &Config) -> Result<PathBuf, JournalError> {
+        let entries = Self::create_entries(moved_files, config)?;
+        Self::write_entries(&entries, config)
+    }
+
+    /// Create journal entries for the given files without writing them
+    ///
+    /// # Arguments
+    /// * `moved_files` - Vector of paths to files that were moved
+    /// * `config` - Configuration containing journal formatting options
+    ///
+    /// # Returns
+    /// * `Ok(Vec<JournalEntry>)` - One entry per file, in the same order
+    /// * `Err(JournalError)` - Error if the list is empty or a filename is invalid
+    pub fn create_entries(
+        moved_files: &[PathBuf],
+        config: &Config,
+    ) -> Result<Vec<JournalEntry>, JournalError> {
+        Self::create_entries_with(moved_files, config, &SystemClock)
+    }
+
+    /// Create entries like [`Self::create_entries`], stamped with the time of `clock`
+    pub fn create_entries_with(
+        moved_files: &[PathBuf],
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Result<Vec<JournalEntry>, JournalError> {
         if moved_files.is_empty() {
             return Err(JournalError::EntryFormattingError(
                 "No files provided for journal entries".to_string(),
             ));
         }
 
+        let now = Self::current_time(config, clock);
+        moved_files
+            .iter()
+            .map(|path| {
+                let mut entry = Self::linked_entry(path, config, now)?
+                    .with_tags(&config.journal_tags)
+                    .with_timestamp_style(TimestampStyle::from_config(config));
+                let attachment = config.is_attachment(path);
+                if let Some(template) = &config.journal_entry_details
+                    && !attachment
+                {
+                    entry = entry.with_details(template, path);
+                }
+                if config.journal_heading_entries && !attachment {
+                    entry = entry.with_sections(path);
+                }
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    /// Create an entry for `path` with only its link set up
+    ///
+    /// Attachments are linked by their file name with `attachment_link_format`.
+    fn linked_entry(
+        path: &Path,
+        config: &Config,
+        now: NaiveDateTime,
+    ) -> Result<JournalEntry, JournalError> {
+        let mut entry = JournalEntry::new_at(path, now)?;
+        let attachment = config.is_attachment(path);
+        let name = match config.link_style {
+            _ if attachment => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            LinkStyle::RelativePath => Self::relative_path_name(path, config),
+            LinkStyle::BasenameStem if config.namespaced_links => {
+                Self::namespaced_name(path, config)
+            }
+            LinkStyle::BasenameStem => None,
+        };
+        if let Some(name) = name {
+            entry.filename = name;
+        }
+        if config.normalize_unicode {
+            entry.filename = FileNaming::normalize_unicode(&entry.filename);
+        }
+        if attachment {
+            entry = entry.with_link_format(
+                &config.attachment_link_format,
+                Self::page_path(path, config),
+            );
+        } else if config.link_format != DEFAULT_LINK_FORMAT {
+            entry = entry.with_link_format(&config.link_format, Self::page_path(path, config));
+        }
+        Ok(entry)
+    }
+
+    /// Point journal links at pages that moved within the Knowledge Base
+    ///
+    /// For every `(old, new)` page path whose link changes, each occurrence
+    /// of the old link in a journal file is replaced by the new one. Returns
+    /// the journals that were rewritten.
+    pub fn update_links(
+        moved_pages: &[(PathBuf, PathBuf)],
+        config: &Config,
+    ) -> Result<Vec<PathBuf>, JournalError> {
+        Self::update_links_with(moved_pages, config, None, &RealFs)
+    }
+
+    /// Point links to the original names of imported files at their pages,
+    /// searching the last `rewrite_links_days` journals up to the day `clock` reads
+    ///
+    /// `imported` holds `(source, destination)` pairs. A file whose original
+    /// name still belongs to a page next to its destination is left out, as
+    /// links by that name lead to the existing page.
+    pub fn update_recent_links(
+        imported: &[(PathBuf, PathBuf)],
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Result<Vec<PathBuf>, JournalError> {
+        let renamed: Vec<(PathBuf, PathBuf)> = imported
+            .iter()
+            .filter(|(source, destination)| {
+                source
+                    .file_name()
+                    .is_none_or(|name| !destination.with_file_name(name).exists())
+            })
+            .cloned()
+            .collect();
+        let today = Self::today(config, clock);
+        let since = today
+            .checked_sub_days(Days::new(u64::from(config.rewrite_links_days) - 1))
+            .unwrap_or(NaiveDate::MIN);
+        Self::update_links_with(&renamed, config, Some(since), &RealFs)
+    }
+
+    /// Update links like [`Self::update_links`], through `fs`, only in
+    /// journals dated `since` or later when given
+    ///
+    /// A single-file journal is searched as a whole.
+    pub fn update_links_with(
+        moved_pages: &[(PathBuf, PathBuf)],
+        config: &Config,
+        since: Option<NaiveDate>,
+        fs: &dyn FileSystem,
+    ) -> Result<Vec<PathBuf>, JournalError> {
+        // Links do not depend on the time, only entries need one
+        let now = Self::current_time(config, &SystemClock);
+        let mut replacements = Vec::new();
+        for (old, new) in moved_pages {
+            let old_link = Self::linked_entry(old, config, now)?.link();
+            let new_link = Self::linked_entry(new, config, now)?.link();
+            if old_link != new_link {
+                replacements.push((old_link, new_link));
+            }
+        }
+        if replacements.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let journals_dir = Self::get_journals_directory(config)?;
+        let mut journals: Vec<PathBuf> = match fs.read_dir(&journals_dir) {
+            Ok(entries) => entries
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .filter(|path| {
+                    if config.journal_mode == JournalMode::SingleFile {
+                        return path.file_name() == Some(config.journal_file.as_ref());
+                    }
+                    Self::journal_file_date(path, &config.journal_file_format)
+                        .is_some_and(|date| since.is_none_or(|since| date >= since))
+                })
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        journals.sort();
+
+        let mut updated = Vec::new();
+        for journal in journals {
+            let content = fs.read_to_string(&journal)?;
+            let rewritten = replacements
+                .iter()
+                .fold(content.clone(), |text, (old_link, new_link)| {
+                    text.replace(old_link.as_str(), new_link)
+                });
+            if rewritten != content {
+                Self::atomic_rewrite(&journal, &rewritten, fs)?;
+                updated.push(journal);
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Whether a file is named like a journal written by `stow`, dated with `format`
+    pub(crate) fn is_journal_file(path: &Path, format: &str) -> bool {
+        Self::journal_file_date(path, format).is_some()
+    }
+
+    /// Date of a journal file named with `format`
+    fn journal_file_date(path: &Path, format: &str) -> Option<NaiveDate> {
+        if path.extension().is_none_or(|ext| ext != "md") {
+            return None;
+        }
+        NaiveDate::parse_from_str(path.file_stem()?.to_str()?, format).ok()
+    }
+
+    /// Build a Logseq namespace page name (`projects/foo`) for a moved file
+    ///
+    /// The name is the destination path relative to the pages directory,
+    /// or to the configured destination subdirectory when the file lives
+    /// outside `pages`, without the extension and with `/` separators.
+    fn namespaced_name(path: &Path, config: &Config) -> Option<String> {
+        let relative = path
+            .strip_prefix(config.knowledge_base_subdir(&config.pages_dir_name))
+            .or_else(|_| {
+                path.strip_prefix(config.knowledge_base_subdir(config.destination_dir_name()))
+            })
+            .ok()?;
+
+        Self::link_path(relative)
+    }
+
+    /// Build a link to a moved file by its path relative to the Knowledge
+    /// Base (`pages/projects/foo`), as used by `link_style: RelativePath`
+    fn relative_path_name(path: &Path, config: &Config) -> Option<String> {
+        let relative = path.strip_prefix(config.knowledge_base_subdir(".")).ok()?;
+        Self::link_path(relative)
+    }
+
+    /// Path of a moved file relative to the Knowledge Base with `/`
+    /// separators, or the path as is when it lies outside
+    fn page_path(path: &Path, config: &Config) -> String {
+        match path.strip_prefix(config.knowledge_base_subdir(".")) {
+            Ok(relative) => relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            Err(_) => path.to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Join a relative page path with `/` separators, dropping the extension
+    fn link_path(relative: &Path) -> Option<String> {
+        let mut components: Vec<String> = relative
+            .parent()
+            .into_iter()
+            .flat_map(|parent| parent.components())
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        components.push(relative.file_stem()?.to_string_lossy().into_owned());
+        Some(components.join("/"))
+    }
+
+    /// Write already created entries to today's journal file
+    ///
+    /// # Arguments
+    /// * `entries` - Entries to append
+    /// * `config` - Configuration containing Knowledge Base path
+    ///
+    /// # Returns
+    /// * `Ok(PathBuf)` - Path to the journal file that was updated
+    /// * `Err(JournalError)` - Error if operation failed
+    pub fn write_entries(
+        entries: &[JournalEntry],
+        config: &Config,
+    ) -> Result<PathBuf, JournalError> {
+        Self::write_entries_with(entries, config, &RealFs, &SystemClock)
+    }
+
+    /// Write entries like [`Self::write_entries`], through `fs`, into the
+    /// journal of the day `clock` reads
+    pub fn write_entries_with(
+        entries: &[JournalEntry],
+        config: &Config,
+        fs: &dyn FileSystem,
+        clock: &dyn Clock,
+    ) -> Result<PathBuf, JournalError> {
         // Get journal file path for today
-        let journal_path = Self::get_today_journal_path(config)?;
+        let date = Self::today(config, clock);
+        let journal_path = Self::journal_path_for(config, date)?;
 
         // Ensure journals directory exists
         if let Some(parent) = journal_path.parent() {
-            Self::ensure_directory_exists(parent)?;
+            Self::ensure_directory_exists(parent, fs)?;
         }
 
-        // Create journal entries
-        let entries: Result<Vec<_>, _> = moved_files
-            .iter()
-            .map(|path| JournalEntry::new(path))
-            .collect();
-        let entries = entries?;
+        if config.journal_backup {
+            Self::backup_journal(&journal_path, fs)?;
+        }
 
         // Write entries to journal file
-        Self::append_entries_to_journal(&journal_path, &entries)?;
+        Self::append_entries_to_journal(
+            &journal_path,
+            entries,
+            config.journal_dedupe_ignore_timestamp,
+            config.journal_section.as_deref(),
+            Self::single_file_day(config, date),
+            config.line_ending,
+            fs,
+        )?;
 
         Ok(journal_path)
     }
 
-    /// Get the path to today's journal file
+    /// Copy an existing journal to `<journal>.bak`, replacing any older backup
+    ///
+    /// Returns the backup path, or `None` when there is no journal yet.
+    fn backup_journal(
+        journal_path: &Path,
+        fs: &dyn FileSystem,
+    ) -> Result<Option<PathBuf>, JournalError> {
+        if fs.metadata(journal_path).is_err() {
+            return Ok(None);
+        }
+
+        let mut backup_name = journal_path.file_name().unwrap_or_default().to_os_string();
+        backup_name.push(".bak");
+        let backup_path = journal_path.with_file_name(backup_name);
+
+        fs.copy(journal_path, &backup_path).map_err(|e| {
+            JournalError::WriteOperationFailed(format!(
+                "Failed to back up journal file {}: {}",
+                journal_path.display(),
+                e
+            ))
+        })?;
+        Ok(Some(backup_path))
+    }
+
+    /// Get the path to today's journal file as read from `clock`
+    ///
+    /// Constructs path in format: {{Knowledge Base}}/journals/YYYY_MM_DD.md,
+    /// honouring `journal_file_format`, `timezone` and `day_start_hour`; with
+    /// `journal_mode: SingleFile` it is `journal_file` on every day.
+    pub fn get_today_journal_path(
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Result<PathBuf, JournalError> {
+        Self::journal_path_for(config, Self::today(config, clock))
+    }
+
+    /// Get today's journal day as read from `clock`
     ///
-    /// Constructs path in format: {{Knowledge Base}}/journals/YYYY_MM_DD.md
-    fn get_today_journal_path(config: &Config) -> Result<PathBuf, JournalError> {
+    /// Before `day_start_hour` the previous day's journal is used.
+    fn today(config: &Config, clock: &dyn Clock) -> NaiveDate {
+        Self::journal_date(Self::current_time(config, clock), config.day_start_hour)
+    }
+
+    /// Journal day a wall-clock time belongs to, given the hour the day starts
+    fn journal_date(now: NaiveDateTime, day_start_hour: u32) -> NaiveDate {
+        if now.hour() < day_start_hour {
+            now.date().pred_opt().unwrap_or(now.date())
+        } else {
+            now.date()
+        }
+    }
+
+    /// Get the path to the journal file for `date`
+    ///
+    /// With `journal_mode: SingleFile` every date shares `journal_file`.
+    fn journal_path_for(config: &Config, date: NaiveDate) -> Result<PathBuf, JournalError> {
         let journals_dir = Self::get_journals_directory(config)?;
+        if config.journal_mode == JournalMode::SingleFile {
+            return Ok(journals_dir.join(&config.journal_file));
+        }
 
-        // Format today's date as YYYY_MM_DD
-        let today = Local::now().date_naive();
-        let date_str = today.format("%Y_%m_%d").to_string();
+        let date_str = date.format(&config.journal_file_format).to_string();
         let filename = format!("{}.md", date_str);
 
         Ok(journals_dir.join(filename))
     }
 
+    /// Heading of today's section in a single-file journal as read from
+    /// `clock`, with whether the journal has that heading yet
+    ///
+    /// Returns `None` for per-day journals.
+    pub fn today_heading(
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Result<Option<(String, bool)>, JournalError> {
+        let date = Self::today(config, clock);
+        let Some(day) = Self::single_file_day(config, date) else {
+            return Ok(None);
+        };
+        let content = Self::read_journal(&Self::journal_path_for(config, date)?, &RealFs)?;
+        let exists = content
+            .lines()
+            .any(|line| Self::heading_day(line) == Some(day));
+        Ok(Some((
+            format!("## {}", day.format(DAY_HEADING_FORMAT)),
+            exists,
+        )))
+    }
+
+    /// Index of the day `heading` among `lines` of a single-file journal and
+    /// the end of its section, which runs until the next day heading
+    pub fn day_section(lines: &[&str], heading: &str) -> Option<(usize, usize)> {
+        let day = Self::heading_day(heading)?;
+        let start = lines
+            .iter()
+            .position(|line| Self::heading_day(line) == Some(day))?;
+        let end = lines[start + 1..]
+            .iter()
+            .position(|line| Self::heading_day(line).is_some())
+            .map_or(lines.len(), |offset| start + 1 + offset);
+        Some((start, end))
+    }
+
+    /// The day whose heading entries for `date` go under, in a single-file journal
+    fn single_file_day(config: &Config, date: NaiveDate) -> Option<NaiveDate> {
+        (config.journal_mode == JournalMode::SingleFile).then_some(date)
+    }
+
+    /// Current wall-clock time of `clock` in the configured journal time zone
+    fn current_time(config: &Config, clock: &dyn Clock) -> NaiveDateTime {
+        Self::wall_clock(clock.now().to_utc(), config.journal_timezone())
+    }
+
+    /// Convert an instant to wall-clock time in `timezone`, or the system
+    /// time zone when unset
+    fn wall_clock(now: DateTime<Utc>, timezone: Option<chrono_tz::Tz>) -> NaiveDateTime {
+        match timezone {
+            Some(timezone) => now.with_timezone(&timezone).naive_local(),
+            None => now.with_timezone(&Local).naive_local(),
+        }
+    }
+
     /// Get the journals directory path from config
     ///
-    /// Constructs the full path to {{Knowledge Base}}/journals
+    /// Constructs the full path to {{Knowledge Base}}/{{journals_dir_name}}
     fn get_journals_directory(config: &Config) -> Result<PathBuf, JournalError> {
-        let kb_path = config.get_knowledge_base_path();
-        let mut journals_path = PathBuf::from(kb_path);
-        journals_path.push("journals");
-        Ok(journals_path)
+        Ok(config.knowledge_base_subdir(&config.journals_dir_name))
     }
 
     /// Ensure directory exists, creating it if necessary
-    fn ensure_directory_exists(dir_path: &Path) -> Result<(), JournalError> {
-        if !dir_path.exists() {
-            fs::create_dir_all(dir_path).map_err(|e| {
+    fn ensure_directory_exists(dir_path: &Path, fs: &dyn FileSystem) -> Result<(), JournalError> {
+        if fs.metadata(dir_path).is_err() {
+            fs.create_dir_all(dir_path).map_err(|e| {
                 JournalError::DirectoryCreationFailed(format!("{}: {}", dir_path.display(), e))
             })?;
         }
@@ -147,73 +752,368 @@ impl JournalManager {
     /// Append journal entries to the specified journal file
     ///
     /// Creates the file if it doesn't exist, or appends to existing file.
-    /// Entries in the same batch are written consecutively without blank lines.
     /// Uses atomic operations to prevent corruption.
+    ///
+    /// When `section` is set, entries go under that heading instead of the end
+    /// of the file; see [`Self::insert_under_section`]. When `day` is set the
+    /// file is a single-file journal and entries go under the heading of that
+    /// day instead; see [`Self::insert_under_day_heading`].
     fn append_entries_to_journal(
         journal_path: &Path,
         entries: &[JournalEntry],
+        ignore_timestamp: bool,
+        section: Option<&str>,
+        day: Option<NaiveDate>,
+        line_ending: LineEnding,
+        fs: &dyn FileSystem,
     ) -> Result<(), JournalError> {
-        // Format all entries as strings
-        let entry_lines: Vec<String> = entries.iter().map(|entry| entry.format()).collect();
+        let existing_content = Self::read_journal(journal_path, fs)?;
+        let entry_lines = Self::new_entry_lines(
+            &Self::dedupe_scope(&existing_content, day),
+            entries,
+            ignore_timestamp,
+        );
+        if entry_lines.is_empty() {
+            return Ok(());
+        }
+        let newline = line_ending.resolve(&existing_content);
+
+        if let Some(content) = Self::inserted_content(&existing_content, section, day, &entry_lines)
+        {
+            return Self::atomic_rewrite(
+                journal_path,
+                &Self::with_line_ending(&content, newline),
+                fs,
+            );
+        }
 
-        // Create the content to append
-        let mut content = String::new();
+        // Atomic append operation
+        Self::atomic_append(
+            journal_path,
+            &Self::with_line_ending(
+                &Self::appended_content(&existing_content, &entry_lines),
+                newline,
+            ),
+            fs,
+        )
+    }
+
+    /// Show how writing `entries` would change today's journal, without writing it
+    ///
+    /// Mirrors [`Self::write_entries`]: entries already in the journal are
+    /// left out and `journal_section` is honoured.
+    pub fn preview_entries(
+        entries: &[JournalEntry],
+        config: &Config,
+    ) -> Result<JournalPreview, JournalError> {
+        Self::preview_entries_with(entries, config, &SystemClock)
+    }
+
+    /// Preview entries like [`Self::preview_entries`] for the journal of
+    /// the day `clock` reads
+    pub fn preview_entries_with(
+        entries: &[JournalEntry],
+        config: &Config,
+        clock: &dyn Clock,
+    ) -> Result<JournalPreview, JournalError> {
+        let date = Self::today(config, clock);
+        let journal_path = Self::journal_path_for(config, date)?;
+        let day = Self::single_file_day(config, date);
+        let current = Self::read_journal(&journal_path, &RealFs)?;
+        let entry_lines = Self::new_entry_lines(
+            &Self::dedupe_scope(&current, day),
+            entries,
+            config.journal_dedupe_ignore_timestamp,
+        );
+
+        let newline = config.line_ending.resolve(&current);
+        let inserted = Self::inserted_content(
+            &current,
+            config.journal_section.as_deref(),
+            day,
+            &entry_lines,
+        );
+        let proposed = if entry_lines.is_empty() {
+            current.clone()
+        } else if let Some(content) = inserted {
+            Self::with_line_ending(&content, newline)
+        } else {
+            current.clone()
+                + &Self::with_line_ending(&Self::appended_content(&current, &entry_lines), newline)
+        };
 
-        // If file exists and has content, check if we need separation
-        if journal_path.exists() && fs::metadata(journal_path)?.len() > 0 {
-            // Read the last byte to check if file ends with newline
-            let existing_content = fs::read_to_string(journal_path)?;
-            if !existing_content.ends_with('\n') {
-                content.push('\n');
+        Ok(JournalPreview {
+            exists: journal_path.exists(),
+            path: journal_path,
+            current,
+            proposed,
+        })
+    }
+
+    /// Content of a journal file, or an empty string when it doesn't exist yet
+    fn read_journal(journal_path: &Path, fs: &dyn FileSystem) -> Result<String, JournalError> {
+        match fs.read_to_string(journal_path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Formatted lines for the entries not yet present in `existing_content`
+    ///
+    /// Entries already present in the journal (or earlier in the same batch)
+    /// are skipped; with `ignore_timestamp` an entry counts as present
+    /// whenever its link appears, otherwise its whole first line must match.
+    fn new_entry_lines(
+        existing_content: &str,
+        entries: &[JournalEntry],
+        ignore_timestamp: bool,
+    ) -> Vec<String> {
+        // Lines and links already written, looked up once per entry
+        let mut present_lines: HashSet<String> = existing_content
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect();
+        let mut batch_links: HashSet<String> = HashSet::new();
+
+        // Format all new entries as strings, preserving their order
+        let mut entry_lines: Vec<String> = Vec::new();
+        for entry in entries {
+            let line = entry.format();
+            let link = entry.link();
+            let is_duplicate = if ignore_timestamp {
+                batch_links.contains(&link) || existing_content.contains(&link)
+            } else {
+                present_lines.contains(line.lines().next().unwrap_or_default().trim_end())
+            };
+
+            if !is_duplicate {
+                present_lines.extend(line.lines().map(|line| line.trim_end().to_string()));
+                batch_links.insert(link);
+                entry_lines.push(line);
             }
         }
+        entry_lines
+    }
+
+    /// Text appended to a journal ending in `existing_content` to add `entry_lines`
+    ///
+    /// Entries in the same batch are written consecutively without blank lines.
+    fn appended_content(existing_content: &str, entry_lines: &[String]) -> String {
+        let mut content = String::new();
+
+        // If file has content, check if we need separation
+        if !existing_content.is_empty() && !existing_content.ends_with('\n') {
+            content.push('\n');
+        }
 
         // Join all entries with newlines (no blank lines between entries in same batch)
         content.push_str(&entry_lines.join("\n"));
 
         // Ensure content ends with a newline
         content.push('\n');
+        content
+    }
 
-        // Atomic append operation
-        Self::atomic_append(journal_path, &content)?;
+    /// Convert every line ending of `text` to `newline`
+    ///
+    /// Journal text is assembled with `\n`; a rewritten journal is converted
+    /// as a whole so it never ends up with mixed endings.
+    fn with_line_ending(text: &str, newline: &str) -> String {
+        let text = text.replace("\r\n", "\n");
+        if newline == "\n" {
+            text
+        } else {
+            text.replace('\n', newline)
+        }
+    }
 
-        Ok(())
+    /// Insert entry lines under the `section` heading of a journal
+    ///
+    /// The heading matches a line equal to `section` on its own or as a
+    /// Logseq block (`- ## Imported`). Entries go right after the heading and
+    /// any entries already listed directly beneath it, so earlier imports stay
+    /// first. When the heading is missing it is added at the end of the file.
+    fn insert_under_section(content: &str, section: &str, entry_lines: &[String]) -> String {
+        let section = section.trim();
+        let lines: Vec<&str> = content.lines().collect();
+        let heading = lines.iter().position(|line| {
+            let line = line.trim();
+            line == section || line.strip_prefix("- ").map(str::trim) == Some(section)
+        });
+
+        let Some(heading) = heading else {
+            let mut result = content.to_string();
+            if !result.is_empty() && !result.ends_with('\n') {
+                result.push('\n');
+            }
+            result.push_str(section);
+            result.push('\n');
+            for line in entry_lines {
+                result.push_str(line);
+                result.push('\n');
+            }
+            return result;
+        };
+
+        let mut insert_at = heading + 1;
+        while insert_at < lines.len() && Self::is_entry_line(lines[insert_at]) {
+            insert_at += 1;
+        }
+
+        let mut result: Vec<&str> = lines[..insert_at].to_vec();
+        result.extend(entry_lines.iter().map(String::as_str));
+        result.extend_from_slice(&lines[insert_at..]);
+
+        let mut output = result.join("\n");
+        output.push('\n');
+        output
     }
 
-    /// Perform atomic append operation to avoid corruption
+    /// Journal content with `entry_lines` placed under their heading, or
+    /// `None` when they are appended to the end of the file
+    ///
+    /// The heading of `day` in a single-file journal takes precedence over
+    /// `section`.
+    fn inserted_content(
+        content: &str,
+        section: Option<&str>,
+        day: Option<NaiveDate>,
+        entry_lines: &[String],
+    ) -> Option<String> {
+        match (day, section) {
+            (Some(day), _) => Some(Self::insert_under_day_heading(content, day, entry_lines)),
+            (None, Some(section)) => {
+                Some(Self::insert_under_section(content, section, entry_lines))
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Part of a journal that new entries are checked against for duplicates
+    ///
+    /// In a single-file journal only the section of `day` counts, so an
+    /// entry imported on another day is not mistaken for a duplicate.
+    fn dedupe_scope(content: &str, day: Option<NaiveDate>) -> String {
+        let Some(day) = day else {
+            return content.to_string();
+        };
+        content
+            .lines()
+            .skip_while(|line| Self::heading_day(line) != Some(day))
+            .skip(1)
+            .take_while(|line| Self::heading_day(line).is_none())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Insert entry lines under the `## YYYY-MM-DD` heading of `day` in a
+    /// single-file journal
+    ///
+    /// An existing heading is handled like [`Self::insert_under_section`]. A
+    /// missing one is added before the first dated heading that belongs
+    /// after `day`, following the order the file already uses (oldest or
+    /// newest first), or at the end of the file.
+    fn insert_under_day_heading(content: &str, day: NaiveDate, entry_lines: &[String]) -> String {
+        let heading = format!("## {}", day.format(DAY_HEADING_FORMAT));
+        let lines: Vec<&str> = content.lines().collect();
+        let days: Vec<(usize, NaiveDate)> = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| Self::heading_day(line).map(|date| (index, date)))
+            .collect();
+        if days.iter().any(|(_, date)| *date == day) {
+            return Self::insert_under_section(content, &heading, entry_lines);
+        }
+
+        let newest_first = days
+            .windows(2)
+            .next()
+            .is_some_and(|pair| pair[0].1 > pair[1].1);
+        let insert_at = days
+            .iter()
+            .find(|(_, date)| {
+                if newest_first {
+                    *date < day
+                } else {
+                    *date > day
+                }
+            })
+            .map(|(index, _)| *index);
+
+        let Some(insert_at) = insert_at else {
+            return Self::insert_under_section(content, &heading, entry_lines);
+        };
+
+        let mut result: Vec<&str> = lines[..insert_at].to_vec();
+        result.push(&heading);
+        result.extend(entry_lines.iter().map(String::as_str));
+        result.extend_from_slice(&lines[insert_at..]);
+
+        let mut output = result.join("\n");
+        output.push('\n');
+        output
+    }
+
+    /// Date of a `## YYYY-MM-DD` heading line, also as a Logseq block
+    fn heading_day(line: &str) -> Option<NaiveDate> {
+        let line = line.trim();
+        let line = line.strip_prefix("- ").map(str::trim).unwrap_or(line);
+        let date = line.strip_prefix("## ")?.trim();
+        NaiveDate::parse_from_str(date, DAY_HEADING_FORMAT).ok()
+    }
+
+    /// Whether a journal line looks like an entry written by this tool
+    fn is_entry_line(line: &str) -> bool {
+        line.trim_start()
+            .strip_prefix("- ")
+            .is_some_and(|rest| !rest.starts_with('#') && rest.contains("[["))
+    }
+
+    /// Replace a file's content atomically via a temporary file and rename
     ///
-    /// Uses OpenOptions to append safely to the file
-    fn atomic_append(file_path: &Path, content: &str) -> Result<(), JournalError> {
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(file_path)
+    /// The temporary file lives next to the journal so the rename stays on one
+    /// filesystem; readers see either the old or the new content, never a mix.
+    /// Use this for any change other than a plain append.
+    pub(crate) fn atomic_rewrite(
+        file_path: &Path,
+        content: &str,
+        fs: &dyn FileSystem,
+    ) -> Result<(), JournalError> {
+        let file_name = file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temp_path = file_path.with_file_name(format!(".{}.local_shelf.tmp", file_name));
+
+        fs.write(&temp_path, content.as_bytes())
+            .and_then(|_| fs.rename(&temp_path, file_path))
             .map_err(|e| {
+                let _ = fs.remove_file(&temp_path);
                 JournalError::WriteOperationFailed(format!(
-                    "Failed to open journal file {}: {}",
+                    "Failed to rewrite journal file {}: {}",
                     file_path.display(),
                     e
                 ))
-            })?;
+            })
+    }
 
-        file.write_all(content.as_bytes()).map_err(|e| {
+    /// Perform atomic append operation to avoid corruption
+    ///
+    /// Appends in a single write to a file opened for appending
+    fn atomic_append(
+        file_path: &Path,
+        content: &str,
+        fs: &dyn FileSystem,
+    ) -> Result<(), JournalError> {
+        fs.append(file_path, content.as_bytes()).map_err(|e| {
             JournalError::WriteOperationFailed(format!(
                 "Failed to write to journal file {}: {}",
                 file_path.display(),
                 e
             ))
-        })?;
-
-        file.flush().map_err(|e| {
-            JournalError::WriteOperationFailed(format!(
-                "Failed to flush journal file {}: {}",
-                file_path.display(),
-                e
-            ))
-        })?;
-
-        Ok(())
-    }
+        })
+    }
 
     /// Parse date from journal filename (for testing and validation)
     ///
@@ -243,24 +1143,31 @@ impl JournalManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveDate;
+    use crate::clock::FixedClock;
+    use crate::filesystem::MockFs;
+    use chrono::{NaiveDate, TimeZone};
     use std::fs;
     use tempfile::tempdir;
 
     fn create_test_config(kb_path: &str) -> Config {
         Config {
             knowledge_base_path: kb_path.to_string(),
+            ..Default::default()
         }
     }
 
+    /// A clock reading 14:30 local time on 2024-03-15
+    fn fixed_clock() -> FixedClock {
+        FixedClock(Local.with_ymd_and_hms(2024, 3, 15, 14, 30, 0).unwrap())
+    }
+
     #[test]
     fn test_journal_entry_creation() {
         let file_path = PathBuf::from("test_article.md");
-        let entry = JournalEntry::new(&file_path).unwrap();
+        let entry = JournalEntry::new(&file_path, &fixed_clock()).unwrap();
 
         assert_eq!(entry.filename, "test_article");
-        assert!(entry.timestamp.len() == 5); // HH:MM format
-        assert!(entry.timestamp.contains(':'));
+        assert_eq!(entry.timestamp, "14:30");
     }
 
     #[test]
@@ -268,16 +1175,160 @@ mod tests {
         let entry = JournalEntry {
             timestamp: "14:30".to_string(),
             filename: "my_article".to_string(),
+            ..Default::default()
         };
 
         let formatted = entry.format();
         assert_eq!(formatted, "- **14:30** [[my_article]]");
     }
 
+    #[test]
+    fn test_journal_entry_formatting_with_one_tag() {
+        let entry = JournalEntry {
+            timestamp: "14:30".to_string(),
+            filename: "name".to_string(),
+            ..Default::default()
+        }
+        .with_tags(&["import".to_string()]);
+
+        assert_eq!(entry.format(), "- **14:30** [[name]] #import");
+    }
+
+    #[test]
+    fn test_journal_entry_formatting_with_source() {
+        let entry = JournalEntry {
+            timestamp: "14:30".to_string(),
+            filename: "name".to_string(),
+            ..Default::default()
+        }
+        .with_tags(&["import".to_string()])
+        .with_source(Path::new("/home/user/Downloads/name.md"));
+
+        assert_eq!(
+            entry.format(),
+            "- **14:30** [[name]] #import (from /home/user/Downloads/name.md)"
+        );
+    }
+
+    #[test]
+    fn test_journal_entry_formatting_with_multiple_tags() {
+        let entry = JournalEntry {
+            timestamp: "14:30".to_string(),
+            filename: "name".to_string(),
+            ..Default::default()
+        }
+        .with_tags(&["#import".to_string(), "web".to_string()]);
+
+        assert_eq!(entry.format(), "- **14:30** [[name]] #import #web");
+    }
+
+    #[test]
+    fn test_journal_entry_timestamp_styles() {
+        let cases = [
+            (true, true, "- **14:30** [[article]]"),
+            (true, false, "- 14:30 [[article]]"),
+            (false, true, "- [[article]]"),
+            (false, false, "- [[article]]"),
+        ];
+
+        for (include_timestamp, bold_timestamp, expected) in cases {
+            let config = Config {
+                include_timestamp,
+                bold_timestamp,
+                ..create_test_config("/kb")
+            };
+            let mut entry = JournalManager::create_entries(&[PathBuf::from("article.md")], &config)
+                .unwrap()
+                .remove(0);
+            entry.timestamp = "14:30".to_string();
+            assert_eq!(
+                entry.format(),
+                expected,
+                "include_timestamp={} bold_timestamp={}",
+                include_timestamp,
+                bold_timestamp
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_entries_applies_configured_tags() {
+        let config = Config {
+            journal_tags: vec!["import".to_string()],
+            ..Default::default()
+        };
+
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("article.md")], &config).unwrap();
+        assert!(entries[0].format().ends_with("[[article]] #import"));
+    }
+
+    #[test]
+    fn test_create_entries_namespaced_links() {
+        let config = Config {
+            namespaced_links: true,
+            organize_by_date: true,
+            ..create_test_config("/kb")
+        };
+
+        let entries = JournalManager::create_entries(
+            &[
+                PathBuf::from("/kb/pages/projects/foo.md"),
+                PathBuf::from("/kb/pages/2024/03/article.md"),
+                PathBuf::from("/kb/pages/flat.md"),
+            ],
+            &config,
+        )
+        .unwrap();
+
+        let links: Vec<String> = entries.iter().map(JournalEntry::link).collect();
+        assert_eq!(
+            links,
+            vec!["[[projects/foo]]", "[[2024/03/article]]", "[[flat]]"]
+        );
+    }
+
+    #[test]
+    fn test_create_entries_namespaced_links_custom_destination() {
+        let config = Config {
+            namespaced_links: true,
+            destination_subdir: Some("inbox".to_string()),
+            ..create_test_config("/kb")
+        };
+
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("/kb/inbox/reading/foo.md")], &config)
+                .unwrap();
+        assert_eq!(entries[0].link(), "[[reading/foo]]");
+    }
+
+    #[test]
+    fn test_create_entries_without_namespaced_links_uses_stem() {
+        let config = create_test_config("/kb");
+
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("/kb/pages/projects/foo.md")], &config)
+                .unwrap();
+        assert_eq!(entries[0].link(), "[[foo]]");
+    }
+
+    #[test]
+    fn test_create_entries_normalize_unicode() {
+        let config = Config {
+            normalize_unicode: true,
+            ..create_test_config("/kb")
+        };
+
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("/kb/pages/cafe\u{301}.md")], &config)
+                .unwrap();
+        assert_eq!(entries[0].link(), "[[caf\u{e9}]]");
+    }
+
     #[test]
     fn test_journal_entry_with_complex_filename() {
         let file_path = PathBuf::from("Complex File Name-With_Special.Characters.md");
-        let entry = JournalEntry::new(&file_path).unwrap();
+        let entry = JournalEntry::new(&file_path, &fixed_clock()).unwrap();
 
         assert_eq!(entry.filename, "Complex File Name-With_Special.Characters");
     }
@@ -289,25 +1340,251 @@ mod tests {
         assert_eq!(journals_dir, PathBuf::from("/test/kb/journals"));
     }
 
+    #[test]
+    fn test_get_journals_directory_custom_name() {
+        let config = Config {
+            journals_dir_name: "Daily Notes".to_string(),
+            ..create_test_config("/test/kb")
+        };
+        let journals_dir = JournalManager::get_journals_directory(&config).unwrap();
+        assert_eq!(journals_dir, PathBuf::from("/test/kb/Daily Notes"));
+    }
+
+    #[test]
+    fn test_journal_path_obsidian_daily_note() {
+        let config = Config {
+            journals_dir_name: ".".to_string(),
+            journal_file_format: "%Y-%m-%d".to_string(),
+            ..create_test_config("/test/kb")
+        };
+        let date = NaiveDate::from_ymd_opt(2026, 2, 7).unwrap();
+
+        let journal_path = JournalManager::journal_path_for(&config, date).unwrap();
+        assert_eq!(journal_path, PathBuf::from("/test/kb/2026-02-07.md"));
+    }
+
+    #[test]
+    fn test_create_entries_namespaced_links_obsidian_root() {
+        let config = Config {
+            namespaced_links: true,
+            pages_dir_name: ".".to_string(),
+            ..create_test_config("/kb")
+        };
+
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("/kb/projects/foo.md")], &config)
+                .unwrap();
+        assert_eq!(entries[0].link(), "[[projects/foo]]");
+    }
+
+    #[test]
+    fn test_create_entries_link_style_for_nested_destination() {
+        let nested = [PathBuf::from("/kb/pages/projects/2026/plan.md")];
+
+        let basename = JournalManager::create_entries(&nested, &create_test_config("/kb")).unwrap();
+        assert_eq!(basename[0].link(), "[[plan]]");
+
+        let config = Config {
+            link_style: LinkStyle::RelativePath,
+            ..create_test_config("/kb")
+        };
+        let relative = JournalManager::create_entries(&nested, &config).unwrap();
+        assert_eq!(relative[0].link(), "[[pages/projects/2026/plan]]");
+
+        // The relative path wins over namespaced links, which stop at `pages`
+        let config = Config {
+            link_style: LinkStyle::RelativePath,
+            namespaced_links: true,
+            ..create_test_config("/kb")
+        };
+        let relative = JournalManager::create_entries(&nested, &config).unwrap();
+        assert_eq!(relative[0].link(), "[[pages/projects/2026/plan]]");
+    }
+
+    #[test]
+    fn test_create_entries_relative_path_outside_knowledge_base_uses_stem() {
+        let config = Config {
+            link_style: LinkStyle::RelativePath,
+            ..create_test_config("/kb")
+        };
+
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("/elsewhere/notes/foo.md")], &config)
+                .unwrap();
+        assert_eq!(entries[0].link(), "[[foo]]");
+    }
+
+    #[test]
+    fn test_create_entries_renders_word_count_and_size() {
+        let temp_dir = tempdir().unwrap();
+        let page = temp_dir.path().join("pages/article.md");
+        fs::create_dir_all(page.parent().unwrap()).unwrap();
+        fs::write(&page, "# Reading log\n\nOne  two\tthree\nfour ").unwrap();
+        let config = Config {
+            journal_entry_details: Some("({words} words, {size})".to_string()),
+            timezone: Some("UTC".to_string()),
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+
+        let entries = JournalManager::create_entries(std::slice::from_ref(&page), &config).unwrap();
+        assert_eq!(entries[0].details.as_deref(), Some("(7 words, 35 B)"));
+        assert!(
+            entries[0]
+                .format()
+                .ends_with(" [[article]] (7 words, 35 B)")
+        );
+
+        // A page that cannot be read gets no details
+        let missing = JournalEntry::default()
+            .with_details("({words} words)", &temp_dir.path().join("missing.md"));
+        assert_eq!(missing.details, None);
+    }
+
+    #[test]
+    fn test_create_entries_nests_an_entry_per_heading() {
+        let temp_dir = tempdir().unwrap();
+        let page = temp_dir.path().join("pages/guide.md");
+        fs::create_dir_all(page.parent().unwrap()).unwrap();
+        fs::write(
+            &page,
+            "# Guide\n\n## Install\nsteps\n\n## Configure\nmore\n",
+        )
+        .unwrap();
+        let config = Config {
+            journal_heading_entries: true,
+            include_timestamp: false,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+
+        let entries = JournalManager::create_entries(std::slice::from_ref(&page), &config).unwrap();
+        assert_eq!(
+            entries[0].format(),
+            "- [[guide]]\n  - [[guide#Install]]\n  - [[guide#Configure]]"
+        );
+
+        let journal = JournalManager::write_entries(&entries, &config).unwrap();
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- [[guide]]\n  - [[guide#Install]]\n  - [[guide#Configure]]\n"
+        );
+
+        // Writing the same entry again adds nothing
+        JournalManager::write_entries(&entries, &config).unwrap();
+        assert_eq!(fs::read_to_string(&journal).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_create_entries_without_heading_entries_is_single_line() {
+        let temp_dir = tempdir().unwrap();
+        let page = temp_dir.path().join("pages/guide.md");
+        fs::create_dir_all(page.parent().unwrap()).unwrap();
+        fs::write(&page, "## Install\n## Configure\n").unwrap();
+        let config = Config {
+            include_timestamp: false,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+
+        let entries = JournalManager::create_entries(std::slice::from_ref(&page), &config).unwrap();
+        assert_eq!(entries[0].format(), "- [[guide]]");
+    }
+
+    #[test]
+    fn test_format_count_groups_thousands() {
+        assert_eq!(JournalEntry::format_count(0), "0");
+        assert_eq!(JournalEntry::format_count(999), "999");
+        assert_eq!(JournalEntry::format_count(1234), "1,234");
+        assert_eq!(JournalEntry::format_count(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_create_entries_markdown_link_format() {
+        let config = Config {
+            link_format: "[{name}]({path})".to_string(),
+            ..create_test_config("/kb")
+        };
+
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("/kb/pages/projects/plan.md")], &config)
+                .unwrap();
+        assert_eq!(entries[0].link(), "[plan](pages/projects/plan.md)");
+    }
+
+    #[test]
+    fn test_create_entries_default_link_format_is_wikilink() {
+        let config = create_test_config("/kb");
+        assert_eq!(config.link_format, DEFAULT_LINK_FORMAT);
+
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("/kb/pages/projects/plan.md")], &config)
+                .unwrap();
+        assert_eq!(entries[0].link(), "[[plan]]");
+    }
+
     #[test]
     fn test_get_today_journal_path() {
-        let config = create_test_config("/test/kb");
-        let journal_path = JournalManager::get_today_journal_path(&config).unwrap();
+        let mut config = create_test_config("/test/kb");
+        let journal_path = JournalManager::get_today_journal_path(&config, &fixed_clock()).unwrap();
+        assert_eq!(
+            journal_path,
+            PathBuf::from("/test/kb/journals/2024_03_15.md")
+        );
 
-        let today = Local::now().date_naive().format("%Y_%m_%d").to_string();
-        let expected_filename = format!("{}.md", today);
+        // 14:30 is still the previous journal day when days start at 15:00
+        config.day_start_hour = 15;
+        let journal_path = JournalManager::get_today_journal_path(&config, &fixed_clock()).unwrap();
+        assert_eq!(
+            journal_path,
+            PathBuf::from("/test/kb/journals/2024_03_14.md")
+        );
+    }
+
+    #[test]
+    fn test_wall_clock_uses_configured_timezone() {
+        use chrono::TimeZone;
 
+        // 23:30 UTC is already 00:30 the next day in Berlin (CET, UTC+1)
+        let instant = Utc.with_ymd_and_hms(2024, 3, 15, 23, 30, 0).unwrap();
+        let berlin = JournalManager::wall_clock(instant, Some(chrono_tz::Europe::Berlin));
+        let utc = JournalManager::wall_clock(instant, Some(chrono_tz::UTC));
+
+        let config = create_test_config("/test/kb");
+        let entry = JournalEntry::new_at(Path::new("article.md"), berlin).unwrap();
+        assert_eq!(entry.timestamp, "00:30");
         assert_eq!(
-            journal_path.file_name().unwrap().to_str().unwrap(),
-            expected_filename
+            JournalManager::journal_path_for(&config, berlin.date()).unwrap(),
+            PathBuf::from("/test/kb/journals/2024_03_16.md")
         );
-        assert!(
-            journal_path
-                .to_string_lossy()
-                .contains("/test/kb/journals/")
+
+        let entry = JournalEntry::new_at(Path::new("article.md"), utc).unwrap();
+        assert_eq!(entry.timestamp, "23:30");
+        assert_eq!(
+            JournalManager::journal_path_for(&config, utc.date()).unwrap(),
+            PathBuf::from("/test/kb/journals/2024_03_15.md")
         );
     }
 
+    #[test]
+    fn test_journal_date_respects_day_start_hour() {
+        let at = |hour, minute| {
+            NaiveDate::from_ymd_opt(2024, 3, 1)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap()
+        };
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let yesterday = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+
+        assert_eq!(JournalManager::journal_date(at(0, 30), 4), yesterday);
+        assert_eq!(JournalManager::journal_date(at(3, 59), 4), yesterday);
+        assert_eq!(JournalManager::journal_date(at(4, 0), 4), today);
+        assert_eq!(JournalManager::journal_date(at(5, 0), 4), today);
+        assert_eq!(JournalManager::journal_date(at(0, 30), 0), today);
+
+        // The entry keeps the actual clock time
+        let entry = JournalEntry::new_at(Path::new("late-read.md"), at(0, 30)).unwrap();
+        assert_eq!(entry.timestamp, "00:30");
+    }
+
     #[test]
     fn test_ensure_directory_exists() {
         let temp_dir = tempdir().unwrap();
@@ -315,7 +1592,7 @@ mod tests {
 
         assert!(!new_dir.exists());
 
-        JournalManager::ensure_directory_exists(&new_dir).unwrap();
+        JournalManager::ensure_directory_exists(&new_dir, &RealFs).unwrap();
 
         assert!(new_dir.exists());
         assert!(new_dir.is_dir());
@@ -361,6 +1638,39 @@ mod tests {
         assert!(content.matches("- **").count() == 2); // Two entries
     }
 
+    #[test]
+    fn test_entries_follow_modified_asc_order() {
+        use crate::config::ProcessOrder;
+        use crate::file_discovery::FileDiscovery;
+
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let config = create_test_config(&kb_dir.path().display().to_string());
+
+        let ages = [("newest", 60), ("oldest", 3_600), ("middle", 600)];
+        let now = std::time::SystemTime::now();
+        for (name, age_secs) in ages {
+            let path = source_dir.path().join(format!("{}.md", name));
+            fs::write(&path, "content").unwrap();
+            let mtime = now - std::time::Duration::from_secs(age_secs);
+            filetime::set_file_mtime(&path, filetime::FileTime::from_system_time(mtime)).unwrap();
+        }
+
+        let mut files =
+            FileDiscovery::discover_in_directory_detailed(source_dir.path(), &config).unwrap();
+        FileDiscovery::sort_files(&mut files, ProcessOrder::ModifiedAsc);
+        let journal_path =
+            JournalManager::add_entries(&FileDiscovery::into_paths(files), &config).unwrap();
+
+        let content = fs::read_to_string(&journal_path).unwrap();
+        let links: Vec<&str> = content
+            .lines()
+            .filter_map(|line| line.split("[[").nth(1))
+            .map(|rest| rest.trim_end_matches("]]"))
+            .collect();
+        assert_eq!(links, vec!["oldest", "middle", "newest"]);
+    }
+
     #[test]
     fn test_add_entries_to_existing_journal() {
         let temp_dir = tempdir().unwrap();
@@ -370,13 +1680,16 @@ mod tests {
         let journals_dir = temp_dir.path().join("journals");
         fs::create_dir_all(&journals_dir).unwrap();
 
-        let today = Local::now().date_naive().format("%Y_%m_%d").to_string();
-        let journal_file = journals_dir.join(format!("{}.md", today));
+        let journal_file = journals_dir.join("2024_03_15.md");
         fs::write(&journal_file, "# Existing content\n").unwrap();
 
         // Add new entries
+        let clock = fixed_clock();
         let moved_files = vec![PathBuf::from("new_article.md")];
-        let journal_path = JournalManager::add_entries(&moved_files, &config).unwrap();
+        let entries = JournalManager::create_entries_with(&moved_files, &config, &clock).unwrap();
+        let journal_path =
+            JournalManager::write_entries_with(&entries, &config, &RealFs, &clock).unwrap();
+        assert_eq!(journal_path, journal_file);
 
         // Verify content was appended
         let content = fs::read_to_string(&journal_path).unwrap();
@@ -384,6 +1697,150 @@ mod tests {
         assert!(content.contains("[[new_article]]"));
     }
 
+    #[test]
+    fn test_preview_entries_leaves_journal_untouched() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            include_timestamp: false,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("new_article.md")], &config).unwrap();
+
+        let preview = JournalManager::preview_entries(&entries, &config).unwrap();
+        assert!(!preview.exists);
+        assert_eq!(preview.proposed, "- [[new_article]]\n");
+        assert!(preview.unified_diff().starts_with("--- /dev/null\n"));
+        assert!(!preview.path.exists());
+
+        fs::create_dir_all(preview.path.parent().unwrap()).unwrap();
+        fs::write(&preview.path, "# Existing content\n- [[new_article]]\n").unwrap();
+        let preview = JournalManager::preview_entries(&entries, &config).unwrap();
+        assert!(!preview.has_changes());
+    }
+
+    #[test]
+    fn test_preview_entries_diff_shows_added_lines() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            include_timestamp: false,
+            journal_section: Some("## Imported".to_string()),
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("article.md")], &config).unwrap();
+        let journal_path = JournalManager::preview_entries(&entries, &config)
+            .unwrap()
+            .path;
+        fs::create_dir_all(journal_path.parent().unwrap()).unwrap();
+        fs::write(&journal_path, "## Imported\n- [[older]]\n## Notes\n").unwrap();
+
+        let preview = JournalManager::preview_entries(&entries, &config).unwrap();
+
+        assert_eq!(
+            preview.proposed,
+            "## Imported\n- [[older]]\n- [[article]]\n## Notes\n"
+        );
+        let diff = preview.unified_diff();
+        assert!(diff.contains("+- [[article]]\n"));
+        assert!(!diff.contains("/dev/null"));
+    }
+
+    #[test]
+    fn test_single_file_journal_creates_day_section_in_order() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            include_timestamp: false,
+            journal_mode: JournalMode::SingleFile,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let entries =
+            JournalManager::create_entries(&[PathBuf::from("article.md")], &config).unwrap();
+        let journal_path = temp_dir.path().join("journals/journal.md");
+
+        let written =
+            JournalManager::write_entries_with(&entries, &config, &RealFs, &fixed_clock()).unwrap();
+        assert_eq!(written, journal_path);
+        assert_eq!(
+            fs::read_to_string(&journal_path).unwrap(),
+            "## 2024-03-15\n- [[article]]\n"
+        );
+
+        for (existing, expected) in [
+            (
+                "# Journal\n## 2024-03-10\n- [[older]]\n## 2024-03-20\n- [[later]]\n",
+                "# Journal\n## 2024-03-10\n- [[older]]\n## 2024-03-15\n- [[article]]\n## 2024-03-20\n- [[later]]\n",
+            ),
+            (
+                "## 2024-03-20\n- [[later]]\n## 2024-03-10\n- [[older]]\n",
+                "## 2024-03-20\n- [[later]]\n## 2024-03-15\n- [[article]]\n## 2024-03-10\n- [[older]]\n",
+            ),
+            (
+                "## 2024-03-10\n- [[older]]\n",
+                "## 2024-03-10\n- [[older]]\n## 2024-03-15\n- [[article]]\n",
+            ),
+        ] {
+            fs::write(&journal_path, existing).unwrap();
+            JournalManager::write_entries_with(&entries, &config, &RealFs, &fixed_clock()).unwrap();
+            assert_eq!(fs::read_to_string(&journal_path).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_single_file_journal_appends_to_existing_day_section() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            include_timestamp: false,
+            journal_mode: JournalMode::SingleFile,
+            journal_file: "Journal.md".to_string(),
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let journal_path = temp_dir.path().join("journals/Journal.md");
+        fs::create_dir_all(journal_path.parent().unwrap()).unwrap();
+        fs::write(
+            &journal_path,
+            "## 2024-03-15\n- [[first]]\nNotes\n## 2024-03-14\n- [[article]]\n",
+        )
+        .unwrap();
+
+        // The entry from another day does not make today's a duplicate
+        let entries = JournalManager::create_entries(
+            &[PathBuf::from("article.md"), PathBuf::from("first.md")],
+            &config,
+        )
+        .unwrap();
+        JournalManager::write_entries_with(&entries, &config, &RealFs, &fixed_clock()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal_path).unwrap(),
+            "## 2024-03-15\n- [[first]]\n- [[article]]\nNotes\n## 2024-03-14\n- [[article]]\n"
+        );
+    }
+
+    #[test]
+    fn test_add_entries_backs_up_existing_journal() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            journal_backup: true,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+        let journals_dir = temp_dir.path().join("journals");
+        fs::create_dir_all(&journals_dir).unwrap();
+
+        // No backup is written for a journal that does not exist yet
+        let journal_path =
+            JournalManager::add_entries(&[PathBuf::from("first.md")], &config).unwrap();
+        let backup_path = journals_dir.join(format!(
+            "{}.bak",
+            journal_path.file_name().unwrap().to_string_lossy()
+        ));
+        assert!(!backup_path.exists());
+
+        let before = fs::read_to_string(&journal_path).unwrap();
+        JournalManager::add_entries(&[PathBuf::from("second.md")], &config).unwrap();
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), before);
+    }
+
     #[test]
     fn test_add_entries_empty_files_list() {
         let temp_dir = tempdir().unwrap();
@@ -399,13 +1856,350 @@ mod tests {
         ));
     }
 
+    fn entry(timestamp: &str, filename: &str) -> JournalEntry {
+        JournalEntry {
+            timestamp: timestamp.to_string(),
+            filename: filename.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_append_skips_existing_identical_line() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- **09:00** [[article]]\n").unwrap();
+
+        let entries = vec![entry("09:00", "article"), entry("09:00", "other")];
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &entries,
+            false,
+            None,
+            None,
+            LineEnding::Lf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- **09:00** [[article]]\n- **09:00** [[other]]\n"
+        );
+    }
+
+    #[test]
+    fn test_append_exact_mode_keeps_different_timestamp() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- **09:00** [[article]]\n").unwrap();
+
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &[entry("10:00", "article")],
+            false,
+            None,
+            None,
+            LineEnding::Lf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(fs::read_to_string(&journal).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_append_ignore_timestamp_mode_matches_link() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- **09:00** [[article]]\n").unwrap();
+
+        let entries = vec![
+            entry("10:00", "first"),
+            entry("10:00", "article"),
+            entry("10:00", "second"),
+            entry("10:01", "first"),
+        ];
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &entries,
+            true,
+            None,
+            None,
+            LineEnding::Lf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- **09:00** [[article]]\n- **10:00** [[first]]\n- **10:00** [[second]]\n"
+        );
+    }
+
+    #[test]
+    fn test_append_under_existing_section() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(
+            &journal,
+            "- Morning notes\n- ## Imported\n- **08:00** [[earlier]]\n- ## Tasks\n- TODO review\n",
+        )
+        .unwrap();
+
+        let entries = vec![entry("09:00", "article"), entry("09:00", "other")];
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &entries,
+            false,
+            Some("## Imported"),
+            None,
+            LineEnding::Lf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- Morning notes\n- ## Imported\n- **08:00** [[earlier]]\n- **09:00** [[article]]\n- **09:00** [[other]]\n- ## Tasks\n- TODO review\n"
+        );
+    }
+
+    #[test]
+    fn test_append_under_section_after_plain_entries() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- ## Imported\n- [[earlier]]\n- ## Tasks\n").unwrap();
+
+        let entry = JournalEntry {
+            timestamp_style: TimestampStyle::Hidden,
+            ..entry("09:00", "article")
+        };
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &[entry],
+            false,
+            Some("## Imported"),
+            None,
+            LineEnding::Lf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- ## Imported\n- [[earlier]]\n- [[article]]\n- ## Tasks\n"
+        );
+    }
+
+    #[test]
+    fn test_append_creates_missing_section_at_end() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "## Morning\nSome text").unwrap();
+
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &[entry("09:00", "article")],
+            false,
+            Some("## Imported"),
+            None,
+            LineEnding::Lf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "## Morning\nSome text\n## Imported\n- **09:00** [[article]]\n"
+        );
+        assert!(!temp_dir.path().join(".journal.md.local_shelf.tmp").exists());
+    }
+
+    #[test]
+    fn test_append_section_in_new_journal() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &[entry("09:00", "article")],
+            false,
+            Some("## Imported"),
+            None,
+            LineEnding::Lf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "## Imported\n- **09:00** [[article]]\n"
+        );
+    }
+
+    #[test]
+    fn test_append_with_crlf_line_endings() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- Morning notes").unwrap();
+
+        let entries = vec![entry("09:00", "article"), entry("09:00", "other")];
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &entries,
+            false,
+            None,
+            None,
+            LineEnding::Crlf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- Morning notes\r\n- **09:00** [[article]]\r\n- **09:00** [[other]]\r\n"
+        );
+    }
+
+    #[test]
+    fn test_append_under_section_with_crlf_rewrites_every_line() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- ## Imported\n- [[earlier]]\r\n- ## Tasks\n").unwrap();
+
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &[entry("09:00", "article")],
+            false,
+            Some("## Imported"),
+            None,
+            LineEnding::Crlf,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- ## Imported\r\n- [[earlier]]\r\n- **09:00** [[article]]\r\n- ## Tasks\r\n"
+        );
+    }
+
+    #[test]
+    fn test_append_native_line_ending_follows_existing_journal() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- **08:00** [[earlier]]\r\n- ## Notes\r\n").unwrap();
+
+        JournalManager::append_entries_to_journal(
+            &journal,
+            &[entry("09:00", "article")],
+            false,
+            None,
+            None,
+            LineEnding::Native,
+            &RealFs,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- **08:00** [[earlier]]\r\n- ## Notes\r\n- **09:00** [[article]]\r\n"
+        );
+    }
+
+    #[test]
+    fn test_write_entries_uses_configured_line_ending() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            line_ending: LineEnding::Crlf,
+            ..create_test_config(&temp_dir.path().display().to_string())
+        };
+
+        let journal_path = JournalManager::add_entries(
+            &[PathBuf::from("first.md"), PathBuf::from("second.md")],
+            &config,
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(journal_path).unwrap();
+        assert_eq!(content.matches("\r\n").count(), 2);
+        assert_eq!(content.matches('\n').count(), 2);
+        assert!(content.ends_with("[[second]]\r\n"));
+    }
+
+    #[test]
+    fn test_add_same_file_twice_creates_single_line() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            knowledge_base_path: temp_dir.path().display().to_string(),
+            journal_dedupe_ignore_timestamp: true,
+            ..Default::default()
+        };
+
+        let files = vec![PathBuf::from("article.md")];
+        let journal_path = JournalManager::add_entries(&files, &config).unwrap();
+        JournalManager::add_entries(&files, &config).unwrap();
+
+        let content = fs::read_to_string(&journal_path).unwrap();
+        assert_eq!(content.matches("[[article]]").count(), 1);
+    }
+
+    #[test]
+    fn test_atomic_rewrite_replaces_content_without_temp_file() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- old entry\n- another\n").unwrap();
+
+        JournalManager::atomic_rewrite(&journal, "- new entry\n", &RealFs).unwrap();
+
+        assert_eq!(fs::read_to_string(&journal).unwrap(), "- new entry\n");
+        let names: Vec<_> = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("journal.md")]);
+    }
+
+    #[test]
+    fn test_atomic_rewrite_keeps_journal_when_rename_fails() {
+        let temp_dir = tempdir().unwrap();
+        let journal = temp_dir.path().join("journal.md");
+        fs::write(&journal, "- old entry\n").unwrap();
+        let fs = MockFs::default().fail("rename", std::io::ErrorKind::PermissionDenied, 1);
+
+        let result = JournalManager::atomic_rewrite(&journal, "- new entry\n", &fs);
+
+        assert!(matches!(result, Err(JournalError::WriteOperationFailed(_))));
+        assert_eq!(fs::read_to_string(&journal).unwrap(), "- old entry\n");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_entries_reports_failed_append() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().display().to_string());
+        let clock = fixed_clock();
+        let entries =
+            JournalManager::create_entries_with(&[PathBuf::from("article.md")], &config, &clock)
+                .unwrap();
+        let fs = MockFs::default().fail("append", std::io::ErrorKind::StorageFull, 1);
+
+        let result = JournalManager::write_entries_with(&entries, &config, &fs, &clock);
+
+        assert!(matches!(result, Err(JournalError::WriteOperationFailed(_))));
+        let journal_path =
+            JournalManager::write_entries_with(&entries, &config, &RealFs, &clock).unwrap();
+        assert_eq!(fs::read_to_string(journal_path).unwrap().lines().count(), 1);
+    }
+
     #[test]
     fn test_atomic_append_creates_file() {
         let temp_dir = tempdir().unwrap();
         let test_file = temp_dir.path().join("test_journal.md");
 
         let content = "- **14:30** [[test_file]]\n";
-        JournalManager::atomic_append(&test_file, content).unwrap();
+        JournalManager::atomic_append(&test_file, content, &RealFs).unwrap();
 
         assert!(test_file.exists());
         let file_content = fs::read_to_string(&test_file).unwrap();
@@ -422,7 +2216,7 @@ mod tests {
 
         // Append new content
         let new_content = "- **15:45** [[new_entry]]\n";
-        JournalManager::atomic_append(&test_file, new_content).unwrap();
+        JournalManager::atomic_append(&test_file, new_content, &RealFs).unwrap();
 
         let final_content = fs::read_to_string(&test_file).unwrap();
         assert_eq!(
@@ -501,15 +2295,16 @@ mod tests {
         let journals_dir = temp_dir.path().join("journals");
         fs::create_dir_all(&journals_dir).unwrap();
 
-        let today = Local::now().date_naive().format("%Y_%m_%d").to_string();
-        let journal_file = journals_dir.join(format!("{}.md", today));
+        let journal_file = journals_dir.join("2024_03_15.md");
 
         // Write content WITHOUT trailing newline
         fs::write(&journal_file, "- **15:00** [[existing_entry]]").unwrap();
 
         // Add new entry
+        let clock = fixed_clock();
         let new_files = vec![PathBuf::from("new_file.md")];
-        JournalManager::add_entries(&new_files, &config).unwrap();
+        let entries = JournalManager::create_entries_with(&new_files, &config, &clock).unwrap();
+        JournalManager::write_entries_with(&entries, &config, &RealFs, &clock).unwrap();
 
         let content = fs::read_to_string(&journal_file).unwrap();
         let lines: Vec<&str> = content.lines().collect();
@@ -0,0 +1,594 @@
+//! Move ledger used by the `undo` subcommand
+//!
+//! Every `stow` run appends one JSON object per moved file to
+//! `ledger.jsonl` in the configuration directory:
+//!
+//! ```text
+//! {"run_id":"2026-02-07T10:15:00+01:00","from":"/home/user/Downloads/article.md","to":"/home/user/Knowledge Base/pages/article.md","timestamp":"2026-02-07T10:15:01+01:00","journal_path":"/home/user/Knowledge Base/journals/2026_02_07.md","journal_line":"- **10:15** [[article]]","size":1234,"modified":1770455701}
+//! ```
+//!
+//! * `run_id` - start time of the run; entries sharing it belong to the same run
+//! * `from` / `to` - original source path and final destination path
+//! * `timestamp` - RFC 3339 time the move completed
+//! * `journal_path` / `journal_line` - journal file and exact line(s) added for the file, if any
+//! * `size` / `modified` - destination size in bytes and modification time in Unix
+//!   seconds right after the move, used to detect later edits
+//! * `created_dirs` - directories containing `to` that the run created, which `clean`
+//!   may remove once they are empty again
+//! * `journal_heading` / `journal_heading_created` - in a single-file journal, the day
+//!   heading the line went under and whether the run added that heading
+//! * `rollback_failed` - set when a `transactional` run was rolled back but this page
+//!   could not be put back; only such pages of a rolled back run are recorded
+//!
+//! Every field except `from` and `to` is optional so older entries keep parsing.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::config::{Config, ConfigError, IntegrityHash};
+use crate::file_operations::{FileOperationError, FileOperations};
+use crate::filesystem::RealFs;
+use crate::journal_management::{JournalError, JournalManager};
+
+/// Error types for ledger operations
+#[derive(Debug, thiserror::Error)]
+pub enum LedgerError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Config error: {0}")]
+    ConfigError(#[from] ConfigError),
+    #[error("Ledger parse error: {0}")]
+    ParseError(#[from] serde_json::Error),
+    #[error("File operation error: {0}")]
+    FileOperationError(#[from] FileOperationError),
+    #[error("Journal error: {0}")]
+    JournalError(#[from] JournalError),
+}
+
+/// A single recorded move
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    #[serde(default)]
+    pub run_id: String,
+    pub from: PathBuf,
+    pub to: PathBuf,
+    #[serde(default)]
+    pub timestamp: String,
+    #[serde(default)]
+    pub journal_path: Option<PathBuf>,
+    #[serde(default)]
+    pub journal_line: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub modified: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub created_dirs: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal_heading: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub journal_heading_created: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rollback_failed: bool,
+}
+
+impl LedgerEntry {
+    /// Create an entry for a completed move, capturing the destination's
+    /// current size and modification time
+    pub fn new(run_id: &str, from: &Path, to: &Path, timestamp: &str) -> Self {
+        let (size, modified) = Self::fingerprint(to);
+
+        LedgerEntry {
+            run_id: run_id.to_string(),
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            timestamp: timestamp.to_string(),
+            journal_path: None,
+            journal_line: None,
+            size,
+            modified,
+            created_dirs: Vec::new(),
+            journal_heading: None,
+            journal_heading_created: false,
+            rollback_failed: false,
+        }
+    }
+
+    /// Read the size and modification time (Unix seconds) of a file
+    fn fingerprint(path: &Path) -> (Option<u64>, Option<u64>) {
+        match fs::metadata(path) {
+            Ok(metadata) => {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs());
+                (Some(metadata.len()), modified)
+            }
+            Err(_) => (None, None),
+        }
+    }
+}
+
+/// Result of undoing a run
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    /// Entries whose files were moved back to their source
+    pub restored: Vec<LedgerEntry>,
+    /// Entries left untouched, with the reason
+    pub skipped: Vec<(LedgerEntry, String)>,
+}
+
+/// Public interface for ledger operations
+pub struct Ledger;
+
+impl Ledger {
+    /// Get the default ledger file path inside the configuration directory
+    pub fn default_path() -> Result<PathBuf, LedgerError> {
+        Ok(Config::config_dir()?.join("ledger.jsonl"))
+    }
+
+    /// Append entries to the ledger, creating it if necessary
+    pub fn append(ledger_path: &Path, entries: &[LedgerEntry]) -> Result<(), LedgerError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(parent) = ledger_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut content = String::new();
+        for entry in entries {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(ledger_path)?;
+        file.write_all(content.as_bytes())?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// Read all entries from the ledger
+    ///
+    /// A missing ledger is treated as empty. Blank lines are ignored.
+    pub fn read(ledger_path: &Path) -> Result<Vec<LedgerEntry>, LedgerError> {
+        if !ledger_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        fs::read_to_string(ledger_path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(LedgerError::from))
+            .collect()
+    }
+
+    /// Reverse the most recent run recorded in the ledger
+    ///
+    /// Files are moved from their destination back to their original
+    /// location and the journal lines they added are removed. Files that
+    /// were deleted or edited since the import, or whose original location
+    /// is occupied again, are skipped rather than clobbered. The restored
+    /// entries are removed from the ledger afterwards; skipped ones stay, so
+    /// the next `undo` retries them once the conflict is resolved.
+    pub fn undo_last_run(ledger_path: &Path) -> Result<UndoReport, LedgerError> {
+        let entries = Self::read(ledger_path)?;
+        let mut report = UndoReport::default();
+
+        let Some(last_run) = entries.last().map(|entry| entry.run_id.clone()) else {
+            return Ok(report);
+        };
+
+        let (run, remaining): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|entry| entry.run_id == last_run);
+
+        for entry in run {
+            match Self::restore(&entry) {
+                Ok(()) => report.restored.push(entry),
+                Err(reason) => report.skipped.push((entry, reason)),
+            }
+        }
+
+        Self::strip_journal_lines(&report.restored)?;
+        let kept: Vec<LedgerEntry> = remaining
+            .into_iter()
+            .chain(report.skipped.iter().map(|(entry, _)| entry.clone()))
+            .collect();
+        Self::rewrite(ledger_path, &kept)?;
+
+        Ok(report)
+    }
+
+    /// Move a single file back to its source, or explain why it can't be
+    pub(crate) fn restore(entry: &LedgerEntry) -> Result<(), String> {
+        if !entry.to.exists() {
+            return Err(format!("{} no longer exists", entry.to.display()));
+        }
+
+        let (size, modified) = LedgerEntry::fingerprint(&entry.to);
+        if (entry.size.is_some() && size != entry.size)
+            || (entry.modified.is_some() && modified != entry.modified)
+        {
+            return Err(format!("{} was modified after import", entry.to.display()));
+        }
+
+        if entry.from.exists() {
+            return Err(format!("{} already exists", entry.from.display()));
+        }
+
+        if let Some(parent) = entry.from.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        FileOperations::atomic_move(&entry.to, &entry.from, IntegrityHash::default(), &RealFs)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Remove the journal lines recorded for the given entries
+    ///
+    /// Lines recorded under a day heading of a single-file journal are only
+    /// looked for in that day's section, and a heading the run added is
+    /// removed once its section is empty.
+    fn strip_journal_lines(entries: &[LedgerEntry]) -> Result<(), LedgerError> {
+        let mut journals: Vec<&Path> = entries
+            .iter()
+            .filter_map(|entry| entry.journal_path.as_deref())
+            .collect();
+        journals.sort();
+        journals.dedup();
+
+        for journal_path in journals {
+            if !journal_path.exists() {
+                continue;
+            }
+
+            let journal_entries: Vec<&LedgerEntry> = entries
+                .iter()
+                .filter(|entry| entry.journal_path.as_deref() == Some(journal_path))
+                .collect();
+
+            let content = fs::read_to_string(journal_path)?;
+            let lines: Vec<&str> = content.lines().collect();
+            let mut removed = vec![false; lines.len()];
+            for entry in &journal_entries {
+                let (start, end) = match &entry.journal_heading {
+                    Some(heading) => match JournalManager::day_section(&lines, heading) {
+                        Some((heading_index, end)) => (heading_index + 1, end),
+                        None => continue,
+                    },
+                    None => (0, lines.len()),
+                };
+                for remove in entry.journal_line.as_deref().unwrap_or("").lines() {
+                    if let Some(index) =
+                        (start..end).find(|index| !removed[*index] && lines[*index] == remove)
+                    {
+                        removed[index] = true;
+                    }
+                }
+            }
+
+            let mut created: Vec<&str> = journal_entries
+                .iter()
+                .filter(|entry| entry.journal_heading_created)
+                .filter_map(|entry| entry.journal_heading.as_deref())
+                .collect();
+            created.dedup();
+            for heading in created {
+                if let Some((heading_index, end)) = JournalManager::day_section(&lines, heading)
+                    && (heading_index + 1..end)
+                        .all(|index| removed[index] || lines[index].trim().is_empty())
+                {
+                    removed[heading_index..end].fill(true);
+                }
+            }
+
+            let kept: Vec<&str> = lines
+                .iter()
+                .zip(&removed)
+                .filter(|(_, removed)| !**removed)
+                .map(|(line, _)| *line)
+                .collect();
+            let mut new_content = kept.join("\n");
+            if !new_content.is_empty() {
+                new_content.push('\n');
+            }
+            JournalManager::atomic_rewrite(journal_path, &new_content, &RealFs)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the ledger content with the given entries
+    fn rewrite(ledger_path: &Path, entries: &[LedgerEntry]) -> Result<(), LedgerError> {
+        let mut content = String::new();
+        for entry in entries {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+        fs::write(ledger_path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_test_config(kb_path: &str) -> Config {
+        Config {
+            knowledge_base_path: kb_path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn entry(run_id: &str, from: &str, to: &str) -> LedgerEntry {
+        LedgerEntry {
+            run_id: run_id.to_string(),
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+            timestamp: String::new(),
+            journal_path: None,
+            journal_line: None,
+            size: None,
+            modified: None,
+            created_dirs: Vec::new(),
+            journal_heading: None,
+            journal_heading_created: false,
+            rollback_failed: false,
+        }
+    }
+
+    /// Move a file into pages, journal it, and record it in the ledger
+    fn stow(source: &Path, config: &Config, ledger_path: &Path, run_id: &str) -> LedgerEntry {
+        let destination = FileOperations::move_to_pages(source, config).unwrap();
+        let entries =
+            JournalManager::create_entries(std::slice::from_ref(&destination), config).unwrap();
+        let journal_path = JournalManager::write_entries(&entries, config).unwrap();
+
+        let mut ledger_entry = LedgerEntry::new(run_id, source, &destination, "now");
+        ledger_entry.journal_path = Some(journal_path);
+        ledger_entry.journal_line = Some(entries[0].format());
+        Ledger::append(ledger_path, std::slice::from_ref(&ledger_entry)).unwrap();
+        ledger_entry
+    }
+
+    #[test]
+    fn test_append_and_read_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+
+        let entries = vec![
+            entry("run1", "/a.md", "/kb/a.md"),
+            entry("run1", "/b.md", "/kb/b.md"),
+        ];
+        Ledger::append(&ledger_path, &entries).unwrap();
+
+        assert_eq!(Ledger::read(&ledger_path).unwrap(), entries);
+    }
+
+    #[test]
+    fn test_read_missing_ledger_is_empty() {
+        let temp_dir = tempdir().unwrap();
+        let entries = Ledger::read(&temp_dir.path().join("missing.jsonl")).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_read_tolerates_missing_optional_fields() {
+        let temp_dir = tempdir().unwrap();
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+        fs::write(&ledger_path, "{\"from\":\"/a.md\",\"to\":\"/kb/a.md\"}\n\n").unwrap();
+
+        let entries = Ledger::read(&ledger_path).unwrap();
+        assert_eq!(entries, vec![entry("", "/a.md", "/kb/a.md")]);
+    }
+
+    #[test]
+    fn test_move_then_undo_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().join("kb").display().to_string());
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+
+        let source = temp_dir.path().join("article.md");
+        fs::write(&source, "# Article").unwrap();
+        let recorded = stow(&source, &config, &ledger_path, "run1");
+
+        let journal_path = recorded.journal_path.clone().unwrap();
+        fs::write(
+            &journal_path,
+            format!(
+                "- my own note\n{}\n",
+                recorded.journal_line.clone().unwrap()
+            ),
+        )
+        .unwrap();
+
+        let report = Ledger::undo_last_run(&ledger_path).unwrap();
+
+        assert_eq!(report.restored.len(), 1);
+        assert!(report.skipped.is_empty());
+        assert_eq!(fs::read_to_string(&source).unwrap(), "# Article");
+        assert!(!recorded.to.exists());
+        assert_eq!(
+            fs::read_to_string(&journal_path).unwrap(),
+            "- my own note\n"
+        );
+        assert!(Ledger::read(&ledger_path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_undo_removes_nested_heading_entries() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config {
+            journal_heading_entries: true,
+            ..create_test_config(&temp_dir.path().join("kb").display().to_string())
+        };
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+
+        let source = temp_dir.path().join("guide.md");
+        fs::write(&source, "## Install\n## Configure\n").unwrap();
+        let recorded = stow(&source, &config, &ledger_path, "run1");
+        let journal_path = recorded.journal_path.clone().unwrap();
+        assert_eq!(
+            fs::read_to_string(&journal_path).unwrap().lines().count(),
+            3
+        );
+
+        Ledger::undo_last_run(&ledger_path).unwrap();
+
+        assert_eq!(fs::read_to_string(&journal_path).unwrap(), "");
+    }
+
+    #[test]
+    fn test_strip_journal_lines_stays_in_the_recorded_day_section() {
+        let temp_dir = tempdir().unwrap();
+        let journal_path = temp_dir.path().join("journal.md");
+        let content = "## 2026-02-06\n- [[article]]\n## 2026-02-07\n- [[other]]\n- [[article]]\n";
+        fs::write(&journal_path, content).unwrap();
+
+        let mut recorded = entry("run1", "/downloads/article.md", "/kb/pages/article.md");
+        recorded.journal_path = Some(journal_path.clone());
+        recorded.journal_line = Some("- [[article]]".to_string());
+        recorded.journal_heading = Some("## 2026-02-07".to_string());
+        Ledger::strip_journal_lines(std::slice::from_ref(&recorded)).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&journal_path).unwrap(),
+            "## 2026-02-06\n- [[article]]\n## 2026-02-07\n- [[other]]\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_journal_lines_removes_heading_the_run_added_once_empty() {
+        let temp_dir = tempdir().unwrap();
+        let journal_path = temp_dir.path().join("journal.md");
+        let content = "## 2026-02-06\n- [[article]]\n## 2026-02-07\n- [[article]]\n";
+
+        let mut recorded = entry("run1", "/downloads/article.md", "/kb/pages/article.md");
+        recorded.journal_path = Some(journal_path.clone());
+        recorded.journal_line = Some("- [[article]]".to_string());
+        recorded.journal_heading = Some("## 2026-02-07".to_string());
+
+        // A heading that was there before the run stays
+        fs::write(&journal_path, content).unwrap();
+        Ledger::strip_journal_lines(std::slice::from_ref(&recorded)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&journal_path).unwrap(),
+            "## 2026-02-06\n- [[article]]\n## 2026-02-07\n"
+        );
+
+        recorded.journal_heading_created = true;
+        fs::write(&journal_path, content).unwrap();
+        Ledger::strip_journal_lines(std::slice::from_ref(&recorded)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&journal_path).unwrap(),
+            "## 2026-02-06\n- [[article]]\n"
+        );
+    }
+
+    #[test]
+    fn test_undo_only_reverts_last_run() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().join("kb").display().to_string());
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+
+        let first = temp_dir.path().join("first.md");
+        let second = temp_dir.path().join("second.md");
+        fs::write(&first, "first").unwrap();
+        fs::write(&second, "second").unwrap();
+        let first_entry = stow(&first, &config, &ledger_path, "run1");
+        stow(&second, &config, &ledger_path, "run2");
+
+        let report = Ledger::undo_last_run(&ledger_path).unwrap();
+
+        assert_eq!(report.restored.len(), 1);
+        assert!(second.exists());
+        assert!(!first.exists());
+        assert_eq!(Ledger::read(&ledger_path).unwrap(), vec![first_entry]);
+    }
+
+    #[test]
+    fn test_undo_skips_deleted_file() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().join("kb").display().to_string());
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+
+        let source = temp_dir.path().join("article.md");
+        fs::write(&source, "content").unwrap();
+        let recorded = stow(&source, &config, &ledger_path, "run1");
+        fs::remove_file(&recorded.to).unwrap();
+
+        let report = Ledger::undo_last_run(&ledger_path).unwrap();
+
+        assert!(report.restored.is_empty());
+        assert_eq!(report.skipped.len(), 1);
+        assert!(!source.exists());
+        // Journal line is kept because the file was not restored
+        let journal = fs::read_to_string(recorded.journal_path.unwrap()).unwrap();
+        assert!(journal.contains("[[article]]"));
+    }
+
+    #[test]
+    fn test_undo_skips_edited_file() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().join("kb").display().to_string());
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+
+        let source = temp_dir.path().join("article.md");
+        fs::write(&source, "content").unwrap();
+        let recorded = stow(&source, &config, &ledger_path, "run1");
+        fs::write(&recorded.to, "content edited in the vault").unwrap();
+
+        let report = Ledger::undo_last_run(&ledger_path).unwrap();
+
+        assert!(report.restored.is_empty());
+        assert!(report.skipped[0].1.contains("modified"));
+        assert!(recorded.to.exists());
+        assert!(!source.exists());
+    }
+
+    #[test]
+    fn test_undo_does_not_clobber_recreated_source() {
+        let temp_dir = tempdir().unwrap();
+        let config = create_test_config(&temp_dir.path().join("kb").display().to_string());
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+
+        let source = temp_dir.path().join("article.md");
+        fs::write(&source, "original").unwrap();
+        let recorded = stow(&source, &config, &ledger_path, "run1");
+        fs::write(&source, "newer download").unwrap();
+
+        let report = Ledger::undo_last_run(&ledger_path).unwrap();
+
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(fs::read_to_string(&source).unwrap(), "newer download");
+        assert_eq!(fs::read_to_string(&recorded.to).unwrap(), "original");
+        assert_eq!(Ledger::read(&ledger_path).unwrap(), vec![recorded.clone()]);
+
+        // Once the source location is free again the next undo restores it
+        fs::remove_file(&source).unwrap();
+        let report = Ledger::undo_last_run(&ledger_path).unwrap();
+        assert_eq!(report.restored, vec![recorded]);
+        assert_eq!(fs::read_to_string(&source).unwrap(), "original");
+        assert!(Ledger::read(&ledger_path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_undo_with_empty_ledger() {
+        let temp_dir = tempdir().unwrap();
+        let report = Ledger::undo_last_run(&temp_dir.path().join("ledger.jsonl")).unwrap();
+        assert!(report.restored.is_empty());
+        assert!(report.skipped.is_empty());
+    }
+}
@@ -1,4 +1,27 @@
+pub mod archive;
+pub mod cleanup;
+pub mod clock;
+pub mod color;
 pub mod config;
+pub mod content_index;
+pub mod doctor;
+pub mod events;
 pub mod file_discovery;
+pub mod file_naming;
 pub mod file_operations;
+pub mod filesystem;
+pub mod headings;
+pub mod hooks;
 pub mod journal_management;
+pub mod ledger;
+pub mod manifest;
+pub mod page_properties;
+pub mod paths;
+pub mod pipeline;
+pub mod processed;
+pub mod prompt;
+pub mod run_summary;
+pub mod stats;
+pub mod url_import;
+
+pub use pipeline::{AppError, RunOptions, run};
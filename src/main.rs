@@ -1,51 +1,143 @@
+pub mod archive;
+pub mod cleanup;
+pub mod clock;
+pub mod color;
 pub mod config;
+pub mod content_index;
+pub mod doctor;
+pub mod events;
 pub mod file_discovery;
+pub mod file_naming;
 pub mod file_operations;
+pub mod filesystem;
+pub mod headings;
+pub mod hooks;
 pub mod journal_management;
+pub mod ledger;
+pub mod manifest;
+pub mod page_properties;
+pub mod paths;
+pub mod pipeline;
+pub mod processed;
+pub mod prompt;
+pub mod run_summary;
+pub mod stats;
+pub mod url_import;
 
-use clap::{Parser, Subcommand};
-use config::{Config, ConfigError};
-use file_discovery::FileDiscoveryError;
-use file_operations::{FileOperationError, FileOperations};
-use journal_management::{JournalError, JournalManager};
-use std::path::PathBuf;
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use cleanup::{Cleanup, CleanupPlan};
+use clock::SystemClock;
+use color::ColorChoice;
+use config::{Config, ProcessedMarker};
+use doctor::Doctor;
+use events::{EventSink, ProgressSink, StdoutSink};
+use file_discovery::{DiscoveryReport, FileDiscovery, FileDiscoveryError};
+use journal_management::JournalManager;
+use ledger::Ledger;
+use pipeline::{AppError, RunOptions};
+use processed::Processed;
+use prompt::Prompt;
+use run_summary::{EXIT_FATAL, EXIT_SUCCESS, RunSummary, format_bytes};
+use stats::ImportStats;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-#[derive(Debug, thiserror::Error)]
-enum AppError {
-    #[error("Configuration error: {0}")]
-    Config(#[from] ConfigError),
-    #[error("File discovery error: {0}")]
-    FileDiscovery(#[from] FileDiscoveryError),
-    #[error("File operation error: {0}")]
-    FileOperation(#[from] FileOperationError),
-    #[error("Journal error: {0}")]
-    Journal(#[from] JournalError),
-    #[error("Conversion error: {0}")]
-    Conversion(String),
-}
-
 #[derive(Parser)]
 #[command(
     name = "local_shelf",
     version = env!("CARGO_PKG_VERSION"),
     about = "A tool for organizing and converting markdown files",
-    author = "Local Shelf Contributors"
+    author = "Local Shelf Contributors",
+    arg_required_else_help = true
 )]
 struct Cli {
+    /// Knowledge Base to use for this run instead of `knowledge_base_path`
+    /// and KNOWLEDGE_BASE (expands `~`); without a command, runs `stow`
+    #[arg(value_name = "KNOWLEDGE_BASE")]
+    knowledge_base: Option<String>,
+
+    /// Path to the configuration file (LOCAL_SHELF_CONFIG takes precedence); `-` reads YAML or TOML from stdin
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Leave a legacy ~/.config/local-shelf directory untouched (same as LOCAL_SHELF_NO_MIGRATE=1)
+    #[arg(long, global = true)]
+    no_migrate: bool,
+
+    /// Color output: `auto` colors terminals unless NO_COLOR is set
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
+}
+
+/// Arguments for the `stow` subcommand
+#[derive(Args, Default)]
+struct StowArgs {
+    /// Directory containing markdown files to move (defaults to current directory)
+    #[arg(help = "Path to directory containing markdown files")]
+    path: Option<PathBuf>,
+    /// Scan this directory for this run only (expands `~` and environment variables)
+    #[arg(long, value_name = "DIR", conflicts_with = "path")]
+    source: Option<String>,
+    /// Import the markdown files inside this zip archive without extracting it to disk
+    #[arg(long, value_name = "ZIP", conflicts_with_all = ["path", "source"])]
+    from_zip: Option<PathBuf>,
+    /// Reorganize the pages already in the Knowledge Base by the current
+    /// naming and organization rules instead of importing anything
+    #[arg(long, conflicts_with_all = ["path", "source", "from_zip", "dest", "interactive"])]
+    pages_only: bool,
+    /// Knowledge Base subdirectory to move files into for this run (overrides `destination_subdir`)
+    #[arg(long, value_name = "SUBDIR")]
+    dest: Option<String>,
+    /// Import files with this extension instead of the configured ones (repeatable)
+    #[arg(long = "ext", value_name = "EXT")]
+    extensions: Vec<String>,
+    /// Process at most this many files, taken in the configured order (overrides `limit`)
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+    /// Exit with code 3 when there are no files to import (overrides `fail_on_empty`)
+    #[arg(long)]
+    fail_on_empty: bool,
+    /// Only import files modified on or after this day (overrides `modified_since`)
+    #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_date_arg)]
+    since: Option<NaiveDate>,
+    /// Only import files modified on or before this day (overrides `modified_until`)
+    #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_date_arg)]
+    until: Option<NaiveDate>,
+    /// Move files without adding journal entries (overrides `write_journal`)
+    #[arg(long)]
+    no_journal: bool,
+    /// List the files that would be moved without moving anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Ask before moving each file: y(es), N(o), a(ll remaining) or q(uit)
+    #[arg(long, conflicts_with_all = ["json", "from_zip"])]
+    interactive: bool,
+    /// Print a JSON object describing the run instead of progress output
+    #[arg(long)]
+    json: bool,
+    /// Never show the progress bar while files are moved
+    #[arg(long)]
+    no_progress: bool,
+    /// Continue when the Knowledge Base parent directory is missing or the
+    /// Knowledge Base is not writable, printing a warning instead
+    #[arg(long)]
+    force: bool,
+    /// Run the interactive setup wizard even if a configuration file exists,
+    /// replacing only its Knowledge Base path
+    #[arg(long)]
+    init: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Move markdown files from a directory to Knowledge Base pages directory
     #[command(name = "stow")]
-    Stow {
-        /// Directory containing markdown files to move (defaults to current directory)
-        #[arg(help = "Path to directory containing markdown files")]
-        path: Option<PathBuf>,
-    },
+    Stow(StowArgs),
     /// Convert markdown files in a directory to EPUB format
     #[command(name = "convert")]
     Convert {
@@ -56,6 +148,55 @@ enum Commands {
     /// Display configuration information and example configuration
     #[command(name = "config")]
     Config,
+    /// Move files from the last stow run back to where they came from
+    #[command(name = "undo")]
+    Undo,
+    /// List the files `stow` would import, without moving or journaling anything
+    #[command(name = "scan")]
+    Scan {
+        /// Directory to scan (defaults to current directory)
+        #[arg(help = "Path to directory containing markdown files")]
+        path: Option<PathBuf>,
+    },
+    /// Show how many files were imported per day and week, from the undo ledger
+    #[command(name = "stats")]
+    Stats {
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check the configuration, Knowledge Base and source directory and report what needs fixing
+    #[command(name = "doctor")]
+    Doctor {
+        /// Source directory to check (defaults to current directory)
+        #[arg(help = "Path to directory containing markdown files")]
+        path: Option<PathBuf>,
+    },
+    /// Fetch a markdown file from a URL and import it like a stowed file
+    #[command(name = "import-url")]
+    ImportUrl {
+        /// URL of the markdown file, e.g. a raw gist URL
+        url: String,
+        /// File name for the page (defaults to the last segment of the URL path)
+        #[arg(long, value_name = "NAME")]
+        name: Option<String>,
+        /// Fetch the file and show what would be imported without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the run summary as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove empty journal files and empty directories from the Knowledge Base
+    #[command(name = "clean")]
+    Clean {
+        /// Remove without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Print the path of today's journal file, for scripts that post-process it
+    #[command(name = "journal-path")]
+    JournalPath,
 }
 
 fn check_pandoc() -> Result<(), AppError> {
@@ -154,14 +295,16 @@ fn handle_convert_command(path: Option<PathBuf>) -> Result<(), AppError> {
         match convert_markdown_to_epub(md_file) {
             Ok(epub_file) => {
                 println!(
-                    "✓ Successfully converted: {}",
+                    "{} Successfully converted: {}",
+                    color::success("✓"),
                     epub_file.file_name().unwrap().to_string_lossy()
                 );
                 converted += 1;
             }
             Err(e) => {
                 eprintln!(
-                    "✗ Failed to convert {}: {}",
+                    "{} Failed to convert {}: {}",
+                    color::failure_stderr("✗"),
                     md_file.file_name().unwrap().to_string_lossy(),
                     e
                 );
@@ -179,90 +322,429 @@ fn handle_convert_command(path: Option<PathBuf>) -> Result<(), AppError> {
     Ok(())
 }
 
-fn handle_stow_command(path: Option<PathBuf>) -> Result<(), AppError> {
-    // Initialize configuration on first run
-    Config::initialize()?;
+/// Parse a `--since`/`--until` date for clap
+fn parse_date_arg(value: &str) -> Result<NaiveDate, String> {
+    Config::parse_date(value).map_err(|e| match e {
+        config::ConfigError::ValidationError(message) => message,
+        other => other.to_string(),
+    })
+}
 
-    // Load configuration
-    let config = Config::load()?;
+fn handle_stow_command(
+    args: StowArgs,
+    config_override: Option<&Path>,
+    knowledge_base: Option<&str>,
+    no_migrate: bool,
+) -> Result<RunSummary, AppError> {
+    let StowArgs {
+        path,
+        source,
+        from_zip,
+        pages_only,
+        dest,
+        extensions,
+        limit,
+        fail_on_empty,
+        since,
+        until,
+        no_journal,
+        dry_run,
+        interactive,
+        json,
+        no_progress,
+        force,
+        init,
+    } = args;
 
-    // Use specified directory or default to current directory
-    let target_directory = path.unwrap_or_else(|| PathBuf::from("."));
+    // Initialize configuration on first run, asking for the Knowledge Base
+    // path when someone is at the terminal and did not give one. The prompt
+    // holds the stdin lock, so it must be gone before `--config -` reads the
+    // configuration.
+    {
+        let interactive = init || (knowledge_base.is_none() && std::io::stdin().is_terminal());
+        let mut prompt = interactive.then(Prompt::stdio);
+        Config::initialize_with_prompt(config_override, prompt.as_mut(), init, !no_migrate)?;
+    }
 
-    println!("Local Shelf starting...");
-    println!("Knowledge Base path: {}", config.get_knowledge_base_path());
+    // Load configuration
+    let mut config = Config::resolve_for(config_override, knowledge_base, force)?.config;
+    if let Some(dest) = dest {
+        config.destination_subdir = Some(dest);
+    }
+    if !extensions.is_empty() {
+        config.extensions = extensions.iter().map(|ext| ext.to_lowercase()).collect();
+    }
+    if let Some(limit) = limit {
+        config.limit = Some(limit);
+    }
+    if let Some(since) = since {
+        config.modified_since = Some(since.format("%Y-%m-%d").to_string());
+    }
+    if let Some(until) = until {
+        config.modified_until = Some(until.format("%Y-%m-%d").to_string());
+    }
+    if no_journal {
+        config.write_journal = false;
+    }
+    if fail_on_empty {
+        config.fail_on_empty = true;
+    }
+    for warning in config.validate_with(force)? {
+        eprintln!(
+            "{} {} (continuing because of --force)",
+            color::warning_stderr("⚠"),
+            warning
+        );
+    }
 
-    // Discover markdown files in specified directory
-    println!(
-        "Scanning {} for markdown files...",
-        target_directory.display()
-    );
-    let markdown_files = discover_markdown_files_in_directory(&target_directory)?;
+    // The progress bar only makes sense for someone watching a terminal
+    let output = StdoutSink { quiet: json };
+    let progress = (!json && !no_progress && std::io::stdout().is_terminal())
+        .then(|| ProgressSink::new(output));
+    let events: &dyn EventSink = match &progress {
+        Some(progress) => progress,
+        None => &output,
+    };
 
-    if markdown_files.is_empty() {
-        println!("No markdown files found in {}", target_directory.display());
-        return Ok(());
+    if pages_only {
+        let summary = pipeline::run_reorganize(&config, dry_run, &SystemClock, events)?;
+        print_run_summary(&summary, json);
+        return Ok(summary);
     }
 
-    println!(
-        "Found {} markdown file(s) in {}:",
-        markdown_files.len(),
-        target_directory.display()
-    );
-    for file in &markdown_files {
-        println!("  - {}", file.display());
+    if let Some(archive) = from_zip {
+        let summary = pipeline::run_archive(&config, &archive, dry_run, &SystemClock, events)?;
+        print_run_summary(&summary, json);
+        return Ok(summary);
     }
 
-    // Move files to pages directory
-    println!("\nMoving files to {{Knowledge Base}}/pages...");
-    let mut moved_files = Vec::new();
+    // Use the --source override, the specified directory, or default to the
+    // current directory
+    let source_directory = source
+        .map(|source| paths::expand(&source))
+        .or(path)
+        .unwrap_or_else(|| PathBuf::from("."));
 
-    for file_path in &markdown_files {
-        match FileOperations::move_to_pages(file_path, &config) {
-            Ok(destination) => {
-                println!(
-                    "✓ Moved {} → {}",
-                    file_path.file_name().unwrap().to_string_lossy(),
-                    destination.display()
-                );
-                moved_files.push(destination);
-            }
+    let ledger_path = match Ledger::default_path() {
+        Ok(path) => Some(path),
+        Err(e) => {
+            eprintln!(
+                "{} Failed to record moves for undo: {}",
+                color::failure_stderr("✗"),
+                e
+            );
+            None
+        }
+    };
+
+    let seen_path = if config.processed_marker == ProcessedMarker::SeenSet {
+        match Processed::default_seen_path() {
+            Ok(path) => Some(path),
             Err(e) => {
-                eprintln!("✗ Failed to move {}: {}", file_path.display(), e);
+                eprintln!(
+                    "{} Failed to locate the seen set: {}",
+                    color::failure_stderr("✗"),
+                    e
+                );
+                None
             }
         }
-    }
+    } else {
+        None
+    };
 
-    if moved_files.is_empty() {
-        println!("No files were successfully moved.");
-        return Ok(());
+    let options = RunOptions {
+        source_directory,
+        dry_run,
+        ledger_path,
+        seen_path,
+        now: None,
+    };
+    let summary = if interactive {
+        pipeline::run_interactive(&config, options, events, &mut Prompt::stdio())?
+    } else {
+        pipeline::run(&config, options, events)?
+    };
+    print_run_summary(&summary, json);
+
+    Ok(summary)
+}
+
+/// Print the end-of-run summary, or the whole run as a JSON object in JSON mode
+fn print_run_summary(summary: &RunSummary, json: bool) {
+    if json {
+        let moved: Vec<_> = summary
+            .moved_files
+            .iter()
+            .map(|(from, to)| serde_json::json!({"from": from, "to": to}))
+            .collect();
+        let skipped: Vec<_> = summary
+            .skipped_files
+            .iter()
+            .map(|(path, reason)| serde_json::json!({"path": path, "reason": reason}))
+            .collect();
+        let hook_failures: Vec<_> = summary
+            .hook_failures
+            .iter()
+            .map(|(path, error)| serde_json::json!({"path": path, "error": error}))
+            .collect();
+        let rolled_back: Vec<_> = summary
+            .rolled_back
+            .iter()
+            .map(|(from, to)| serde_json::json!({"from": from, "to": to}))
+            .collect();
+        let rollback_failures: Vec<_> = summary
+            .rollback_failures
+            .iter()
+            .map(|(path, error)| serde_json::json!({"path": path, "error": error}))
+            .collect();
+        println!(
+            "{}",
+            serde_json::json!({
+                "summary": summary,
+                "moved": moved,
+                "skipped": skipped,
+                "hook_failures": hook_failures,
+                "rolled_back": rolled_back,
+                "rollback_failures": rollback_failures,
+            })
+        );
+    } else {
+        print!("\n{}", summary.render());
     }
+}
 
-    println!(
-        "\nSuccessfully moved {} file(s) to pages directory.",
-        moved_files.len()
+/// Fetch a web page into the Knowledge Base and journal it like a stowed file
+fn handle_import_url_command(
+    url: &str,
+    name: Option<&str>,
+    dry_run: bool,
+    json: bool,
+    config_override: Option<&Path>,
+    knowledge_base: Option<&str>,
+) -> Result<RunSummary, AppError> {
+    let config = Config::resolve_for(config_override, knowledge_base, false)?.config;
+    let output = StdoutSink { quiet: json };
+    let summary = pipeline::run_url(&config, url, name, dry_run, &SystemClock, &output)?;
+    print_run_summary(&summary, json);
+    Ok(summary)
+}
+
+/// Run discovery only and print what `stow` would pick up
+///
+/// This never creates the configuration, moves files or writes journals, so
+/// it is safe to run with an untested configuration.
+fn handle_scan_command(
+    path: Option<PathBuf>,
+    config_override: Option<&Path>,
+    knowledge_base: Option<&str>,
+) -> Result<(), AppError> {
+    let config = Config::resolve_for(config_override, knowledge_base, false)?.config;
+    let directory = path.unwrap_or_else(|| PathBuf::from("."));
+
+    let mut report = FileDiscovery::discover_in_directory_report(&directory, &config)?;
+    FileDiscovery::skip_knowledge_base_files(&mut report, &directory, &config);
+    print!(
+        "{}",
+        format_scan_report(&directory, &report, std::time::SystemTime::now())
     );
+    Ok(())
+}
 
-    // Add journal entries for moved files
-    println!("Creating journal entries...");
-    match JournalManager::add_entries(&moved_files, &config) {
-        Ok(journal_path) => {
-            println!(
-                "✓ Added {} journal entr{} to {}",
-                moved_files.len(),
-                if moved_files.len() == 1 { "y" } else { "ies" },
-                journal_path.display()
-            );
+/// Render the pending and skipped files of a scan
+fn format_scan_report(
+    directory: &Path,
+    report: &DiscoveryReport,
+    now: std::time::SystemTime,
+) -> String {
+    let mut files = report.files.clone();
+    FileDiscovery::sort_files(&mut files, config::ProcessOrder::NameAsc);
+
+    let mut output = format!(
+        "{} file(s) pending in {}:\n",
+        files.len(),
+        directory.display()
+    );
+    for file in &files {
+        let age = now
+            .duration_since(file.modified)
+            .unwrap_or_default()
+            .as_secs();
+        output.push_str(&format!(
+            "  - {} ({}, {})\n",
+            file.path.display(),
+            format_bytes(file.size),
+            format_age(age)
+        ));
+    }
+
+    let mut summary = RunSummary::default();
+    for (path, reason) in &report.skipped {
+        summary.record_skipped(path.clone(), *reason);
+    }
+    for (reason, paths) in summary.skipped_by_reason() {
+        output.push_str(&format!("Skipped, {} ({}):\n", reason, paths.len()));
+        for path in paths {
+            output.push_str(&format!("  - {}\n", path.display()));
         }
-        Err(e) => {
-            eprintln!("✗ Failed to create journal entries: {}", e);
+    }
+
+    output
+}
+
+/// Format an age in seconds as a short human-readable string, e.g. `3d old`
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m old", seconds / 60),
+        3600..86400 => format!("{}h old", seconds / 3600),
+        _ => format!("{}d old", seconds / 86400),
+    }
+}
+
+/// Print the doctor's report, returning [`EXIT_FATAL`] when a check failed
+fn handle_doctor_command(
+    path: Option<PathBuf>,
+    config_override: Option<&Path>,
+    knowledge_base: Option<&str>,
+) -> i32 {
+    let directory = path.unwrap_or_else(|| PathBuf::from("."));
+    let report = Doctor::diagnose(config_override, knowledge_base, &directory);
+    print!("{}", report.render());
+    if report.has_failures() {
+        EXIT_FATAL
+    } else {
+        EXIT_SUCCESS
+    }
+}
+
+fn handle_clean_command(
+    config_override: Option<&Path>,
+    knowledge_base: Option<&str>,
+    yes: bool,
+) -> Result<(), AppError> {
+    let config = Config::resolve_for(config_override, knowledge_base, false)?.config;
+    let created: Vec<PathBuf> = Ledger::read(&Ledger::default_path()?)?
+        .into_iter()
+        .flat_map(|entry| entry.created_dirs)
+        .collect();
+    let mut prompt = Prompt::stdio();
+    clean_knowledge_base(&config, &created, (!yes).then_some(&mut prompt))?;
+    Ok(())
+}
+
+fn handle_journal_path_command(
+    config_override: Option<&Path>,
+    knowledge_base: Option<&str>,
+) -> Result<(), AppError> {
+    let config = Config::resolve_for(config_override, knowledge_base, false)?.config;
+    let journal_path = JournalManager::get_today_journal_path(&config, &SystemClock)?;
+    println!("{}", journal_path.display());
+    Ok(())
+}
+
+/// Remove empty journals and the empty directories among `created`,
+/// asking through `prompt` first
+///
+/// Without a prompt everything found is removed straight away. Any answer
+/// other than `y` or `yes`, including a closed stdin, keeps everything.
+/// Returns what was removed.
+fn clean_knowledge_base(
+    config: &Config,
+    created: &[PathBuf],
+    prompt: Option<&mut Prompt>,
+) -> Result<CleanupPlan, AppError> {
+    let plan = Cleanup::plan(config, created)?;
+    if plan.is_empty() {
+        println!("Nothing to clean up.");
+        return Ok(CleanupPlan::default());
+    }
+
+    println!("Empty entries in {}:", config.get_knowledge_base_path());
+    for path in plan.files.iter().chain(&plan.directories) {
+        println!("  - {}", path.display());
+    }
+
+    if let Some(prompt) = prompt {
+        let answer = prompt
+            .ask(
+                &format!(
+                    "Remove {} file(s) and {} director(ies)? (y/N)",
+                    plan.files.len(),
+                    plan.directories.len()
+                ),
+                None,
+            )
+            .unwrap_or_default();
+        if !matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+            println!("Nothing was removed.");
+            return Ok(CleanupPlan::default());
         }
     }
 
+    let removed = Cleanup::execute(&plan)?;
+    println!(
+        "{} Removed {} file(s) and {} director(ies).",
+        color::success("✓"),
+        removed.files.len(),
+        removed.directories.len()
+    );
+    Ok(removed)
+}
+
+fn handle_stats_command(json: bool) -> Result<(), AppError> {
+    let entries = Ledger::read(&Ledger::default_path()?)?;
+    let stats = ImportStats::from_entries(&entries);
+
+    if json {
+        println!("{}", serde_json::json!(stats));
+    } else {
+        print!("{}", stats.render());
+    }
     Ok(())
 }
 
-fn handle_config_command() -> Result<(), AppError> {
+fn handle_undo_command() -> Result<(), AppError> {
+    let ledger_path = Ledger::default_path()?;
+
+    println!("Undoing last stow run...");
+    let report = Ledger::undo_last_run(&ledger_path)?;
+
+    if report.restored.is_empty() && report.skipped.is_empty() {
+        println!("Nothing to undo.");
+        return Ok(());
+    }
+
+    for entry in &report.restored {
+        println!(
+            "{} Restored {} → {}",
+            color::success("✓"),
+            entry.to.display(),
+            entry.from.display()
+        );
+    }
+    for (entry, reason) in &report.skipped {
+        eprintln!(
+            "{} Skipped {}: {}",
+            color::warning_stderr("⚠"),
+            entry.to.display(),
+            reason
+        );
+    }
+
+    println!(
+        "\nRestored {} file(s), skipped {}.",
+        report.restored.len(),
+        report.skipped.len()
+    );
+
+    Ok(())
+}
+
+fn handle_config_command(
+    config_override: Option<&Path>,
+    knowledge_base: Option<&str>,
+) -> Result<(), AppError> {
     println!("Local Shelf Configuration");
     println!("=========================");
     println!();
@@ -272,10 +754,12 @@ fn handle_config_command() -> Result<(), AppError> {
         Ok(config_dir) => {
             println!("Configuration directory: {}", config_dir.display());
 
-            let config_file = Config::config_file_path()?;
+            let config_file = Config::resolve_config_file_path(config_override)?;
             println!("Configuration file: {}", config_file.display());
 
-            if config_file.exists() {
+            if Config::is_stdin_path(&config_file) {
+                println!("Status: Configuration is read from standard input");
+            } else if config_file.exists() {
                 println!("Status: Configuration file exists");
             } else {
                 println!(
@@ -301,14 +785,20 @@ fn handle_config_command() -> Result<(), AppError> {
     println!("Environment Variables:");
     println!("=====================");
     println!("KNOWLEDGE_BASE - Override the knowledge_base_path setting");
+    println!(
+        "LOCAL_SHELF_CONFIG - Use a different configuration file (takes precedence over --config)"
+    );
+    println!("XDG_CONFIG_HOME - Look for local_shelf/config.yaml here instead of ~/.config");
+    println!();
+    println!("A config.toml file with the same keys is used instead when no config.yaml exists.");
     println!();
 
     // Display current effective configuration if possible
-    match Config::load() {
-        Ok(config) => {
-            println!("Current Configuration:");
-            println!("=====================");
-            println!("Knowledge Base path: {}", config.get_knowledge_base_path());
+    match Config::resolve_for(config_override, knowledge_base, false) {
+        Ok(resolved) => {
+            println!("Effective Configuration:");
+            println!("=======================");
+            print!("{}", resolved.render());
         }
         Err(e) => {
             println!("Note: Could not load current configuration: {}", e);
@@ -318,14 +808,65 @@ fn handle_config_command() -> Result<(), AppError> {
     Ok(())
 }
 
-fn main() -> Result<(), AppError> {
-    let cli = Cli::parse();
+/// Run a parsed command and return the process exit code
+///
+/// 0 means every file was handled, 1 means some files failed to move, 2
+/// means a fatal error stopped the run and 3 means `fail_on_empty` found
+/// nothing to import.
+fn run(cli: Cli) -> i32 {
+    cli.color.apply();
+    let config_override = cli.config.as_deref();
+    let knowledge_base = cli.knowledge_base.as_deref();
+    let command = cli
+        .command
+        .unwrap_or_else(|| Commands::Stow(StowArgs::default()));
+    let result = match command {
+        Commands::Stow(args) => {
+            handle_stow_command(args, config_override, knowledge_base, cli.no_migrate)
+                .map(|summary| summary.exit_code())
+        }
+        Commands::Convert { path } => handle_convert_command(path).map(|_| EXIT_SUCCESS),
+        Commands::Config => {
+            handle_config_command(config_override, knowledge_base).map(|_| EXIT_SUCCESS)
+        }
+        Commands::Undo => handle_undo_command().map(|_| EXIT_SUCCESS),
+        Commands::Scan { path } => {
+            handle_scan_command(path, config_override, knowledge_base).map(|_| EXIT_SUCCESS)
+        }
+        Commands::Stats { json } => handle_stats_command(json).map(|_| EXIT_SUCCESS),
+        Commands::Doctor { path } => {
+            Ok(handle_doctor_command(path, config_override, knowledge_base))
+        }
+        Commands::ImportUrl {
+            url,
+            name,
+            dry_run,
+            json,
+        } => handle_import_url_command(
+            &url,
+            name.as_deref(),
+            dry_run,
+            json,
+            config_override,
+            knowledge_base,
+        )
+        .map(|summary| summary.exit_code()),
+        Commands::Clean { yes } => {
+            handle_clean_command(config_override, knowledge_base, yes).map(|_| EXIT_SUCCESS)
+        }
+        Commands::JournalPath => {
+            handle_journal_path_command(config_override, knowledge_base).map(|_| EXIT_SUCCESS)
+        }
+    };
 
-    match cli.command {
-        Commands::Stow { path } => handle_stow_command(path),
-        Commands::Convert { path } => handle_convert_command(path),
-        Commands::Config => handle_config_command(),
-    }
+    result.unwrap_or_else(|e| {
+        eprintln!("{} {}", color::failure_stderr("Error:"), e);
+        e.exit_code()
+    })
+}
+
+fn main() {
+    std::process::exit(run(Cli::parse()));
 }
 
 #[cfg(test)]
@@ -336,19 +877,23 @@ mod tests {
     #[test]
     fn test_handle_config_command() {
         // Test that config command doesn't panic and returns Ok
-        let result = handle_config_command();
+        let result = handle_config_command(None, None);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_config_command_with_no_config_file() {
+        let _guard = config::TEST_ENV_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
         // Set environment variable to skip config initialization
         unsafe {
             env::set_var("LOCAL_SHELF_SKIP_CONFIG_INIT", "1");
         }
 
         // This should still work even without a config file
-        let result = handle_config_command();
+        let result = handle_config_command(None, None);
         assert!(result.is_ok());
 
         // Clean up
@@ -359,11 +904,15 @@ mod tests {
 
     #[test]
     fn test_config_command_with_environment_override() {
+        let _guard = config::TEST_ENV_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
         unsafe {
             env::set_var("KNOWLEDGE_BASE", "/tmp/test_kb");
         }
 
-        let result = handle_config_command();
+        let result = handle_config_command(None, None);
         assert!(result.is_ok());
 
         unsafe {
@@ -371,6 +920,310 @@ mod tests {
         }
     }
 
+    /// Run `local_shelf` with `args` against a temporary config, ledger and
+    /// Knowledge Base
+    fn run_isolated(temp: &Path, config_yaml: &str, args: &[&str]) -> i32 {
+        let _guard = config::TEST_ENV_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let config_path = temp.join("config.yaml");
+        std::fs::write(&config_path, config_yaml).unwrap();
+
+        let previous_xdg = env::var_os("XDG_CONFIG_HOME");
+        unsafe {
+            env::remove_var("KNOWLEDGE_BASE");
+            env::set_var("XDG_CONFIG_HOME", temp.join("xdg"));
+        }
+
+        let mut argv = vec!["local_shelf", "--config", config_path.to_str().unwrap()];
+        argv.extend_from_slice(args);
+        let code = run(Cli::parse_from(argv));
+
+        unsafe {
+            match previous_xdg {
+                Some(value) => env::set_var("XDG_CONFIG_HOME", value),
+                None => env::remove_var("XDG_CONFIG_HOME"),
+            }
+        }
+        code
+    }
+
+    #[test]
+    fn test_run_exit_code_partial_failure() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("inbox");
+        let kb = temp.path().join("kb");
+        std::fs::create_dir_all(&source).unwrap();
+
+        // Files from 2023 move fine, files from 2024 are blocked because
+        // `pages/2024` is a regular file rather than a directory
+        std::fs::create_dir_all(kb.join("pages")).unwrap();
+        std::fs::write(kb.join("pages/2024"), "not a directory").unwrap();
+        for (name, year) in [("ok.md", 2023), ("blocked.md", 2024)] {
+            let path = source.join(name);
+            std::fs::write(&path, "content").unwrap();
+            let mtime = chrono::NaiveDate::from_ymd_opt(year, 6, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_utc()
+                .timestamp();
+            filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+
+        let config_yaml = format!(
+            "knowledge_base_path: \"{}\"\norganize_by_date: true\ndate_subdir_pattern: \"%Y\"\n",
+            kb.display()
+        );
+        let code = run_isolated(
+            temp.path(),
+            &config_yaml,
+            &["stow", source.to_str().unwrap()],
+        );
+
+        assert_eq!(code, run_summary::EXIT_PARTIAL_FAILURE);
+        assert!(kb.join("pages/2023/ok.md").exists());
+        assert!(source.join("blocked.md").exists());
+    }
+
+    #[test]
+    fn test_run_exit_code_success_and_fatal() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("inbox");
+        let kb = temp.path().join("kb");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("note.md"), "content").unwrap();
+        let config_yaml = format!("knowledge_base_path: \"{}\"\n", kb.display());
+
+        let code = run_isolated(
+            temp.path(),
+            &config_yaml,
+            &["stow", source.to_str().unwrap()],
+        );
+        assert_eq!(code, EXIT_SUCCESS);
+
+        let missing = temp.path().join("missing");
+        let code = run_isolated(
+            temp.path(),
+            &config_yaml,
+            &["stow", missing.to_str().unwrap()],
+        );
+        assert_eq!(code, EXIT_FATAL);
+    }
+
+    #[test]
+    fn test_stow_ext_flag_overrides_extensions() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("inbox");
+        let kb = temp.path().join("kb");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("notes.txt"), "plain text").unwrap();
+        std::fs::write(source.join("article.md"), "# Article").unwrap();
+        let config_yaml = format!("knowledge_base_path: \"{}\"\n", kb.display());
+
+        let code = run_isolated(
+            temp.path(),
+            &config_yaml,
+            &["stow", source.to_str().unwrap(), "--ext", "TXT"],
+        );
+
+        assert_eq!(code, EXIT_SUCCESS);
+        assert!(kb.join("pages/notes.txt").exists());
+        assert!(!kb.join("pages/article.md").exists());
+        assert!(source.join("article.md").exists());
+    }
+
+    #[test]
+    fn test_stow_limit_flag_caps_files_per_run() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("inbox");
+        let kb = temp.path().join("kb");
+        std::fs::create_dir_all(&source).unwrap();
+        for name in ["a.md", "b.md", "c.md"] {
+            std::fs::write(source.join(name), "# Note").unwrap();
+        }
+        let config_yaml = format!("knowledge_base_path: \"{}\"\n", kb.display());
+
+        let code = run_isolated(
+            temp.path(),
+            &config_yaml,
+            &["stow", source.to_str().unwrap(), "--limit", "2"],
+        );
+
+        assert_eq!(code, EXIT_SUCCESS);
+        assert!(kb.join("pages/a.md").exists());
+        assert!(kb.join("pages/b.md").exists());
+        assert!(source.join("c.md").exists());
+        assert!(!kb.join("pages/c.md").exists());
+    }
+
+    #[test]
+    fn test_stow_no_journal_flag_skips_journal() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("inbox");
+        let kb = temp.path().join("kb");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("article.md"), "# Article").unwrap();
+        let config_yaml = format!("knowledge_base_path: \"{}\"\n", kb.display());
+
+        let code = run_isolated(
+            temp.path(),
+            &config_yaml,
+            &["stow", source.to_str().unwrap(), "--no-journal"],
+        );
+
+        assert_eq!(code, EXIT_SUCCESS);
+        assert!(kb.join("pages/article.md").exists());
+        assert!(!kb.join("journals").exists());
+    }
+
+    #[test]
+    fn test_stow_since_and_until_flags_filter_by_modification_date() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("inbox");
+        let kb = temp.path().join("kb");
+        std::fs::create_dir_all(&source).unwrap();
+        for (name, day) in [
+            ("old.md", 1),
+            ("cutoff.md", 10),
+            ("new.md", 20),
+            ("future.md", 28),
+        ] {
+            let path = source.join(name);
+            std::fs::write(&path, "content").unwrap();
+            let mtime = chrono::NaiveDate::from_ymd_opt(2026, 2, day)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_local_timezone(chrono::Local)
+                .unwrap()
+                .timestamp();
+            filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+        let config_yaml = format!("knowledge_base_path: \"{}\"\n", kb.display());
+
+        let code = run_isolated(
+            temp.path(),
+            &config_yaml,
+            &[
+                "stow",
+                source.to_str().unwrap(),
+                "--since",
+                "2026-02-10",
+                "--until",
+                "2026-02-20",
+            ],
+        );
+
+        assert_eq!(code, EXIT_SUCCESS);
+        assert!(kb.join("pages/cutoff.md").exists());
+        assert!(kb.join("pages/new.md").exists());
+        assert!(source.join("old.md").exists());
+        assert!(source.join("future.md").exists());
+    }
+
+    #[test]
+    fn test_stow_rejects_invalid_since_date() {
+        for date in ["10/02/2026", "2026-02-30", "yesterday"] {
+            let error = Cli::try_parse_from(["local_shelf", "stow", "--since", date])
+                .err()
+                .unwrap_or_else(|| panic!("{} should be rejected", date));
+            let message = error.to_string();
+            assert!(message.contains("expected YYYY-MM-DD"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_scan_lists_pending_files_without_side_effects() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("inbox");
+        let kb = temp.path().join("kb");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("article.md"), "# Article").unwrap();
+        std::fs::write(source.join("stub.md"), "").unwrap();
+        std::fs::write(source.join("photo.jpg"), "binary").unwrap();
+        let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(3 * 86400);
+        filetime::set_file_mtime(
+            source.join("article.md"),
+            filetime::FileTime::from_system_time(modified),
+        )
+        .unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb.display().to_string(),
+            min_file_size_bytes: 1,
+            ..Default::default()
+        };
+        let report = FileDiscovery::discover_in_directory_report(&source, &config).unwrap();
+        let output = format_scan_report(&source, &report, std::time::SystemTime::now());
+
+        assert_eq!(
+            output,
+            format!(
+                "1 file(s) pending in {dir}:\n  - {dir}/article.md (9 B, 3d old)\n\
+                 Skipped, below minimum size (1):\n  - {dir}/stub.md\n",
+                dir = source.display()
+            )
+        );
+        assert!(source.join("article.md").exists());
+        assert!(!kb.exists());
+    }
+
+    #[test]
+    fn test_clean_asks_before_removing_empty_journals() {
+        let temp = tempfile::tempdir().unwrap();
+        let journals = temp.path().join("journals");
+        std::fs::create_dir_all(&journals).unwrap();
+        std::fs::write(journals.join("2024_03_01.md"), "").unwrap();
+        std::fs::write(journals.join("2024_03_02.md"), "- [[article]]\n").unwrap();
+        let config = Config {
+            knowledge_base_path: temp.path().display().to_string(),
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        let mut declined = Prompt::new(std::io::Cursor::new("n\n"), &mut output);
+        let removed = clean_knowledge_base(&config, &[], Some(&mut declined)).unwrap();
+        assert!(removed.is_empty());
+        assert!(journals.join("2024_03_01.md").exists());
+
+        let mut output = Vec::new();
+        let mut confirmed = Prompt::new(std::io::Cursor::new("y\n"), &mut output);
+        let removed = clean_knowledge_base(&config, &[], Some(&mut confirmed)).unwrap();
+        assert_eq!(removed.files, vec![journals.join("2024_03_01.md")]);
+        assert!(!journals.join("2024_03_01.md").exists());
+        assert!(journals.join("2024_03_02.md").exists());
+    }
+
+    #[test]
+    fn test_scan_command_does_not_move_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("inbox");
+        let kb = temp.path().join("kb");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("article.md"), "# Article").unwrap();
+        let config_yaml = format!("knowledge_base_path: \"{}\"\n", kb.display());
+
+        let code = run_isolated(
+            temp.path(),
+            &config_yaml,
+            &["scan", source.to_str().unwrap()],
+        );
+
+        assert_eq!(code, EXIT_SUCCESS);
+        assert!(source.join("article.md").exists());
+        assert!(!kb.exists());
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5), "just now");
+        assert_eq!(format_age(120), "2m old");
+        assert_eq!(format_age(7200), "2h old");
+        assert_eq!(format_age(3 * 86400 + 5), "3d old");
+    }
+
     #[test]
     fn test_commands_enum_includes_config() {
         // This test ensures Config variant exists in Commands enum
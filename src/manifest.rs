@@ -0,0 +1,179 @@
+//! Checksum manifest of imported pages
+//!
+//! With `write_manifest` enabled, every `stow` run appends one CSV line per
+//! imported page to `manifest.csv` in the Knowledge Base:
+//!
+//! ```text
+//! imported_at,sha256,path
+//! 2026-02-07T10:15:01+01:00,9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08,/home/user/Knowledge Base/pages/article.md
+//! ```
+//!
+//! * `imported_at` - RFC 3339 time the run finished moving files
+//! * `sha256` - hex SHA-256 of the page as written, page properties included
+//! * `path` - final path of the page
+//!
+//! The header is written when the manifest is created. Lines are only ever
+//! appended, and all lines of a run go out in a single write.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::file_operations::{FileOperationError, FileOperations};
+use crate::filesystem::{FileSystem, RealFs};
+
+/// Name of the manifest file inside the Knowledge Base
+pub const MANIFEST_FILE_NAME: &str = "manifest.csv";
+
+/// First line of a new manifest
+const MANIFEST_HEADER: &str = "imported_at,sha256,path";
+
+/// Error types for manifest operations
+#[derive(Debug, thiserror::Error)]
+pub enum ManifestError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("File operation error: {0}")]
+    FileOperationError(#[from] FileOperationError),
+}
+
+/// A single imported page
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub imported_at: String,
+    pub sha256: String,
+    pub path: PathBuf,
+}
+
+impl ManifestEntry {
+    /// Create an entry for an imported page, hashing its current content
+    pub fn new(path: &Path, imported_at: &str) -> Result<Self, ManifestError> {
+        Ok(ManifestEntry {
+            imported_at: imported_at.to_string(),
+            sha256: FileOperations::content_hash(path)?,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// The entry as a CSV line, without the line break
+    fn to_csv(&self) -> String {
+        [
+            Manifest::csv_field(&self.imported_at),
+            Manifest::csv_field(&self.sha256),
+            Manifest::csv_field(&self.path.to_string_lossy()),
+        ]
+        .join(",")
+    }
+}
+
+/// Public interface for manifest operations
+pub struct Manifest;
+
+impl Manifest {
+    /// Path of the manifest inside the configured Knowledge Base
+    pub fn path(config: &Config) -> PathBuf {
+        config.knowledge_base_subdir(MANIFEST_FILE_NAME)
+    }
+
+    /// Append entries to the manifest, creating it with a header if necessary
+    pub fn append(manifest_path: &Path, entries: &[ManifestEntry]) -> Result<(), ManifestError> {
+        Self::append_with(manifest_path, entries, &RealFs)
+    }
+
+    /// Append entries like [`Self::append`], through `fs`
+    pub fn append_with(
+        manifest_path: &Path,
+        entries: &[ManifestEntry],
+        fs: &dyn FileSystem,
+    ) -> Result<(), ManifestError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = String::new();
+        if fs.metadata(manifest_path).is_err() {
+            content.push_str(MANIFEST_HEADER);
+            content.push('\n');
+        }
+        for entry in entries {
+            content.push_str(&entry.to_csv());
+            content.push('\n');
+        }
+
+        fs.append(manifest_path, content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Quote a CSV field when it contains a comma, quote or line break
+    fn csv_field(value: &str) -> Cow<'_, str> {
+        if value.contains([',', '"', '\n', '\r']) {
+            Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+        } else {
+            Cow::Borrowed(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MockFs;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_append_writes_header_once() {
+        let temp_dir = tempdir().unwrap();
+        let page = temp_dir.path().join("article.md");
+        fs::write(&page, "test").unwrap();
+        let manifest = temp_dir.path().join(MANIFEST_FILE_NAME);
+
+        let entry = ManifestEntry::new(&page, "2026-02-07T10:15:01+01:00").unwrap();
+        Manifest::append(&manifest, std::slice::from_ref(&entry)).unwrap();
+        Manifest::append(&manifest, &[entry]).unwrap();
+
+        let line = format!(
+            "2026-02-07T10:15:01+01:00,9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08,{}",
+            page.display()
+        );
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            format!("imported_at,sha256,path\n{}\n{}\n", line, line)
+        );
+    }
+
+    #[test]
+    fn test_append_quotes_paths_with_commas() {
+        let entry = ManifestEntry {
+            imported_at: "2026-02-07T10:15:01+01:00".to_string(),
+            sha256: "ab".to_string(),
+            path: PathBuf::from("/kb/pages/notes, \"draft\".md"),
+        };
+
+        assert_eq!(
+            entry.to_csv(),
+            "2026-02-07T10:15:01+01:00,ab,\"/kb/pages/notes, \"\"draft\"\".md\""
+        );
+    }
+
+    #[test]
+    fn test_append_failure_leaves_manifest_untouched() {
+        let temp_dir = tempdir().unwrap();
+        let manifest = temp_dir.path().join(MANIFEST_FILE_NAME);
+        fs::write(&manifest, "imported_at,sha256,path\n").unwrap();
+        let entry = ManifestEntry {
+            imported_at: "2026-02-07T10:15:01+01:00".to_string(),
+            sha256: "ab".to_string(),
+            path: PathBuf::from("/kb/pages/article.md"),
+        };
+
+        let fs = MockFs::default().fail("append", std::io::ErrorKind::StorageFull, 1);
+        let result = Manifest::append_with(&manifest, &[entry], &fs);
+
+        assert!(matches!(result, Err(ManifestError::IoError(_))));
+        assert_eq!(
+            std::fs::read_to_string(&manifest).unwrap(),
+            "imported_at,sha256,path\n"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Page property blocks written at the top of imported pages
+//!
+//! Two layouts are understood when merging into existing content:
+//! * Logseq properties - leading `key:: value` lines
+//! * YAML front matter - a `---` delimited block with `key: value` lines
+//!
+//! Pages with neither get a Logseq property block prepended.
+
+/// Public interface for page property operations
+pub struct PageProperties;
+
+impl PageProperties {
+    /// Merge properties into page content
+    ///
+    /// Properties whose key is already present are left untouched so
+    /// existing values are never duplicated or overwritten.
+    pub fn merge(content: &str, properties: &[(String, String)]) -> String {
+        if let Some(merged) = Self::merge_front_matter(content, properties) {
+            return merged;
+        }
+
+        let lines: Vec<&str> = content.lines().collect();
+        let block_len = lines
+            .iter()
+            .take_while(|line| Self::property_key(line).is_some())
+            .count();
+        let existing: Vec<&str> = lines[..block_len]
+            .iter()
+            .filter_map(|line| Self::property_key(line))
+            .collect();
+
+        let additions: Vec<String> = properties
+            .iter()
+            .filter(|(key, _)| !existing.contains(&key.as_str()))
+            .map(|(key, value)| format!("{}:: {}", key, value))
+            .collect();
+
+        if additions.is_empty() {
+            return content.to_string();
+        }
+
+        if block_len == 0 {
+            let mut merged = additions.join("\n");
+            merged.push('\n');
+            if !content.is_empty() {
+                merged.push('\n');
+                merged.push_str(content);
+            }
+            return merged;
+        }
+
+        let mut merged: Vec<String> = lines[..block_len].iter().map(|l| l.to_string()).collect();
+        merged.extend(additions);
+        merged.extend(lines[block_len..].iter().map(|l| l.to_string()));
+
+        let mut result = merged.join("\n");
+        if content.ends_with('\n') {
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Remove the properties named by `keys`, undoing what [`Self::merge`] added
+    ///
+    /// A Logseq property block left empty is dropped together with the blank
+    /// line that separated it from the content.
+    pub fn strip(content: &str, keys: &[&str]) -> String {
+        if let Some((front_matter, rest)) = Self::split_front_matter(content) {
+            let mut stripped = String::from("---\n");
+            for line in front_matter.lines() {
+                let key = line.split_once(':').map(|(key, _)| key.trim());
+                if !key.is_some_and(|key| keys.contains(&key)) {
+                    stripped.push_str(line);
+                    stripped.push('\n');
+                }
+            }
+            stripped.push_str(rest);
+            return stripped;
+        }
+
+        let lines: Vec<&str> = content.lines().collect();
+        let block_len = lines
+            .iter()
+            .take_while(|line| Self::property_key(line).is_some())
+            .count();
+        let kept: Vec<&str> = lines[..block_len]
+            .iter()
+            .filter(|line| !Self::property_key(line).is_some_and(|key| keys.contains(&key)))
+            .copied()
+            .collect();
+        if kept.len() == block_len {
+            return content.to_string();
+        }
+
+        let mut rest = &lines[block_len..];
+        if kept.is_empty() && rest.first() == Some(&"") {
+            rest = &rest[1..];
+        }
+        let mut stripped = kept
+            .iter()
+            .chain(rest)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !stripped.is_empty() && content.ends_with('\n') {
+            stripped.push('\n');
+        }
+        stripped
+    }
+
+    /// Split content starting with a YAML front matter block into the
+    /// block's lines and everything from the closing `---` on
+    fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+        let body = content.strip_prefix("---\n")?;
+        let end = body.find("\n---").map(|pos| pos + 1).or_else(|| {
+            // Empty front matter: the closing marker directly follows
+            body.starts_with("---").then_some(0)
+        })?;
+        Some(body.split_at(end))
+    }
+
+    /// Merge into a YAML front matter block, if the content starts with one
+    fn merge_front_matter(content: &str, properties: &[(String, String)]) -> Option<String> {
+        let (front_matter, rest) = Self::split_front_matter(content)?;
+
+        let existing: Vec<&str> = front_matter
+            .lines()
+            .filter_map(|line| line.split_once(':').map(|(key, _)| key.trim()))
+            .collect();
+
+        let mut merged = String::from("---\n");
+        merged.push_str(front_matter);
+        for (key, value) in properties {
+            if !existing.contains(&key.as_str()) {
+                merged.push_str(&format!("{}: {}\n", key, Self::yaml_scalar(value)));
+            }
+        }
+        merged.push_str(rest);
+
+        Some(merged)
+    }
+
+    /// Quote a front matter value when YAML would not read it back verbatim
+    ///
+    /// Paths and titles can contain `: `, ` #` or start with an indicator
+    /// character; those are written as double-quoted strings.
+    fn yaml_scalar(value: &str) -> String {
+        let needs_quotes = value.is_empty()
+            || value.contains(": ")
+            || value.contains(" #")
+            || value.ends_with(':')
+            || value.trim() != value
+            || value.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c));
+        if !needs_quotes {
+            return value.to_string();
+        }
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+    /// Get the key of a Logseq `key:: value` property line
+    fn property_key(line: &str) -> Option<&str> {
+        let (key, _) = line.split_once("::")?;
+        let valid = !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        valid.then_some(key)
+    }
+
+    /// Replace `{date}` and `{source}` placeholders in a property value
+    pub fn render_value(template: &str, date: &str, source: &str) -> String {
+        template.replace("{date}", date).replace("{source}", source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties() -> Vec<(String, String)> {
+        vec![
+            ("import-date".to_string(), "2026-02-07".to_string()),
+            ("source".to_string(), "/downloads/a.md".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_merge_without_existing_properties() {
+        let merged = PageProperties::merge("# Title\n\nBody\n", &properties());
+        assert_eq!(
+            merged,
+            "import-date:: 2026-02-07\nsource:: /downloads/a.md\n\n# Title\n\nBody\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_into_empty_content() {
+        let merged = PageProperties::merge("", &properties());
+        assert_eq!(
+            merged,
+            "import-date:: 2026-02-07\nsource:: /downloads/a.md\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_into_existing_logseq_properties() {
+        let content = "source:: https://example.com\ntags:: reading\n\n# Title\n";
+        let merged = PageProperties::merge(content, &properties());
+        assert_eq!(
+            merged,
+            "source:: https://example.com\ntags:: reading\nimport-date:: 2026-02-07\n\n# Title\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_into_yaml_front_matter() {
+        let content = "---\ntitle: Article\nsource: web\n---\n# Title\n";
+        let merged = PageProperties::merge(content, &properties());
+        assert_eq!(
+            merged,
+            "---\ntitle: Article\nsource: web\nimport-date: 2026-02-07\n---\n# Title\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_into_yaml_front_matter_quotes_unsafe_values() {
+        let content = "---\ntitle: Article\n---\n";
+        let properties = vec![
+            (
+                "source-path".to_string(),
+                "/downloads/Notes: \"draft\" #2.md".to_string(),
+            ),
+            ("plain".to_string(), "/downloads/a.md".to_string()),
+        ];
+        let merged = PageProperties::merge(content, &properties);
+        assert_eq!(
+            merged,
+            "---\ntitle: Article\nsource-path: \"/downloads/Notes: \\\"draft\\\" #2.md\"\nplain: /downloads/a.md\n---\n"
+        );
+
+        let front_matter = merged
+            .trim_start_matches("---\n")
+            .split("---")
+            .next()
+            .unwrap();
+        let parsed: serde_yaml::Value = serde_yaml::from_str(front_matter).unwrap();
+        assert_eq!(parsed["source-path"], "/downloads/Notes: \"draft\" #2.md");
+    }
+
+    #[test]
+    fn test_merge_is_idempotent() {
+        let once = PageProperties::merge("Body\n", &properties());
+        let twice = PageProperties::merge(&once, &properties());
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn test_strip_undoes_merge() {
+        let keys = ["import-date", "source"];
+        for content in [
+            "",
+            "# Title\n\nBody\n",
+            "Body",
+            "tags:: reading\n# Title\n",
+            "---\ntitle: Article\n---\nBody\n",
+        ] {
+            let merged = PageProperties::merge(content, &properties());
+            assert_eq!(PageProperties::strip(&merged, &keys), content);
+        }
+    }
+
+    #[test]
+    fn test_render_value_placeholders() {
+        let value = PageProperties::render_value("{date} from {source}", "2026-02-07", "/a.md");
+        assert_eq!(value, "2026-02-07 from /a.md");
+    }
+}
@@ -0,0 +1,131 @@
+//! Shared path expansion helpers used by configuration and discovery
+
+use std::env;
+use std::path::PathBuf;
+
+/// Expand environment variables and a leading home reference in a path
+///
+/// Environment variables are expanded first, then a bare `~` or a `~/`
+/// prefix is replaced with the home directory. `~user` style paths are left
+/// unchanged, as is everything else when the home directory is unknown.
+pub fn expand(path: &str) -> PathBuf {
+    let path = expand_env_vars(path);
+    expand_home(&path).unwrap_or_else(|| PathBuf::from(path))
+}
+
+/// Check whether a path starts with a reference to the current user's home
+/// (`~` on its own or followed by a separator)
+pub fn is_home_reference(path: &str) -> bool {
+    home_relative(path).is_some()
+}
+
+/// Expand a leading home reference, returning `None` if the path has none
+/// or the home directory cannot be determined
+pub fn expand_home(path: &str) -> Option<PathBuf> {
+    let relative = home_relative(path)?;
+    home_dir().map(|home| join_components(home, relative))
+}
+
+/// Get the part of a path after its home reference
+fn home_relative(path: &str) -> Option<&str> {
+    let rest = path.strip_prefix('~')?;
+    if rest.is_empty() || rest.starts_with(std::path::is_separator) {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+/// Get the current user's home directory
+///
+/// On Windows `%USERPROFILE%` is preferred when it is set.
+pub fn home_dir() -> Option<PathBuf> {
+    if cfg!(windows)
+        && let Some(profile) = env::var_os("USERPROFILE").filter(|p| !p.is_empty())
+    {
+        return Some(PathBuf::from(profile));
+    }
+    dirs::home_dir()
+}
+
+/// Join a `/` (or platform separator) delimited relative path onto `base`
+/// so the result uses the native separator throughout
+pub fn join_components(base: PathBuf, relative: &str) -> PathBuf {
+    relative
+        .split(std::path::is_separator)
+        .filter(|component| !component.is_empty())
+        .fold(base, |path, component| path.join(component))
+}
+
+/// Replace `$VAR` and `${VAR}` references with their environment values
+pub fn expand_env_vars(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut rest = path;
+
+    while let Some(dollar) = rest.find('$') {
+        result.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+
+        let (name, reference_len) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        let reference = &rest[dollar..dollar + 1 + reference_len];
+        match env::var(name) {
+            Ok(value) if !name.is_empty() => result.push_str(&value),
+            _ => result.push_str(reference),
+        }
+
+        rest = &rest[dollar + 1 + reference_len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_bare_tilde() {
+        assert_eq!(expand("~"), home_dir().unwrap());
+    }
+
+    #[test]
+    fn test_expand_tilde_with_trailing_slash() {
+        assert_eq!(expand("~/"), home_dir().unwrap());
+    }
+
+    #[test]
+    fn test_expand_tilde_subdirectory() {
+        assert_eq!(expand("~/sub"), home_dir().unwrap().join("sub"));
+    }
+
+    #[test]
+    fn test_expand_leaves_user_style_paths_alone() {
+        assert_eq!(expand("~other/notes"), PathBuf::from("~other/notes"));
+        assert_eq!(expand("~Knowledge Base"), PathBuf::from("~Knowledge Base"));
+        assert!(!is_home_reference("~other"));
+    }
+
+    #[test]
+    fn test_expand_home_without_reference() {
+        assert_eq!(expand_home("/absolute/path"), None);
+        assert_eq!(expand("/absolute/path"), PathBuf::from("/absolute/path"));
+    }
+
+    #[test]
+    fn test_join_components_normalizes_separators() {
+        let joined = join_components(PathBuf::from("base"), "/a//b/");
+        assert_eq!(joined, PathBuf::from("base").join("a").join("b"));
+    }
+}
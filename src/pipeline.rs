@@ -0,0 +1,2473 @@
+//! The scan → move → journal pipeline behind `stow`, usable as a library
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+use crate::archive::{Archive, ArchiveEntry, ArchiveError};
+use crate::cleanup::CleanupError;
+use crate::clock::{Clock, FixedClock, SystemClock};
+use crate::config::{Config, ConfigError, ProcessedMarker, SourcePathAnnotation};
+use crate::content_index::{ContentIndex, Lookup};
+use crate::events::EventSink;
+use crate::file_discovery::{DiscoveredFile, FileDiscovery, FileDiscoveryError};
+use crate::file_operations::{FileOperationError, FileOperations, RunDestinations};
+use crate::filesystem::RealFs;
+use crate::hooks::Hooks;
+use crate::journal_management::{JournalEntry, JournalError, JournalManager, JournalPreview};
+use crate::ledger::{Ledger, LedgerEntry, LedgerError};
+use crate::manifest::{Manifest, ManifestEntry};
+use crate::processed::Processed;
+use crate::prompt::Prompt;
+use crate::run_summary::{EXIT_FATAL, EXIT_NO_FILES, RunSummary, SkipReason};
+use crate::url_import::{UrlImport, UrlImportError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Configuration error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("File discovery error: {0}")]
+    FileDiscovery(#[from] FileDiscoveryError),
+    #[error("File operation error: {0}")]
+    FileOperation(#[from] FileOperationError),
+    #[error("Journal error: {0}")]
+    Journal(#[from] JournalError),
+    #[error("Ledger error: {0}")]
+    Ledger(#[from] LedgerError),
+    #[error("Archive error: {0}")]
+    Archive(#[from] ArchiveError),
+    #[error("Cleanup error: {0}")]
+    Cleanup(#[from] CleanupError),
+    #[error("{0}")]
+    UrlImport(#[from] UrlImportError),
+    #[error("No markdown files found in {} (fail_on_empty is set)", .0.display())]
+    NoFilesFound(PathBuf),
+    #[error(
+        "Refusing to reorganize {}: the pages directory is the Knowledge Base itself",
+        .0.display()
+    )]
+    PagesDirIsKnowledgeBase(PathBuf),
+    #[error("Conversion error: {0}")]
+    Conversion(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl AppError {
+    /// Process exit code for a command stopped by this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::NoFilesFound(_) => EXIT_NO_FILES,
+            _ => EXIT_FATAL,
+        }
+    }
+}
+
+/// Options for a single pipeline run
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Directory scanned for markdown files
+    pub source_directory: PathBuf,
+    /// List what would be moved without touching any files
+    pub dry_run: bool,
+    /// Ledger that records moves for `undo` (recording is skipped when unset)
+    pub ledger_path: Option<PathBuf>,
+    /// Seen set used by `processed_marker: SeenSet` (ignored when unset)
+    pub seen_path: Option<PathBuf>,
+    /// Run as if it were this time instead of reading the system clock
+    pub now: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            source_directory: PathBuf::from("."),
+            dry_run: false,
+            ledger_path: None,
+            seen_path: None,
+            now: None,
+        }
+    }
+}
+
+impl RunOptions {
+    /// Clock the run reads the time from
+    fn clock(&self) -> Box<dyn Clock> {
+        match self.now {
+            Some(now) => Box::new(FixedClock(now)),
+            None => Box::new(SystemClock),
+        }
+    }
+}
+
+/// Discover markdown files, move them into the Knowledge Base and journal them
+///
+/// Progress is reported to `events`; pass [`crate::events::StdoutSink`] for
+/// the CLI output or [`crate::events::SilentSink`] for none. Individual move
+/// failures are reported as events and counted in the returned summary; only
+/// configuration and discovery problems abort the run.
+pub fn run(
+    config: &Config,
+    options: RunOptions,
+    events: &dyn EventSink,
+) -> Result<RunSummary, AppError> {
+    run_with_prompt(config, options, events, None)
+}
+
+/// Run the pipeline like [`run`], asking on `prompt` before each move
+///
+/// Every file is shown with its planned destination and the answer to
+/// `[y/N/a(ll)/q(uit)]` decides whether it moves: `a` approves it and every
+/// remaining file, `q` (or the end of input) leaves it and every remaining
+/// file in place. Declined files are skipped as [`SkipReason::Declined`].
+pub fn run_interactive(
+    config: &Config,
+    options: RunOptions,
+    events: &dyn EventSink,
+    prompt: &mut Prompt<'_>,
+) -> Result<RunSummary, AppError> {
+    run_with_prompt(config, options, events, Some(prompt))
+}
+
+fn run_with_prompt(
+    config: &Config,
+    options: RunOptions,
+    events: &dyn EventSink,
+    prompt: Option<&mut Prompt<'_>>,
+) -> Result<RunSummary, AppError> {
+    let target_directory = &options.source_directory;
+    let clock = options.clock();
+    let clock = clock.as_ref();
+    events.run_started(&config.get_knowledge_base_path(), target_directory);
+
+    // Discover markdown files in specified directory
+    let mut report =
+        FileDiscovery::discover_in_directory_report_with(target_directory, config, &RealFs, clock)?;
+    FileDiscovery::skip_knowledge_base_files(&mut report, target_directory, config);
+    let seen_path = options
+        .seen_path
+        .as_deref()
+        .filter(|_| config.processed_marker == ProcessedMarker::SeenSet);
+    if let Some(seen_path) = seen_path {
+        match Processed::load_seen(seen_path) {
+            Ok(seen) => Processed::skip_seen(&mut report, &seen),
+            Err(e) => events.seen_set_failed(&e),
+        }
+    }
+    let mut discovered = report.files;
+    FileDiscovery::sort_files(&mut discovered, config.process_order);
+    if let Some(limit) = config.limit
+        && discovered.len() > limit
+    {
+        events.limit_applied(limit, discovered.len());
+        discovered.truncate(limit);
+    }
+
+    let mut summary = RunSummary {
+        journal_disabled: !config.write_journal,
+        ..Default::default()
+    };
+    for (path, reason) in report.skipped {
+        events.skipped(&path, reason);
+        summary.record_skipped(path, reason);
+    }
+
+    if discovered.is_empty() && config.fail_on_empty {
+        return Err(AppError::NoFilesFound(target_directory.clone()));
+    }
+    events.files_discovered(target_directory, &discovered);
+    if discovered.is_empty() {
+        return Ok(summary);
+    }
+
+    if options.dry_run {
+        events.dry_run();
+        for file in &discovered {
+            events.skipped(&file.path, SkipReason::DryRun);
+            summary.record_skipped(file.path.clone(), SkipReason::DryRun);
+        }
+        if config.write_journal {
+            match preview_journal(&discovered, config, clock) {
+                Ok(Some(preview)) => events.journal_preview(&preview),
+                Ok(None) => {}
+                Err(e) => events.journal_failed(&e),
+            }
+        }
+        return Ok(summary);
+    }
+
+    if let Some(prompt) = prompt {
+        discovered = confirm_moves(discovered, config, prompt, &mut summary, events)?;
+        if discovered.is_empty() {
+            return Ok(summary);
+        }
+    }
+
+    // Move files to pages directory
+    events.moves_started(config.destination_dir_name(), discovered.len());
+    let content_index = if config.dedupe_by_content {
+        let pages_dir = config.knowledge_base_subdir(config.destination_dir_name());
+        let index = ContentIndex::build(&pages_dir, config)?;
+        events.content_indexed(index.len());
+        Some(index)
+    } else {
+        None
+    };
+
+    let run_started = clock.now().to_rfc3339();
+    let mut moved_files = Vec::new();
+    let mut ledger_entries = Vec::new();
+    // Size of the file behind each ledger entry, for a rollback
+    let mut moved_sizes = Vec::new();
+    // Destination and source of every moved file to journal, with its ledger index
+    let mut journaled = Vec::new();
+    let mut journaled_ledger_indices = Vec::new();
+
+    // Hash sources for the seen set while they are still in place
+    let source_hashes: Vec<Option<String>> = discovered
+        .iter()
+        .map(|file| seen_path.and_then(|_| FileOperations::content_hash(&file.path).ok()))
+        .collect();
+    let mut seen_entries = Vec::new();
+
+    let destinations = RunDestinations::default();
+    let results = move_files(
+        &discovered,
+        config,
+        content_index.as_ref(),
+        &destinations,
+        clock,
+        events,
+    );
+    for ((file, result), hash) in discovered.iter().zip(results).zip(source_hashes) {
+        let file_path = &file.path;
+        if let (Ok(_), Some(hash)) = (&result, hash) {
+            seen_entries.push((hash, Processed::absolute(file_path)));
+        }
+        match result {
+            Ok(MoveOutcome::Duplicate {
+                existing,
+                source_removed,
+            }) => {
+                events.duplicate_skipped(file_path, &existing, source_removed);
+                events.skipped(file_path, SkipReason::Duplicate);
+                summary.record_skipped(file_path.clone(), SkipReason::Duplicate);
+                if !source_removed
+                    && let Err(e) =
+                        Processed::mark_left_in_place(file_path, config.processed_marker)
+                {
+                    events.processed_mark_failed(file_path, &e);
+                }
+            }
+            Ok(MoveOutcome::Moved(destination)) => {
+                events.moved(file_path, &destination);
+                let source = std::path::absolute(file_path).unwrap_or_else(|_| file_path.clone());
+                let mut ledger_entry = LedgerEntry::new(
+                    &run_started,
+                    &source,
+                    &destination,
+                    &clock.now().to_rfc3339(),
+                );
+                ledger_entry.created_dirs = destinations.created_ancestors(&destination);
+                ledger_entries.push(ledger_entry);
+                moved_sizes.push(file.size);
+                summary.record_moved(source.clone(), destination.clone(), file.size);
+                if config
+                    .route_for(file_path)
+                    .is_none_or(|route| route.journal)
+                {
+                    journaled.push((destination.clone(), source));
+                    journaled_ledger_indices.push(ledger_entries.len() - 1);
+                }
+                moved_files.push(destination);
+            }
+            Err(e) => {
+                events.move_failed(file_path, &e);
+                summary.record_failed();
+            }
+        }
+    }
+
+    events.moves_finished(moved_files.len(), config.destination_dir_name());
+    if moved_files.is_empty() {
+        record_seen(seen_path, &seen_entries, &summary, events);
+        return Ok(summary);
+    }
+
+    // Add journal entries for moved files, except those routed without journaling
+    match write_journal(config, &journaled, events, clock) {
+        Ok(Some(written)) => {
+            for (index, journal_entry) in journaled_ledger_indices.iter().zip(&written.entries) {
+                let ledger_entry = &mut ledger_entries[*index];
+                ledger_entry.journal_path = Some(written.path.clone());
+                ledger_entry.journal_line = Some(journal_entry.format());
+                if let Some((heading, existed)) = &written.heading {
+                    ledger_entry.journal_heading = Some(heading.clone());
+                    ledger_entry.journal_heading_created = !existed;
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(_) if config.transactional => {
+            let moves: Vec<_> = ledger_entries.into_iter().zip(moved_sizes).collect();
+            roll_back(&moves, Rollback::MoveBack, &mut summary, events);
+            record_seen(seen_path, &seen_entries, &summary, events);
+
+            // Pages that could not be put back stay recorded, so `undo` can still reach them
+            let left_in_place: Vec<LedgerEntry> = moves
+                .into_iter()
+                .filter(|(entry, _)| {
+                    summary
+                        .rollback_failures
+                        .iter()
+                        .any(|(path, _)| *path == entry.to)
+                })
+                .map(|(entry, _)| LedgerEntry {
+                    rollback_failed: true,
+                    ..entry
+                })
+                .collect();
+            if let Some(ledger_path) = &options.ledger_path
+                && !left_in_place.is_empty()
+                && let Err(e) = Ledger::append(ledger_path, &left_in_place)
+            {
+                events.ledger_failed(&e);
+            }
+            return Ok(summary);
+        }
+        Err(e) => summary.record_journal_failed(e.to_string()),
+    }
+    record_seen(seen_path, &seen_entries, &summary, events);
+
+    if config.rewrite_links {
+        rewrite_links(config, &summary.moved_files, events, clock);
+    }
+
+    if config.write_manifest {
+        record_manifest(config, &summary.moved_files, events, clock);
+    }
+
+    if let Some(hook) = &config.per_file_hook {
+        run_file_hooks(hook, config, &mut summary, events);
+    }
+
+    // Record the run so it can be undone
+    if let Some(ledger_path) = &options.ledger_path
+        && let Err(e) = Ledger::append(ledger_path, &ledger_entries)
+    {
+        events.ledger_failed(&e);
+    }
+
+    Ok(summary)
+}
+
+/// Import the markdown entries of a zip archive into the Knowledge Base and journal them
+///
+/// Works like [`run`], except that pages are written straight from the
+/// archive's content: nothing is extracted next to the archive, and the
+/// archive itself is left untouched. Entries go through the same filters
+/// as discovered files, with the ignore file read from the archive's
+/// directory. Such pages are not recorded in the ledger, since `undo` would
+/// have no source file to move them back to.
+pub fn run_archive(
+    config: &Config,
+    archive: &Path,
+    dry_run: bool,
+    clock: &dyn Clock,
+    events: &dyn EventSink,
+) -> Result<RunSummary, AppError> {
+    events.run_started(&config.get_knowledge_base_path(), archive);
+
+    let report = Archive::read_file(archive, config)?;
+    let mut skipped: Vec<(PathBuf, SkipReason)> = report
+        .skipped
+        .into_iter()
+        .map(|(path, reason)| (archive.join(path), reason))
+        .collect();
+    let skip_reason =
+        FileDiscovery::entry_filter(archive.parent().unwrap_or(Path::new(".")), config, clock)?;
+    let mut entries: Vec<(DiscoveredFile, ArchiveEntry)> = Vec::new();
+    for entry in report.entries {
+        let file = DiscoveredFile {
+            path: archive.join(&entry.path),
+            size: entry.content.len() as u64,
+            modified: entry.modified,
+        };
+        match skip_reason(&file) {
+            Some(reason) => skipped.push((file.path, reason)),
+            None => entries.push((file, entry)),
+        }
+    }
+    entries.sort_by(|(a, _), (b, _)| FileDiscovery::compare_files(a, b, config.process_order));
+    if let Some(limit) = config.limit
+        && entries.len() > limit
+    {
+        events.limit_applied(limit, entries.len());
+        entries.truncate(limit);
+    }
+
+    let mut summary = RunSummary {
+        journal_disabled: !config.write_journal,
+        ..Default::default()
+    };
+    for (path, reason) in skipped {
+        events.skipped(&path, reason);
+        summary.record_skipped(path, reason);
+    }
+
+    let discovered: Vec<DiscoveredFile> = entries.iter().map(|(file, _)| file.clone()).collect();
+    if discovered.is_empty() && config.fail_on_empty {
+        return Err(AppError::NoFilesFound(archive.to_path_buf()));
+    }
+    events.files_discovered(archive, &discovered);
+    if discovered.is_empty() {
+        return Ok(summary);
+    }
+
+    if dry_run {
+        events.dry_run();
+        for file in &discovered {
+            events.skipped(&file.path, SkipReason::DryRun);
+            summary.record_skipped(file.path.clone(), SkipReason::DryRun);
+        }
+        return Ok(summary);
+    }
+
+    events.moves_started(config.destination_dir_name(), entries.len());
+    let destinations = RunDestinations::default();
+    let mut journaled = Vec::new();
+    let mut written = Vec::new();
+    for (file, entry) in &entries {
+        events.about_to_move(&file.path);
+        let result =
+            FileOperations::find_identical_entry(archive, entry, config).and_then(|existing| {
+                match existing {
+                    Some(existing) => Ok(MoveOutcome::Duplicate {
+                        existing,
+                        source_removed: false,
+                    }),
+                    None => MoveOutcome::from_move(FileOperations::import_archive_entry(
+                        archive,
+                        entry,
+                        config,
+                        &destinations,
+                        clock,
+                    )),
+                }
+            });
+        events.file_processed(&file.path);
+
+        match result {
+            Ok(MoveOutcome::Duplicate { existing, .. }) => {
+                events.duplicate_skipped(&file.path, &existing, false);
+                events.skipped(&file.path, SkipReason::Duplicate);
+                summary.record_skipped(file.path.clone(), SkipReason::Duplicate);
+            }
+            Ok(MoveOutcome::Moved(destination)) => {
+                events.moved(&file.path, &destination);
+                summary.record_moved(file.path.clone(), destination.clone(), file.size);
+                written.push((
+                    LedgerEntry::new("", &file.path, &destination, ""),
+                    file.size,
+                ));
+                if config
+                    .route_for(&file.path)
+                    .is_none_or(|route| route.journal)
+                {
+                    journaled.push((destination, file.path.clone()));
+                }
+            }
+            Err(e) => {
+                events.move_failed(&file.path, &e);
+                summary.record_failed();
+            }
+        }
+    }
+
+    events.moves_finished(summary.moved, config.destination_dir_name());
+    if summary.moved > 0 {
+        if let Err(e) = write_journal(config, &journaled, events, clock) {
+            if config.transactional {
+                roll_back(&written, Rollback::Delete, &mut summary, events);
+                return Ok(summary);
+            }
+            summary.record_journal_failed(e.to_string());
+        }
+        if config.rewrite_links {
+            rewrite_links(config, &summary.moved_files, events, clock);
+        }
+        if config.write_manifest {
+            record_manifest(config, &summary.moved_files, events, clock);
+        }
+        if let Some(hook) = &config.per_file_hook {
+            run_file_hooks(hook, config, &mut summary, events);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Fetch a markdown page from `url` and import it into the Knowledge Base
+///
+/// The page is named `name`, or after the URL when unset, and is then
+/// handled like a moved file of that name: it gets a free name next to
+/// existing pages, is skipped when an identical page is already there and
+/// is journaled. Page properties and journal entries record the URL as the
+/// source. Nothing is recorded in the ledger, as `undo` would have no source
+/// file to move the page back to.
+pub fn run_url(
+    config: &Config,
+    url: &str,
+    name: Option<&str>,
+    dry_run: bool,
+    clock: &dyn Clock,
+    events: &dyn EventSink,
+) -> Result<RunSummary, AppError> {
+    let source = PathBuf::from(url);
+    events.run_started(&config.get_knowledge_base_path(), &source);
+
+    let page = UrlImport::fetch(url, name, config)?;
+    let file = DiscoveredFile {
+        path: source.clone(),
+        size: page.content.len() as u64,
+        modified: clock.now().into(),
+    };
+    events.files_discovered(&source, std::slice::from_ref(&file));
+
+    let mut summary = RunSummary {
+        journal_disabled: !config.write_journal,
+        ..Default::default()
+    };
+    if dry_run {
+        events.dry_run();
+        events.skipped(&source, SkipReason::DryRun);
+        summary.record_skipped(source, SkipReason::DryRun);
+        return Ok(summary);
+    }
+
+    events.moves_started(config.destination_dir_name(), 1);
+    events.about_to_move(&source);
+    let result = FileOperations::find_identical_page(&page.name, &page.content, config, clock)
+        .and_then(|existing| match existing {
+            Some(existing) => Ok(MoveOutcome::Duplicate {
+                existing,
+                source_removed: false,
+            }),
+            None => MoveOutcome::from_move(FileOperations::import_page(
+                url,
+                &page.name,
+                &page.content,
+                config,
+                &RunDestinations::default(),
+                clock,
+            )),
+        });
+    events.file_processed(&source);
+
+    let mut journaled = Vec::new();
+    let mut written = Vec::new();
+    match result {
+        Ok(MoveOutcome::Duplicate { existing, .. }) => {
+            events.duplicate_skipped(&source, &existing, false);
+            events.skipped(&source, SkipReason::Duplicate);
+            summary.record_skipped(source, SkipReason::Duplicate);
+        }
+        Ok(MoveOutcome::Moved(destination)) => {
+            events.moved(&source, &destination);
+            summary.record_moved(source.clone(), destination.clone(), file.size);
+            written.push((LedgerEntry::new("", &source, &destination, ""), file.size));
+            if config
+                .route_for(Path::new(&page.name))
+                .is_none_or(|route| route.journal)
+            {
+                journaled.push((destination, source));
+            }
+        }
+        Err(e) => {
+            events.move_failed(&source, &e);
+            summary.record_failed();
+        }
+    }
+
+    events.moves_finished(summary.moved, config.destination_dir_name());
+    if summary.moved > 0 {
+        if let Err(e) = write_journal(config, &journaled, events, clock) {
+            if config.transactional {
+                roll_back(&written, Rollback::Delete, &mut summary, events);
+                return Ok(summary);
+            }
+            summary.record_journal_failed(e.to_string());
+        }
+        if config.write_manifest {
+            record_manifest(config, &summary.moved_files, events, clock);
+        }
+        if let Some(hook) = &config.per_file_hook {
+            run_file_hooks(hook, config, &mut summary, events);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Reorganize the pages already in the Knowledge Base by the current rules
+///
+/// The pages directory is scanned in place of a source directory and every
+/// page is moved to where the naming and organization rules would put it
+/// today, e.g. into its dated subfolder after `organize_by_date` was turned
+/// on. Pages already there, and pages in a folder named by
+/// `date_subdir_pattern`, are skipped as [`SkipReason::InPlace`]. Nested
+/// pages keep their folder inside the pages directory. The journals and
+/// assets directories and routing destinations inside the pages directory
+/// are left out. Links to moved pages are then updated in every journal.
+///
+/// Refuses to run when the pages directory is the Knowledge Base itself,
+/// since the whole vault would be re-filed.
+///
+/// Only the page's location changes: page properties, source paths,
+/// `filename_prefix`/`filename_suffix` and `destination_subdir` are left
+/// out, and no journal entries, manifest lines, hooks or ledger entries are
+/// written.
+pub fn run_reorganize(
+    config: &Config,
+    dry_run: bool,
+    clock: &dyn Clock,
+    events: &dyn EventSink,
+) -> Result<RunSummary, AppError> {
+    let pages_dir = config.knowledge_base_subdir(&config.pages_dir_name);
+    if pages_dir == config.expanded_knowledge_base_path() {
+        return Err(AppError::PagesDirIsKnowledgeBase(pages_dir));
+    }
+    let config = &Config {
+        destination_subdir: None,
+        add_page_properties: false,
+        record_source_path: SourcePathAnnotation::Off,
+        filename_prefix: String::new(),
+        filename_suffix: String::new(),
+        dedupe_by_content: false,
+        ..config.clone()
+    };
+    events.run_started(&config.get_knowledge_base_path(), &pages_dir);
+
+    let report =
+        FileDiscovery::discover_in_directory_report_with(&pages_dir, config, &RealFs, clock)?;
+    let mut summary = RunSummary {
+        journal_disabled: !config.write_journal,
+        ..Default::default()
+    };
+    for (path, reason) in report.skipped {
+        events.skipped(&path, reason);
+        summary.record_skipped(path, reason);
+    }
+
+    let excluded: Vec<PathBuf> = [&config.journals_dir_name, &config.assets_dir_name]
+        .into_iter()
+        .chain(config.routing.iter().map(|route| &route.destination))
+        .map(|name| config.knowledge_base_subdir(name))
+        .filter(|dir| dir.starts_with(&pages_dir) && *dir != pages_dir)
+        .collect();
+
+    // Folder of a page inside pages, and the destination that keeps it there
+    let relative_dir = |path: &Path| -> PathBuf {
+        path.parent()
+            .and_then(|parent| parent.strip_prefix(&pages_dir).ok())
+            .unwrap_or(Path::new(""))
+            .to_path_buf()
+    };
+    let subdir_for = |path: &Path| -> Option<String> {
+        let relative = relative_dir(path);
+        (!relative.as_os_str().is_empty()).then(|| {
+            Path::new(&config.pages_dir_name)
+                .join(relative)
+                .to_string_lossy()
+                .into_owned()
+        })
+    };
+
+    let mut discovered = Vec::new();
+    for file in report.files {
+        if excluded.iter().any(|dir| file.path.starts_with(dir)) {
+            continue;
+        }
+        if is_dated_directory(&relative_dir(&file.path), &config.date_subdir_pattern) {
+            events.skipped(&file.path, SkipReason::InPlace);
+            summary.record_skipped(file.path, SkipReason::InPlace);
+            continue;
+        }
+        let file_config = Config {
+            destination_subdir: subdir_for(&file.path),
+            ..config.clone()
+        };
+        match FileOperations::planned_destination(&file.path, &file_config) {
+            Ok(destination) if destination == file.path => {
+                events.skipped(&file.path, SkipReason::InPlace);
+                summary.record_skipped(file.path, SkipReason::InPlace);
+            }
+            _ => discovered.push(file),
+        }
+    }
+    FileDiscovery::sort_files(&mut discovered, config.process_order);
+    if let Some(limit) = config.limit
+        && discovered.len() > limit
+    {
+        events.limit_applied(limit, discovered.len());
+        discovered.truncate(limit);
+    }
+
+    events.files_discovered(&pages_dir, &discovered);
+    if discovered.is_empty() {
+        return Ok(summary);
+    }
+
+    if dry_run {
+        events.dry_run();
+        for file in &discovered {
+            events.skipped(&file.path, SkipReason::DryRun);
+            summary.record_skipped(file.path.clone(), SkipReason::DryRun);
+        }
+        return Ok(summary);
+    }
+
+    // Pages are moved folder by folder, each keeping its place under pages
+    let mut groups: Vec<(Option<String>, Vec<DiscoveredFile>)> = Vec::new();
+    for file in discovered {
+        let subdir = subdir_for(&file.path);
+        match groups.iter_mut().find(|(existing, _)| *existing == subdir) {
+            Some((_, files)) => files.push(file),
+            None => groups.push((subdir, vec![file])),
+        }
+    }
+
+    events.moves_started(
+        &config.pages_dir_name,
+        groups.iter().map(|(_, files)| files.len()).sum(),
+    );
+    let destinations = RunDestinations::default();
+    let moves = groups.iter().flat_map(|(subdir, files)| {
+        let group_config = Config {
+            destination_subdir: subdir.clone(),
+            ..config.clone()
+        };
+        files.iter().zip(move_files(
+            files,
+            &group_config,
+            None,
+            &destinations,
+            clock,
+            events,
+        ))
+    });
+    for (file, result) in moves {
+        match result {
+            Ok(MoveOutcome::Duplicate { existing, .. }) => {
+                events.duplicate_skipped(&file.path, &existing, false);
+                events.skipped(&file.path, SkipReason::Duplicate);
+                summary.record_skipped(file.path.clone(), SkipReason::Duplicate);
+            }
+            Ok(MoveOutcome::Moved(destination)) => {
+                events.moved(&file.path, &destination);
+                summary.record_moved(file.path.clone(), destination, file.size);
+            }
+            Err(e) => {
+                events.move_failed(&file.path, &e);
+                summary.record_failed();
+            }
+        }
+    }
+
+    events.moves_finished(summary.moved, &config.pages_dir_name);
+    if summary.moved > 0 {
+        match JournalManager::update_links(&summary.moved_files, config) {
+            Ok(journals) if journals.is_empty() => {}
+            Ok(journals) => events.journal_links_updated(journals.len()),
+            Err(e) => events.journal_links_failed(&e),
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Whether `relative`, a folder inside the pages directory, is or lies in
+/// a dated subfolder written by `organize_by_date` with `pattern`
+fn is_dated_directory(relative: &Path, pattern: &str) -> bool {
+    let depth = Path::new(pattern).components().count();
+    let components: Vec<_> = relative.components().collect();
+    depth > 0
+        && components.windows(depth).any(|window| {
+            let name: PathBuf = window.iter().collect();
+            let mut parsed = chrono::format::Parsed::new();
+            chrono::format::parse(
+                &mut parsed,
+                &name.to_string_lossy(),
+                chrono::format::StrftimeItems::new(pattern),
+            )
+            .is_ok()
+        })
+}
+
+/// Answer to the confirmation asked for a file in an interactive run
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Confirmation {
+    Yes,
+    No,
+    All,
+    Quit,
+}
+
+impl Confirmation {
+    /// Parse an answer; an empty answer means no, anything unknown is `None`
+    fn parse(answer: &str) -> Option<Self> {
+        match answer.to_lowercase().as_str() {
+            "y" | "yes" => Some(Confirmation::Yes),
+            "" | "n" | "no" => Some(Confirmation::No),
+            "a" | "all" => Some(Confirmation::All),
+            "q" | "quit" => Some(Confirmation::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Ask on `prompt` whether each file should be moved, keeping the approved ones
+///
+/// Declined files are recorded in `summary` and reported to `events`.
+fn confirm_moves(
+    files: Vec<DiscoveredFile>,
+    config: &Config,
+    prompt: &mut Prompt<'_>,
+    summary: &mut RunSummary,
+    events: &dyn EventSink,
+) -> Result<Vec<DiscoveredFile>, AppError> {
+    let mut approved = Vec::with_capacity(files.len());
+    let mut answer = Confirmation::No;
+
+    for file in files {
+        if !matches!(answer, Confirmation::All | Confirmation::Quit) {
+            let destination = FileOperations::planned_destination(&file.path, config)
+                .map(|destination| destination.display().to_string())
+                .unwrap_or_else(|e| format!("(unknown: {})", e));
+            prompt.say(&format!("\n{}\n  → {}", file.path.display(), destination))?;
+            answer = loop {
+                match prompt.ask("Move this file? [y/N/a(ll)/q(uit)]", None) {
+                    Ok(reply) => match Confirmation::parse(&reply) {
+                        Some(confirmation) => break confirmation,
+                        None => prompt.say("Please answer y, n, a or q.")?,
+                    },
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                        break Confirmation::Quit;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            };
+        }
+
+        if matches!(answer, Confirmation::Yes | Confirmation::All) {
+            approved.push(file);
+        } else {
+            events.skipped(&file.path, SkipReason::Declined);
+            summary.record_skipped(file.path, SkipReason::Declined);
+        }
+    }
+
+    Ok(approved)
+}
+
+/// Journal written for a run's moved files
+struct WrittenJournal {
+    path: PathBuf,
+    /// Entries written for the files, in the order the files were given
+    entries: Vec<JournalEntry>,
+    /// Day heading the entries went under in a single-file journal, and
+    /// whether the run added it
+    heading: Option<(String, bool)>,
+}
+
+/// Journal moved files given as `(destination, source)` pairs
+///
+/// Writes to the journal of the day `clock` reads; failures are also
+/// reported to `events`.
+fn write_journal(
+    config: &Config,
+    journaled: &[(PathBuf, PathBuf)],
+    events: &dyn EventSink,
+    clock: &dyn Clock,
+) -> Result<Option<WrittenJournal>, JournalError> {
+    if !config.write_journal {
+        events.journal_disabled();
+        return Ok(None);
+    }
+    if journaled.is_empty() {
+        return Ok(None);
+    }
+
+    events.journal_started();
+    // One reading of the clock, so the heading is the day the entries go under
+    let clock = &FixedClock(clock.now());
+    let destinations: Vec<PathBuf> = journaled.iter().map(|(to, _)| to.clone()).collect();
+    let result = JournalManager::create_entries_with(&destinations, config, clock).and_then(
+        |mut entries| {
+            if config.record_source_path == SourcePathAnnotation::Journal {
+                entries = entries
+                    .into_iter()
+                    .zip(journaled)
+                    .map(|(entry, (_, source))| entry.with_source(source))
+                    .collect();
+            }
+            let heading = JournalManager::today_heading(config, clock)?;
+            let path = JournalManager::write_entries_with(&entries, config, &RealFs, clock)?;
+            Ok(WrittenJournal {
+                path,
+                entries,
+                heading,
+            })
+        },
+    );
+    match result {
+        Ok(written) => {
+            events.journal_written(&written.path, written.entries.len());
+            Ok(Some(written))
+        }
+        Err(e) => {
+            events.journal_failed(&e);
+            Err(e)
+        }
+    }
+}
+
+/// How [`roll_back`] undoes an import
+enum Rollback {
+    /// Move the file back to its source
+    MoveBack,
+    /// Delete the page, which was written from content that is still at its source
+    Delete,
+}
+
+/// Undo the imports of a `transactional` run whose journal could not be written
+///
+/// `moves` holds the ledger entry and size of every imported file; the
+/// newest import is undone first. Files that cannot be put back are
+/// reported and left where they are.
+fn roll_back(
+    moves: &[(LedgerEntry, u64)],
+    rollback: Rollback,
+    summary: &mut RunSummary,
+    events: &dyn EventSink,
+) {
+    for (entry, size) in moves.iter().rev() {
+        let result = match rollback {
+            Rollback::MoveBack => Ledger::restore(entry),
+            Rollback::Delete => std::fs::remove_file(&entry.to).map_err(|e| e.to_string()),
+        };
+        match result {
+            Ok(()) => {
+                events.rolled_back(&entry.from, &entry.to);
+                summary.record_rolled_back(entry.from.clone(), entry.to.clone(), *size);
+            }
+            Err(error) => {
+                events.rollback_failed(&entry.to, &error);
+                summary.record_rollback_failed(entry.to.clone(), error);
+            }
+        }
+    }
+}
+
+/// Add the handled files to the seen set, except the ones rolled back
+fn record_seen(
+    seen_path: Option<&Path>,
+    entries: &[(String, PathBuf)],
+    summary: &RunSummary,
+    events: &dyn EventSink,
+) {
+    let Some(seen_path) = seen_path else {
+        return;
+    };
+    let entries: Vec<_> = entries
+        .iter()
+        .filter(|(_, path)| !summary.rolled_back.iter().any(|(from, _)| from == path))
+        .cloned()
+        .collect();
+    if let Err(e) = Processed::record_seen(seen_path, &entries) {
+        events.seen_set_failed(&e);
+    }
+}
+
+/// Point links to the original names of `imported` `(source, destination)`
+/// pairs in recent journals at their pages, reporting the outcome to `events`
+fn rewrite_links(
+    config: &Config,
+    imported: &[(PathBuf, PathBuf)],
+    events: &dyn EventSink,
+    clock: &dyn Clock,
+) {
+    match JournalManager::update_recent_links(imported, config, clock) {
+        Ok(journals) if journals.is_empty() => {}
+        Ok(journals) => events.journal_links_updated(journals.len()),
+        Err(e) => events.journal_links_failed(&e),
+    }
+}
+
+/// Append the destinations of `moved` `(source, destination)` pairs to the
+/// checksum manifest, reporting a failure to `events`
+fn record_manifest(
+    config: &Config,
+    moved: &[(PathBuf, PathBuf)],
+    events: &dyn EventSink,
+    clock: &dyn Clock,
+) {
+    let imported_at = clock.now().to_rfc3339();
+    let result = moved
+        .iter()
+        .map(|(_, destination)| ManifestEntry::new(destination, &imported_at))
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|entries| Manifest::append(&Manifest::path(config), &entries));
+    if let Err(e) = result {
+        events.manifest_failed(&e);
+    }
+}
+
+/// Run `hook` for every moved file in `summary`, recording the failures there
+fn run_file_hooks(hook: &str, config: &Config, summary: &mut RunSummary, events: &dyn EventSink) {
+    let moved = summary.moved_files.clone();
+    let results = Hooks::run_per_file_all(hook, &moved, config.parallel_moves);
+    for ((_, destination), result) in moved.into_iter().zip(results) {
+        if let Err(e) = result {
+            events.hook_failed(&destination, &e);
+            summary.record_hook_failed(destination, e.to_string());
+        }
+    }
+}
+
+/// Preview how today's journal would change if `files` were moved
+///
+/// Returns `None` when no entry would be added. Files routed without
+/// journaling and files whose destination cannot be predicted are left out.
+fn preview_journal(
+    files: &[DiscoveredFile],
+    config: &Config,
+    clock: &dyn Clock,
+) -> Result<Option<JournalPreview>, JournalError> {
+    let (destinations, sources): (Vec<PathBuf>, Vec<&PathBuf>) = files
+        .iter()
+        .filter(|file| {
+            config
+                .route_for(&file.path)
+                .is_none_or(|route| route.journal)
+        })
+        .filter_map(|file| {
+            FileOperations::planned_destination(&file.path, config)
+                .ok()
+                .map(|destination| (destination, &file.path))
+        })
+        .unzip();
+    if destinations.is_empty() {
+        return Ok(None);
+    }
+
+    let mut entries = JournalManager::create_entries_with(&destinations, config, clock)?;
+    if config.record_source_path == SourcePathAnnotation::Journal {
+        entries = entries
+            .into_iter()
+            .zip(sources)
+            .map(|(entry, source)| {
+                entry.with_source(&std::path::absolute(source).unwrap_or_else(|_| source.clone()))
+            })
+            .collect();
+    }
+
+    let preview = JournalManager::preview_entries_with(&entries, config, clock)?;
+    Ok(preview.has_changes().then_some(preview))
+}
+
+/// What happened to a single file handed to a move worker
+enum MoveOutcome {
+    /// The file was moved to this destination
+    Moved(PathBuf),
+    /// The file was not imported because `existing` already has its content
+    Duplicate {
+        existing: PathBuf,
+        source_removed: bool,
+    },
+}
+
+impl MoveOutcome {
+    /// Outcome of a move, counting a destination found to hold the same
+    /// content as a duplicate rather than a failure
+    fn from_move(result: Result<PathBuf, FileOperationError>) -> Result<Self, FileOperationError> {
+        match result {
+            Ok(destination) => Ok(MoveOutcome::Moved(destination)),
+            Err(FileOperationError::DuplicateContent(existing)) => Ok(MoveOutcome::Duplicate {
+                existing,
+                source_removed: false,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Move files on up to `parallel_moves` threads
+///
+/// Results are returned in the same order as `files` so journal entries and
+/// output stay deterministic regardless of which move finishes first. All
+/// workers share one [`RunDestinations`], so two files never resolve to the
+/// same destination within the run.
+fn move_files(
+    files: &[DiscoveredFile],
+    config: &Config,
+    content_index: Option<&ContentIndex>,
+    destinations: &RunDestinations,
+    clock: &dyn Clock,
+    events: &dyn EventSink,
+) -> Vec<Result<MoveOutcome, FileOperationError>> {
+    let workers = config.parallel_moves.clamp(1, files.len().max(1));
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(file) = files.get(index) else {
+                        break;
+                    };
+                    events.about_to_move(&file.path);
+                    let result = move_file(&file.path, config, content_index, destinations, clock);
+                    events.file_processed(&file.path);
+                    let _ = sender.send((index, result));
+                }
+            });
+        }
+    });
+    drop(sender);
+
+    let mut results: Vec<_> = receiver.into_iter().collect();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Move a single file unless an identical page already exists
+///
+/// With a `content_index` any page with the same content counts, including
+/// pages imported earlier in the run, and the source is deleted when
+/// `dedupe_delete_source` is set. Without one only the names the move would
+/// use are checked and the source is kept.
+fn move_file(
+    path: &Path,
+    config: &Config,
+    content_index: Option<&ContentIndex>,
+    destinations: &RunDestinations,
+    clock: &dyn Clock,
+) -> Result<MoveOutcome, FileOperationError> {
+    let Some(index) = content_index else {
+        return move_new_file(path, config, destinations, clock);
+    };
+    let hash = match index.reserve(path, config)? {
+        Lookup::Existing(existing) => {
+            if config.dedupe_delete_source {
+                std::fs::remove_file(path)?;
+            }
+            return Ok(MoveOutcome::Duplicate {
+                existing,
+                source_removed: config.dedupe_delete_source,
+            });
+        }
+        Lookup::Reserved(hash) => hash,
+        Lookup::Unindexed => return move_new_file(path, config, destinations, clock),
+    };
+
+    let result = move_new_file(path, config, destinations, clock);
+    let page = match &result {
+        Ok(MoveOutcome::Moved(page) | MoveOutcome::Duplicate { existing: page, .. }) => {
+            Some(page.as_path())
+        }
+        Err(_) => None,
+    };
+    index.finish(&hash, page);
+    result
+}
+
+/// Move a file that no indexed page holds, unless a page under one of the
+/// names the move would use already has its content
+///
+/// A page found to hold the content while the name is resolved, which is
+/// only checked with `dedupe_by_content`, is handled like an indexed one:
+/// the source is deleted when `dedupe_delete_source` is set.
+fn move_new_file(
+    path: &Path,
+    config: &Config,
+    destinations: &RunDestinations,
+    clock: &dyn Clock,
+) -> Result<MoveOutcome, FileOperationError> {
+    if let Some(existing) = FileOperations::find_identical(path, config)? {
+        return Ok(MoveOutcome::Duplicate {
+            existing,
+            source_removed: false,
+        });
+    }
+
+    match MoveOutcome::from_move(FileOperations::move_to_pages_in_run(
+        path,
+        config,
+        destinations,
+        clock,
+    ))? {
+        MoveOutcome::Duplicate { existing, .. } if config.dedupe_delete_source => {
+            std::fs::remove_file(path)?;
+            Ok(MoveOutcome::Duplicate {
+                existing,
+                source_removed: true,
+            })
+        }
+        outcome => Ok(outcome),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RouteRule;
+    use std::fs;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    /// Sink that records a short description of every event
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl RecordingSink {
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn name(path: &Path) -> String {
+            path.file_name().unwrap().to_string_lossy().into_owned()
+        }
+    }
+
+    impl EventSink for RecordingSink {
+        fn run_started(&self, _knowledge_base: &str, _source: &Path) {
+            self.record("run_started".to_string());
+        }
+
+        fn files_discovered(&self, _source: &Path, files: &[DiscoveredFile]) {
+            self.record(format!("files_discovered {}", files.len()));
+        }
+
+        fn skipped(&self, path: &Path, reason: SkipReason) {
+            self.record(format!("skipped {} ({:?})", Self::name(path), reason));
+        }
+
+        fn moves_started(&self, destination: &str, total: usize) {
+            self.record(format!("moves_started {} {}", destination, total));
+        }
+
+        fn about_to_move(&self, path: &Path) {
+            self.record(format!("about_to_move {}", Self::name(path)));
+        }
+
+        fn file_processed(&self, path: &Path) {
+            self.record(format!("file_processed {}", Self::name(path)));
+        }
+
+        fn moved(&self, from: &Path, to: &Path) {
+            self.record(format!("moved {} -> {}", Self::name(from), Self::name(to)));
+        }
+
+        fn moves_finished(&self, moved: usize, _destination: &str) {
+            self.record(format!("moves_finished {}", moved));
+        }
+
+        fn journal_started(&self) {
+            self.record("journal_started".to_string());
+        }
+
+        fn journal_written(&self, _journal: &Path, entries: usize) {
+            self.record(format!("journal_written {}", entries));
+        }
+
+        fn journal_failed(&self, _error: &JournalError) {
+            self.record("journal_failed".to_string());
+        }
+
+        fn rolled_back(&self, from: &Path, _to: &Path) {
+            self.record(format!("rolled_back {}", Self::name(from)));
+        }
+    }
+
+    #[test]
+    fn test_run_reports_events_in_order() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join("alpha.md"), "# Alpha").unwrap();
+        fs::write(source_dir.path().join("beta.md"), "# Beta").unwrap();
+        fs::write(source_dir.path().join("stub.md"), "").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            min_file_size_bytes: 1,
+            parallel_moves: 1,
+            ..Default::default()
+        };
+        let sink = RecordingSink::default();
+        let summary = run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ..Default::default()
+            },
+            &sink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 2);
+        assert_eq!(
+            sink.events.into_inner().unwrap(),
+            vec![
+                "run_started",
+                "skipped stub.md (TooSmall)",
+                "files_discovered 2",
+                "moves_started pages 2",
+                "about_to_move alpha.md",
+                "file_processed alpha.md",
+                "about_to_move beta.md",
+                "file_processed beta.md",
+                "moved alpha.md -> alpha.md",
+                "moved beta.md -> beta.md",
+                "moves_finished 2",
+                "journal_started",
+                "journal_written 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_skips_journal_for_routes_without_journaling() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join("article.md"), "# Article").unwrap();
+        fs::write(source_dir.path().join("sketch.excalidraw.md"), "{}").unwrap();
+        fs::write(source_dir.path().join("todo-draft.md"), "# Todo").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            routing: vec![
+                RouteRule {
+                    pattern: "excalidraw.md".to_string(),
+                    destination: "assets".to_string(),
+                    journal: false,
+                },
+                RouteRule {
+                    pattern: "*-draft.md".to_string(),
+                    destination: "drafts".to_string(),
+                    journal: true,
+                },
+            ],
+            include_timestamp: false,
+            ..Default::default()
+        };
+        let ledger_path = kb_dir.path().join("ledger.jsonl");
+        let summary = run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ledger_path: Some(ledger_path.clone()),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 3);
+        assert!(kb_dir.path().join("assets/sketch.excalidraw.md").exists());
+        assert!(kb_dir.path().join("drafts/todo-draft.md").exists());
+        assert!(kb_dir.path().join("pages/article.md").exists());
+
+        let journal = fs::read_dir(kb_dir.path().join("journals"))
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        assert_eq!(journal, "- [[article]]\n- [[todo-draft]]\n");
+
+        let ledger = Ledger::read(&ledger_path).unwrap();
+        let journal_lines: Vec<_> = ledger
+            .iter()
+            .map(|entry| entry.journal_line.as_deref())
+            .collect();
+        assert_eq!(
+            journal_lines,
+            vec![Some("- [[article]]"), None, Some("- [[todo-draft]]")]
+        );
+        let created: Vec<_> = ledger
+            .iter()
+            .map(|entry| entry.created_dirs.clone())
+            .collect();
+        assert_eq!(
+            created,
+            vec![
+                vec![kb_dir.path().join("pages")],
+                vec![kb_dir.path().join("assets")],
+                vec![kb_dir.path().join("drafts")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_undo_of_single_file_journal_run_removes_its_day_section() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let journals = kb_dir.path().join("journals");
+        fs::create_dir_all(&journals).unwrap();
+        fs::write(
+            journals.join("journal.md"),
+            "## 2020-01-01\n- [[article]]\n",
+        )
+        .unwrap();
+        fs::write(source_dir.path().join("article.md"), "# Article").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            journal_mode: crate::config::JournalMode::SingleFile,
+            include_timestamp: false,
+            ..Default::default()
+        };
+        let ledger_path = kb_dir.path().join("ledger.jsonl");
+        run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ledger_path: Some(ledger_path.clone()),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        let ledger = Ledger::read(&ledger_path).unwrap();
+        assert!(ledger[0].journal_heading.is_some());
+        assert!(ledger[0].journal_heading_created);
+
+        Ledger::undo_last_run(&ledger_path).unwrap();
+        assert_eq!(
+            fs::read_to_string(journals.join("journal.md")).unwrap(),
+            "## 2020-01-01\n- [[article]]\n"
+        );
+    }
+
+    #[test]
+    fn test_run_moves_attachments_to_assets_with_embed_entries() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join("article.md"), "# Article").unwrap();
+        fs::write(source_dir.path().join("Paper.PDF"), "%PDF-1.7").unwrap();
+        fs::write(source_dir.path().join("photo.png"), "png").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            attachment_extensions: vec!["pdf".to_string()],
+            normalize_extension: true,
+            add_page_properties: true,
+            include_timestamp: false,
+            ..Default::default()
+        };
+        let summary = run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 2);
+        assert!(kb_dir.path().join("pages/article.md").exists());
+        assert_eq!(
+            fs::read_to_string(kb_dir.path().join("assets/Paper.PDF")).unwrap(),
+            "%PDF-1.7"
+        );
+        assert!(source_dir.path().join("photo.png").exists());
+
+        let journal = fs::read_dir(kb_dir.path().join("journals"))
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        assert_eq!(journal, "- ![[Paper.PDF]]\n- [[article]]\n");
+    }
+
+    #[test]
+    fn test_run_appends_manifest_line_per_imported_file() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join("alpha.md"), "# Alpha").unwrap();
+        fs::write(source_dir.path().join("beta.md"), "# Beta").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            write_manifest: true,
+            ..Default::default()
+        };
+        let summary = run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        let manifest = fs::read_to_string(kb_dir.path().join("manifest.csv")).unwrap();
+        let mut lines = manifest.lines();
+        assert_eq!(lines.next(), Some("imported_at,sha256,path"));
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len(), 2);
+        for (row, (_, destination)) in rows.iter().zip(&summary.moved_files) {
+            let digest = row[1];
+            assert_eq!(digest.len(), 64);
+            assert!(digest.chars().all(|c| c.is_ascii_hexdigit()));
+            assert_eq!(digest, FileOperations::content_hash(destination).unwrap());
+            assert_eq!(row[2], destination.display().to_string());
+        }
+
+        // Without the flag no manifest is written
+        let kb_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join("gamma.md"), "# Gamma").unwrap();
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            ..Default::default()
+        };
+        run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+        assert!(!kb_dir.path().join("manifest.csv").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_rewrites_links_to_renamed_files_in_recent_journals() {
+        use chrono::TimeZone;
+
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let journals = kb_dir.path().join("journals");
+        fs::create_dir_all(&journals).unwrap();
+        fs::write(source_dir.path().join("download (3).md"), "# Real title").unwrap();
+
+        // Seven days of journals back from 2024-03-15 start at 2024-03-09
+        let now = chrono::Local
+            .with_ymd_and_hms(2024, 3, 15, 12, 0, 0)
+            .unwrap();
+        let journal_for = |date: &str| journals.join(format!("{}.md", date));
+        fs::write(journal_for("2024_03_09"), "- read later [[download (3)]]\n").unwrap();
+        fs::write(journal_for("2024_03_08"), "- read later [[download (3)]]\n").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            filename_prefix: "web-".to_string(),
+            rewrite_links: true,
+            write_journal: false,
+            ..Default::default()
+        };
+        let summary = run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                now: Some(now),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 1);
+        assert!(kb_dir.path().join("pages/web-download (3).md").exists());
+        assert_eq!(
+            fs::read_to_string(journal_for("2024_03_09")).unwrap(),
+            "- read later [[web-download (3)]]\n"
+        );
+        // Journals older than rewrite_links_days are left alone
+        assert_eq!(
+            fs::read_to_string(journal_for("2024_03_08")).unwrap(),
+            "- read later [[download (3)]]\n"
+        );
+        let leftovers: Vec<_> = fs::read_dir(&journals)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_transactional_run_restores_sources_when_journal_write_fails() {
+        for transactional in [false, true] {
+            let source_dir = tempdir().unwrap();
+            let kb_dir = tempdir().unwrap();
+            fs::write(source_dir.path().join("alpha.md"), "# Alpha").unwrap();
+            fs::write(source_dir.path().join("beta.md"), "# Beta").unwrap();
+            // A file where the journals directory should be makes the journal write fail
+            fs::write(kb_dir.path().join("journals"), "").unwrap();
+
+            let config = Config {
+                knowledge_base_path: kb_dir.path().display().to_string(),
+                transactional,
+                ..Default::default()
+            };
+            let ledger_path = kb_dir.path().join("ledger.jsonl");
+            let sink = RecordingSink::default();
+            let summary = run(
+                &config,
+                RunOptions {
+                    source_directory: source_dir.path().to_path_buf(),
+                    ledger_path: Some(ledger_path.clone()),
+                    ..Default::default()
+                },
+                &sink,
+            )
+            .unwrap();
+
+            let events = sink.events.into_inner().unwrap();
+            assert!(events.contains(&"journal_failed".to_string()));
+            if transactional {
+                assert_eq!(summary.moved, 0);
+                assert_eq!(summary.rolled_back.len(), 2);
+                assert_eq!(
+                    summary.exit_code(),
+                    crate::run_summary::EXIT_PARTIAL_FAILURE
+                );
+                assert_eq!(
+                    fs::read_to_string(source_dir.path().join("alpha.md")).unwrap(),
+                    "# Alpha"
+                );
+                assert!(source_dir.path().join("beta.md").exists());
+                assert!(!kb_dir.path().join("pages/alpha.md").exists());
+                assert!(!kb_dir.path().join("pages/beta.md").exists());
+                assert!(events.contains(&"rolled_back beta.md".to_string()));
+                assert!(!ledger_path.exists());
+            } else {
+                assert_eq!(summary.moved, 2);
+                assert!(summary.journal_failure.is_some());
+                assert_eq!(
+                    summary.exit_code(),
+                    crate::run_summary::EXIT_PARTIAL_FAILURE
+                );
+                assert!(kb_dir.path().join("pages/alpha.md").exists());
+                assert!(!source_dir.path().join("alpha.md").exists());
+            }
+        }
+    }
+
+    #[test]
+    fn test_transactional_run_records_pages_it_could_not_put_back() {
+        /// Recreates a source once the journal fails, so its page cannot be put back
+        struct RecreateSource(PathBuf);
+        impl EventSink for RecreateSource {
+            fn journal_failed(&self, _error: &JournalError) {
+                fs::write(&self.0, "# New alpha").unwrap();
+            }
+        }
+
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join("alpha.md"), "# Alpha").unwrap();
+        fs::write(source_dir.path().join("beta.md"), "# Beta").unwrap();
+        fs::write(kb_dir.path().join("journals"), "").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            transactional: true,
+            ..Default::default()
+        };
+        let ledger_path = kb_dir.path().join("ledger.jsonl");
+        let summary = run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ledger_path: Some(ledger_path.clone()),
+                ..Default::default()
+            },
+            &RecreateSource(source_dir.path().join("alpha.md")),
+        )
+        .unwrap();
+
+        let page = kb_dir.path().join("pages/alpha.md");
+        assert_eq!(summary.rolled_back.len(), 1);
+        assert_eq!(summary.rollback_failures.len(), 1);
+        assert!(page.exists());
+        let recorded = Ledger::read(&ledger_path).unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].to, page);
+        assert!(recorded[0].rollback_failed);
+    }
+
+    #[test]
+    fn test_run_marks_duplicates_left_in_place_so_next_run_skips_them() {
+        for marker in [
+            ProcessedMarker::DoneSuffix,
+            ProcessedMarker::ProcessedFolder,
+        ] {
+            let source_dir = tempdir().unwrap();
+            let kb_dir = tempdir().unwrap();
+            fs::create_dir_all(kb_dir.path().join("pages")).unwrap();
+            fs::write(kb_dir.path().join("pages/note.md"), "# Note").unwrap();
+            fs::write(source_dir.path().join("note.md"), "# Note").unwrap();
+
+            let config = Config {
+                knowledge_base_path: kb_dir.path().display().to_string(),
+                processed_marker: marker,
+                recursive: true,
+                ..Default::default()
+            };
+            let options = || RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ..Default::default()
+            };
+
+            let first = run(&config, options(), &crate::events::SilentSink).unwrap();
+            assert_eq!(
+                first.skipped_files,
+                vec![(source_dir.path().join("note.md"), SkipReason::Duplicate)]
+            );
+            let marked = match marker {
+                ProcessedMarker::DoneSuffix => source_dir.path().join("note.md.done"),
+                _ => source_dir.path().join("processed/note.md"),
+            };
+            assert!(marked.exists(), "{:?}", marker);
+            assert!(!source_dir.path().join("note.md").exists());
+
+            let second = run(&config, options(), &crate::events::SilentSink).unwrap();
+            assert_eq!(second.moved, 0, "{:?}", marker);
+            assert!(
+                second
+                    .skipped_files
+                    .iter()
+                    .all(|(_, reason)| *reason == SkipReason::Excluded),
+                "{:?}",
+                marker
+            );
+        }
+    }
+
+    #[test]
+    fn test_run_with_seen_set_skips_file_imported_before() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let source = source_dir.path().join("article.md");
+        fs::write(&source, "# Article").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            processed_marker: ProcessedMarker::SeenSet,
+            ..Default::default()
+        };
+        let seen_path = kb_dir.path().join("seen.txt");
+        let options = || RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            seen_path: Some(seen_path.clone()),
+            ..Default::default()
+        };
+
+        let first = run(&config, options(), &crate::events::SilentSink).unwrap();
+        assert_eq!(first.moved, 1);
+
+        // The same article is downloaded again
+        fs::write(&source, "# Article").unwrap();
+        let second = run(&config, options(), &crate::events::SilentSink).unwrap();
+
+        assert_eq!(second.moved, 0);
+        assert_eq!(
+            second.skipped_files,
+            vec![(source.clone(), SkipReason::AlreadyImported)]
+        );
+        assert!(source.exists());
+        assert!(!kb_dir.path().join("pages/article_1.md").exists());
+    }
+
+    #[test]
+    fn test_run_with_knowledge_base_as_source_skips_its_pages_and_journals() {
+        let kb_dir = tempdir().unwrap();
+        let pages = kb_dir.path().join("pages");
+        let journals = kb_dir.path().join("journals");
+        fs::create_dir_all(&pages).unwrap();
+        fs::create_dir_all(&journals).unwrap();
+        fs::write(pages.join("existing.md"), "# Existing").unwrap();
+        fs::write(journals.join("2026_02_07.md"), "- [[existing]]\n").unwrap();
+        fs::write(kb_dir.path().join("inbox.md"), "# Inbox").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            recursive: true,
+            write_journal: false,
+            ..Default::default()
+        };
+        let summary = run(
+            &config,
+            RunOptions {
+                // Reach the Knowledge Base through `..` to check canonicalization
+                source_directory: pages.join(".."),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 1);
+        assert!(pages.join("inbox.md").exists());
+        let mut protected = summary.skipped_by_reason()[&SkipReason::InKnowledgeBase].clone();
+        protected.sort();
+        assert_eq!(
+            protected,
+            vec![
+                &pages.join("../journals/2026_02_07.md"),
+                &pages.join("../pages/existing.md"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(journals.join("2026_02_07.md")).unwrap(),
+            "- [[existing]]\n"
+        );
+        assert_eq!(
+            fs::read_to_string(pages.join("existing.md")).unwrap(),
+            "# Existing"
+        );
+    }
+
+    #[test]
+    fn test_run_with_vault_root_as_source_and_pages_moves_nothing() {
+        let kb_dir = tempdir().unwrap();
+        fs::write(kb_dir.path().join("note.md"), "# Note").unwrap();
+        fs::write(kb_dir.path().join("2026-02-07.md"), "- [[note]]\n").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            pages_dir_name: ".".to_string(),
+            journals_dir_name: ".".to_string(),
+            journal_file_format: "%Y-%m-%d".to_string(),
+            write_journal: false,
+            ..Default::default()
+        };
+        let summary = run(
+            &config,
+            RunOptions {
+                source_directory: kb_dir.path().to_path_buf(),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 0);
+        assert_eq!(
+            summary.skipped_by_reason()[&SkipReason::InKnowledgeBase].len(),
+            2
+        );
+        assert!(kb_dir.path().join("note.md").exists());
+    }
+
+    #[test]
+    fn test_run_invokes_per_file_hook_once_per_moved_file() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join("alpha.md"), "# Alpha").unwrap();
+        fs::write(source_dir.path().join("beta.md"), "# Beta").unwrap();
+        let log = kb_dir.path().join("hook.log");
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            per_file_hook: Some(format!(
+                "printf '%s|%s\\n' \"$LOCAL_SHELF_SOURCE\" \"$LOCAL_SHELF_DEST\" >> '{}'; \
+                 case \"$LOCAL_SHELF_DEST\" in *beta.md) exit 1;; esac",
+                log.display()
+            )),
+            ..Default::default()
+        };
+        let summary = run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 2);
+        let mut lines: Vec<String> = fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        lines.sort();
+        let mut expected: Vec<String> = summary
+            .moved_files
+            .iter()
+            .map(|(source, destination)| format!("{}|{}", source.display(), destination.display()))
+            .collect();
+        expected.sort();
+        assert_eq!(lines, expected);
+
+        let beta = kb_dir.path().join("pages/beta.md");
+        assert!(beta.exists());
+        assert_eq!(summary.hook_failures.len(), 1);
+        assert_eq!(summary.hook_failures[0].0, beta);
+        assert_eq!(
+            summary.exit_code(),
+            crate::run_summary::EXIT_PARTIAL_FAILURE
+        );
+    }
+
+    /// Run interactively in `source_dir` with `answers` as the prompt input,
+    /// returning the summary and everything the prompt printed
+    fn run_with_answers(source_dir: &Path, kb_dir: &Path, answers: &str) -> (RunSummary, String) {
+        let config = Config {
+            knowledge_base_path: kb_dir.display().to_string(),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut prompt = Prompt::new(std::io::Cursor::new(answers.to_string()), &mut output);
+        let summary = run_interactive(
+            &config,
+            RunOptions {
+                source_directory: source_dir.to_path_buf(),
+                ..Default::default()
+            },
+            &crate::events::SilentSink,
+            &mut prompt,
+        )
+        .unwrap();
+        drop(prompt);
+        (summary, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_run_interactive_follows_scripted_answers() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        for name in ["a.md", "b.md", "c.md", "d.md", "e.md"] {
+            fs::write(source_dir.path().join(name), name).unwrap();
+        }
+
+        // Yes, the default no, an invalid answer asked again, then all
+        let (summary, output) =
+            run_with_answers(source_dir.path(), kb_dir.path(), "y\n\nmaybe\na\n");
+
+        let pages = kb_dir.path().join("pages");
+        for name in ["a.md", "c.md", "d.md", "e.md"] {
+            assert!(pages.join(name).exists(), "{} should be moved", name);
+        }
+        assert!(source_dir.path().join("b.md").exists());
+        assert!(!pages.join("b.md").exists());
+        assert_eq!(summary.moved, 4);
+        assert_eq!(
+            summary.skipped_files,
+            vec![(source_dir.path().join("b.md"), SkipReason::Declined)]
+        );
+        assert!(output.contains(&format!("  → {}", pages.join("a.md").display())));
+        assert_eq!(output.matches("[y/N/a(ll)/q(uit)]").count(), 4);
+        assert_eq!(output.matches("Please answer y, n, a or q.").count(), 1);
+    }
+
+    #[test]
+    fn test_run_interactive_quit_leaves_remaining_files() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        for name in ["a.md", "b.md", "c.md"] {
+            fs::write(source_dir.path().join(name), name).unwrap();
+        }
+
+        let (summary, output) = run_with_answers(source_dir.path(), kb_dir.path(), "y\nq\n");
+
+        assert_eq!(summary.moved, 1);
+        assert_eq!(summary.skipped_by_reason()[&SkipReason::Declined].len(), 2);
+        assert!(source_dir.path().join("b.md").exists());
+        assert!(source_dir.path().join("c.md").exists());
+        assert_eq!(output.matches("[y/N/a(ll)/q(uit)]").count(), 2);
+
+        // Running out of answers counts as quitting
+        let (summary, _) = run_with_answers(source_dir.path(), kb_dir.path(), "");
+        assert_eq!(summary.moved, 0);
+        assert_eq!(summary.skipped_by_reason()[&SkipReason::Declined].len(), 2);
+        assert!(
+            !kb_dir
+                .path()
+                .join("journals")
+                .read_dir()
+                .unwrap()
+                .any(|entry| {
+                    fs::read_to_string(entry.unwrap().path())
+                        .unwrap()
+                        .contains("[[b]]")
+                })
+        );
+    }
+
+    #[test]
+    fn test_dry_run_previews_journal_diff() {
+        let source_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join("article.md"), "# Article").unwrap();
+        fs::write(source_dir.path().join("notes.md"), "# Notes").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            include_timestamp: false,
+            ..Default::default()
+        };
+        let journal = JournalManager::add_entries(&[PathBuf::from("earlier.md")], &config).unwrap();
+
+        #[derive(Default)]
+        struct PreviewSink(Mutex<Option<JournalPreview>>);
+        impl EventSink for PreviewSink {
+            fn journal_preview(&self, preview: &JournalPreview) {
+                *self.0.lock().unwrap() = Some(preview.clone());
+            }
+        }
+        let sink = PreviewSink::default();
+        run(
+            &config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                dry_run: true,
+                ..Default::default()
+            },
+            &sink,
+        )
+        .unwrap();
+
+        let diff = sink.0.into_inner().unwrap().unwrap().unified_diff();
+        assert!(diff.contains(" - [[earlier]]\n"));
+        assert!(diff.contains("+- [[article]]\n+- [[notes]]\n"));
+        assert_eq!(fs::read_to_string(&journal).unwrap(), "- [[earlier]]\n");
+        assert!(source_dir.path().join("article.md").exists());
+    }
+
+    /// Build a zip archive in memory from `(name, content)` pairs
+    fn zip_bytes(files: &[(&str, &str)]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_run_archive_imports_entries_without_extracting() {
+        let download_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let archive = download_dir.path().join("export.zip");
+        fs::write(
+            &archive,
+            zip_bytes(&[
+                ("export/article.md", "# Article"),
+                ("export/nested/notes.md", "# Notes"),
+                ("export/cover.png", "binary"),
+                ("../escape.md", "outside"),
+            ]),
+        )
+        .unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            include_timestamp: false,
+            ..Default::default()
+        };
+        let summary = run_archive(
+            &config,
+            &archive,
+            false,
+            &SystemClock,
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 2);
+        assert_eq!(
+            summary.skipped_files,
+            vec![(archive.join("../escape.md"), SkipReason::UnsafePath)]
+        );
+        let pages = kb_dir.path().join("pages");
+        assert_eq!(
+            fs::read_to_string(pages.join("article.md")).unwrap(),
+            "# Article"
+        );
+        assert_eq!(
+            fs::read_to_string(pages.join("notes.md")).unwrap(),
+            "# Notes"
+        );
+        assert!(!kb_dir.path().join("escape.md").exists());
+
+        let journal = fs::read_dir(kb_dir.path().join("journals"))
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        assert_eq!(journal, "- [[article]]\n- [[notes]]\n");
+
+        // The archive is left alone and nothing was extracted next to it
+        let downloads: Vec<_> = fs::read_dir(download_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(downloads, vec!["export.zip"]);
+
+        // Importing the same archive again finds the pages already there
+        let summary = run_archive(
+            &config,
+            &archive,
+            false,
+            &SystemClock,
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+        assert_eq!(summary.moved, 0);
+        assert_eq!(summary.skipped_by_reason()[&SkipReason::Duplicate].len(), 2);
+    }
+
+    #[test]
+    fn test_run_archive_applies_discovery_filters_to_entries() {
+        use chrono::TimeZone;
+        let download_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let archive = download_dir.path().join("export.zip");
+        fs::write(
+            &archive,
+            zip_bytes(&[
+                ("kept.md", "# Kept page"),
+                ("draft-notes.md", "# Draft page"),
+                ("private.md", "# Private page"),
+                ("stub.md", "#"),
+            ]),
+        )
+        .unwrap();
+        fs::write(
+            download_dir.path().join(".localshelfignore"),
+            "private.md\n",
+        )
+        .unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            exclude_patterns: vec!["draft-*".to_string()],
+            min_file_size_bytes: 4,
+            max_age_days: Some(1),
+            ..Default::default()
+        };
+        // Entries written without a time are dated 1980-01-01 in the archive
+        let day_after = chrono::Local.with_ymd_and_hms(1980, 1, 2, 0, 0, 0).unwrap();
+        let summary = run_archive(
+            &config,
+            &archive,
+            false,
+            &FixedClock(day_after),
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 1);
+        let skipped = summary.skipped_by_reason();
+        assert_eq!(
+            skipped[&SkipReason::Excluded],
+            vec![&archive.join("draft-notes.md"), &archive.join("private.md")]
+        );
+        assert_eq!(
+            skipped[&SkipReason::TooSmall],
+            vec![&archive.join("stub.md")]
+        );
+
+        let clock = FixedClock(day_after + chrono::Duration::days(1));
+        let summary =
+            run_archive(&config, &archive, false, &clock, &crate::events::SilentSink).unwrap();
+        assert_eq!(summary.moved, 0);
+        assert_eq!(
+            summary.skipped_by_reason()[&SkipReason::TooOld],
+            vec![&archive.join("kept.md")]
+        );
+    }
+
+    #[test]
+    fn test_run_archive_imports_attachments_to_assets() {
+        let download_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let archive = download_dir.path().join("export.zip");
+        fs::write(
+            &archive,
+            zip_bytes(&[("article.md", "# Article"), ("Paper.pdf", "%PDF-1.7")]),
+        )
+        .unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            attachment_extensions: vec!["pdf".to_string()],
+            add_page_properties: true,
+            include_timestamp: false,
+            ..Default::default()
+        };
+        let summary = run_archive(
+            &config,
+            &archive,
+            false,
+            &SystemClock,
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert_eq!(summary.moved, 2);
+        assert_eq!(
+            fs::read_to_string(kb_dir.path().join("assets/Paper.pdf")).unwrap(),
+            "%PDF-1.7"
+        );
+        let journal = fs::read_dir(kb_dir.path().join("journals"))
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        assert_eq!(journal, "- ![[Paper.pdf]]\n- [[article]]\n");
+    }
+
+    #[test]
+    fn test_run_archive_keeps_directories_with_namespaced_links() {
+        let download_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let archive = download_dir.path().join("export.zip");
+        fs::write(
+            &archive,
+            zip_bytes(&[("projects/plan.md", "# Plan"), ("notes.md", "# Notes")]),
+        )
+        .unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            include_timestamp: false,
+            namespaced_links: true,
+            ..Default::default()
+        };
+        run_archive(
+            &config,
+            &archive,
+            false,
+            &SystemClock,
+            &crate::events::SilentSink,
+        )
+        .unwrap();
+
+        assert!(kb_dir.path().join("pages/projects/plan.md").exists());
+        assert!(kb_dir.path().join("pages/notes.md").exists());
+        let journal = fs::read_dir(kb_dir.path().join("journals"))
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        assert_eq!(journal, "- [[notes]]\n- [[projects/plan]]\n");
+    }
+
+    #[test]
+    fn test_run_archive_dates_pages_and_journal_by_clock() {
+        use chrono::TimeZone;
+        let download_dir = tempdir().unwrap();
+        let kb_dir = tempdir().unwrap();
+        let archive = download_dir.path().join("export.zip");
+        fs::write(&archive, zip_bytes(&[("article.md", "# Article\n")])).unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            include_timestamp: false,
+            add_page_properties: true,
+            ..Default::default()
+        };
+        let clock = FixedClock(
+            chrono::Local
+                .with_ymd_and_hms(2024, 3, 9, 23, 30, 0)
+                .unwrap(),
+        );
+        run_archive(&config, &archive, false, &clock, &crate::events::SilentSink).unwrap();
+
+        let page = fs::read_to_string(kb_dir.path().join("pages/article.md")).unwrap();
+        assert!(page.starts_with("import-date:: 2024-03-09\n"));
+        let journal = JournalManager::get_today_journal_path(&config, &clock).unwrap();
+        assert_eq!(fs::read_to_string(journal).unwrap(), "- [[article]]\n");
+    }
+
+    /// Set a file's modification time to noon local time on `date`
+    fn set_modified(path: &Path, date: (i32, u32, u32)) {
+        use chrono::TimeZone;
+        let modified = chrono::Local
+            .with_ymd_and_hms(date.0, date.1, date.2, 12, 0, 0)
+            .unwrap();
+        filetime::set_file_mtime(
+            path,
+            filetime::FileTime::from_unix_time(modified.timestamp(), 0),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_run_reorganize_moves_pages_and_updates_journal_links() {
+        let kb_dir = tempdir().unwrap();
+        let pages = kb_dir.path().join("pages");
+        let journals = kb_dir.path().join("journals");
+        fs::create_dir_all(pages.join("2026/01")).unwrap();
+        fs::create_dir_all(&journals).unwrap();
+        fs::write(pages.join("note.md"), "# Note").unwrap();
+        set_modified(&pages.join("note.md"), (2026, 2, 7));
+        fs::write(pages.join("2026/01/older.md"), "# Older").unwrap();
+        set_modified(&pages.join("2026/01/older.md"), (2026, 1, 15));
+        fs::write(
+            journals.join("2026_02_07.md"),
+            "- 10:15 [[note]]\n- 10:16 [[notebook]]\n",
+        )
+        .unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            organize_by_date: true,
+            date_subdir_pattern: "%Y/%m".to_string(),
+            namespaced_links: true,
+            recursive: true,
+            ..Default::default()
+        };
+        let summary =
+            run_reorganize(&config, false, &SystemClock, &crate::events::SilentSink).unwrap();
+
+        assert_eq!(summary.moved, 1);
+        assert!(!pages.join("note.md").exists());
+        assert_eq!(
+            fs::read_to_string(pages.join("2026/02/note.md")).unwrap(),
+            "# Note"
+        );
+        assert_eq!(
+            summary.skipped_files,
+            vec![(pages.join("2026/01/older.md"), SkipReason::InPlace)]
+        );
+        assert_eq!(
+            fs::read_to_string(journals.join("2026_02_07.md")).unwrap(),
+            "- 10:15 [[2026/02/note]]\n- 10:16 [[notebook]]\n"
+        );
+    }
+
+    #[test]
+    fn test_run_reorganize_keeps_nested_folders_and_skips_other_directories() {
+        let kb_dir = tempdir().unwrap();
+        let pages = kb_dir.path().join("pages");
+        for dir in ["projects", "journals", "assets", "drafts"] {
+            fs::create_dir_all(pages.join(dir)).unwrap();
+        }
+        fs::write(pages.join("projects/plan.md"), "# Plan").unwrap();
+        set_modified(&pages.join("projects/plan.md"), (2026, 2, 7));
+        fs::write(pages.join("journals/2026_02_07.md"), "- [[plan]]\n").unwrap();
+        fs::write(pages.join("assets/diagram.md"), "# Diagram").unwrap();
+        fs::write(pages.join("drafts/todo-draft.md"), "# Todo").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            journals_dir_name: "pages/journals".to_string(),
+            assets_dir_name: "pages/assets".to_string(),
+            routing: vec![RouteRule {
+                pattern: "*-draft.md".to_string(),
+                destination: "pages/drafts".to_string(),
+                journal: true,
+            }],
+            organize_by_date: true,
+            date_subdir_pattern: "%Y/%m".to_string(),
+            recursive: true,
+            ..Default::default()
+        };
+        let summary =
+            run_reorganize(&config, false, &SystemClock, &crate::events::SilentSink).unwrap();
+
+        assert_eq!(summary.moved, 1);
+        assert!(pages.join("projects/2026/02/plan.md").exists());
+        assert!(pages.join("journals/2026_02_07.md").exists());
+        assert!(pages.join("assets/diagram.md").exists());
+        assert!(pages.join("drafts/todo-draft.md").exists());
+
+        let again =
+            run_reorganize(&config, false, &SystemClock, &crate::events::SilentSink).unwrap();
+        assert_eq!(again.moved, 0);
+        assert!(pages.join("projects/2026/02/plan.md").exists());
+    }
+
+    #[test]
+    fn test_run_reorganize_refuses_when_pages_is_the_knowledge_base() {
+        let kb_dir = tempdir().unwrap();
+        fs::create_dir_all(kb_dir.path().join("Projects")).unwrap();
+        fs::write(kb_dir.path().join("Projects/plan.md"), "# Plan").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            pages_dir_name: ".".to_string(),
+            organize_by_date: true,
+            recursive: true,
+            ..Default::default()
+        };
+        let result = run_reorganize(&config, false, &SystemClock, &crate::events::SilentSink);
+
+        assert!(matches!(result, Err(AppError::PagesDirIsKnowledgeBase(_))));
+        assert!(kb_dir.path().join("Projects/plan.md").exists());
+    }
+
+    #[test]
+    fn test_run_reorganize_dry_run_leaves_pages_in_place() {
+        let kb_dir = tempdir().unwrap();
+        let pages = kb_dir.path().join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("note.md"), "# Note").unwrap();
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            organize_by_date: true,
+            ..Default::default()
+        };
+        let summary =
+            run_reorganize(&config, true, &SystemClock, &crate::events::SilentSink).unwrap();
+
+        assert_eq!(summary.moved, 0);
+        assert_eq!(
+            summary.skipped_files,
+            vec![(pages.join("note.md"), SkipReason::DryRun)]
+        );
+        assert!(pages.join("note.md").exists());
+    }
+
+    #[test]
+    fn test_run_reorganize_measures_max_age_by_clock() {
+        use chrono::TimeZone;
+        let kb_dir = tempdir().unwrap();
+        let pages = kb_dir.path().join("pages");
+        fs::create_dir_all(&pages).unwrap();
+        fs::write(pages.join("old.md"), "# Old").unwrap();
+        set_modified(&pages.join("old.md"), (2026, 1, 15));
+        fs::write(pages.join("recent.md"), "# Recent").unwrap();
+        set_modified(&pages.join("recent.md"), (2026, 2, 7));
+
+        let config = Config {
+            knowledge_base_path: kb_dir.path().display().to_string(),
+            organize_by_date: true,
+            date_subdir_pattern: "%Y/%m".to_string(),
+            max_age_days: Some(7),
+            ..Default::default()
+        };
+        let clock = FixedClock(
+            chrono::Local
+                .with_ymd_and_hms(2026, 2, 10, 12, 0, 0)
+                .unwrap(),
+        );
+        let summary = run_reorganize(&config, false, &clock, &crate::events::SilentSink).unwrap();
+
+        assert_eq!(summary.moved, 1);
+        assert!(pages.join("2026/02/recent.md").exists());
+        assert_eq!(
+            summary.skipped_files,
+            vec![(pages.join("old.md"), SkipReason::TooOld)]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Marking handled source files so later runs skip them (`processed_marker`)
+//!
+//! Moved files leave nothing behind in the source directory, but a file
+//! skipped as a duplicate of an existing page stays there and is found again
+//! on every run. Depending on the strategy, such a file is renamed to
+//! `<name>.done` or moved into a `processed` subfolder next to it.
+//!
+//! The `SeenSet` strategy instead records every handled file, moved or
+//! not, in `seen.txt` in the configuration directory, one line per file
+//! holding its SHA-256 and absolute source path separated by a tab.
+//!
+//! A file found later at the same path with the same SHA-256 is skipped, so
+//! downloading the same article again does not import it twice.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, ConfigError, ProcessedMarker};
+use crate::file_discovery::DiscoveryReport;
+use crate::file_operations::FileOperations;
+use crate::filesystem::{FileSystem, RealFs};
+use crate::run_summary::SkipReason;
+
+/// Name of the seen-set file inside the configuration directory
+pub const SEEN_FILE_NAME: &str = "seen.txt";
+
+/// Suffix added to files left in place by `DoneSuffix`
+pub const DONE_SUFFIX: &str = ".done";
+
+/// Subfolder files left in place are moved into by `ProcessedFolder`
+pub const PROCESSED_DIR_NAME: &str = "processed";
+
+/// Public interface for processed markers
+pub struct Processed;
+
+impl Processed {
+    /// Default location of the seen-set file
+    pub fn default_seen_path() -> Result<PathBuf, ConfigError> {
+        Ok(Config::config_dir()?.join(SEEN_FILE_NAME))
+    }
+
+    /// Mark a handled file that is still in the source directory
+    ///
+    /// Returns where the file went, or `None` when `marker` does not touch
+    /// files left in place. An existing file at the new name is never
+    /// overwritten.
+    pub fn mark_left_in_place(path: &Path, marker: ProcessedMarker) -> io::Result<Option<PathBuf>> {
+        let marked = match marker {
+            ProcessedMarker::DoneSuffix => {
+                let mut name = path.file_name().unwrap_or_default().to_os_string();
+                name.push(DONE_SUFFIX);
+                path.with_file_name(name)
+            }
+            ProcessedMarker::ProcessedFolder => {
+                let folder = path.with_file_name(PROCESSED_DIR_NAME);
+                fs::create_dir_all(&folder)?;
+                folder.join(path.file_name().unwrap_or_default())
+            }
+            ProcessedMarker::Off | ProcessedMarker::SeenSet => return Ok(None),
+        };
+
+        if marked.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", marked.display()),
+            ));
+        }
+        fs::rename(path, &marked)?;
+        Ok(Some(marked))
+    }
+
+    /// Whether `path` lies directly in a `ProcessedFolder` subfolder
+    pub fn is_in_processed_folder(path: &Path) -> bool {
+        path.parent()
+            .and_then(Path::file_name)
+            .is_some_and(|name| name == PROCESSED_DIR_NAME)
+    }
+
+    /// Read the `(sha256, path)` entries of a seen-set file
+    ///
+    /// A missing file is an empty set; malformed lines are ignored.
+    pub fn load_seen(seen_path: &Path) -> io::Result<HashSet<(String, PathBuf)>> {
+        let content = match fs::read_to_string(seen_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(e) => return Err(e),
+        };
+
+        Ok(content
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(hash, path)| (hash.to_string(), PathBuf::from(path)))
+            .collect())
+    }
+
+    /// Append `(sha256, path)` entries to a seen-set file, creating it if necessary
+    pub fn record_seen(seen_path: &Path, entries: &[(String, PathBuf)]) -> io::Result<()> {
+        Self::record_seen_with(seen_path, entries, &RealFs)
+    }
+
+    /// Append entries like [`Self::record_seen`], through `fs`
+    pub fn record_seen_with(
+        seen_path: &Path,
+        entries: &[(String, PathBuf)],
+        fs: &dyn FileSystem,
+    ) -> io::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = seen_path.parent() {
+            fs.create_dir_all(parent)?;
+        }
+
+        let content: String = entries
+            .iter()
+            .map(|(hash, path)| format!("{}\t{}\n", hash, path.display()))
+            .collect();
+        fs.append(seen_path, content.as_bytes())
+    }
+
+    /// Skip discovered files recorded in `seen` with their current content
+    ///
+    /// Only files whose path is in the set are hashed.
+    pub fn skip_seen(report: &mut DiscoveryReport, seen: &HashSet<(String, PathBuf)>) {
+        if seen.is_empty() {
+            return;
+        }
+        let seen_paths: HashSet<&Path> = seen.iter().map(|(_, path)| path.as_path()).collect();
+
+        let (kept, skipped): (Vec<_>, Vec<_>) = std::mem::take(&mut report.files)
+            .into_iter()
+            .partition(|file| {
+                let path = Self::absolute(&file.path);
+                !seen_paths.contains(path.as_path())
+                    || FileOperations::content_hash(&file.path)
+                        .is_ok_and(|hash| !seen.contains(&(hash, path)))
+            });
+        report.files = kept;
+        report.skipped.extend(
+            skipped
+                .into_iter()
+                .map(|file| (file.path, SkipReason::AlreadyImported)),
+        );
+    }
+
+    /// Absolute form of a source path, as recorded in the seen set
+    pub fn absolute(path: &Path) -> PathBuf {
+        std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_discovery::FileDiscovery;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_seen_set_roundtrip_skips_unchanged_files_only() {
+        let temp_dir = tempdir().unwrap();
+        let seen_path = temp_dir.path().join("config").join(SEEN_FILE_NAME);
+        let source = temp_dir.path().join("downloads");
+        fs::create_dir_all(&source).unwrap();
+        let article = source.join("article.md");
+        fs::write(&article, "# Article").unwrap();
+        fs::write(source.join("other.md"), "# Other").unwrap();
+
+        let hash = FileOperations::content_hash(&article).unwrap();
+        Processed::record_seen(&seen_path, &[(hash, article.clone())]).unwrap();
+        let seen = Processed::load_seen(&seen_path).unwrap();
+
+        let mut report =
+            FileDiscovery::discover_in_directory_report(&source, &Config::default()).unwrap();
+        Processed::skip_seen(&mut report, &seen);
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].path, source.join("other.md"));
+        assert_eq!(
+            report.skipped,
+            vec![(article.clone(), SkipReason::AlreadyImported)]
+        );
+
+        // A changed file at the same path is new again
+        fs::write(&article, "# Article, revised").unwrap();
+        let mut report =
+            FileDiscovery::discover_in_directory_report(&source, &Config::default()).unwrap();
+        Processed::skip_seen(&mut report, &seen);
+        assert_eq!(report.files.len(), 2);
+    }
+
+    #[test]
+    fn test_mark_left_in_place_never_overwrites() {
+        let temp_dir = tempdir().unwrap();
+        let note = temp_dir.path().join("note.md");
+        fs::write(&note, "new").unwrap();
+        fs::write(temp_dir.path().join("note.md.done"), "old").unwrap();
+
+        let result = Processed::mark_left_in_place(&note, ProcessedMarker::DoneSuffix);
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert!(note.exists());
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("note.md.done")).unwrap(),
+            "old"
+        );
+    }
+}
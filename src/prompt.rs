@@ -0,0 +1,94 @@
+use std::io::{self, BufRead, Write};
+
+/// Line-based question/answer helper for interactive flows
+///
+/// The reader and writer are injected so prompts can be driven by scripted
+/// input in tests.
+pub struct Prompt<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    writer: Box<dyn Write + 'a>,
+}
+
+impl<'a> Prompt<'a> {
+    /// Create a prompt reading answers from `reader` and writing questions to `writer`
+    pub fn new(reader: impl BufRead + 'a, writer: impl Write + 'a) -> Self {
+        Prompt {
+            reader: Box::new(reader),
+            writer: Box::new(writer),
+        }
+    }
+
+    /// Create a prompt attached to the process's stdin and stdout
+    pub fn stdio() -> Prompt<'static> {
+        Prompt::new(io::stdin().lock(), io::stdout())
+    }
+
+    /// Ask a question and return the trimmed answer
+    ///
+    /// An empty answer yields `default` when one is given. Reaching the end
+    /// of input is reported as an `UnexpectedEof` error so callers never
+    /// loop on a closed stdin.
+    pub fn ask(&mut self, question: &str, default: Option<&str>) -> io::Result<String> {
+        match default {
+            Some(default) => write!(self.writer, "{} [{}]: ", question, default)?,
+            None => write!(self.writer, "{}: ", question)?,
+        }
+        self.writer.flush()?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "No answer provided",
+            ));
+        }
+
+        let answer = line.trim();
+        match default {
+            Some(default) if answer.is_empty() => Ok(default.to_string()),
+            _ => Ok(answer.to_string()),
+        }
+    }
+
+    /// Write an informational line
+    pub fn say(&mut self, message: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ask_returns_trimmed_answer() {
+        let mut output = Vec::new();
+        let mut prompt = Prompt::new(Cursor::new("  /my/vault  \n"), &mut output);
+
+        let answer = prompt.ask("Path", None).unwrap();
+        drop(prompt);
+
+        assert_eq!(answer, "/my/vault");
+        assert_eq!(String::from_utf8(output).unwrap(), "Path: ");
+    }
+
+    #[test]
+    fn test_ask_uses_default_for_empty_answer() {
+        let mut output = Vec::new();
+        let mut prompt = Prompt::new(Cursor::new("\n"), &mut output);
+
+        let answer = prompt.ask("Path", Some("~/KB")).unwrap();
+        drop(prompt);
+
+        assert_eq!(answer, "~/KB");
+        assert_eq!(String::from_utf8(output).unwrap(), "Path [~/KB]: ");
+    }
+
+    #[test]
+    fn test_ask_at_end_of_input() {
+        let mut prompt = Prompt::new(Cursor::new(""), io::sink());
+        let result = prompt.ask("Path", Some("~/KB"));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
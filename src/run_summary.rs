@@ -0,0 +1,417 @@
+//! Per-run counts and sizes reported at the end of `stow`
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::color;
+
+/// Exit code when every file was handled
+pub const EXIT_SUCCESS: i32 = 0;
+/// Exit code when at least one file failed to move
+pub const EXIT_PARTIAL_FAILURE: i32 = 1;
+/// Exit code for fatal errors such as invalid configuration or discovery failures
+pub const EXIT_FATAL: i32 = 2;
+/// Exit code when `fail_on_empty` is set and there was nothing to import
+pub const EXIT_NO_FILES: i32 = 3;
+
+/// Why a file was left in place instead of being moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The name marks a download that is still in progress
+    InProgress,
+    /// The name matches one of `exclude_patterns`
+    Excluded,
+    /// The entry is a symbolic link and `follow_symlinks` is off
+    Symlink,
+    /// The file's metadata could not be read, or the directory could not be listed
+    Unreadable,
+    /// The archive entry's name points outside the archive
+    UnsafePath,
+    /// The file was still growing during the stability check
+    Unstable,
+    /// The file is smaller than `min_file_size_bytes`
+    TooSmall,
+    /// The file is larger than `max_file_size_bytes`
+    TooLarge,
+    /// The file is older than `max_age_days`
+    TooOld,
+    /// The file was modified outside `modified_since`..`modified_until`
+    OutsideDateRange,
+    /// The file was handled by an earlier run, as recorded by `processed_marker: SeenSet`
+    AlreadyImported,
+    /// An identical page already exists at the destination
+    Duplicate,
+    /// The move was declined at the `--interactive` prompt
+    Declined,
+    /// The file lives in the Knowledge Base's pages, journals or
+    /// configuration directories
+    InKnowledgeBase,
+    /// The page already sits where the naming and organization rules put it
+    InPlace,
+    /// The run was a dry run
+    DryRun,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            SkipReason::InProgress => "download in progress",
+            SkipReason::Excluded => "matches an exclude pattern",
+            SkipReason::Symlink => "symbolic link",
+            SkipReason::Unreadable => "unreadable",
+            SkipReason::UnsafePath => "path outside the archive",
+            SkipReason::Unstable => "still being written",
+            SkipReason::TooSmall => "below minimum size",
+            SkipReason::TooLarge => "above maximum size",
+            SkipReason::TooOld => "older than max age",
+            SkipReason::OutsideDateRange => "modified outside the date range",
+            SkipReason::AlreadyImported => "imported before",
+            SkipReason::Duplicate => "identical page already exists",
+            SkipReason::Declined => "declined at the prompt",
+            SkipReason::InKnowledgeBase => "inside the Knowledge Base",
+            SkipReason::InPlace => "already in place",
+            SkipReason::DryRun => "dry run",
+        };
+        f.write_str(label)
+    }
+}
+
+/// Counts of files by outcome and the total size of moved files
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct RunSummary {
+    pub moved: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub bytes_moved: u64,
+    /// Source and destination of every moved file, in processing order
+    #[serde(skip)]
+    pub moved_files: Vec<(PathBuf, PathBuf)>,
+    /// Every skipped file with the reason it was left in place
+    #[serde(skip)]
+    pub skipped_files: Vec<(PathBuf, SkipReason)>,
+    /// Moved files whose `per_file_hook` failed, with the error
+    #[serde(skip)]
+    pub hook_failures: Vec<(PathBuf, String)>,
+    /// Journaling was turned off for the run
+    #[serde(skip)]
+    pub journal_disabled: bool,
+    /// Why the journal could not be written, when the moves were kept anyway
+    #[serde(skip)]
+    pub journal_failure: Option<String>,
+    /// Source and destination of every file put back after the journal
+    /// could not be written in `transactional` mode
+    #[serde(skip)]
+    pub rolled_back: Vec<(PathBuf, PathBuf)>,
+    /// Moved files that could not be put back, with the reason
+    #[serde(skip)]
+    pub rollback_failures: Vec<(PathBuf, String)>,
+}
+
+impl RunSummary {
+    /// Record a file of `size` bytes successfully moved from `from` to `to`
+    pub fn record_moved(&mut self, from: PathBuf, to: PathBuf, size: u64) {
+        self.moved += 1;
+        self.bytes_moved += size;
+        self.moved_files.push((from, to));
+    }
+
+    /// Record a file that was intentionally left in place
+    pub fn record_skipped(&mut self, path: PathBuf, reason: SkipReason) {
+        self.skipped += 1;
+        self.skipped_files.push((path, reason));
+    }
+
+    /// Skipped files grouped by reason, in a stable order
+    pub fn skipped_by_reason(&self) -> BTreeMap<SkipReason, Vec<&PathBuf>> {
+        let mut groups: BTreeMap<SkipReason, Vec<&PathBuf>> = BTreeMap::new();
+        for (path, reason) in &self.skipped_files {
+            groups.entry(*reason).or_default().push(path);
+        }
+        groups
+    }
+
+    /// Record that the file of `size` bytes moved from `from` to `to` was put back
+    pub fn record_rolled_back(&mut self, from: PathBuf, to: PathBuf, size: u64) {
+        self.moved = self.moved.saturating_sub(1);
+        self.bytes_moved = self.bytes_moved.saturating_sub(size);
+        self.moved_files
+            .retain(|(_, destination)| *destination != to);
+        self.rolled_back.push((from, to));
+    }
+
+    /// Record that the journal could not be written and the moves were kept
+    pub fn record_journal_failed(&mut self, error: String) {
+        self.journal_failure = Some(error);
+    }
+
+    /// Record a moved file that could not be put back
+    pub fn record_rollback_failed(&mut self, path: PathBuf, error: String) {
+        self.rollback_failures.push((path, error));
+    }
+
+    /// Record a file that could not be moved
+    pub fn record_failed(&mut self) {
+        self.failed += 1;
+    }
+
+    /// Record a moved file whose per-file hook failed
+    pub fn record_hook_failed(&mut self, path: PathBuf, error: String) {
+        self.hook_failures.push((path, error));
+    }
+
+    /// Total number of files seen during the run
+    pub fn total(&self) -> usize {
+        self.moved + self.skipped + self.failed
+    }
+
+    /// Process exit code for the run
+    ///
+    /// A failed per-file hook counts as a partial failure, although the
+    /// file itself was moved, and so does an unwritten journal or a rolled
+    /// back run.
+    pub fn exit_code(&self) -> i32 {
+        if self.failed > 0
+            || !self.hook_failures.is_empty()
+            || self.journal_failure.is_some()
+            || !self.rolled_back.is_empty()
+            || !self.rollback_failures.is_empty()
+        {
+            EXIT_PARTIAL_FAILURE
+        } else {
+            EXIT_SUCCESS
+        }
+    }
+
+    /// Render the summary as an aligned table
+    ///
+    /// Skipped files are listed after the counts, grouped by reason.
+    pub fn render(&self) -> String {
+        let mut rendered = format!(
+            "Summary:\n  {}   {} ({})\n  {} {}\n  {}  {}\n  Total:   {}\n",
+            color::success("Moved:"),
+            self.moved,
+            format_bytes(self.bytes_moved),
+            color::warning("Skipped:"),
+            self.skipped,
+            color::failure("Failed:"),
+            self.failed,
+            self.total()
+        );
+        if self.journal_disabled {
+            rendered.push_str("  Journal: disabled\n");
+        }
+        if let Some(error) = &self.journal_failure {
+            rendered.push_str(&format!(
+                "  Journal: {} ({})\n",
+                color::failure("failed"),
+                error
+            ));
+        }
+
+        let groups = self.skipped_by_reason();
+        if !groups.is_empty() {
+            rendered.push_str("\nSkipped files:\n");
+            for (reason, paths) in groups {
+                rendered.push_str(&format!("  {} ({}):\n", reason, paths.len()));
+                for path in paths {
+                    rendered.push_str(&format!("    - {}\n", path.display()));
+                }
+            }
+        }
+
+        if !self.hook_failures.is_empty() {
+            rendered.push_str(&format!(
+                "\nHook failures ({}):\n",
+                self.hook_failures.len()
+            ));
+            for (path, error) in &self.hook_failures {
+                rendered.push_str(&format!("    - {}: {}\n", path.display(), error));
+            }
+        }
+
+        if !self.rolled_back.is_empty() {
+            rendered.push_str(&format!(
+                "\nRolled back, the journal could not be written ({}):\n",
+                self.rolled_back.len()
+            ));
+            for (from, to) in &self.rolled_back {
+                rendered.push_str(&format!("    - {} → {}\n", to.display(), from.display()));
+            }
+        }
+
+        if !self.rollback_failures.is_empty() {
+            rendered.push_str(&format!(
+                "\nNot rolled back ({}):\n",
+                self.rollback_failures.len()
+            ));
+            for (path, error) in &self.rollback_failures {
+                rendered.push_str(&format!("    - {}: {}\n", path.display(), error));
+            }
+        }
+
+        rendered
+    }
+}
+
+/// Format a byte count using binary units, e.g. `1.5 KiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_aggregation() {
+        let mut summary = RunSummary::default();
+        summary.record_moved("a.md".into(), "pages/a.md".into(), 100);
+        summary.record_moved("b.md".into(), "pages/b.md".into(), 2_000);
+        summary.record_skipped("c.md".into(), SkipReason::TooSmall);
+        summary.record_failed();
+        summary.record_failed();
+
+        assert_eq!(
+            summary,
+            RunSummary {
+                moved: 2,
+                skipped: 1,
+                failed: 2,
+                bytes_moved: 2_100,
+                moved_files: vec![
+                    ("a.md".into(), "pages/a.md".into()),
+                    ("b.md".into(), "pages/b.md".into()),
+                ],
+                skipped_files: vec![("c.md".into(), SkipReason::TooSmall)],
+                hook_failures: Vec::new(),
+                journal_disabled: false,
+                journal_failure: None,
+                rolled_back: Vec::new(),
+                rollback_failures: Vec::new(),
+            }
+        );
+        assert_eq!(summary.total(), 5);
+    }
+
+    #[test]
+    fn test_summary_exit_code() {
+        let mut summary = RunSummary::default();
+        assert_eq!(summary.exit_code(), EXIT_SUCCESS);
+
+        summary.record_moved("a.md".into(), "pages/a.md".into(), 10);
+        summary.record_skipped("b.md".into(), SkipReason::Excluded);
+        assert_eq!(summary.exit_code(), EXIT_SUCCESS);
+
+        summary.record_failed();
+        assert_eq!(summary.exit_code(), EXIT_PARTIAL_FAILURE);
+    }
+
+    #[test]
+    fn test_summary_render() {
+        let mut summary = RunSummary::default();
+        summary.record_moved("a.md".into(), "pages/a.md".into(), 1536);
+        summary.record_failed();
+
+        let rendered = summary.render();
+        assert!(rendered.contains("Moved:   1 (1.5 KiB)"));
+        assert!(rendered.contains("Skipped: 0"));
+        assert!(rendered.contains("Failed:  1"));
+        assert!(rendered.contains("Total:   2"));
+        assert!(!rendered.contains("Journal:"));
+
+        summary.journal_disabled = true;
+        assert!(summary.render().contains("  Journal: disabled\n"));
+    }
+
+    #[test]
+    fn test_summary_journal_failure() {
+        let mut summary = RunSummary::default();
+        summary.record_moved("a.md".into(), "pages/a.md".into(), 10);
+        assert_eq!(summary.exit_code(), EXIT_SUCCESS);
+
+        summary.record_journal_failed("Permission denied".into());
+        assert_eq!(summary.exit_code(), EXIT_PARTIAL_FAILURE);
+        assert!(summary.render().contains("(Permission denied)\n"));
+    }
+
+    #[test]
+    fn test_summary_render_groups_skipped_by_reason() {
+        let mut summary = RunSummary::default();
+        summary.record_skipped("stub.md".into(), SkipReason::TooSmall);
+        summary.record_skipped("copy.md".into(), SkipReason::Duplicate);
+        summary.record_skipped("empty.md".into(), SkipReason::TooSmall);
+
+        let rendered = summary.render();
+        assert!(rendered.ends_with(
+            "Skipped files:\n  below minimum size (2):\n    - stub.md\n    - empty.md\n  identical page already exists (1):\n    - copy.md\n"
+        ));
+    }
+
+    #[test]
+    fn test_summary_hook_failures() {
+        let mut summary = RunSummary::default();
+        summary.record_moved("a.md".into(), "pages/a.md".into(), 10);
+        summary.record_hook_failed("pages/a.md".into(), "Hook failed (exit status: 1)".into());
+
+        assert_eq!(summary.exit_code(), EXIT_PARTIAL_FAILURE);
+        assert!(
+            summary
+                .render()
+                .ends_with("Hook failures (1):\n    - pages/a.md: Hook failed (exit status: 1)\n")
+        );
+    }
+
+    #[test]
+    fn test_summary_rolled_back() {
+        let mut summary = RunSummary::default();
+        summary.record_moved("a.md".into(), "pages/a.md".into(), 10);
+        summary.record_moved("b.md".into(), "pages/b.md".into(), 20);
+        summary.record_rolled_back("b.md".into(), "pages/b.md".into(), 20);
+        summary.record_rollback_failed("pages/a.md".into(), "a.md already exists".into());
+
+        assert_eq!(summary.moved, 1);
+        assert_eq!(summary.bytes_moved, 10);
+        assert_eq!(
+            summary.moved_files,
+            vec![("a.md".into(), "pages/a.md".into())]
+        );
+        assert_eq!(summary.exit_code(), EXIT_PARTIAL_FAILURE);
+        assert!(summary.render().ends_with(
+            "Rolled back, the journal could not be written (1):\n    - pages/b.md → b.md\n\nNot rolled back (1):\n    - pages/a.md: a.md already exists\n"
+        ));
+    }
+
+    #[test]
+    fn test_summary_serializes_counts() {
+        let mut summary = RunSummary::default();
+        summary.record_moved("a.md".into(), "pages/a.md".into(), 42);
+
+        let value = serde_json::to_value(&summary).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"moved": 1, "skipped": 0, "failed": 0, "bytes_moved": 42})
+        );
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
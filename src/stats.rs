@@ -0,0 +1,265 @@
+//! Import statistics computed from the move ledger
+//!
+//! Every file `stow` moved (and that was not undone since) has a ledger
+//! entry, so the ledger doubles as a history of imports. Entries from older
+//! versions may lack a timestamp or size: the run start time stands in for a
+//! missing timestamp, and entries with neither are only counted in the totals.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDate};
+use serde::Serialize;
+
+use crate::ledger::LedgerEntry;
+use crate::run_summary::format_bytes;
+
+/// Number of files imported on a single day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DayCount {
+    /// Day in `YYYY-MM-DD` form
+    pub date: String,
+    pub files: usize,
+}
+
+/// Totals over every import recorded in the ledger
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportStats {
+    /// Number of imported files
+    pub total_files: usize,
+    /// Bytes imported, counting only entries that recorded a size
+    pub total_bytes: u64,
+    /// Entries without a usable timestamp, left out of the per-day and per-week counts
+    pub undated_files: usize,
+    /// Files imported per day, keyed by `YYYY-MM-DD`
+    pub per_day: BTreeMap<String, usize>,
+    /// Files imported per ISO week, keyed by `YYYY-Www`
+    pub per_week: BTreeMap<String, usize>,
+    /// Day with the most imports; the earliest one wins a tie
+    pub busiest_day: Option<DayCount>,
+}
+
+impl ImportStats {
+    /// Compute statistics over ledger entries
+    pub fn from_entries(entries: &[LedgerEntry]) -> Self {
+        let mut stats = ImportStats::default();
+
+        for entry in entries {
+            stats.total_files += 1;
+            stats.total_bytes += entry.size.unwrap_or(0);
+
+            let Some(date) = Self::import_date(entry) else {
+                stats.undated_files += 1;
+                continue;
+            };
+            *stats
+                .per_day
+                .entry(date.format("%Y-%m-%d").to_string())
+                .or_default() += 1;
+            let week = date.iso_week();
+            *stats
+                .per_week
+                .entry(format!("{}-W{:02}", week.year(), week.week()))
+                .or_default() += 1;
+        }
+
+        stats.busiest_day = stats
+            .per_day
+            .iter()
+            .fold(
+                None::<(&String, usize)>,
+                |busiest, (date, &files)| match busiest {
+                    Some((_, most)) if most >= files => busiest,
+                    _ => Some((date, files)),
+                },
+            )
+            .map(|(date, files)| DayCount {
+                date: date.clone(),
+                files,
+            });
+
+        stats
+    }
+
+    /// Render the statistics as a short report with per-day and per-week tables
+    pub fn render(&self) -> String {
+        if self.total_files == 0 {
+            return "No imports recorded yet.\n".to_string();
+        }
+
+        let mut output = format!(
+            "Imported: {} file(s), {}\n",
+            self.total_files,
+            format_bytes(self.total_bytes)
+        );
+        if let Some(busiest) = &self.busiest_day {
+            output.push_str(&format!(
+                "Busiest day: {} ({} file(s))\n",
+                busiest.date, busiest.files
+            ));
+        }
+        if self.undated_files > 0 {
+            output.push_str(&format!("Without a date: {} file(s)\n", self.undated_files));
+        }
+
+        for (title, counts) in [("Per day", &self.per_day), ("Per week", &self.per_week)] {
+            if counts.is_empty() {
+                continue;
+            }
+            output.push_str(&format!("\n{}:\n", title));
+            for (period, files) in counts {
+                output.push_str(&format!("  {:<10}  {:>5}\n", period, files));
+            }
+        }
+
+        output
+    }
+
+    /// Day an entry was imported, from its timestamp or else its run start
+    fn import_date(entry: &LedgerEntry) -> Option<NaiveDate> {
+        [&entry.timestamp, &entry.run_id]
+            .into_iter()
+            .find_map(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.date_naive())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(timestamp: &str, size: Option<u64>) -> LedgerEntry {
+        LedgerEntry {
+            run_id: String::new(),
+            from: PathBuf::from("/downloads/a.md"),
+            to: PathBuf::from("/kb/pages/a.md"),
+            timestamp: timestamp.to_string(),
+            journal_path: None,
+            journal_line: None,
+            size,
+            modified: None,
+            created_dirs: Vec::new(),
+            journal_heading: None,
+            journal_heading_created: false,
+            rollback_failed: false,
+        }
+    }
+
+    #[test]
+    fn test_from_entries_groups_by_day_and_week() {
+        let entries = vec![
+            entry("2026-02-06T09:00:00+01:00", Some(100)),
+            entry("2026-02-07T10:15:00+01:00", Some(200)),
+            entry("2026-02-07T23:59:00+01:00", Some(300)),
+            entry("2026-02-09T08:00:00+01:00", None),
+        ];
+
+        let stats = ImportStats::from_entries(&entries);
+
+        assert_eq!(stats.total_files, 4);
+        assert_eq!(stats.total_bytes, 600);
+        assert_eq!(stats.undated_files, 0);
+        assert_eq!(
+            stats.per_day,
+            BTreeMap::from([
+                ("2026-02-06".to_string(), 1),
+                ("2026-02-07".to_string(), 2),
+                ("2026-02-09".to_string(), 1),
+            ])
+        );
+        assert_eq!(
+            stats.per_week,
+            BTreeMap::from([("2026-W06".to_string(), 3), ("2026-W07".to_string(), 1)])
+        );
+        assert_eq!(
+            stats.busiest_day,
+            Some(DayCount {
+                date: "2026-02-07".to_string(),
+                files: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_entries_tolerates_missing_fields() {
+        let mut from_run_id = entry("", None);
+        from_run_id.run_id = "2026-02-06T09:00:00+01:00".to_string();
+        let entries = vec![from_run_id, entry("", Some(50)), entry("yesterday", None)];
+
+        let stats = ImportStats::from_entries(&entries);
+
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.total_bytes, 50);
+        assert_eq!(stats.undated_files, 2);
+        assert_eq!(
+            stats.per_day,
+            BTreeMap::from([("2026-02-06".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_from_ledger_file_with_older_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ledger_path = temp_dir.path().join("ledger.jsonl");
+        std::fs::write(
+            &ledger_path,
+            concat!(
+                r#"{"from":"/downloads/old.md","to":"/kb/pages/old.md"}"#,
+                "\n",
+                r#"{"run_id":"2026-02-06T09:00:00+01:00","from":"/downloads/a.md","to":"/kb/pages/a.md"}"#,
+                "\n",
+                r#"{"run_id":"2026-02-10T09:00:00+01:00","from":"/downloads/b.md","to":"/kb/pages/b.md","timestamp":"2026-02-10T09:00:01+01:00","size":2048}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let entries = crate::ledger::Ledger::read(&ledger_path).unwrap();
+        let stats = ImportStats::from_entries(&entries);
+
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.total_bytes, 2048);
+        assert_eq!(stats.undated_files, 1);
+        assert_eq!(
+            stats.per_week,
+            BTreeMap::from([("2026-W06".to_string(), 1), ("2026-W07".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_busiest_day_prefers_earliest_on_tie() {
+        let entries = vec![
+            entry("2026-03-02T10:00:00Z", None),
+            entry("2026-03-01T10:00:00Z", None),
+        ];
+
+        let stats = ImportStats::from_entries(&entries);
+
+        assert_eq!(stats.busiest_day.unwrap().date, "2026-03-01");
+    }
+
+    #[test]
+    fn test_render() {
+        let entries = vec![
+            entry("2026-02-06T09:00:00+01:00", Some(1024)),
+            entry("2026-02-07T10:15:00+01:00", Some(1024)),
+            entry("2026-02-07T11:00:00+01:00", None),
+            entry("", None),
+        ];
+
+        assert_eq!(
+            ImportStats::from_entries(&entries).render(),
+            "Imported: 4 file(s), 2.0 KiB\n\
+             Busiest day: 2026-02-07 (2 file(s))\n\
+             Without a date: 1 file(s)\n\
+             \n\
+             Per day:\n  2026-02-06      1\n  2026-02-07      2\n\
+             \n\
+             Per week:\n  2026-W06        3\n"
+        );
+        assert_eq!(
+            ImportStats::default().render(),
+            "No imports recorded yet.\n"
+        );
+    }
+}
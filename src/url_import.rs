@@ -0,0 +1,270 @@
+//! Fetching a markdown page from a URL for `import-url`
+//!
+//! Only a successful (`200 OK`) response with a markdown or plain text
+//! content type is accepted, since raw gist and repository URLs serve
+//! markdown as `text/plain`. A response without a content type is taken as
+//! markdown. The page is named after the last segment of the URL path
+//! unless a name is given, with `.md` added when it lacks a configured
+//! extension.
+
+use std::time::Duration;
+
+use crate::config::Config;
+
+/// Content types accepted as markdown
+const MARKDOWN_CONTENT_TYPES: [&str; 3] = ["text/markdown", "text/x-markdown", "text/plain"];
+
+/// How long a fetch may take in total
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Error types for fetching pages
+#[derive(Debug, thiserror::Error)]
+pub enum UrlImportError {
+    #[error("Failed to fetch {url}: {message}")]
+    Request { url: String, message: String },
+    #[error("Failed to fetch {url}: server answered {status}")]
+    Status { url: String, status: u16 },
+    #[error("{url} is not markdown (content type {content_type})")]
+    ContentType { url: String, content_type: String },
+    #[error("{url} is larger than max_file_size_bytes ({max} bytes)")]
+    TooLarge { url: String, max: u64 },
+    #[error("Cannot derive a file name from {0}; pass one with --name")]
+    MissingName(String),
+}
+
+/// A page fetched from a URL, not yet written to the Knowledge Base
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchedPage {
+    pub url: String,
+    /// File name the page is imported as, e.g. `notes.md`
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+/// Public interface for fetching pages from URLs
+pub struct UrlImport;
+
+impl UrlImport {
+    /// Fetch `url` and name the page `name`, or after the URL when unset
+    pub fn fetch(
+        url: &str,
+        name: Option<&str>,
+        config: &Config,
+    ) -> Result<FetchedPage, UrlImportError> {
+        let name = match name {
+            Some(name) => Self::with_markdown_extension(name, config),
+            None => Self::file_name(url, config)?,
+        };
+
+        let request_error = |e: ureq::Error| UrlImportError::Request {
+            url: url.to_string(),
+            message: e.to_string(),
+        };
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(FETCH_TIMEOUT))
+            .build()
+            .into();
+        let mut response = agent.get(url).call().map_err(request_error)?;
+
+        let status = response.status().as_u16();
+        if status != 200 {
+            return Err(UrlImportError::Status {
+                url: url.to_string(),
+                status,
+            });
+        }
+        if let Some(content_type) = response.body().mime_type()
+            && !MARKDOWN_CONTENT_TYPES.contains(&content_type.to_ascii_lowercase().as_str())
+        {
+            return Err(UrlImportError::ContentType {
+                url: url.to_string(),
+                content_type: content_type.to_string(),
+            });
+        }
+
+        // The body is read through a limit, so an oversized page is never
+        // held in full; a page exactly at the cap is kept
+        let content = match config.max_file_size_bytes {
+            Some(max) => {
+                let too_large = || UrlImportError::TooLarge {
+                    url: url.to_string(),
+                    max,
+                };
+                let content = response
+                    .body_mut()
+                    .with_config()
+                    .limit(max.saturating_add(1))
+                    .read_to_vec()
+                    .map_err(|e| match e {
+                        ureq::Error::BodyExceedsLimit(_) => too_large(),
+                        e => request_error(e),
+                    })?;
+                if content.len() as u64 > max {
+                    return Err(too_large());
+                }
+                content
+            }
+            None => response.body_mut().read_to_vec().map_err(request_error)?,
+        };
+        Ok(FetchedPage {
+            url: url.to_string(),
+            name,
+            content,
+        })
+    }
+
+    /// File name for a page fetched from `url`, taken from the last segment of its path
+    pub fn file_name(url: &str, config: &Config) -> Result<String, UrlImportError> {
+        let path = url.split_once("://").map_or(url, |(_, rest)| {
+            rest.split_once('/').map_or("", |(_, path)| path)
+        });
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let segment = Self::percent_decode(path.rsplit('/').next().unwrap_or_default());
+        let segment = segment.trim();
+        if segment.is_empty() || segment == "." || segment == ".." {
+            return Err(UrlImportError::MissingName(url.to_string()));
+        }
+        Ok(Self::with_markdown_extension(segment, config))
+    }
+
+    /// `name` with `.md` appended unless it already has a configured extension
+    fn with_markdown_extension(name: &str, config: &Config) -> String {
+        let has_extension = name.rsplit_once('.').is_some_and(|(stem, ext)| {
+            !stem.is_empty()
+                && config
+                    .extensions
+                    .iter()
+                    .any(|configured| configured.eq_ignore_ascii_case(ext))
+        });
+        if has_extension {
+            name.to_string()
+        } else {
+            format!("{}.md", name)
+        }
+    }
+
+    /// Decode `%XX` escapes, keeping malformed ones as written
+    fn percent_decode(text: &str) -> String {
+        let bytes = text.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut index = 0;
+        while index < bytes.len() {
+            let escaped = (bytes[index] == b'%')
+                .then(|| text.get(index + 1..index + 3))
+                .flatten()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match escaped {
+                Some(byte) => {
+                    decoded.push(byte);
+                    index += 3;
+                }
+                None => {
+                    decoded.push(bytes[index]);
+                    index += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serve a single HTTP response on a local port and return the URL to fetch
+    fn serve_once(status: &str, content_type: Option<&str>, body: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+        if let Some(content_type) = content_type {
+            response.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        response.push_str("Connection: close\r\n\r\n");
+        response.push_str(body);
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+                line.clear();
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        format!("http://{}/notes/Reading%20List", address)
+    }
+
+    #[test]
+    fn test_fetch_markdown_page() {
+        let url = serve_once("200 OK", Some("text/markdown; charset=utf-8"), "# Reading");
+
+        let page = UrlImport::fetch(&url, None, &Config::default()).unwrap();
+
+        assert_eq!(page.name, "Reading List.md");
+        assert_eq!(page.content, b"# Reading");
+    }
+
+    #[test]
+    fn test_fetch_rejects_error_status() {
+        let url = serve_once("404 Not Found", Some("text/plain"), "missing");
+
+        let error = UrlImport::fetch(&url, None, &Config::default()).unwrap_err();
+
+        assert!(matches!(error, UrlImportError::Status { status: 404, .. }));
+        assert!(error.to_string().ends_with("server answered 404"));
+    }
+
+    #[test]
+    fn test_fetch_rejects_non_markdown_content_type() {
+        let url = serve_once("200 OK", Some("text/html"), "<html></html>");
+
+        let error = UrlImport::fetch(&url, Some("page"), &Config::default()).unwrap_err();
+
+        assert!(
+            matches!(error, UrlImportError::ContentType { ref content_type, .. } if content_type == "text/html")
+        );
+    }
+
+    #[test]
+    fn test_fetch_rejects_body_above_max_file_size() {
+        let config = Config {
+            max_file_size_bytes: Some(4),
+            ..Default::default()
+        };
+
+        let url = serve_once("200 OK", Some("text/markdown"), "# Long page");
+        let error = UrlImport::fetch(&url, None, &config).unwrap_err();
+        assert!(matches!(error, UrlImportError::TooLarge { max: 4, .. }));
+
+        let url = serve_once("200 OK", Some("text/markdown"), "# Ok");
+        assert_eq!(
+            UrlImport::fetch(&url, None, &config).unwrap().content,
+            b"# Ok"
+        );
+    }
+
+    #[test]
+    fn test_file_name_from_url() {
+        let config = Config::default();
+        let name = |url| UrlImport::file_name(url, &config);
+
+        assert_eq!(
+            name("https://gist.githubusercontent.com/u/abc/raw/notes.md?x=1").unwrap(),
+            "notes.md"
+        );
+        assert_eq!(name("https://example.com/raw/abc123").unwrap(), "abc123.md");
+        assert_eq!(
+            name("https://example.com/a/My%20Notes.MD#top").unwrap(),
+            "My Notes.MD"
+        );
+        assert!(matches!(
+            name("https://example.com/"),
+            Err(UrlImportError::MissingName(_))
+        ));
+        assert!(name("https://example.com").is_err());
+    }
+}
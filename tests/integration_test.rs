@@ -1,6 +1,12 @@
-use local_shelf::config::Config;
+use chrono::TimeZone;
+use local_shelf::RunOptions;
+use local_shelf::config::{Config, SourcePathAnnotation};
+use local_shelf::events::SilentSink;
+use local_shelf::ledger::Ledger;
+use local_shelf::run_summary::SkipReason;
 use std::env;
 use std::fs;
+use std::path::Path;
 use std::process::Command;
 use tempfile::tempdir;
 
@@ -51,6 +57,7 @@ knowledge_base_path: "/custom/path"
 
         let mut config = Config {
             knowledge_base_path: "/config/file/path".to_string(),
+            ..Default::default()
         };
 
         // Simulate environment override (as done in Config::load)
@@ -70,6 +77,7 @@ knowledge_base_path: "/custom/path"
     {
         let config = Config {
             knowledge_base_path: "~/TestKB".to_string(),
+            ..Default::default()
         };
 
         let expanded = config.get_knowledge_base_path();
@@ -81,6 +89,7 @@ knowledge_base_path: "/custom/path"
     {
         let invalid_config = Config {
             knowledge_base_path: "".to_string(),
+            ..Default::default()
         };
         assert!(invalid_config.validate().is_err());
     }
@@ -90,6 +99,7 @@ knowledge_base_path: "/custom/path"
 fn test_yaml_roundtrip() {
     let original_config = Config {
         knowledge_base_path: "/test/roundtrip/path".to_string(),
+        ..Default::default()
     };
 
     // Serialize to YAML
@@ -123,6 +133,88 @@ knowledge_base_path: "/legacy/path"
     assert_eq!(config.knowledge_base_path, "/legacy/path");
 }
 
+#[test]
+fn test_read_only_commands_leave_config_file_unchanged() {
+    let temp_dir = tempdir().unwrap();
+    let source_dir = temp_dir.path().join("inbox");
+    fs::create_dir(&source_dir).unwrap();
+    fs::write(source_dir.join("article.md"), "# Article").unwrap();
+    let config_path = temp_dir.path().join("config.yaml");
+    let content = format!(
+        "# Vault on the laptop\nversion: 1\nknowledge_base_path: \"{}/vault\"  # synced\nrecursive: true\n",
+        temp_dir.path().display()
+    );
+    fs::write(&config_path, &content).unwrap();
+
+    let source = source_dir.to_str().unwrap();
+    for args in [
+        vec!["journal-path"],
+        vec!["scan", source],
+        vec!["doctor", source],
+        vec!["config"],
+        vec!["stow", "--dry-run", source],
+    ] {
+        let output = Command::new("cargo")
+            .env("HOME", temp_dir.path())
+            .env("XDG_CONFIG_HOME", temp_dir.path().join(".config"))
+            .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+            .env_remove("KNOWLEDGE_BASE")
+            .env_remove("LOCAL_SHELF_CONFIG")
+            .args(["run", "--", "--config"])
+            .arg(&config_path)
+            .args(&args)
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.code().is_some(), "{:?} did not exit", args);
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            content,
+            "{:?} rewrote the configuration file",
+            args
+        );
+    }
+}
+
+#[test]
+fn test_detected_obsidian_layout_survives_repeated_runs() {
+    let temp_dir = tempdir().unwrap();
+    let vault = temp_dir.path().join("vault");
+    fs::create_dir_all(vault.join(".obsidian")).unwrap();
+    let config_path = temp_dir.path().join("config.yaml");
+    let content = format!("version: 1\nknowledge_base_path: \"{}\"\n", vault.display());
+    fs::write(&config_path, &content).unwrap();
+
+    for name in ["first.md", "second.md"] {
+        let source_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join(name), "# Note").unwrap();
+        let output = Command::new("cargo")
+            .env("HOME", temp_dir.path())
+            .env("XDG_CONFIG_HOME", temp_dir.path().join(".config"))
+            .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+            .env_remove("KNOWLEDGE_BASE")
+            .env_remove("LOCAL_SHELF_CONFIG")
+            .args(["run", "--", "--config"])
+            .arg(&config_path)
+            .arg("stow")
+            .arg(source_dir.path())
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success());
+    }
+
+    assert!(vault.join("first.md").exists());
+    assert!(vault.join("second.md").exists());
+    assert!(!vault.join("pages").exists());
+    assert!(!vault.join("journals").exists());
+    let journals: Vec<String> = fs::read_dir(&vault)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.len() == "YYYY-MM-DD.md".len() && name.as_bytes()[4] == b'-')
+        .collect();
+    assert_eq!(journals.len(), 1, "{:?}", journals);
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), content);
+}
+
 #[test]
 fn test_cli_help_output() {
     let output = run_cargo_with_test_env(&["run", "--", "--help"]);
@@ -286,6 +378,448 @@ fn test_stow_with_nonexistent_directory() {
     assert!(stderr.contains("Directory") && stderr.contains("does not exist"));
 }
 
+#[test]
+fn test_stow_with_source_flag() {
+    let source_dir = tempdir().unwrap();
+    let home_dir = tempdir().unwrap();
+    let kb_dir = home_dir.path().join("vault");
+    fs::write(source_dir.path().join("first.md"), "# First").unwrap();
+    fs::write(source_dir.path().join("second.md"), "# Second").unwrap();
+
+    let output = Command::new("cargo")
+        .env("HOME", home_dir.path())
+        .env("XDG_CONFIG_HOME", home_dir.path().join(".config"))
+        .env("KNOWLEDGE_BASE", &kb_dir)
+        .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+        .args([
+            "run",
+            "--",
+            "stow",
+            "--source",
+            source_dir.path().to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(kb_dir.join("pages/first.md").exists());
+    assert!(kb_dir.join("pages/second.md").exists());
+    assert!(!source_dir.path().join("first.md").exists());
+
+    let journals: Vec<_> = fs::read_dir(kb_dir.join("journals")).unwrap().collect();
+    assert_eq!(journals.len(), 1);
+    let journal = fs::read_to_string(journals[0].as_ref().unwrap().path()).unwrap();
+    assert!(journal.contains("[[first]]"));
+    assert!(journal.contains("[[second]]"));
+}
+
+#[test]
+fn test_stow_with_missing_source_flag_directory() {
+    let output = Command::new("cargo")
+        .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+        .args(["run", "--", "stow", "--source", "/nonexistent/source"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'/nonexistent/source' does not exist"));
+}
+
+#[test]
+fn test_stow_force_skips_parent_directory_check() {
+    let source_dir = tempdir().unwrap();
+    let home_dir = tempdir().unwrap();
+    let kb_dir = home_dir.path().join("automount/vault");
+    fs::write(source_dir.path().join("note.md"), "# Note").unwrap();
+
+    let stow = |knowledge_base: &str, force: bool| {
+        let mut args = vec!["run", "--", "stow", source_dir.path().to_str().unwrap()];
+        if force {
+            args.push("--force");
+        }
+        Command::new("cargo")
+            .env("HOME", home_dir.path())
+            .env("XDG_CONFIG_HOME", home_dir.path().join(".config"))
+            .env("KNOWLEDGE_BASE", knowledge_base)
+            .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+            .args(args)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let rejected = stow(kb_dir.to_str().unwrap(), false);
+    assert!(!rejected.status.success());
+    assert!(String::from_utf8_lossy(&rejected.stderr).contains("Parent directory does not exist"));
+    assert!(source_dir.path().join("note.md").exists());
+
+    let empty = stow("", true);
+    assert!(!empty.status.success());
+    assert!(String::from_utf8_lossy(&empty.stderr).contains("knowledge_base_path cannot be empty"));
+
+    let forced = stow(kb_dir.to_str().unwrap(), true);
+    assert!(
+        forced.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&forced.stderr)
+    );
+    assert!(String::from_utf8_lossy(&forced.stderr).contains("continuing because of --force"));
+    assert!(kb_dir.join("pages/note.md").exists());
+}
+
+#[test]
+fn test_stow_color_flag_controls_ansi_escapes() {
+    let home_dir = tempdir().unwrap();
+    let kb_dir = home_dir.path().join("vault");
+
+    let stow = |color: &str| {
+        let source_dir = tempdir().unwrap();
+        fs::write(source_dir.path().join(format!("{}.md", color)), "# Note").unwrap();
+        Command::new("cargo")
+            .env("HOME", home_dir.path())
+            .env("XDG_CONFIG_HOME", home_dir.path().join(".config"))
+            .env("KNOWLEDGE_BASE", &kb_dir)
+            .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+            .env_remove("NO_COLOR")
+            .env("CARGO_TERM_COLOR", "never")
+            .args([
+                "run",
+                "--",
+                "--color",
+                color,
+                "stow",
+                source_dir.path().to_str().unwrap(),
+            ])
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let never = stow("never");
+    assert!(never.status.success());
+    let stdout = String::from_utf8_lossy(&never.stdout);
+    assert!(stdout.contains("✓ Moved never.md"));
+    assert!(!stdout.contains('\x1b'));
+    assert!(!String::from_utf8_lossy(&never.stderr).contains('\x1b'));
+
+    let always = stow("always");
+    assert!(always.status.success());
+    assert!(String::from_utf8_lossy(&always.stdout).contains("\x1b[32m✓\x1b[39m Moved always.md"));
+}
+
+#[test]
+fn test_positional_knowledge_base_overrides_environment() {
+    let home_dir = tempdir().unwrap();
+    let env_kb_dir = home_dir.path().join("env-vault");
+    let kb_dir = tempdir().unwrap();
+    let source_dir = tempdir().unwrap();
+    let cwd_dir = tempdir().unwrap();
+    fs::write(source_dir.path().join("one-off.md"), "# One-off").unwrap();
+    fs::write(cwd_dir.path().join("default.md"), "# Default").unwrap();
+
+    let run = |args: &[&str], current_dir: &Path| {
+        Command::new("cargo")
+            .current_dir(current_dir)
+            .env("HOME", home_dir.path())
+            .env("XDG_CONFIG_HOME", home_dir.path().join(".config"))
+            .env("KNOWLEDGE_BASE", &env_kb_dir)
+            .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+            .args(["run", "--manifest-path"])
+            .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+            .arg("--")
+            .args(args)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let kb = kb_dir.path().to_str().unwrap();
+    let output = run(
+        &[kb, "stow", source_dir.path().to_str().unwrap()],
+        Path::new(env!("CARGO_MANIFEST_DIR")),
+    );
+    assert!(output.status.success());
+    assert!(kb_dir.path().join("pages/one-off.md").exists());
+
+    // Without a command the Knowledge Base argument stows the current directory
+    let output = run(&[kb], cwd_dir.path());
+    assert!(output.status.success());
+    assert!(kb_dir.path().join("pages/default.md").exists());
+    assert!(!env_kb_dir.exists());
+
+    // A bare relative name is taken from the current directory
+    fs::create_dir(cwd_dir.path().join("vault")).unwrap();
+    fs::write(cwd_dir.path().join("relative.md"), "# Relative").unwrap();
+    let output = run(&["vault"], cwd_dir.path());
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(cwd_dir.path().join("vault/pages/relative.md").exists());
+
+    // A name that is neither a command nor a directory is reported
+    let output = run(&["stwo"], cwd_dir.path());
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Knowledge Base 'stwo' does not exist")
+    );
+    assert!(!cwd_dir.path().join("stwo").exists());
+}
+
+#[test]
+fn test_fail_on_empty_exit_code() {
+    let home_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    let source_dir = tempdir().unwrap();
+
+    let run = |extra: &[&str]| {
+        Command::new("cargo")
+            .env("HOME", home_dir.path())
+            .env("XDG_CONFIG_HOME", home_dir.path().join(".config"))
+            .env("KNOWLEDGE_BASE", kb_dir.path())
+            .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+            .args(["run", "--", "stow"])
+            .arg(source_dir.path())
+            .args(extra)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No markdown files found"));
+
+    let output = run(&["--fail-on-empty"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No markdown files found"));
+}
+
+/// Answer one HTTP request on a local port with `body` as markdown and return the server URL
+fn serve_markdown_once(body: &'static str) -> String {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+            line.clear();
+        }
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+    format!("http://{}", address)
+}
+
+#[test]
+fn test_import_url_writes_page_and_journal() {
+    let home_dir = tempdir().unwrap();
+    let kb_dir = home_dir.path().join("vault");
+    let url = format!(
+        "{}/raw/reading-list.md",
+        serve_markdown_once("# Reading list\n")
+    );
+
+    let output = Command::new("cargo")
+        .env("HOME", home_dir.path())
+        .env("XDG_CONFIG_HOME", home_dir.path().join(".config"))
+        .env("KNOWLEDGE_BASE", &kb_dir)
+        .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+        .env("NO_PROXY", "127.0.0.1")
+        .args(["run", "--", "import-url", &url, "--name", "Books"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(kb_dir.join("pages/Books.md")).unwrap(),
+        "# Reading list\n"
+    );
+    let journals: Vec<_> = fs::read_dir(kb_dir.join("journals")).unwrap().collect();
+    assert_eq!(journals.len(), 1);
+    let journal = fs::read_to_string(journals[0].as_ref().unwrap().path()).unwrap();
+    assert!(journal.contains("[[Books]]"));
+}
+
+#[test]
+fn test_import_url_reports_unreachable_server() {
+    let temp_dir = tempdir().unwrap();
+    let vault = temp_dir.path().join("vault");
+    fs::create_dir(&vault).unwrap();
+
+    let output = Command::new("cargo")
+        .env("HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path().join(".config"))
+        .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+        .env("KNOWLEDGE_BASE", &vault)
+        .env_remove("LOCAL_SHELF_CONFIG")
+        .args(["run", "--", "import-url", "http://127.0.0.1:9/missing.md"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("Failed to fetch http://127.0.0.1:9/missing.md")
+    );
+    assert_eq!(fs::read_dir(&vault).unwrap().count(), 0);
+}
+
+#[test]
+fn test_config_flag_overrides_config_location() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("portable.yaml");
+    fs::write(
+        &config_path,
+        format!(
+            "knowledge_base_path: \"{}/vault\"\n",
+            temp_dir.path().display()
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new("cargo")
+        .env_remove("KNOWLEDGE_BASE")
+        .env_remove("LOCAL_SHELF_CONFIG")
+        .args([
+            "run",
+            "--",
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("Configuration file: {}", config_path.display())));
+    assert!(stdout.contains(&format!("{}/vault", temp_dir.path().display())));
+}
+
+#[test]
+fn test_stow_reads_config_from_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = tempdir().unwrap();
+    let source_dir = temp_dir.path().join("inbox");
+    fs::create_dir(&source_dir).unwrap();
+    fs::write(source_dir.join("article.md"), "# Article").unwrap();
+
+    let mut child = Command::new("cargo")
+        .env_remove("KNOWLEDGE_BASE")
+        .args(["run", "--", "--config", "-", "stow", "--dry-run"])
+        .arg(&source_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    writeln!(
+        child.stdin.take().unwrap(),
+        "knowledge_base_path: \"{}/vault\"",
+        temp_dir.path().display()
+    )
+    .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains(&format!(
+        "Knowledge Base path: {}/vault",
+        temp_dir.path().display()
+    )));
+    assert!(source_dir.join("article.md").exists());
+}
+
+#[test]
+fn test_journal_path_follows_configured_format_and_timezone() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.yaml");
+    fs::write(
+        &config_path,
+        format!(
+            "knowledge_base_path: \"{}/vault\"\njournals_dir_name: Daily\njournal_file_format: \"%d.%m.%Y\"\ntimezone: Pacific/Kiritimati\n",
+            temp_dir.path().display()
+        ),
+    )
+    .unwrap();
+
+    let today = || {
+        chrono::Utc::now()
+            .with_timezone(&chrono_tz::Pacific::Kiritimati)
+            .format("%d.%m.%Y")
+            .to_string()
+    };
+    let before = today();
+    let output = Command::new("cargo")
+        .env("HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path().join(".config"))
+        .env("LOCAL_SHELF_SKIP_CONFIG_INIT", "1")
+        .env_remove("LOCAL_SHELF_NO_MIGRATE")
+        .env_remove("KNOWLEDGE_BASE")
+        .env_remove("LOCAL_SHELF_CONFIG")
+        .args(["run", "--", "--config"])
+        .arg(&config_path)
+        .arg("journal-path")
+        .output()
+        .expect("Failed to execute command");
+    let after = today();
+
+    // The day may roll over while the command runs, so either date is fine
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = |date: &str| format!("{}/vault/Daily/{}.md\n", temp_dir.path().display(), date);
+    assert!(
+        stdout == expected(&before) || stdout == expected(&after),
+        "unexpected journal path: {stdout}"
+    );
+    assert!(!temp_dir.path().join("vault").exists());
+}
+
+#[test]
+fn test_legacy_migration_note_keeps_json_output_parseable() {
+    let temp_dir = tempdir().unwrap();
+    fs::create_dir_all(temp_dir.path().join(".config/local-shelf")).unwrap();
+    let source_dir = temp_dir.path().join("inbox");
+    fs::create_dir(&source_dir).unwrap();
+    fs::write(source_dir.join("article.md"), "# Article").unwrap();
+
+    let output = Command::new("cargo")
+        .env("HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path().join(".config"))
+        .env("LOCAL_SHELF_NO_MIGRATE", "1")
+        .env("KNOWLEDGE_BASE", temp_dir.path().join("vault"))
+        .env_remove("LOCAL_SHELF_SKIP_CONFIG_INIT")
+        .env_remove("LOCAL_SHELF_CONFIG")
+        .args(["run", "--", "stow", "--dry-run", "--json"])
+        .arg(&source_dir)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        serde_json::from_str::<serde_json::Value>(&stdout).is_ok(),
+        "{stdout}"
+    );
+    assert!(stderr.contains("was left in place because migration is disabled"));
+}
+
 #[test]
 fn test_unified_directory_parameters() {
     let temp_dir = tempdir().unwrap();
@@ -319,3 +853,377 @@ fn test_unified_directory_parameters() {
     assert!(stow_help.contains("directory containing markdown files"));
     assert!(convert_help.contains("directory containing markdown files"));
 }
+
+#[test]
+fn test_library_run_moves_and_journals() {
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    fs::write(source_dir.path().join("alpha.md"), "# Alpha").unwrap();
+    fs::write(source_dir.path().join("beta.md"), "# Beta content").unwrap();
+
+    let config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        ..Default::default()
+    };
+    let ledger_path = kb_dir.path().join("ledger.jsonl");
+    let summary = local_shelf::run(
+        &config,
+        RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            ledger_path: Some(ledger_path.clone()),
+            ..Default::default()
+        },
+        &SilentSink,
+    )
+    .unwrap();
+
+    assert_eq!(summary.moved, 2);
+    assert_eq!(summary.failed, 0);
+    assert_eq!(summary.bytes_moved, 7 + 14);
+    assert!(kb_dir.path().join("pages/alpha.md").exists());
+    assert!(kb_dir.path().join("pages/beta.md").exists());
+    assert!(!source_dir.path().join("alpha.md").exists());
+
+    let journal = fs::read_dir(kb_dir.path().join("journals"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let content = fs::read_to_string(journal).unwrap();
+    assert!(content.contains("[[alpha]]"));
+    assert!(content.contains("[[beta]]"));
+    assert_eq!(Ledger::read(&ledger_path).unwrap().len(), 2);
+}
+
+#[test]
+fn test_library_run_records_source_path_in_journal() {
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    let source = source_dir.path().join("alpha.md");
+    fs::write(&source, "# Alpha").unwrap();
+
+    let config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        record_source_path: SourcePathAnnotation::Journal,
+        ..Default::default()
+    };
+    let summary = local_shelf::run(
+        &config,
+        RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            ..Default::default()
+        },
+        &SilentSink,
+    )
+    .unwrap();
+
+    assert_eq!(summary.moved, 1);
+    let journal = fs::read_dir(kb_dir.path().join("journals"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let content = fs::read_to_string(journal).unwrap();
+    assert!(content.contains(&format!("[[alpha]] (from {})", source.display())));
+    assert_eq!(
+        fs::read_to_string(kb_dir.path().join("pages/alpha.md")).unwrap(),
+        "# Alpha"
+    );
+}
+
+#[test]
+fn test_library_run_dry_run_leaves_files() {
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    fs::write(source_dir.path().join("alpha.md"), "# Alpha").unwrap();
+
+    let config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        ..Default::default()
+    };
+    let summary = local_shelf::run(
+        &config,
+        RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            dry_run: true,
+            ..Default::default()
+        },
+        &SilentSink,
+    )
+    .unwrap();
+
+    assert_eq!(summary.moved, 0);
+    assert_eq!(summary.skipped, 1);
+    assert!(source_dir.path().join("alpha.md").exists());
+    assert!(!kb_dir.path().join("pages").exists());
+}
+
+#[test]
+fn test_library_run_reports_skipped_files_with_reasons() {
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    let pages_dir = kb_dir.path().join("pages");
+    fs::create_dir_all(&pages_dir).unwrap();
+    fs::write(pages_dir.join("copy.md"), "# Already imported").unwrap();
+
+    fs::write(source_dir.path().join("stub.md"), "").unwrap();
+    fs::write(source_dir.path().join("copy.md"), "# Already imported").unwrap();
+    fs::write(source_dir.path().join("fresh.md"), "# Fresh").unwrap();
+
+    let config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        min_file_size_bytes: 1,
+        ..Default::default()
+    };
+    let summary = local_shelf::run(
+        &config,
+        RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            ..Default::default()
+        },
+        &SilentSink,
+    )
+    .unwrap();
+
+    assert_eq!(summary.moved, 1);
+    assert_eq!(summary.skipped, 2);
+    assert_eq!(
+        summary.skipped_files,
+        vec![
+            (source_dir.path().join("stub.md"), SkipReason::TooSmall),
+            (source_dir.path().join("copy.md"), SkipReason::Duplicate),
+        ]
+    );
+    assert!(source_dir.path().join("copy.md").exists());
+    assert!(
+        summary
+            .render()
+            .contains("identical page already exists (1)")
+    );
+}
+
+#[test]
+fn test_library_run_backs_up_journal() {
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    let journals_dir = kb_dir.path().join("journals");
+    fs::create_dir_all(&journals_dir).unwrap();
+    let journal = journals_dir.join("2024_03_15.md");
+    fs::write(&journal, "- my own notes\n").unwrap();
+    fs::write(source_dir.path().join("alpha.md"), "# Alpha").unwrap();
+
+    let config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        journal_backup: true,
+        ..Default::default()
+    };
+    local_shelf::run(
+        &config,
+        RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            now: Some(
+                chrono::Local
+                    .with_ymd_and_hms(2024, 3, 15, 14, 30, 0)
+                    .unwrap(),
+            ),
+            ..Default::default()
+        },
+        &SilentSink,
+    )
+    .unwrap();
+
+    let backup = journals_dir.join("2024_03_15.md.bak");
+    assert_eq!(fs::read_to_string(backup).unwrap(), "- my own notes\n");
+    assert_eq!(
+        fs::read_to_string(&journal).unwrap(),
+        "- my own notes\n- **14:30** [[alpha]]\n"
+    );
+}
+
+#[test]
+fn test_library_run_dedupe_by_content_across_names() {
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    let pages_dir = kb_dir.path().join("pages");
+    fs::create_dir_all(&pages_dir).unwrap();
+    fs::write(pages_dir.join("rust-ownership.md"), "# Ownership").unwrap();
+
+    fs::write(source_dir.path().join("article (1).md"), "# Ownership").unwrap();
+    fs::write(source_dir.path().join("article (2).md"), "# Ownership").unwrap();
+    fs::write(source_dir.path().join("fresh.md"), "# Fresh").unwrap();
+
+    let mut config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        dedupe_by_content: true,
+        ..Default::default()
+    };
+    let run = |config: &Config| {
+        local_shelf::run(
+            config,
+            RunOptions {
+                source_directory: source_dir.path().to_path_buf(),
+                ..Default::default()
+            },
+            &SilentSink,
+        )
+        .unwrap()
+    };
+
+    let summary = run(&config);
+    assert_eq!(summary.moved, 1);
+    assert_eq!(summary.skipped, 2);
+    assert!(
+        summary
+            .skipped_files
+            .iter()
+            .all(|(_, reason)| *reason == SkipReason::Duplicate)
+    );
+    assert!(source_dir.path().join("article (1).md").exists());
+    assert!(!pages_dir.join("article (1).md").exists());
+
+    config.dedupe_delete_source = true;
+    let summary = run(&config);
+    assert_eq!(summary.skipped, 2);
+    assert!(!source_dir.path().join("article (1).md").exists());
+    assert!(!source_dir.path().join("article (2).md").exists());
+    assert_eq!(fs::read_dir(&pages_dir).unwrap().count(), 2);
+}
+
+#[test]
+fn test_library_run_dedupe_by_content_within_one_batch() {
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    for name in ["article.md", "article (1).md", "article (2).md"] {
+        fs::write(source_dir.path().join(name), "# Ownership\n").unwrap();
+    }
+
+    let config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        dedupe_by_content: true,
+        add_page_properties: true,
+        parallel_moves: 3,
+        ..Default::default()
+    };
+    let summary = local_shelf::run(
+        &config,
+        RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            ..Default::default()
+        },
+        &SilentSink,
+    )
+    .unwrap();
+
+    assert_eq!(summary.moved, 1);
+    assert_eq!(summary.skipped, 2);
+    assert_eq!(
+        fs::read_dir(kb_dir.path().join("pages")).unwrap().count(),
+        1
+    );
+}
+
+#[test]
+fn test_library_run_gives_same_named_files_distinct_destinations() {
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    for (dir, content) in [("chrome", "# From Chrome"), ("firefox", "# From Firefox")] {
+        let nested = source_dir.path().join(dir);
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("untitled.md"), content).unwrap();
+    }
+
+    let config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        recursive: true,
+        ..Default::default()
+    };
+    let summary = local_shelf::run(
+        &config,
+        RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            ..Default::default()
+        },
+        &SilentSink,
+    )
+    .unwrap();
+
+    assert_eq!(summary.moved, 2);
+    let destinations: Vec<_> = summary.moved_files.iter().map(|(_, to)| to).collect();
+    assert_ne!(destinations[0], destinations[1]);
+    let mut contents: Vec<_> = destinations
+        .iter()
+        .map(|path| fs::read_to_string(path).unwrap())
+        .collect();
+    contents.sort();
+    assert_eq!(contents, vec!["# From Chrome", "# From Firefox"]);
+}
+
+#[test]
+fn test_library_run_parallel_moves_resolve_collisions() {
+    const FILES: usize = 64;
+    let source_dir = tempdir().unwrap();
+    let kb_dir = tempdir().unwrap();
+    let pages_dir = kb_dir.path().join("pages");
+    fs::create_dir_all(&pages_dir).unwrap();
+
+    // Every source file collides with an existing page, and nested sources
+    // share names so concurrent moves compete for the same suffixed names
+    for i in 0..FILES {
+        fs::write(pages_dir.join(format!("note{}.md", i % 8)), "existing").unwrap();
+        let nested = source_dir.path().join(format!("batch{}", i / 8));
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            nested.join(format!("note{}.md", i % 8)),
+            format!("new {}", i),
+        )
+        .unwrap();
+    }
+
+    let config = Config {
+        knowledge_base_path: kb_dir.path().display().to_string(),
+        recursive: true,
+        parallel_moves: 8,
+        ..Default::default()
+    };
+    let summary = local_shelf::run(
+        &config,
+        RunOptions {
+            source_directory: source_dir.path().to_path_buf(),
+            ..Default::default()
+        },
+        &SilentSink,
+    )
+    .unwrap();
+
+    assert_eq!(summary.moved, FILES);
+    assert_eq!(summary.failed, 0);
+
+    let destinations: std::collections::HashSet<_> = summary
+        .moved_files
+        .iter()
+        .map(|(_, to)| to.clone())
+        .collect();
+    assert_eq!(destinations.len(), FILES);
+
+    let mut contents: Vec<String> = fs::read_dir(&pages_dir)
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .filter(|content| content != "existing")
+        .collect();
+    contents.sort();
+    let mut expected: Vec<String> = (0..FILES).map(|i| format!("new {}", i)).collect();
+    expected.sort();
+    assert_eq!(contents, expected);
+
+    // Results stay in discovery order despite concurrent moves
+    let sources: Vec<_> = summary
+        .moved_files
+        .iter()
+        .map(|(from, _)| from.clone())
+        .collect();
+    let mut sorted_sources = sources.clone();
+    sorted_sources.sort();
+    assert_eq!(sources, sorted_sources);
+}